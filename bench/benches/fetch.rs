@@ -0,0 +1,119 @@
+//! Fetch of produced records from a broker running in the same process, over a loopback connection
+
+use anyhow::Result;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use kafka_starter_rust::{
+    client::Client,
+    config::Config,
+    protocol::{
+        record_batch::{PartitionValue, RecordValue, TopicValue},
+        request::fetch::{Partition, TopicRequest},
+    },
+    storage::{BatchWriter, FileStorage},
+    Broker, BrokerHandle,
+};
+
+const TOPIC_ID: &str = "00000000-0000-4000-8000-000000000001";
+const BATCHES: usize = 100;
+const RECORDS_PER_BATCH: usize = 100;
+
+/// Writes the topic with one partition to the metadata log, and the batches of 100 byte records to its log
+fn create_topic(broker: &BrokerHandle) -> Result<u64> {
+    let topic = RecordValue::Topic(TopicValue {
+        topic_name: "foo".to_string(),
+        topic_id: TOPIC_ID.to_string(),
+    })
+    .serialize();
+    let partition = RecordValue::Partition(PartitionValue {
+        partition_id: 0,
+        topic_id: TOPIC_ID.to_string(),
+        replicas: vec![1],
+        in_sync_replicas: vec![1],
+        removing_replicas: Vec::new(),
+        adding_replicas: Vec::new(),
+        leader_id: 1,
+        leader_epoch: 0,
+        partition_epoch: 0,
+        directories: Vec::new(),
+    })
+    .serialize();
+    let metadata_log = broker
+        .log_dir()
+        .join("__cluster_metadata-0")
+        .join("00000000000000000000.log");
+    BatchWriter::new(&metadata_log, 0).append(
+        &FileStorage,
+        &[(None, Some(&topic[..])), (None, Some(&partition[..]))],
+        0,
+    )?;
+
+    let log = broker
+        .log_dir()
+        .join("foo-0")
+        .join("00000000000000000000.log");
+    let value = [b'a'; 100];
+    let records: Vec<_> = (0..RECORDS_PER_BATCH)
+        .map(|_| (None, Some(&value[..])))
+        .collect();
+    let writer = BatchWriter::new(&log, 0);
+    for _ in 0..BATCHES {
+        writer.append(&FileStorage, &records, 1_700_000_000_000)?;
+    }
+    Ok(std::fs::metadata(&log)?.len())
+}
+
+fn fetch(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().expect("start runtime");
+    let config = Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = runtime
+        .block_on(Broker::start(config))
+        .expect("start broker");
+    let log_size = create_topic(&broker).expect("create topic");
+    let mut client = runtime
+        .block_on(Client::connect(broker.addr(), "bench"))
+        .expect("connect");
+
+    let request = |fetch_offset, partition_max_bytes| {
+        vec![TopicRequest {
+            topic_id: TOPIC_ID.to_string(),
+            partitions: vec![Partition {
+                partition: 0,
+                current_leader_epoch: 0,
+                fetch_offset,
+                last_fetched_epoch: 0,
+                log_start_offset: 0,
+                partition_max_bytes,
+            }],
+        }]
+    };
+
+    let mut group = c.benchmark_group("fetch");
+    // the whole log in one response, and a single batch from the middle of it
+    group.throughput(Throughput::Bytes(log_size));
+    group.bench_function("whole_log", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(client.fetch(request(0, i32::MAX as u32)))
+                .unwrap()
+        })
+    });
+    group.throughput(Throughput::Bytes(log_size / BATCHES as u64));
+    group.bench_function("one_batch", |b| {
+        let offset = (BATCHES / 2 * RECORDS_PER_BATCH) as u64;
+        b.iter(|| runtime.block_on(client.fetch(request(offset, 1))).unwrap())
+    });
+    group.finish();
+
+    drop(client);
+    runtime
+        .block_on(broker.shutdown())
+        .expect("shut the broker down");
+}
+
+criterion_group!(benches, fetch);
+criterion_main!(benches);
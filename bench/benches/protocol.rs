@@ -0,0 +1,111 @@
+//! Decoding and encoding of the wire format, without a broker
+
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use kafka_starter_rust::{
+    protocol::{
+        reader::ByteReader,
+        record_batch::{RecordBatch, RecordValue, TopicValue},
+        response::fetch::{FetchResponseV16, TopicPartition, TopicResponse},
+        types::{Records, VarInt},
+        ErrorCode, Response,
+    },
+    storage::BatchWriter,
+};
+
+const TOPIC_ID: &str = "00000000-0000-4000-8000-000000000001";
+
+/// Batch of 100 records of 100 bytes each
+fn batch() -> Bytes {
+    let value = [b'a'; 100];
+    let records: Vec<_> = (0..100).map(|_| (None, Some(&value[..]))).collect();
+    BatchWriter::batch(0, 0, &records, 1_700_000_000_000).freeze()
+}
+
+/// Batch of 100 topic records, fully parsed records are metadata records
+fn metadata_batch() -> Bytes {
+    let values: Vec<_> = (0..100)
+        .map(|i| {
+            RecordValue::Topic(TopicValue {
+                topic_name: format!("topic-{i}"),
+                topic_id: TOPIC_ID.to_string(),
+            })
+            .serialize()
+        })
+        .collect();
+    let records: Vec<_> = values
+        .iter()
+        .map(|value| (None, Some(&value[..])))
+        .collect();
+    BatchWriter::batch(0, 0, &records, 1_700_000_000_000).freeze()
+}
+
+fn varint(c: &mut Criterion) {
+    // one of each encoded length, from 1 to 10 bytes
+    let encoded: Vec<Bytes> = (0..10)
+        .map(|i| VarInt::serialize(1u64 << (i * 7)))
+        .collect();
+    let mut group = c.benchmark_group("varint");
+    group.throughput(Throughput::Elements(encoded.len() as u64));
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for value in &encoded {
+                black_box(VarInt::deserialize(&mut &value[..]).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn record_batch(c: &mut Criterion) {
+    let batch = metadata_batch();
+    let mut group = c.benchmark_group("record_batch");
+    group.throughput(Throughput::Bytes(batch.len() as u64));
+    // the records are parsed when the metadata log is read, only the header for the offsets of the other logs
+    group.bench_function("parse", |b| {
+        b.iter(|| RecordBatch::from_bytes(&mut ByteReader::new(batch.clone())).unwrap())
+    });
+    group.bench_function("parse_header", |b| {
+        b.iter(|| RecordBatch::from_bytes_lazy(&mut ByteReader::new(batch.clone())).unwrap())
+    });
+    group.finish();
+}
+
+fn fetch_response(c: &mut Criterion) {
+    // 10 partitions with 10 batches each
+    let batch = batch();
+    let partitions = || {
+        (0..10)
+            .map(|partition_index| {
+                let mut records = Records::default();
+                for _ in 0..10 {
+                    records.push(batch.clone());
+                }
+                TopicPartition {
+                    partition_index,
+                    error_code: ErrorCode::None,
+                    high_watermark: 1000,
+                    last_stable_offset: 1000,
+                    log_start_offset: 0,
+                    aborted_transactions: Vec::new(),
+                    preferred_read_replica: -1,
+                    records,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut group = c.benchmark_group("fetch_response");
+    group.throughput(Throughput::Bytes(100 * batch.len() as u64));
+    group.bench_function("serialize", |b| {
+        b.iter_batched(
+            || vec![TopicResponse::new(TOPIC_ID.to_string(), partitions())],
+            |responses| FetchResponseV16::new(1, 0, 0, responses).into_bytes(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, varint, record_batch, fetch_response);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+
+use kafka_starter_rust::protocol::{
+    reader::ByteReader,
+    request::{fetch::FetchRequestV16, RequestHeader},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut src = ByteReader::new(Bytes::copy_from_slice(data));
+    if let Ok(header) = RequestHeader::from_bytes(&mut src) {
+        _ = FetchRequestV16::from_bytes(header, &mut src);
+    }
+});
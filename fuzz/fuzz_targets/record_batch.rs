@@ -0,0 +1,16 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+
+use kafka_starter_rust::protocol::{
+    reader::ByteReader,
+    record_batch::{LogOffsets, RecordBatch},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Bytes::copy_from_slice(data);
+    _ = RecordBatch::from_bytes(&mut ByteReader::new(bytes.clone()));
+    // the partition logs are scanned with the same batch layout
+    _ = LogOffsets::scan(bytes);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use bytes::Bytes;
+use libfuzzer_sys::fuzz_target;
+
+use kafka_starter_rust::protocol::{reader::ByteReader, request::RequestHeader};
+
+fuzz_target!(|data: &[u8]| {
+    _ = RequestHeader::from_bytes(&mut ByteReader::new(Bytes::copy_from_slice(data)));
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use kafka_starter_rust::protocol::types::VarInt;
+
+fuzz_target!(|data: &[u8]| {
+    let mut src = data;
+    if let Ok(value) = VarInt::deserialize(&mut src) {
+        // decoded values encode back to the same value, even if the input was not the shortest encoding
+        let encoded = VarInt::serialize(value as u64);
+        assert_eq!(VarInt::deserialize(&mut &encoded[..]), Ok(value));
+    }
+});
@@ -0,0 +1,323 @@
+//! Admin endpoint with JSON views of the state of the broker, for debugging, e.g. what a tester stage
+//! produced and which connections it left open.
+//!
+//! `GET /` lists the views. The views are read when they are requested, the topics from the metadata log
+//! and the watermarks from the actors of the partitions.
+
+use std::{
+    fmt::{self, Write},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+use anyhow::Result;
+
+use crate::{
+    http::{self, Response},
+    logic::BrokerContext,
+    server::Connections,
+    storage::StorageError,
+};
+
+/// Paths of the views and what they show
+const VIEWS: [(&str, &str); 4] = [
+    (
+        "/topics",
+        "topics of the metadata log with their partitions and watermarks",
+    ),
+    ("/connections", "connections open to the broker"),
+    (
+        "/fetch-sessions",
+        "incremental fetch sessions with their epochs and partitions",
+    ),
+    (
+        "/consumer-groups",
+        "consumer groups, none as the broker is no group coordinator",
+    ),
+];
+
+/// Serves the views over HTTP on the given address
+pub async fn serve(
+    addr: SocketAddr,
+    broker: Arc<BrokerContext>,
+    connections: Arc<Connections>,
+) -> Result<()> {
+    let respond: http::Respond = Arc::new(move |path: &str| respond(&broker, &connections, path));
+    http::serve(addr, "admin", respond).await
+}
+
+fn respond(broker: &BrokerContext, connections: &Connections, path: &str) -> Response {
+    let view = match path {
+        "/" => Ok(Json::Object(
+            VIEWS
+                .iter()
+                .map(|&(path, description)| (path, Json::String(description.to_string())))
+                .collect(),
+        )),
+        "/topics" => topics(broker),
+        "/connections" => Ok(Json::Array(
+            connections
+                .list()
+                .into_iter()
+                .map(|connection| {
+                    Json::Object(vec![
+                        ("peer", Json::String(connection.peer.to_string())),
+                        ("opened_ms", Json::Number(connection.opened_ms)),
+                    ])
+                })
+                .collect(),
+        )),
+        "/fetch-sessions" => Ok(Json::Array(
+            broker
+                .fetch_sessions
+                .list()
+                .into_iter()
+                .map(|session| {
+                    Json::Object(vec![
+                        ("session_id", Json::Number(session.session_id.into())),
+                        ("epoch", Json::Number(session.epoch.into())),
+                        ("partitions", Json::Number(session.partitions as i64)),
+                        ("last_used_ms", Json::Number(session.last_used_ms)),
+                    ])
+                })
+                .collect(),
+        )),
+        "/consumer-groups" => Ok(Json::Array(Vec::new())),
+        _ => return Response::not_found(),
+    };
+    match view {
+        Ok(view) => Response::ok("application/json", format!("{view}\n")),
+        Err(err) => Response {
+            status: "500 Internal Server Error",
+            content_type: "application/json",
+            body: format!("{}\n", error(&err)),
+        },
+    }
+}
+
+/// Topics with their partitions, the watermarks of the partitions are read by their actors at the same time
+fn topics(broker: &BrokerContext) -> Result<Json, StorageError> {
+    let metadata = broker.metadata()?;
+    let topics = metadata
+        .topics()
+        .map(|topic| {
+            let partitions: Vec<_> = metadata
+                .partitions(&topic.topic_id)
+                .iter()
+                .map(|partition| {
+                    let file = broker.partition_log_file(&topic.topic_name, partition.partition_id);
+                    (partition, broker.partitions.watermarks(&file))
+                })
+                .collect();
+            let partitions = partitions
+                .into_iter()
+                .map(|(partition, watermarks)| {
+                    let mut fields = vec![
+                        ("partition", Json::Number(partition.partition_id.into())),
+                        ("leader", Json::Number(partition.leader_id.into())),
+                        ("leader_epoch", Json::Number(partition.leader_epoch.into())),
+                        ("replicas", ids(&partition.replicas)),
+                        ("in_sync_replicas", ids(&partition.in_sync_replicas)),
+                    ];
+                    match watermarks.wait() {
+                        // the partitions have no followers, the log end offset is the high watermark
+                        Ok(watermarks) => fields.extend([
+                            (
+                                "log_start_offset",
+                                Json::Number(watermarks.log_start_offset),
+                            ),
+                            ("log_end_offset", Json::Number(watermarks.log_end_offset)),
+                            ("high_watermark", Json::Number(watermarks.log_end_offset)),
+                        ]),
+                        Err(err) => fields.push(("error", Json::String(err.to_string()))),
+                    }
+                    Json::Object(fields)
+                })
+                .collect();
+            Json::Object(vec![
+                ("name", Json::String(topic.topic_name.clone())),
+                ("topic_id", Json::String(topic.topic_id.clone())),
+                ("partitions", Json::Array(partitions)),
+            ])
+        })
+        .collect();
+    Ok(Json::Array(topics))
+}
+
+fn ids(ids: &[u32]) -> Json {
+    Json::Array(ids.iter().map(|&id| Json::Number(id.into())).collect())
+}
+
+fn error(err: &StorageError) -> Json {
+    Json::Object(vec![("error", Json::String(err.to_string()))])
+}
+
+/// JSON value of a view
+enum Json {
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Writes the string with the quotes, backslashes and control characters escaped
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use super::{respond, Json};
+    use crate::{
+        config::Config,
+        logic::BrokerContext,
+        protocol::{
+            record_batch::{PartitionValue, RecordBatch, RecordValue, TopicValue},
+            request::fetch::{Partition, TopicRequest},
+            types::Serialize,
+        },
+        server::Connections,
+        storage::MemoryStorage,
+    };
+
+    const TOPIC_ID: &str = "0b8c1a2d-3e4f-4a5b-8c6d-7e8f9a0b1c2d";
+
+    #[test]
+    fn topics_with_partition_watermarks() {
+        let config = Config::default();
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: TOPIC_ID.to_string(),
+        });
+        let partition = |partition_id| {
+            RecordValue::Partition(PartitionValue {
+                partition_id,
+                topic_id: TOPIC_ID.to_string(),
+                replicas: vec![1],
+                in_sync_replicas: vec![1],
+                removing_replicas: Vec::new(),
+                adding_replicas: Vec::new(),
+                leader_id: 1,
+                leader_epoch: 3,
+                partition_epoch: 0,
+                directories: Vec::new(),
+            })
+        };
+        let metadata =
+            RecordBatch::of_values(0, vec![topic.clone(), partition(0), partition(1)]).serialize();
+        // offsets 4 and 5 in partition 0, nothing was produced to partition 1
+        let log = RecordBatch::of_values(4, vec![topic.clone(), topic]).serialize();
+        let storage = MemoryStorage::with_files([
+            (config.metadata_log_file(), metadata),
+            (
+                config.partition_log_file(&MemoryStorage::default(), "foo", 0),
+                log,
+            ),
+        ]);
+        let broker = BrokerContext::with_storage(storage);
+        let connections = Arc::new(Connections::default());
+
+        let response = respond(&broker, &connections, "/topics");
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(
+            response.body,
+            format!(
+                concat!(
+                    r#"[{{"name":"foo","topic_id":"{}","partitions":["#,
+                    r#"{{"partition":0,"leader":1,"leader_epoch":3,"replicas":[1],"in_sync_replicas":[1],"#,
+                    r#""log_start_offset":4,"log_end_offset":6,"high_watermark":6}},"#,
+                    r#"{{"partition":1,"leader":1,"leader_epoch":3,"replicas":[1],"in_sync_replicas":[1],"#,
+                    r#""log_start_offset":0,"log_end_offset":0,"high_watermark":0}}]}}]"#,
+                    "\n"
+                ),
+                TOPIC_ID
+            )
+        );
+
+        assert_eq!(respond(&broker, &connections, "/connections").body, "[]\n");
+        assert_eq!(
+            respond(&broker, &connections, "/unknown").status,
+            "404 Not Found"
+        );
+    }
+
+    #[test]
+    fn fetch_sessions() {
+        let broker = BrokerContext::with_storage(MemoryStorage::default());
+        let connections = Arc::new(Connections::default());
+        assert_eq!(
+            respond(&broker, &connections, "/fetch-sessions").body,
+            "[]\n"
+        );
+
+        let partition = |partition| Partition {
+            partition,
+            current_leader_epoch: 0,
+            fetch_offset: 0,
+            last_fetched_epoch: 0,
+            log_start_offset: 0,
+            partition_max_bytes: 1024,
+        };
+        let topic = TopicRequest {
+            topic_id: TOPIC_ID.to_string(),
+            partitions: vec![partition(0), partition(1)],
+        };
+        let session_id = broker.fetch_sessions.create(&[topic], Instant::now());
+
+        let body = respond(&broker, &connections, "/fetch-sessions").body;
+        let expected =
+            format!(r#"[{{"session_id":{session_id},"epoch":1,"partitions":2,"last_used_ms":"#);
+        assert!(body.starts_with(&expected), "{body}");
+    }
+
+    #[test]
+    fn escapes_strings() {
+        let json = Json::Object(vec![(
+            "client",
+            Json::String("a\"b\\c\nd\u{1}é".to_string()),
+        )]);
+        assert_eq!(json.to_string(), r#"{"client":"a\"b\\c\nd\u0001é"}"#);
+    }
+}
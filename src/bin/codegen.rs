@@ -0,0 +1,1114 @@
+//! Generates Rust message structs from the Kafka message JSON schemas
+//! (`clients/src/main/resources/common/message/*.json` in the Kafka repository).
+//!
+//! Usage: `cargo run --bin codegen -- <output dir> <schema.json>...`
+//!
+//! Every schema produces one `<snake_case_name>.rs` file containing `<Name>Data` struct with
+//! versioned `deserialize` and `serialize` methods, plus the nested structs it declares. `serialize_into` appends
+//! to the buffer of the enclosing message, so nested structs are not serialized into buffers of their own.
+//! Tagged fields are not generated yet, the ones of flexible versions are skipped when read and none are written.
+
+use std::{collections::HashMap, fmt::Write as _, fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((out_dir, schemas)) = args.split_first() else {
+        bail!("usage: codegen <output dir> <schema.json>...");
+    };
+    if schemas.is_empty() {
+        bail!("no schema files given");
+    }
+
+    for schema_path in schemas {
+        let src = fs::read_to_string(schema_path)
+            .with_context(|| format!("read schema file '{}'", schema_path))?;
+        let json = json::parse(&src).with_context(|| format!("parse JSON in '{}'", schema_path))?;
+        let message = Message::from_json(&json)
+            .with_context(|| format!("read message schema from '{}'", schema_path))?;
+
+        let code = generate(&message, schema_path)
+            .with_context(|| format!("generate code for '{}'", message.name))?;
+
+        let out_file = Path::new(out_dir).join(format!("{}.rs", snake_case(&message.name)));
+        fs::write(&out_file, code).with_context(|| format!("write '{}'", out_file.display()))?;
+        eprintln!("generated {}", out_file.display());
+    }
+
+    Ok(())
+}
+
+/// Minimal JSON parser, the schema files are JSON with `//` line comments
+mod json {
+    use anyhow::{bail, Result};
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    impl Json {
+        pub fn get(&self, key: &str) -> Option<&Json> {
+            match self {
+                Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match self {
+                Json::String(s) => Some(s),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&[Json]> {
+            match self {
+                Json::Array(items) => Some(items),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn parse(src: &str) -> Result<Json> {
+        let mut p = Parser {
+            chars: src.chars().collect(),
+            pos: 0,
+        };
+        let value = p.value()?;
+        p.skip_whitespace();
+        if p.pos != p.chars.len() {
+            bail!("unexpected trailing characters at {}", p.pos);
+        }
+        Ok(value)
+    }
+
+    struct Parser {
+        chars: Vec<char>,
+        pos: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.pos).copied()
+        }
+
+        fn next(&mut self) -> Result<char> {
+            match self.peek() {
+                Some(c) => {
+                    self.pos += 1;
+                    Ok(c)
+                }
+                None => bail!("unexpected end of input"),
+            }
+        }
+
+        fn expect(&mut self, expected: char) -> Result<()> {
+            self.skip_whitespace();
+            let c = self.next()?;
+            if c != expected {
+                bail!(
+                    "expected '{}' but found '{}' at {}",
+                    expected,
+                    c,
+                    self.pos - 1
+                );
+            }
+            Ok(())
+        }
+
+        fn skip_whitespace(&mut self) {
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() {
+                    self.pos += 1;
+                } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.pos += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+        }
+
+        fn value(&mut self) -> Result<Json> {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => self.object(),
+                Some('[') => self.array(),
+                Some('"') => Ok(Json::String(self.string()?)),
+                Some('t') => self.literal("true", Json::Bool(true)),
+                Some('f') => self.literal("false", Json::Bool(false)),
+                Some('n') => self.literal("null", Json::Null),
+                Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+                Some(c) => bail!("unexpected character '{}' at {}", c, self.pos),
+                None => bail!("unexpected end of input"),
+            }
+        }
+
+        fn literal(&mut self, word: &str, value: Json) -> Result<Json> {
+            for expected in word.chars() {
+                if self.next()? != expected {
+                    bail!("invalid literal at {}", self.pos - 1);
+                }
+            }
+            Ok(value)
+        }
+
+        fn number(&mut self) -> Result<Json> {
+            let start = self.pos;
+            while matches!(self.peek(), Some(c) if c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit())
+            {
+                self.pos += 1;
+            }
+            let s: String = self.chars[start..self.pos].iter().collect();
+            match s.parse() {
+                Ok(n) => Ok(Json::Number(n)),
+                Err(_) => bail!("invalid number '{}' at {}", s, start),
+            }
+        }
+
+        fn string(&mut self) -> Result<String> {
+            self.expect('"')?;
+            let mut s = String::new();
+            loop {
+                match self.next()? {
+                    '"' => return Ok(s),
+                    '\\' => match self.next()? {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        'u' => {
+                            let hex: String = (0..4).map(|_| self.next()).collect::<Result<_>>()?;
+                            let code = u32::from_str_radix(&hex, 16)?;
+                            s.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        c => s.push(c),
+                    },
+                    c => s.push(c),
+                }
+            }
+        }
+
+        fn array(&mut self) -> Result<Json> {
+            self.expect('[')?;
+            let mut items = Vec::new();
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(self.value()?);
+                self.skip_whitespace();
+                match self.next()? {
+                    ',' => continue,
+                    ']' => return Ok(Json::Array(items)),
+                    c => bail!("expected ',' or ']' but found '{}' at {}", c, self.pos - 1),
+                }
+            }
+        }
+
+        fn object(&mut self) -> Result<Json> {
+            self.expect('{')?;
+            let mut members = Vec::new();
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.pos += 1;
+                return Ok(Json::Object(members));
+            }
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.expect(':')?;
+                let value = self.value()?;
+                members.push((key, value));
+                self.skip_whitespace();
+                match self.next()? {
+                    ',' => continue,
+                    '}' => return Ok(Json::Object(members)),
+                    c => bail!("expected ',' or '}}' but found '{}' at {}", c, self.pos - 1),
+                }
+            }
+        }
+    }
+}
+
+use json::Json;
+
+/// Version range of a message or a field, e.g. `"0+"`, `"3-5"` or `"none"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Versions {
+    lowest: i16,
+    highest: i16,
+}
+
+impl Versions {
+    const NONE: Versions = Versions {
+        lowest: 0,
+        highest: -1,
+    };
+
+    fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s == "none" {
+            return Ok(Self::NONE);
+        }
+        let (lowest, highest) = if let Some(lowest) = s.strip_suffix('+') {
+            (lowest.parse()?, i16::MAX)
+        } else if let Some((lowest, highest)) = s.split_once('-') {
+            (lowest.parse()?, highest.parse()?)
+        } else {
+            let v = s.parse()?;
+            (v, v)
+        };
+        Ok(Self { lowest, highest })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lowest > self.highest
+    }
+
+    fn intersect(&self, other: &Versions) -> Versions {
+        Versions {
+            lowest: self.lowest.max(other.lowest),
+            highest: self.highest.min(other.highest),
+        }
+    }
+
+    /// Condition on `version` variable that holds for versions in `self` within the `valid` versions,
+    /// `None` if it holds for all of them.
+    fn condition(&self, valid: &Versions) -> Option<String> {
+        let v = self.intersect(valid);
+        match (v.lowest <= valid.lowest, v.highest >= valid.highest) {
+            (true, true) => None,
+            (false, true) => Some(format!("version >= {}", v.lowest)),
+            (true, false) => Some(format!("version <= {}", v.highest)),
+            (false, false) => Some(format!("({}..={}).contains(&version)", v.lowest, v.highest)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum FieldType {
+    Bool,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint16,
+    Uint32,
+    Float64,
+    String,
+    /// String with `nullableVersions`
+    NullableString,
+    Uuid,
+    Bytes,
+    Records,
+    Struct(String),
+    Array(Box<FieldType>),
+    /// Array with `nullableVersions`
+    NullableArray(Box<FieldType>),
+}
+
+impl FieldType {
+    fn parse(s: &str) -> FieldType {
+        if let Some(item) = s.strip_prefix("[]") {
+            return FieldType::Array(Box::new(FieldType::parse(item)));
+        }
+        match s {
+            "bool" => FieldType::Bool,
+            "int8" => FieldType::Int8,
+            "int16" => FieldType::Int16,
+            "int32" => FieldType::Int32,
+            "int64" => FieldType::Int64,
+            "uint16" => FieldType::Uint16,
+            "uint32" => FieldType::Uint32,
+            "float64" => FieldType::Float64,
+            "string" => FieldType::String,
+            "uuid" => FieldType::Uuid,
+            "bytes" => FieldType::Bytes,
+            "records" => FieldType::Records,
+            name => FieldType::Struct(name.to_string()),
+        }
+    }
+
+    fn rust_type(&self) -> String {
+        match self {
+            FieldType::Bool => "bool".into(),
+            FieldType::Int8 => "i8".into(),
+            FieldType::Int16 => "i16".into(),
+            FieldType::Int32 => "i32".into(),
+            FieldType::Int64 => "i64".into(),
+            FieldType::Uint16 => "u16".into(),
+            FieldType::Uint32 => "u32".into(),
+            FieldType::Float64 => "f64".into(),
+            FieldType::String | FieldType::Uuid => "String".into(),
+            FieldType::NullableString => "Option<String>".into(),
+            FieldType::Bytes | FieldType::Records => "Vec<u8>".into(),
+            FieldType::Struct(name) => name.clone(),
+            FieldType::Array(item) => format!("Vec<{}>", item.rust_type()),
+            FieldType::NullableArray(item) => format!("Option<Vec<{}>>", item.rust_type()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    field_type: FieldType,
+    versions: Versions,
+    tagged: bool,
+    default: Option<String>,
+    about: Option<String>,
+    /// Fields of the struct declared inline by this field
+    fields: Vec<Field>,
+}
+
+impl Field {
+    fn from_json(json: &Json) -> Result<Self> {
+        let name = str_member(json, "name")?.to_string();
+        let field_type = match FieldType::parse(str_member(json, "type")?) {
+            FieldType::String if json.get("nullableVersions").is_some() => {
+                FieldType::NullableString
+            }
+            FieldType::Array(item) if json.get("nullableVersions").is_some() => {
+                FieldType::NullableArray(item)
+            }
+            field_type => field_type,
+        };
+        let versions = Versions::parse(str_member(json, "versions")?)
+            .with_context(|| format!("versions of field '{}'", name))?;
+        let fields = fields_from_json(json).with_context(|| format!("fields of '{}'", name))?;
+        let default = match json.get("default") {
+            Some(Json::String(s)) => Some(s.clone()),
+            Some(Json::Number(n)) => Some(n.to_string()),
+            Some(Json::Bool(b)) => Some(b.to_string()),
+            _ => None,
+        };
+
+        Ok(Self {
+            name,
+            field_type,
+            versions,
+            tagged: json.get("taggedVersions").is_some(),
+            default,
+            about: json.get("about").and_then(Json::as_str).map(str::to_string),
+            fields,
+        })
+    }
+
+    fn rust_name(&self) -> String {
+        let name = snake_case(&self.name);
+        match name.as_str() {
+            "type" | "match" | "ref" | "self" | "move" | "loop" | "mod" | "use" => {
+                format!("r#{}", name)
+            }
+            _ => name,
+        }
+    }
+
+    fn default_value(&self) -> Result<String> {
+        let default = self.default.as_deref();
+        Ok(match &self.field_type {
+            FieldType::Bool => default.unwrap_or("false").to_string(),
+            FieldType::Int8
+            | FieldType::Int16
+            | FieldType::Int32
+            | FieldType::Int64
+            | FieldType::Uint16
+            | FieldType::Uint32 => match default {
+                Some(d) if d.starts_with("0x") => {
+                    let n = i64::from_str_radix(&d[2..], 16)
+                        .with_context(|| format!("default value of '{}'", self.name))?;
+                    n.to_string()
+                }
+                Some(d) => d.to_string(),
+                None => "0".to_string(),
+            },
+            FieldType::Float64 => match default {
+                Some(d) if d.contains('.') => d.to_string(),
+                Some(d) => format!("{}.0", d),
+                None => "0.0".to_string(),
+            },
+            FieldType::String => match default {
+                Some("null") | None => "String::new()".to_string(),
+                Some(d) => format!("{:?}.to_string()", d),
+            },
+            FieldType::NullableString => match default {
+                Some("null") | None => "None".to_string(),
+                Some(d) => format!("Some({:?}.to_string())", d),
+            },
+            FieldType::Uuid => "\"00000000-0000-0000-0000-000000000000\".to_string()".to_string(),
+            FieldType::Bytes | FieldType::Records | FieldType::Array(_) => "Vec::new()".to_string(),
+            FieldType::NullableArray(_) => match default {
+                Some("null") => "None".to_string(),
+                _ => "Some(Vec::new())".to_string(),
+            },
+            FieldType::Struct(name) => format!("{}::default()", name),
+        })
+    }
+}
+
+fn str_member<'a>(json: &'a Json, key: &str) -> Result<&'a str> {
+    json.get(key)
+        .and_then(Json::as_str)
+        .ok_or_else(|| anyhow!("missing string member '{}'", key))
+}
+
+fn fields_from_json(json: &Json) -> Result<Vec<Field>> {
+    match json.get("fields") {
+        Some(fields) => fields
+            .as_array()
+            .ok_or_else(|| anyhow!("'fields' is not an array"))?
+            .iter()
+            .map(Field::from_json)
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+#[derive(Debug)]
+struct Message {
+    api_key: Option<i16>,
+    name: String,
+    valid_versions: Versions,
+    flexible_versions: Versions,
+    fields: Vec<Field>,
+    common_structs: Vec<(String, Vec<Field>)>,
+}
+
+impl Message {
+    fn from_json(json: &Json) -> Result<Self> {
+        let api_key = match json.get("apiKey") {
+            Some(Json::Number(n)) => Some(*n as i16),
+            _ => None,
+        };
+        let common_structs = match json.get("commonStructs").and_then(Json::as_array) {
+            Some(structs) => structs
+                .iter()
+                .map(|s| Ok((str_member(s, "name")?.to_string(), fields_from_json(s)?)))
+                .collect::<Result<_>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            api_key,
+            name: str_member(json, "name")?.to_string(),
+            valid_versions: Versions::parse(str_member(json, "validVersions")?)?,
+            flexible_versions: Versions::parse(str_member(json, "flexibleVersions")?)?,
+            fields: fields_from_json(json)?,
+            common_structs,
+        })
+    }
+}
+
+/// Reference to the value passed to a serializer, array items are references already
+fn borrow(value: &str) -> String {
+    if value == "item" {
+        value.to_string()
+    } else {
+        format!("&{}", value)
+    }
+}
+
+/// Converts `CamelCase` schema names to `snake_case`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut s = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                s.push('_');
+            }
+        }
+        s.push(c.to_ascii_lowercase());
+    }
+    s
+}
+
+struct Generator<'a> {
+    message: &'a Message,
+    out: String,
+    /// Struct declarations collected from the schema in order of appearance
+    structs: Vec<(String, Vec<Field>)>,
+}
+
+fn generate(message: &Message, schema_path: &str) -> Result<String> {
+    let mut generator = Generator {
+        message,
+        out: String::new(),
+        structs: Vec::new(),
+    };
+
+    // like Kafka's generator, data schemas such as `DefaultPrincipalData` are not suffixed again
+    let top_name = if message.name.ends_with("Data") {
+        message.name.clone()
+    } else {
+        format!("{}Data", message.name)
+    };
+    generator.collect_structs(&top_name, &message.fields)?;
+    for (name, fields) in &message.common_structs {
+        generator.collect_structs(name, fields)?;
+    }
+
+    let schema_file = Path::new(schema_path)
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_else(|| schema_path.to_string());
+
+    let out = &mut generator.out;
+    writeln!(
+        out,
+        "// Generated by `src/bin/codegen.rs` from `{}`. Do not edit by hand.",
+        schema_file
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "// Generated code covers the whole message, not every part of it has to be used"
+    )?;
+    writeln!(out, "#![allow(dead_code)]")?;
+    writeln!(out)?;
+    // messages of strings only are serialized without `BufMut`
+    writeln!(out, "#[allow(unused_imports)]")?;
+    writeln!(out, "use bytes::{{BufMut, Bytes, BytesMut}};")?;
+    writeln!(out)?;
+    writeln!(out, "#[allow(unused_imports)]")?;
+    writeln!(out, "use crate::protocol::{{")?;
+    writeln!(out, "    reader::ByteReader,")?;
+    writeln!(out, "    types::{{CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields, Uuid, VarInt}},")?;
+    writeln!(out, "    ProtocolError,")?;
+    writeln!(out, "}};")?;
+
+    let structs = std::mem::take(&mut generator.structs);
+    for (name, fields) in &structs {
+        generator.write_struct(name, fields, *name == top_name)?;
+    }
+
+    Ok(generator.out)
+}
+
+impl Generator<'_> {
+    fn collect_structs(&mut self, name: &str, fields: &[Field]) -> Result<()> {
+        if self.structs.iter().any(|(n, _)| n == name) {
+            bail!("struct '{}' is declared more than once", name);
+        }
+        self.structs.push((name.to_string(), fields.to_vec()));
+
+        for field in fields {
+            if field.fields.is_empty() || field.tagged {
+                continue;
+            }
+            let struct_name = match &field.field_type {
+                FieldType::Struct(n) => n.clone(),
+                FieldType::Array(item) | FieldType::NullableArray(item) => match item.as_ref() {
+                    FieldType::Struct(n) => n.clone(),
+                    _ => bail!("field '{}' declares fields but is not a struct", field.name),
+                },
+                _ => bail!("field '{}' declares fields but is not a struct", field.name),
+            };
+            self.collect_structs(&struct_name, &field.fields)?;
+        }
+        Ok(())
+    }
+
+    /// Fields that are present on the wire in some valid version
+    fn wire_fields<'f>(&self, fields: &'f [Field]) -> Vec<&'f Field> {
+        fields
+            .iter()
+            .filter(|f| {
+                !f.tagged
+                    && !f
+                        .versions
+                        .intersect(&self.message.valid_versions)
+                        .is_empty()
+            })
+            .collect()
+    }
+
+    fn write_struct(&mut self, name: &str, fields: &[Field], top_level: bool) -> Result<()> {
+        let valid = self.message.valid_versions;
+        let fields = self.wire_fields(fields);
+        let mut out = String::new();
+
+        writeln!(out)?;
+        if top_level {
+            writeln!(
+                out,
+                "/// {}, versions {}-{}",
+                self.message.name, valid.lowest, valid.highest
+            )?;
+        }
+        let defaults = fields
+            .iter()
+            .map(|f| f.default_value())
+            .collect::<Result<Vec<_>>>()?;
+        // derive Default when every field has the default value of its type
+        let derive_default = defaults.iter().all(|d| {
+            matches!(
+                d.as_str(),
+                "0" | "0.0" | "false" | "String::new()" | "Vec::new()" | "None"
+            ) || d.ends_with("::default()")
+        });
+        if derive_default {
+            writeln!(out, "#[derive(Debug, Clone, Default, PartialEq)]")?;
+        } else {
+            writeln!(out, "#[derive(Debug, Clone, PartialEq)]")?;
+        }
+        writeln!(out, "pub struct {} {{", name)?;
+        for field in &fields {
+            if let Some(about) = &field.about {
+                writeln!(out, "    /// {}", about)?;
+            }
+            writeln!(
+                out,
+                "    pub {}: {},",
+                field.rust_name(),
+                field.field_type.rust_type()
+            )?;
+        }
+        writeln!(out, "}}")?;
+
+        if !derive_default {
+            writeln!(out)?;
+            writeln!(out, "impl Default for {} {{", name)?;
+            writeln!(out, "    fn default() -> Self {{")?;
+            writeln!(out, "        Self {{")?;
+            for (field, default) in fields.iter().zip(&defaults) {
+                writeln!(out, "            {}: {},", field.rust_name(), default)?;
+            }
+            writeln!(out, "        }}")?;
+            writeln!(out, "    }}")?;
+            writeln!(out, "}}")?;
+        }
+
+        writeln!(out)?;
+        writeln!(out, "impl {} {{", name)?;
+        if top_level {
+            if let Some(api_key) = self.message.api_key {
+                writeln!(out, "    pub const API_KEY: i16 = {};", api_key)?;
+            }
+            writeln!(
+                out,
+                "    pub const LOWEST_SUPPORTED_VERSION: i16 = {};",
+                valid.lowest
+            )?;
+            writeln!(
+                out,
+                "    pub const HIGHEST_SUPPORTED_VERSION: i16 = {};",
+                valid.highest
+            )?;
+            writeln!(out)?;
+        }
+
+        // deserialize
+        let version_used = fields.iter().any(|f| self.uses_version(f));
+        // the tag buffer depends on the version only if some versions are not flexible
+        let version_arg = if version_used || matches!(self.flexible_condition(), Some(Some(_))) {
+            "version"
+        } else {
+            "_version"
+        };
+        writeln!(
+            out,
+            "    pub fn deserialize(src: &mut ByteReader, {}: i16) -> Result<Self, ProtocolError> {{",
+            version_arg
+        )?;
+        for field in &fields {
+            let read =
+                self.read_expr(&field.field_type, &snake_case(&field.name), &field.versions)?;
+            match field.versions.condition(&valid) {
+                None => writeln!(out, "        let {} = {};", field.rust_name(), read)?,
+                Some(cond) => {
+                    writeln!(out, "        let {} = if {} {{", field.rust_name(), cond)?;
+                    writeln!(out, "            {}", read)?;
+                    writeln!(out, "        }} else {{")?;
+                    writeln!(out, "            {}", field.default_value()?)?;
+                    writeln!(out, "        }};")?;
+                }
+            }
+        }
+        match self.flexible_condition() {
+            Some(None) => writeln!(
+                out,
+                "        _ = TaggedFields::deserialize(src)?; // tag buffer"
+            )?,
+            Some(Some(cond)) => {
+                writeln!(out, "        if {} {{", cond)?;
+                writeln!(
+                    out,
+                    "            _ = TaggedFields::deserialize(src)?; // tag buffer"
+                )?;
+                writeln!(out, "        }}")?;
+            }
+            None => {}
+        }
+        if fields.is_empty() {
+            writeln!(out, "        Ok(Self {{}})")?;
+        } else {
+            writeln!(out, "        Ok(Self {{")?;
+            for field in &fields {
+                writeln!(out, "            {},", field.rust_name())?;
+            }
+            writeln!(out, "        }})")?;
+        }
+        writeln!(out, "    }}")?;
+
+        // serialize, nested structs are serialized into the buffer of the message
+        writeln!(out)?;
+        writeln!(out, "    pub fn serialize(&self, version: i16) -> Bytes {{")?;
+        writeln!(out, "        let mut b = BytesMut::new();")?;
+        writeln!(out, "        self.serialize_into(&mut b, version);")?;
+        writeln!(out, "        b.freeze()")?;
+        writeln!(out, "    }}")?;
+        writeln!(out)?;
+        if fields.is_empty() && self.flexible_condition().is_none() {
+            writeln!(
+                out,
+                "    pub fn serialize_into(&self, _b: &mut BytesMut, {}: i16) {{}}",
+                version_arg
+            )?;
+        } else {
+            writeln!(
+                out,
+                "    pub fn serialize_into(&self, b: &mut BytesMut, {}: i16) {{",
+                version_arg
+            )?;
+            for field in &fields {
+                let value = format!("self.{}", field.rust_name());
+                match field.versions.condition(&valid) {
+                    None => {
+                        self.write_stmt(&mut out, &field.field_type, &value, &field.versions, 2)?
+                    }
+                    Some(cond) => {
+                        writeln!(out, "        if {} {{", cond)?;
+                        self.write_stmt(&mut out, &field.field_type, &value, &field.versions, 3)?;
+                        writeln!(out, "        }}")?;
+                    }
+                }
+            }
+            match self.flexible_condition() {
+                Some(None) => writeln!(
+                    out,
+                    "        TaggedFields::serialize_into(b); // tag buffer"
+                )?,
+                Some(Some(cond)) => {
+                    writeln!(out, "        if {} {{", cond)?;
+                    writeln!(
+                        out,
+                        "            TaggedFields::serialize_into(b); // tag buffer"
+                    )?;
+                    writeln!(out, "        }}")?;
+                }
+                None => {}
+            }
+            writeln!(out, "    }}")?;
+        }
+        writeln!(out, "}}")?;
+
+        self.out.push_str(&out);
+        Ok(())
+    }
+
+    /// `None` if no version is flexible, `Some(None)` if all versions are flexible,
+    /// otherwise the condition selecting the flexible versions
+    fn flexible_condition(&self) -> Option<Option<String>> {
+        let flexible = self
+            .message
+            .flexible_versions
+            .intersect(&self.message.valid_versions);
+        if flexible.is_empty() {
+            return None;
+        }
+        Some(flexible.condition(&self.message.valid_versions))
+    }
+
+    fn uses_version(&self, field: &Field) -> bool {
+        field
+            .versions
+            .condition(&self.message.valid_versions)
+            .is_some()
+            || Self::type_uses_version(&field.field_type, self.flexible_condition())
+    }
+
+    fn type_uses_version(field_type: &FieldType, flexible: Option<Option<String>>) -> bool {
+        let encoding_varies = matches!(flexible, Some(Some(_)));
+        match field_type {
+            FieldType::String
+            | FieldType::NullableString
+            | FieldType::Bytes
+            | FieldType::Records => encoding_varies,
+            FieldType::Struct(_) => true,
+            FieldType::Array(item) | FieldType::NullableArray(item) => {
+                encoding_varies || Self::type_uses_version(item, flexible)
+            }
+            _ => false,
+        }
+    }
+
+    /// Chooses between compact (flexible) and classic encoding for a field present in `versions`
+    fn flexible_choice(&self, versions: &Versions, compact: String, classic: String) -> String {
+        let versions = versions.intersect(&self.message.valid_versions);
+        let flexible = self.message.flexible_versions.intersect(&versions);
+        if flexible.is_empty() {
+            return classic;
+        }
+        match flexible.condition(&versions) {
+            None => compact,
+            Some(cond) => format!("if {} {{ {} }} else {{ {} }}", cond, compact, classic),
+        }
+    }
+
+    fn read_expr(
+        &self,
+        field_type: &FieldType,
+        field: &str,
+        versions: &Versions,
+    ) -> Result<String> {
+        Ok(match field_type {
+            FieldType::Bool => format!("src.get_u8(\"{}\")? != 0", field),
+            FieldType::Int8 => format!("src.get_i8(\"{}\")?", field),
+            FieldType::Int16 => format!("src.get_i16(\"{}\")?", field),
+            FieldType::Int32 => format!("src.get_i32(\"{}\")?", field),
+            FieldType::Int64 => format!("src.get_i64(\"{}\")?", field),
+            FieldType::Uint16 => format!("src.get_u16(\"{}\")?", field),
+            FieldType::Uint32 => format!("src.get_u32(\"{}\")?", field),
+            FieldType::Float64 => format!("f64::from_bits(src.get_u64(\"{}\")?)", field),
+            FieldType::Uuid => "Uuid::deserialize(src)?".to_string(),
+            FieldType::String => self.flexible_choice(
+                versions,
+                "CompactString::deserialize(src)?".to_string(),
+                "NullableString::deserialize(src)?.unwrap_or_default()".to_string(),
+            ),
+            FieldType::NullableString => self.flexible_choice(
+                versions,
+                "CompactNullableString::deserialize(src)?".to_string(),
+                "NullableString::deserialize(src)?".to_string(),
+            ),
+            FieldType::Bytes | FieldType::Records => self.flexible_choice(
+                versions,
+                "CompactNullableBytes::deserialize(src)?".to_string(),
+                format!(
+                    "{{ let len = src.get_i32(\"{f}\")?; src.get_bytes(\"{f}\", len.max(0) as usize)?.to_vec() }}",
+                    f = field
+                ),
+            ),
+            FieldType::Struct(name) => {
+                self.check_struct(name)?;
+                format!("{}::deserialize(src, version)?", name)
+            }
+            FieldType::Array(item) => {
+                let len = self.flexible_choice(
+                    versions,
+                    format!("src.get_varint(\"{}\")? - 1", field),
+                    format!("i64::from(src.get_i32(\"{}\")?)", field),
+                );
+                let item = self.read_expr(item, field, versions)?;
+                format!(
+                    "{{ let len = {}; let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining())); for _ in 0..len {{ items.push({}); }} items }}",
+                    len, item
+                )
+            }
+            FieldType::NullableArray(item) => {
+                // a negative length is the null array
+                let len = self.flexible_choice(
+                    versions,
+                    format!("src.get_varint(\"{}\")? - 1", field),
+                    format!("i64::from(src.get_i32(\"{}\")?)", field),
+                );
+                let item = self.read_expr(item, field, versions)?;
+                format!(
+                    "{{ let len = {}; if len < 0 {{ None }} else {{ let mut items = Vec::with_capacity((len as usize).min(src.remaining())); for _ in 0..len {{ items.push({}); }} Some(items) }} }}",
+                    len, item
+                )
+            }
+        })
+    }
+
+    fn write_stmt(
+        &self,
+        out: &mut String,
+        field_type: &FieldType,
+        value: &str,
+        versions: &Versions,
+        indent: usize,
+    ) -> Result<()> {
+        let pad = "    ".repeat(indent);
+        match field_type {
+            FieldType::Bool => writeln!(out, "{}b.put_u8({}.into());", pad, value)?,
+            FieldType::Int8 => writeln!(out, "{}b.put_i8({});", pad, value)?,
+            FieldType::Int16 => writeln!(out, "{}b.put_i16({});", pad, value)?,
+            FieldType::Int32 => writeln!(out, "{}b.put_i32({});", pad, value)?,
+            FieldType::Int64 => writeln!(out, "{}b.put_i64({});", pad, value)?,
+            FieldType::Uint16 => writeln!(out, "{}b.put_u16({});", pad, value)?,
+            FieldType::Uint32 => writeln!(out, "{}b.put_u32({});", pad, value)?,
+            FieldType::Float64 => writeln!(out, "{}b.put_f64({});", pad, value)?,
+            FieldType::Uuid => writeln!(out, "{}Uuid::serialize_into({}, b);", pad, borrow(value))?,
+            FieldType::String => {
+                let stmt = self.flexible_choice(
+                    versions,
+                    format!("CompactString::serialize_into({}, b);", borrow(value)),
+                    format!(
+                        "b.put_i16({v}.len() as i16); b.put_slice({v}.as_bytes());",
+                        v = value
+                    ),
+                );
+                writeln!(out, "{}{}", pad, stmt)?
+            }
+            FieldType::NullableString => {
+                let stmt = self.flexible_choice(
+                    versions,
+                    format!(
+                        "CompactNullableString::serialize_into({}.as_deref(), b);",
+                        value
+                    ),
+                    format!("NullableString::serialize_into({}.as_deref(), b);", value),
+                );
+                writeln!(out, "{}{}", pad, stmt)?
+            }
+            FieldType::Bytes | FieldType::Records => {
+                let stmt = self.flexible_choice(
+                    versions,
+                    format!(
+                        "CompactNullableBytes::serialize_into({}, b);",
+                        borrow(value)
+                    ),
+                    format!("b.put_i32({v}.len() as i32); b.put_slice(&{v});", v = value),
+                );
+                writeln!(out, "{}{}", pad, stmt)?
+            }
+            FieldType::Struct(name) => {
+                self.check_struct(name)?;
+                writeln!(out, "{}{}.serialize_into(b, version);", pad, value)?
+            }
+            FieldType::Array(item) => {
+                let len = self.flexible_choice(
+                    versions,
+                    format!("VarInt::serialize_into({}.len() as u64 + 1, b);", value),
+                    format!("b.put_i32({}.len() as i32);", value),
+                );
+                writeln!(out, "{}{}", pad, len)?;
+                writeln!(out, "{}for item in &{} {{", pad, value)?;
+                let item_value = match item.as_ref() {
+                    FieldType::Struct(_)
+                    | FieldType::String
+                    | FieldType::NullableString
+                    | FieldType::Uuid
+                    | FieldType::Bytes
+                    | FieldType::Records
+                    | FieldType::Array(_)
+                    | FieldType::NullableArray(_) => "item".to_string(),
+                    _ => "*item".to_string(),
+                };
+                self.write_stmt(out, item, &item_value, versions, indent + 1)?;
+                writeln!(out, "{}}}", pad)?;
+            }
+            FieldType::NullableArray(item) => {
+                let null = self.flexible_choice(
+                    versions,
+                    "VarInt::serialize_into(0, b);".to_string(),
+                    "b.put_i32(-1);".to_string(),
+                );
+                writeln!(out, "{}match &{} {{", pad, value)?;
+                writeln!(out, "{}    None => {{ {} }}", pad, null)?;
+                writeln!(out, "{}    Some(items) => {{", pad)?;
+                self.write_stmt(
+                    out,
+                    &FieldType::Array(item.clone()),
+                    "items[..]",
+                    versions,
+                    indent + 2,
+                )?;
+                writeln!(out, "{}    }}", pad)?;
+                writeln!(out, "{}}}", pad)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_struct(&self, name: &str) -> Result<()> {
+        if !self.structs_known().contains_key(name) {
+            bail!("unknown struct type '{}'", name);
+        }
+        Ok(())
+    }
+
+    fn structs_known(&self) -> HashMap<&str, ()> {
+        let mut known = HashMap::new();
+        for (name, fields) in self.message.common_structs.iter() {
+            known.insert(name.as_str(), ());
+            Self::nested_struct_names(fields, &mut known);
+        }
+        Self::nested_struct_names(&self.message.fields, &mut known);
+        known
+    }
+
+    fn nested_struct_names<'f>(fields: &'f [Field], known: &mut HashMap<&'f str, ()>) {
+        for field in fields {
+            if field.fields.is_empty() {
+                continue;
+            }
+            match &field.field_type {
+                FieldType::Struct(n) => {
+                    known.insert(n.as_str(), ());
+                }
+                FieldType::Array(item) | FieldType::NullableArray(item) => {
+                    if let FieldType::Struct(n) = item.as_ref() {
+                        known.insert(n.as_str(), ());
+                    }
+                }
+                _ => {}
+            }
+            Self::nested_struct_names(&field.fields, known);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json, snake_case, Versions};
+
+    #[test]
+    fn parses_json_with_comments() {
+        let j = json::parse("// header\n{ \"a\": [1, \"x\"], // trailing\n \"b\": true }").unwrap();
+        assert_eq!(
+            j.get("a").and_then(|a| a.as_array()).map(|a| a.len()),
+            Some(2)
+        );
+        assert_eq!(j.get("b"), Some(&json::Json::Bool(true)));
+    }
+
+    #[test]
+    fn version_conditions() {
+        let valid = Versions::parse("0-4").unwrap();
+        assert_eq!(Versions::parse("0+").unwrap().condition(&valid), None);
+        assert_eq!(
+            Versions::parse("3+").unwrap().condition(&valid).as_deref(),
+            Some("version >= 3")
+        );
+        assert_eq!(
+            Versions::parse("1-2").unwrap().condition(&valid).as_deref(),
+            Some("(1..=2).contains(&version)")
+        );
+        assert!(Versions::parse("none").unwrap().is_empty());
+    }
+
+    #[test]
+    fn snake_case_names() {
+        assert_eq!(snake_case("ClientSoftwareName"), "client_software_name");
+        assert_eq!(snake_case("TopicId"), "topic_id");
+        assert_eq!(snake_case("ISRVersion"), "isr_version");
+    }
+}
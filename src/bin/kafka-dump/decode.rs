@@ -0,0 +1,325 @@
+//! Decodes hex or base64 dumps of request and response frames, e.g. copied from the logs of a tester.
+
+use std::fmt::Debug;
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+
+use kafka_starter_rust::protocol::{
+    generated::{
+        api_versions_request::ApiVersionsRequestData,
+        api_versions_response::ApiVersionsResponseData,
+        broker_heartbeat_request::BrokerHeartbeatRequestData,
+        broker_heartbeat_response::BrokerHeartbeatResponseData,
+        broker_registration_request::BrokerRegistrationRequestData,
+        broker_registration_response::BrokerRegistrationResponseData,
+        create_delegation_token_request::CreateDelegationTokenRequestData,
+        create_delegation_token_response::CreateDelegationTokenResponseData,
+        describe_configs_request::DescribeConfigsRequestData,
+        describe_configs_response::DescribeConfigsResponseData,
+        describe_delegation_token_request::DescribeDelegationTokenRequestData,
+        describe_delegation_token_response::DescribeDelegationTokenResponseData,
+        describe_log_dirs_request::DescribeLogDirsRequestData,
+        describe_log_dirs_response::DescribeLogDirsResponseData,
+        envelope_request::EnvelopeRequestData, envelope_response::EnvelopeResponseData,
+        expire_delegation_token_request::ExpireDelegationTokenRequestData,
+        expire_delegation_token_response::ExpireDelegationTokenResponseData,
+        list_offsets_request::ListOffsetsRequestData,
+        list_offsets_response::ListOffsetsResponseData, metadata_request::MetadataRequestData,
+        metadata_response::MetadataResponseData, produce_request::ProduceRequestData,
+        produce_response::ProduceResponseData,
+        renew_delegation_token_request::RenewDelegationTokenRequestData,
+        renew_delegation_token_response::RenewDelegationTokenResponseData,
+        sasl_authenticate_request::SaslAuthenticateRequestData,
+        sasl_authenticate_response::SaslAuthenticateResponseData,
+        sasl_handshake_request::SaslHandshakeRequestData,
+        sasl_handshake_response::SaslHandshakeResponseData,
+    },
+    reader::ByteReader,
+    request::{
+        describe_topic_partitions::DescribeTopicPartitionsRequestV0, fetch::FetchRequestV16,
+        RequestHeader,
+    },
+    response::{
+        describe_topic_partitions::DescribeTopicPartitionsResponseV0, fetch::FetchResponseV16,
+        ResponseHeader,
+    },
+    ApiKey, ProtocolError,
+};
+
+/// Body of a request or response, printed with its fields
+type Body = Box<dyn Debug>;
+
+/// `decode request <dump>` or `decode response <api key> <version> <dump>`
+pub fn run(args: &[String]) -> Result<()> {
+    let text = match args {
+        [kind, dump] if kind == "request" => decode_request(parse_dump(dump)?)?,
+        [kind, api_key, version, dump] if kind == "response" => {
+            let api_key = parse_api_key(api_key)?;
+            let version = version
+                .parse()
+                .with_context(|| format!("invalid version '{version}'"))?;
+            decode_response(api_key, version, parse_dump(dump)?)?
+        }
+        _ => bail!(
+            "usage: kafka-dump decode request <dump> | decode response <api key> <version> <dump>"
+        ),
+    };
+    println!("{text}");
+    Ok(())
+}
+
+/// Request frame, with or without the size prefix, with the header and the fields of the body
+pub fn decode_request(frame: Bytes) -> Result<String> {
+    let mut src = ByteReader::new(strip_size(frame));
+    let header = RequestHeader::from_bytes(&mut src).context("parse request header")?;
+    let api_key = ApiKey::try_from(header.request_api_key)
+        .with_context(|| format!("unknown API key {}", header.request_api_key))?;
+    let version = header.request_api_version;
+
+    let body: Body = match api_key {
+        // the hand-written requests keep their header
+        ApiKey::Fetch => Box::new(FetchRequestV16::from_bytes(header.clone(), &mut src)?),
+        ApiKey::DescribeTopicPartitions => Box::new(DescribeTopicPartitionsRequestV0::from_bytes(
+            header.clone(),
+            &mut src,
+        )?),
+        ApiKey::Produce => body(ProduceRequestData::deserialize, &mut src, version)?,
+        ApiKey::ListOffsets => body(ListOffsetsRequestData::deserialize, &mut src, version)?,
+        ApiKey::Metadata => body(MetadataRequestData::deserialize, &mut src, version)?,
+        ApiKey::SaslHandshake => body(SaslHandshakeRequestData::deserialize, &mut src, version)?,
+        ApiKey::ApiVersions => body(ApiVersionsRequestData::deserialize, &mut src, version)?,
+        ApiKey::CreateDelegationToken => body(
+            CreateDelegationTokenRequestData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::RenewDelegationToken => body(
+            RenewDelegationTokenRequestData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::ExpireDelegationToken => body(
+            ExpireDelegationTokenRequestData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::DescribeDelegationToken => body(
+            DescribeDelegationTokenRequestData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::DescribeLogDirs => {
+            body(DescribeLogDirsRequestData::deserialize, &mut src, version)?
+        }
+        ApiKey::DescribeConfigs => {
+            body(DescribeConfigsRequestData::deserialize, &mut src, version)?
+        }
+        ApiKey::SaslAuthenticate => {
+            body(SaslAuthenticateRequestData::deserialize, &mut src, version)?
+        }
+        ApiKey::Envelope => body(EnvelopeRequestData::deserialize, &mut src, version)?,
+        ApiKey::BrokerRegistration => body(
+            BrokerRegistrationRequestData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::BrokerHeartbeat => {
+            body(BrokerHeartbeatRequestData::deserialize, &mut src, version)?
+        }
+        api_key => bail!("no decoder for {api_key:?} requests"),
+    };
+    Ok(describe(
+        format!("{api_key:?} request v{version} {header:#?}"),
+        body,
+        &src,
+    ))
+}
+
+/// Response frame to a request of the API and version, with or without the size prefix
+pub fn decode_response(api_key: ApiKey, version: i16, frame: Bytes) -> Result<String> {
+    let mut src = ByteReader::new(strip_size(frame));
+    let header =
+        ResponseHeader::from_bytes(&mut src, api_key, version).context("parse response header")?;
+    let correlation_id = header.correlation_id();
+
+    let body: Body = match api_key {
+        ApiKey::Fetch => Box::new(FetchResponseV16::from_bytes(correlation_id, &mut src)?),
+        ApiKey::DescribeTopicPartitions => Box::new(DescribeTopicPartitionsResponseV0::from_bytes(
+            correlation_id,
+            &mut src,
+        )?),
+        ApiKey::Produce => body(ProduceResponseData::deserialize, &mut src, version)?,
+        ApiKey::ListOffsets => body(ListOffsetsResponseData::deserialize, &mut src, version)?,
+        ApiKey::Metadata => body(MetadataResponseData::deserialize, &mut src, version)?,
+        ApiKey::SaslHandshake => body(SaslHandshakeResponseData::deserialize, &mut src, version)?,
+        ApiKey::ApiVersions => body(ApiVersionsResponseData::deserialize, &mut src, version)?,
+        ApiKey::CreateDelegationToken => body(
+            CreateDelegationTokenResponseData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::RenewDelegationToken => body(
+            RenewDelegationTokenResponseData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::ExpireDelegationToken => body(
+            ExpireDelegationTokenResponseData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::DescribeDelegationToken => body(
+            DescribeDelegationTokenResponseData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::DescribeLogDirs => {
+            body(DescribeLogDirsResponseData::deserialize, &mut src, version)?
+        }
+        ApiKey::DescribeConfigs => {
+            body(DescribeConfigsResponseData::deserialize, &mut src, version)?
+        }
+        ApiKey::SaslAuthenticate => {
+            body(SaslAuthenticateResponseData::deserialize, &mut src, version)?
+        }
+        ApiKey::Envelope => body(EnvelopeResponseData::deserialize, &mut src, version)?,
+        ApiKey::BrokerRegistration => body(
+            BrokerRegistrationResponseData::deserialize,
+            &mut src,
+            version,
+        )?,
+        ApiKey::BrokerHeartbeat => {
+            body(BrokerHeartbeatResponseData::deserialize, &mut src, version)?
+        }
+        api_key => bail!("no decoder for {api_key:?} responses"),
+    };
+    Ok(describe(
+        format!("{api_key:?} response v{version} {header:#?}"),
+        body,
+        &src,
+    ))
+}
+
+fn body<T: Debug + 'static>(
+    deserialize: fn(&mut ByteReader, i16) -> Result<T, ProtocolError>,
+    src: &mut ByteReader,
+    version: i16,
+) -> Result<Body> {
+    Ok(Box::new(deserialize(src, version)?))
+}
+
+fn describe(header: String, body: Body, rest: &ByteReader) -> String {
+    let mut text = format!("{header}\n{body:#?}");
+    if rest.remaining() > 0 {
+        // e.g. a newer version than the one given, or tagged fields the structs do not know
+        text.push_str(&format!(
+            "\n{} trailing bytes: {}",
+            rest.remaining(),
+            hex::encode(rest.clone().into_bytes())
+        ));
+    }
+    text
+}
+
+/// Removes the size the frames are prefixed with on the wire, if the dump starts with it
+fn strip_size(frame: Bytes) -> Bytes {
+    match frame.get(..4) {
+        Some(&[a, b, c, d])
+            if i32::from_be_bytes([a, b, c, d]) as i64 == frame.len() as i64 - 4 =>
+        {
+            frame.slice(4..)
+        }
+        _ => frame,
+    }
+}
+
+/// API key given by its name, e.g. `Fetch`, or by its number
+fn parse_api_key(s: &str) -> Result<ApiKey> {
+    if let Ok(key) = s.parse::<i16>() {
+        return ApiKey::try_from(key).with_context(|| format!("unknown API key {key}"));
+    }
+    (0..=i16::MAX)
+        .filter_map(|key| ApiKey::try_from(key).ok())
+        .find(|key| format!("{key:?}").eq_ignore_ascii_case(s))
+        .with_context(|| format!("unknown API '{s}'"))
+}
+
+/// Hex or base64 dump, whitespace is ignored
+fn parse_dump(dump: &str) -> Result<Bytes> {
+    let dump: String = dump.chars().filter(|c| !c.is_whitespace()).collect();
+    let dump = dump.strip_prefix("0x").unwrap_or(&dump);
+    if dump.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Ok(hex::decode(dump).context("decode hex dump")?.into());
+    }
+    decode_base64(dump).map(Bytes::from)
+}
+
+fn decode_base64(s: &str) -> Result<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut bits, mut n_bits) = (0u32, 0);
+    for c in s.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => bail!(
+                "dump is neither hex nor base64, invalid character '{}'",
+                c as char
+            ),
+        };
+        bits = bits << 6 | value as u32;
+        n_bits += 6;
+        if n_bits >= 8 {
+            n_bits -= 8;
+            bytes.push((bits >> n_bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use kafka_starter_rust::protocol::{
+        encode_request, generated::api_versions_request::ApiVersionsRequestData,
+        request::RequestHeader, ApiKey,
+    };
+
+    use super::{decode_request, parse_api_key, parse_dump};
+
+    #[test]
+    fn decodes_request_dumps() {
+        let header = RequestHeader {
+            request_api_key: ApiKey::ApiVersions.into(),
+            request_api_version: 4,
+            correlation_id: 7,
+            client_id: Some("tester".to_string()),
+        };
+        let req = ApiVersionsRequestData {
+            client_software_name: "kafka-cli".to_string(),
+            client_software_version: "0.1".to_string(),
+        };
+        let frame = encode_request(&header, req.serialize(4));
+
+        let text = decode_request(parse_dump(&hex::encode(&frame)).unwrap()).unwrap();
+        assert!(text.starts_with("ApiVersions request v4"), "{text}");
+        assert!(text.contains("correlation_id: 7"), "{text}");
+        assert!(
+            text.contains("client_software_name: \"kafka-cli\""),
+            "{text}"
+        );
+        assert!(!text.contains("trailing bytes"), "{text}");
+
+        // base64 and without the size
+        assert_eq!(
+            parse_dump("AAEC/w==").unwrap(),
+            Bytes::from_static(&[0, 1, 2, 255])
+        );
+        assert_eq!(parse_api_key("fetch").unwrap(), ApiKey::Fetch);
+        assert_eq!(parse_api_key("18").unwrap(), ApiKey::ApiVersions);
+    }
+}
@@ -0,0 +1,272 @@
+//! Prints the record batches and records of log segments, like Kafka's `kafka-dump-log.sh`.
+//!
+//! Usage: `cargo run --bin kafka-dump -- <segment.log>...`
+//!
+//! Records of the metadata log are decoded as KRaft metadata records, the keys and values
+//! of the records of other logs are printed as text. Records of compressed batches are not printed.
+//!
+//! `kafka-dump decode request <dump>` and `kafka-dump decode response <api key> <version> <dump>`
+//! print the fields of a request or response frame given as a hex or base64 dump instead.
+
+mod decode;
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+
+use kafka_starter_rust::protocol::{
+    crc32c::crc32c, reader::ByteReader, record_batch::RecordBatch, ProtocolError,
+};
+
+fn main() -> Result<()> {
+    let files: Vec<String> = std::env::args().skip(1).collect();
+    if files.is_empty() {
+        bail!("usage: kafka-dump <segment.log>... | decode request|response ...");
+    }
+    if files[0] == "decode" {
+        return decode::run(&files[1..]);
+    }
+
+    for file in &files {
+        println!("Dumping {file}");
+        let log = fs::read(file).with_context(|| format!("read '{file}'"))?;
+        dump(Path::new(file), Bytes::from(log)).with_context(|| format!("dump '{file}'"))?;
+    }
+    Ok(())
+}
+
+/// Prints the batches of the segment, the metadata log is recognized by its directory
+fn dump(path: &Path, log: Bytes) -> Result<()> {
+    let metadata_log = path
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir.to_string_lossy().starts_with("__cluster_metadata"));
+
+    let size = log.len();
+    let mut src = ByteReader::new(log);
+    let mut first_batch = true;
+    while src.remaining() > 0 {
+        let position = size - src.remaining();
+        let mut peek = src.clone();
+        _ = peek.get_i64("base_offset")?;
+        let batch_length = peek.get_i32("batch_length")?;
+        if batch_length < 0 {
+            bail!("negative batch length {batch_length} at position {position}");
+        }
+        let batch = src.get_bytes("record batch", 12 + batch_length as usize)?;
+
+        let header = BatchHeader::from_bytes(&mut ByteReader::new(batch.clone()))?;
+        if first_batch {
+            println!("Log starting offset: {}", header.base_offset);
+            first_batch = false;
+        }
+        println!("{}", header.describe(position, &batch));
+
+        if header.compression() != "none" {
+            continue;
+        }
+        if metadata_log {
+            let batch = RecordBatch::from_bytes(&mut ByteReader::new(batch))
+                .context("parse metadata record batch")?;
+            for record in &batch.records {
+                println!(
+                    "| offset: {} {}: {} keySize: {} valueSize: {} payload: {:?}",
+                    header.base_offset + record.offset_delta,
+                    header.timestamp_type(),
+                    header.base_timestamp + record.timestamp_delta,
+                    record.key.as_ref().map_or(-1, |key| key.len() as i64),
+                    record.value_length,
+                    record.value,
+                );
+            }
+        } else {
+            let mut records = ByteReader::new(batch.slice(BatchHeader::SIZE..));
+            for _ in 0..header.records_count {
+                print_record(&header, &mut records)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fields of the record batch header, up to the records
+struct BatchHeader {
+    base_offset: i64,
+    batch_length: i32,
+    partition_leader_epoch: i32,
+    magic: i8,
+    crc: u32,
+    attributes: i16,
+    last_offset_delta: i32,
+    base_timestamp: i64,
+    max_timestamp: i64,
+    producer_id: i64,
+    producer_epoch: i16,
+    base_sequence: i32,
+    records_count: i32,
+}
+
+impl BatchHeader {
+    /// Size of the header, the records follow it
+    const SIZE: usize = 61;
+    /// The CRC covers the batch from the attributes to its end
+    const CRC_START: usize = 21;
+
+    // https://kafka.apache.org/documentation/#recordbatch
+    fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        Ok(Self {
+            base_offset: src.get_i64("base_offset")?,
+            batch_length: src.get_i32("batch_length")?,
+            partition_leader_epoch: src.get_i32("partition_leader_epoch")?,
+            magic: src.get_i8("magic")?,
+            crc: src.get_u32("crc")?,
+            attributes: src.get_i16("attributes")?,
+            last_offset_delta: src.get_i32("last_offset_delta")?,
+            base_timestamp: src.get_i64("base_timestamp")?,
+            max_timestamp: src.get_i64("max_timestamp")?,
+            producer_id: src.get_i64("producer_id")?,
+            producer_epoch: src.get_i16("producer_epoch")?,
+            base_sequence: src.get_i32("base_sequence")?,
+            records_count: src.get_i32("records_count")?,
+        })
+    }
+
+    fn compression(&self) -> &'static str {
+        match self.attributes & 0x07 {
+            0 => "none",
+            1 => "gzip",
+            2 => "snappy",
+            3 => "lz4",
+            4 => "zstd",
+            _ => "unknown",
+        }
+    }
+
+    fn timestamp_type(&self) -> &'static str {
+        if self.attributes & 0x08 == 0 {
+            "CreateTime"
+        } else {
+            "LogAppendTime"
+        }
+    }
+
+    /// Line of `kafka-dump-log.sh` describing the batch at the position of the segment
+    fn describe(&self, position: usize, batch: &Bytes) -> String {
+        let last_sequence = if self.base_sequence < 0 {
+            -1
+        } else {
+            self.base_sequence + self.last_offset_delta
+        };
+        format!(
+            "baseOffset: {} lastOffset: {} count: {} baseSequence: {} lastSequence: {} producerId: {} \
+             producerEpoch: {} partitionLeaderEpoch: {} isTransactional: {} isControl: {} position: {} \
+             {}: {} size: {} magic: {} compresscodec: {} crcValid: {}",
+            self.base_offset,
+            self.base_offset + i64::from(self.last_offset_delta),
+            self.records_count,
+            self.base_sequence,
+            last_sequence,
+            self.producer_id,
+            self.producer_epoch,
+            self.partition_leader_epoch,
+            self.attributes & 0x10 != 0,
+            self.attributes & 0x20 != 0,
+            position,
+            self.timestamp_type(),
+            self.max_timestamp,
+            12 + self.batch_length,
+            self.magic,
+            self.compression(),
+            crc32c(&batch[Self::CRC_START..]) == self.crc,
+        )
+    }
+}
+
+/// Prints the record of a batch that is not compressed, with its key and value as text
+fn print_record(header: &BatchHeader, src: &mut ByteReader) -> Result<()> {
+    let length = src.get_varlong("length")?;
+    let mut record = ByteReader::new(src.get_bytes("record", length.max(0) as usize)?);
+    _ = record.get_i8("attributes")?;
+    let timestamp_delta = record.get_varlong("timestamp_delta")?;
+    let offset_delta = record.get_varlong("offset_delta")?;
+    let key = nullable_bytes(&mut record, "key")?;
+    let value = nullable_bytes(&mut record, "value")?;
+    let headers_count = record.get_varlong("headers_count")?;
+    let mut header_keys = Vec::new();
+    for _ in 0..headers_count {
+        let key = nullable_bytes(&mut record, "header key")?.unwrap_or_default();
+        _ = nullable_bytes(&mut record, "header value")?;
+        header_keys.push(String::from_utf8_lossy(&key).into_owned());
+    }
+
+    let size = |bytes: &Option<Bytes>| bytes.as_ref().map_or(-1, |b| b.len() as i64);
+    let text = |bytes: &Option<Bytes>| {
+        bytes
+            .as_ref()
+            .map_or("null".into(), |b| String::from_utf8_lossy(b).into_owned())
+    };
+    let timestamp = if header.attributes & 0x08 == 0 {
+        header.base_timestamp + timestamp_delta
+    } else {
+        header.max_timestamp
+    };
+    println!(
+        "| offset: {} {}: {} keySize: {} valueSize: {} headerKeys: [{}] key: {} payload: {}",
+        header.base_offset + offset_delta,
+        header.timestamp_type(),
+        timestamp,
+        size(&key),
+        size(&value),
+        header_keys.join(","),
+        text(&key),
+        text(&value),
+    );
+    Ok(())
+}
+
+/// Bytes of a record prefixed with their length as varlong, -1 is null
+fn nullable_bytes(src: &mut ByteReader, field: &'static str) -> Result<Option<Bytes>> {
+    let len = src.get_varlong(field)?;
+    if len < 0 {
+        return Ok(None);
+    }
+    Ok(Some(src.get_bytes(field, len as usize)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+
+    use super::BatchHeader;
+    use kafka_starter_rust::protocol::{crc32c::crc32c, reader::ByteReader};
+
+    #[test]
+    fn describes_batch_header() {
+        let mut batch = BytesMut::new();
+        batch.put_i64(5); // base offset
+        batch.put_i32(49); // batch length, without records
+        batch.put_i32(1); // partition leader epoch
+        batch.put_i8(2);
+        batch.put_u32(0); // crc
+        batch.put_i16(0x01 | 0x08); // gzip, log append time
+        batch.put_i32(2); // last offset delta
+        batch.put_i64(100);
+        batch.put_i64(200);
+        batch.put_i64(7); // producer id
+        batch.put_i16(0);
+        batch.put_i32(10); // base sequence
+        batch.put_i32(3); // records count
+        let crc = crc32c(&batch[BatchHeader::CRC_START..]);
+        batch[17..21].copy_from_slice(&crc.to_be_bytes());
+        let batch = batch.freeze();
+
+        let header = BatchHeader::from_bytes(&mut ByteReader::new(batch.clone())).unwrap();
+        assert_eq!(
+            header.describe(0, &batch),
+            "baseOffset: 5 lastOffset: 7 count: 3 baseSequence: 10 lastSequence: 12 producerId: 7 \
+             producerEpoch: 0 partitionLeaderEpoch: 1 isTransactional: false isControl: false position: 0 \
+             LogAppendTime: 200 size: 61 magic: 2 compresscodec: gzip crcValid: true"
+        );
+    }
+}
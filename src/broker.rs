@@ -0,0 +1,109 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use crate::{
+    config,
+    logic::BrokerContext,
+    server,
+    storage::{FileStorage, Storage},
+};
+
+/// Broker running in the process, e.g. for integration tests that talk to it over TCP
+pub struct Broker;
+
+impl Broker {
+    /// Starts the broker in the background and returns once it accepts connections.
+    ///
+    /// Port 0 picks a free port. Without log directories the logs are written to a new temporary directory,
+    /// with an empty metadata log, which is removed on shutdown.
+    ///
+    /// The broker owns its configuration and the storage of its log directories, so brokers with other
+    /// ports and log directories can run in the same process.
+    pub async fn start(mut config: config::Config) -> Result<BrokerHandle> {
+        let listeners = server::listen(&config)?;
+        let addr = listeners[0].local_addr().context("get listener address")?;
+        config.port = addr.port();
+
+        let temp_log_dir = if config.log_dirs.is_empty() {
+            let dir = std::env::temp_dir().join(format!(
+                "kafka-starter-rust-{}-{}",
+                std::process::id(),
+                addr.port()
+            ));
+            config.log_dirs = vec![dir.clone()];
+            create_metadata_log(&config.metadata_log_file())?;
+            Some(dir)
+        } else {
+            None
+        };
+        let log_dir = config.log_dirs[0].clone();
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(FileStorage));
+        let task = tokio::spawn(server::serve(listeners, broker, async {
+            // a dropped handle shuts the broker down too
+            _ = shutdown_rx.await;
+        }));
+
+        Ok(BrokerHandle {
+            addr,
+            log_dir,
+            temp_log_dir,
+            shutdown,
+            task,
+        })
+    }
+}
+
+fn create_metadata_log(path: &Path) -> Result<()> {
+    FileStorage
+        .append(path, &[])
+        .with_context(|| format!("create {}", path.display()))
+}
+
+/// Running broker started with [`Broker::start`]
+pub struct BrokerHandle {
+    addr: SocketAddr,
+    log_dir: PathBuf,
+    temp_log_dir: Option<PathBuf>,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl BrokerHandle {
+    /// Address the clients connect to, loopback if the broker listens on all addresses
+    pub fn addr(&self) -> SocketAddr {
+        match self.addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => {
+                SocketAddr::new(Ipv4Addr::LOCALHOST.into(), self.addr.port())
+            }
+            IpAddr::V6(ip) if ip.is_unspecified() => {
+                SocketAddr::new(Ipv6Addr::LOCALHOST.into(), self.addr.port())
+            }
+            _ => self.addr,
+        }
+    }
+
+    /// First log directory, with the metadata log
+    pub fn log_dir(&self) -> &Path {
+        &self.log_dir
+    }
+
+    /// Stops accepting connections and waits for the background tasks of the broker to finish.
+    /// The temporary log directory is removed.
+    pub async fn shutdown(self) -> Result<()> {
+        _ = self.shutdown.send(());
+        let result = self.task.await.context("join broker task")?;
+        if let Some(dir) = self.temp_log_dir {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("remove log directory {}", dir.display()))?;
+        }
+        result
+    }
+}
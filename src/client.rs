@@ -0,0 +1,181 @@
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use crate::{
+    codec::{Framed, KafkaFrameCodec},
+    protocol::{
+        decode_response, encode_request,
+        generated::{
+            api_versions_request::ApiVersionsRequestData,
+            api_versions_response::ApiVersionsResponseData,
+            metadata_request::{MetadataRequestData, MetadataRequestTopic},
+            metadata_response::MetadataResponseData,
+            produce_request::ProduceRequestData,
+            produce_response::ProduceResponseData,
+        },
+        reader::ByteReader,
+        request::{
+            fetch::{FetchRequestV16, TopicRequest},
+            RequestHeader,
+        },
+        response::fetch::FetchResponseV16,
+        ApiKey, ProtocolError,
+    },
+};
+
+/// Version of the Fetch request the client sends, the only one the broker parses
+const FETCH_VERSION: i16 = 16;
+
+/// Minimal Kafka client sending one request at a time over a single connection, e.g. to test the broker.
+///
+/// The latest versions of the APIs this broker supports are used, so older brokers may reject them.
+pub struct Client {
+    framed: Framed<TcpStream>,
+    client_id: String,
+    correlation_id: i32,
+}
+
+impl Client {
+    pub async fn connect(addr: impl ToSocketAddrs, client_id: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .context("connect to broker")?;
+        stream.set_nodelay(true).context("set TCP_NODELAY")?;
+        Ok(Self {
+            framed: Framed::new(stream, KafkaFrameCodec::default()),
+            client_id: client_id.to_string(),
+            correlation_id: 0,
+        })
+    }
+
+    pub async fn api_versions(&mut self) -> Result<ApiVersionsResponseData> {
+        let version = ApiVersionsRequestData::HIGHEST_SUPPORTED_VERSION;
+        let req = ApiVersionsRequestData {
+            client_software_name: env!("CARGO_PKG_NAME").to_string(),
+            client_software_version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        let header = self.next_header(ApiKey::ApiVersions, version);
+        self.request(header, req.serialize(version))
+            .await?
+            .decode(ApiVersionsResponseData::deserialize)
+    }
+
+    /// Metadata of the topics with the names, of all topics if `None`
+    pub async fn metadata(&mut self, topics: Option<&[&str]>) -> Result<MetadataResponseData> {
+        let version = MetadataRequestData::HIGHEST_SUPPORTED_VERSION;
+        let req = MetadataRequestData {
+            topics: topics.map(|topics| {
+                topics
+                    .iter()
+                    .map(|name| MetadataRequestTopic {
+                        name: Some(name.to_string()),
+                        ..MetadataRequestTopic::default()
+                    })
+                    .collect()
+            }),
+            allow_auto_topic_creation: false,
+            ..MetadataRequestData::default()
+        };
+        let header = self.next_header(ApiKey::Metadata, version);
+        self.request(header, req.serialize(version))
+            .await?
+            .decode(MetadataResponseData::deserialize)
+    }
+
+    /// Produces the records, `None` if the request has acks 0, as the broker does not answer then
+    pub async fn produce(
+        &mut self,
+        req: ProduceRequestData,
+    ) -> Result<Option<ProduceResponseData>> {
+        let version = ProduceRequestData::HIGHEST_SUPPORTED_VERSION;
+        let header = self.next_header(ApiKey::Produce, version);
+        if req.acks == 0 {
+            self.write(&header, req.serialize(version)).await?;
+            return Ok(None);
+        }
+        self.request(header, req.serialize(version))
+            .await?
+            .decode(ProduceResponseData::deserialize)
+            .map(Some)
+    }
+
+    /// Fetches the partitions of the topics without a fetch session, waiting up to 500 ms for at least one byte
+    pub async fn fetch(&mut self, topics: Vec<TopicRequest>) -> Result<FetchResponseV16> {
+        let mut req = FetchRequestV16 {
+            header: self.next_header(ApiKey::Fetch, FETCH_VERSION),
+            max_wait_ms: 500,
+            min_bytes: 1,
+            max_bytes: 50 * 1024 * 1024, // Kafka's default `fetch.max.bytes`
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: -1,
+            topics,
+            forgotten_topics_data: Vec::new(),
+            rack_id: String::new(),
+        };
+        let body = req.serialize();
+        let correlation_id = req.header.correlation_id;
+        self.request(req.header, body)
+            .await?
+            .decode(|src, _| FetchResponseV16::from_bytes(correlation_id, src))
+    }
+
+    fn next_header(&mut self, api_key: ApiKey, version: i16) -> RequestHeader {
+        self.correlation_id += 1;
+        RequestHeader {
+            request_api_key: api_key.into(),
+            request_api_version: version,
+            correlation_id: self.correlation_id,
+            client_id: Some(self.client_id.clone()),
+        }
+    }
+
+    async fn write(&mut self, header: &RequestHeader, body: Bytes) -> Result<()> {
+        self.framed
+            .send(&encode_request(header, body))
+            .await
+            .context("write request")?;
+        self.framed.flush().await.context("write request")
+    }
+
+    /// Sends the request and reads its response
+    async fn request(&mut self, header: RequestHeader, body: Bytes) -> Result<Raw> {
+        self.write(&header, body).await?;
+        let Some(msg) = self.framed.next_frame().await.context("read response")? else {
+            bail!("broker closed the connection");
+        };
+        Ok(Raw {
+            msg,
+            api_key: ApiKey::try_from(header.request_api_key).context("unknown API key")?,
+            version: header.request_api_version,
+            correlation_id: header.correlation_id,
+        })
+    }
+}
+
+/// Response message that is not decoded yet
+struct Raw {
+    msg: Bytes,
+    api_key: ApiKey,
+    version: i16,
+    correlation_id: i32,
+}
+
+impl Raw {
+    fn decode<T>(
+        self,
+        decode: impl FnOnce(&mut ByteReader, i16) -> Result<T, ProtocolError>,
+    ) -> Result<T> {
+        let (header, body) = decode_response(self.msg, self.api_key, self.version, decode)
+            .with_context(|| format!("decode {:?} response", self.api_key))?;
+        if header.correlation_id() != self.correlation_id {
+            bail!(
+                "response to request {} instead of {}",
+                header.correlation_id(),
+                self.correlation_id
+            );
+        }
+        Ok(body)
+    }
+}
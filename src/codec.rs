@@ -0,0 +1,596 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    future::Future,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter, ReadHalf, WriteHalf},
+    time::Instant,
+};
+
+use crate::{config::TraceWire, protocol::ApiKey};
+
+/// Default maximum size of a request, the same as Kafka's `socket.request.max.bytes`
+pub const MAX_FRAME_SIZE: usize = 100 * 1024 * 1024;
+
+/// Space a connection reads into at least, so that small requests sent one after another are read together
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Splits the byte stream into size-prefixed request messages.
+///
+/// Every message starts with its size as an INT32 which is followed by the message itself.
+// https://kafka.apache.org/protocol.html#protocol_common
+#[derive(Debug, Clone)]
+pub struct KafkaFrameCodec {
+    max_frame_size: usize,
+}
+
+impl Default for KafkaFrameCodec {
+    fn default() -> Self {
+        Self::new(MAX_FRAME_SIZE)
+    }
+}
+
+impl KafkaFrameCodec {
+    pub fn new(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+
+    /// Splits the next message without the size prefix off the buffer.
+    /// Returns `None` if the buffer does not contain the whole message yet.
+    ///
+    /// The size is checked before any space is reserved for the message: a negative or zero size cannot be
+    /// a message, as every one has at least a header, and sizes above the maximum are rejected too.
+    pub fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, FrameError> {
+        let Some(size) = src.first_chunk::<4>() else {
+            src.reserve(4 - src.len());
+            return Ok(None);
+        };
+
+        let size = i32::from_be_bytes(*size);
+        if size < 0 {
+            return Err(FrameError::NegativeSize(size));
+        }
+        if size == 0 {
+            return Err(FrameError::Empty);
+        }
+        let size = size as usize;
+        if size > self.max_frame_size {
+            return Err(FrameError::TooLarge {
+                size,
+                max: self.max_frame_size,
+            });
+        }
+
+        if src.len() < 4 + size {
+            // make room for the rest of the message
+            src.reserve(4 + size - src.len());
+            return Ok(None);
+        }
+
+        src.advance(4);
+        Ok(Some(src.split_to(size).freeze()))
+    }
+
+    /// Like `decode`, called when the peer closed the connection.
+    /// Fails if the buffer ends in the middle of a message.
+    pub fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, FrameError> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(FrameError::UnexpectedEof {
+                buffered: src.len(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FrameError {
+    #[error("negative message size {0}")]
+    NegativeSize(i32),
+    #[error("empty message without a header")]
+    Empty,
+    #[error("message size {size} exceeds maximum {max}")]
+    TooLarge { size: usize, max: usize },
+    #[error("connection closed in the middle of a message, {buffered} bytes received")]
+    UnexpectedEof { buffered: usize },
+    #[error("rest of the message not received in {} ms, {buffered} bytes received", timeout.as_millis())]
+    ReadTimeout { timeout: Duration, buffered: usize },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Connection reading request messages with `KafkaFrameCodec` and writing response messages.
+///
+/// The stream is split into a [`FrameReader`] and a [`FrameWriter`], which can be used by different tasks
+/// with [`Framed::into_split`].
+pub struct Framed<S> {
+    reader: FrameReader<ReadHalf<S>>,
+    writer: FrameWriter<WriteHalf<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite> Framed<S> {
+    pub fn new(stream: S, codec: KafkaFrameCodec) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self {
+            reader: FrameReader::new(reader, codec),
+            writer: FrameWriter::new(writer),
+        }
+    }
+
+    /// Logs the frames read and written, see [`WireTrace`]
+    pub fn trace(&mut self, trace: WireTrace) {
+        let trace = Arc::new(Mutex::new(trace));
+        self.reader.trace = Some(trace.clone());
+        self.writer.trace = Some(trace);
+    }
+
+    /// Bounds the time to read the rest of a message once it started, and to write a message,
+    /// see [`FrameReader::next_frame`] and [`FrameWriter::send`]
+    pub fn timeouts(&mut self, read: Option<Duration>, write: Option<Duration>) {
+        self.reader.read_timeout = read;
+        self.writer.write_timeout = write;
+    }
+
+    /// Reads the next request message, `None` when the peer closed the connection
+    pub async fn next_frame(&mut self) -> Result<Option<Bytes>, FrameError> {
+        self.reader.next_frame().await
+    }
+
+    /// See [`FrameWriter::send`]
+    pub async fn send(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        self.writer.send(msg).await
+    }
+
+    /// See [`FrameWriter::flush`]
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush().await
+    }
+
+    pub fn into_split(self) -> (FrameReader<ReadHalf<S>>, FrameWriter<WriteHalf<S>>) {
+        (self.reader, self.writer)
+    }
+}
+
+/// Reading half of a [`Framed`] connection.
+///
+/// The messages are read into one buffer of the connection and split off it without copying. Once the
+/// messages read before are dropped, their space is reused for the next ones instead of allocating a buffer
+/// per message.
+pub struct FrameReader<R> {
+    stream: R,
+    codec: KafkaFrameCodec,
+    buf: BytesMut,
+    trace: Option<Arc<Mutex<WireTrace>>>,
+    read_timeout: Option<Duration>,
+    /// The rest of the message being read has to be received by then, kept when `next_frame` is cancelled
+    deadline: Option<Instant>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(stream: R, codec: KafkaFrameCodec) -> Self {
+        Self {
+            stream,
+            codec,
+            buf: BytesMut::with_capacity(READ_BUFFER_SIZE),
+            trace: None,
+            read_timeout: None,
+            deadline: None,
+        }
+    }
+
+    /// Reads the next request message, `None` when the peer closed the connection.
+    ///
+    /// The wait for the next message is unbounded, but once a part of it is received the rest has to follow
+    /// within the read timeout. Otherwise a client that stopped in the middle of a message, e.g. after
+    /// the size prefix, would keep the connection open for good.
+    pub async fn next_frame(&mut self) -> Result<Option<Bytes>, FrameError> {
+        loop {
+            if let Some(frame) = self.codec.decode(&mut self.buf)? {
+                self.deadline = None;
+                if let Some(trace) = &self.trace {
+                    eprintln!("{}", lock(trace).request(&frame));
+                }
+                return Ok(Some(frame));
+            }
+            let deadline = match self.read_timeout {
+                Some(timeout) if !self.buf.is_empty() => Some(
+                    *self
+                        .deadline
+                        .get_or_insert_with(|| Instant::now() + timeout),
+                ),
+                _ => None,
+            };
+            // `decode` reserved the space for the missing bytes, so 0 means end of stream.
+            // Reserving reclaims the space of the dropped messages, it allocates only if they are still in use.
+            self.buf.reserve(READ_BUFFER_SIZE);
+            let read = self.stream.read_buf(&mut self.buf);
+            let n = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, read).await {
+                    Ok(n) => n?,
+                    Err(_) => {
+                        return Err(FrameError::ReadTimeout {
+                            timeout: self.read_timeout.unwrap_or_default(),
+                            buffered: self.buf.len(),
+                        })
+                    }
+                },
+                None => read.await?,
+            };
+            if n == 0 {
+                return self.codec.decode_eof(&mut self.buf);
+            }
+        }
+    }
+}
+
+/// Writing half of a [`Framed`] connection.
+///
+/// The messages written are buffered until [`FrameWriter::flush`], so that the responses to pipelined requests
+/// are written together.
+pub struct FrameWriter<W> {
+    stream: BufWriter<W>,
+    trace: Option<Arc<Mutex<WireTrace>>>,
+    write_timeout: Option<Duration>,
+}
+
+impl<W: AsyncWrite + Unpin> FrameWriter<W> {
+    pub fn new(stream: W) -> Self {
+        Self {
+            stream: BufWriter::new(stream),
+            trace: None,
+            write_timeout: None,
+        }
+    }
+
+    /// Writes the message into the write buffer, the message already contains its size.
+    /// Messages larger than the buffer are written to the stream right away.
+    ///
+    /// Writing fails with `TimedOut` after the write timeout, e.g. when the client stopped reading
+    /// and the socket buffers are full.
+    pub async fn send(&mut self, msg: &[u8]) -> std::io::Result<()> {
+        if let Some(trace) = &self.trace {
+            eprintln!("{}", lock(trace).response(msg));
+        }
+        with_timeout(self.write_timeout, self.stream.write_all(msg)).await
+    }
+
+    /// Writes the buffered messages to the stream, within the write timeout as `send`
+    pub async fn flush(&mut self) -> std::io::Result<()> {
+        with_timeout(self.write_timeout, self.stream.flush()).await
+    }
+}
+
+async fn with_timeout(
+    timeout: Option<Duration>,
+    write: impl Future<Output = io::Result<()>>,
+) -> io::Result<()> {
+    let Some(timeout) = timeout else {
+        return write.await;
+    };
+    tokio::time::timeout(timeout, write)
+        .await
+        .unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("message not written in {} ms", timeout.as_millis()),
+            ))
+        })
+}
+
+fn lock(trace: &Mutex<WireTrace>) -> MutexGuard<'_, WireTrace> {
+    trace.lock().expect("wire trace is not poisoned")
+}
+
+/// Describes the request frames of a connection and the response frames written to them, for `--trace-wire`.
+///
+/// A response only has the correlation id of its request, so the API key and version of the requests are kept
+/// until they are answered. The trace is shared by the reader and the writer of the connection.
+pub struct WireTrace {
+    peer: String,
+    mode: TraceWire,
+    /// API key, version and correlation id of the requests waiting for their response, in request order
+    requests: VecDeque<(i16, i16, i32)>,
+}
+
+impl WireTrace {
+    pub fn new(peer: impl ToString, mode: TraceWire) -> Self {
+        Self {
+            peer: peer.to_string(),
+            mode,
+            requests: VecDeque::new(),
+        }
+    }
+
+    /// Request message without the size prefix
+    fn request(&mut self, msg: &[u8]) -> String {
+        // every request header starts with the API key, version and correlation id
+        let Some(header) = msg.first_chunk::<8>() else {
+            return format!(
+                "wire {} <- request too short for a header, {} bytes",
+                self.peer,
+                4 + msg.len()
+            );
+        };
+        let api_key = i16::from_be_bytes([header[0], header[1]]);
+        let version = i16::from_be_bytes([header[2], header[3]]);
+        let correlation_id = i32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        self.requests.push_back((api_key, version, correlation_id));
+        self.describe("<-", Some((api_key, version)), correlation_id, msg)
+    }
+
+    /// Response message with the size prefix
+    fn response(&mut self, msg: &[u8]) -> String {
+        let Some(&[a, b, c, d]) = msg.get(4..8) else {
+            return format!(
+                "wire {} -> response too short for a header, {} bytes",
+                self.peer,
+                msg.len()
+            );
+        };
+        let correlation_id = i32::from_be_bytes([a, b, c, d]);
+        // responses are written in request order, requests without a response (Produce with acks 0) are skipped
+        let mut request = None;
+        while let Some((api_key, version, id)) = self.requests.pop_front() {
+            if id == correlation_id {
+                request = Some((api_key, version));
+                break;
+            }
+        }
+        self.describe("->", request, correlation_id, &msg[4..])
+    }
+
+    fn describe(
+        &self,
+        direction: &str,
+        request: Option<(i16, i16)>,
+        correlation_id: i32,
+        msg: &[u8],
+    ) -> String {
+        let api = match request {
+            Some((api_key, version)) => match ApiKey::try_from(api_key) {
+                Ok(api_key) => format!("{api_key:?} v{version}"),
+                Err(_) => format!("api key {api_key} v{version}"),
+            },
+            None => "unknown request".to_string(),
+        };
+        let mut line = format!(
+            "wire {} {direction} {api} correlation_id={correlation_id} size={}",
+            self.peer,
+            4 + msg.len()
+        );
+        if self.mode == TraceWire::Hexdump {
+            line.push_str(&hexdump(msg));
+        }
+        line
+    }
+}
+
+/// Lines with 16 bytes in hex and as ASCII, like `hexdump -C`, of the message without the size prefix
+fn hexdump(msg: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, chunk) in msg.chunks(16).enumerate() {
+        let hex: Vec<_> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        _ = write!(dump, "\n  {:08x}  {:<47}  |{ascii}|", i * 16, hex.join(" "));
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use bytes::{BufMut, BytesMut};
+    use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+
+    use super::{FrameError, FrameReader, Framed, KafkaFrameCodec, WireTrace, READ_BUFFER_SIZE};
+    use crate::config::TraceWire;
+
+    /// Stream returning as much of the data as is asked for, counting the reads
+    struct Counted {
+        data: BytesMut,
+        reads: usize,
+    }
+
+    impl AsyncRead for Counted {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            self.reads += 1;
+            let n = buf.remaining().min(self.data.len());
+            buf.put_slice(&self.data.split_to(n));
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_the_read_buffer() {
+        // 10 000 messages of 100 bytes, about 1 MB
+        let mut data = BytesMut::new();
+        for i in 0..10_000u32 {
+            data.put_i32(100);
+            data.put_u32(i);
+            data.put_bytes(0, 96);
+        }
+        let stream = Counted { data, reads: 0 };
+        let mut reader = FrameReader::new(stream, KafkaFrameCodec::default());
+
+        let mut count = 0u32;
+        while let Some(frame) = reader.next_frame().await.unwrap() {
+            assert_eq!(frame[..4], count.to_be_bytes());
+            count += 1;
+            assert!(reader.buf.capacity() <= 2 * READ_BUFFER_SIZE);
+        }
+        assert_eq!(count, 10_000);
+        // the messages are read many at a time, not a size prefix and its message by one read each
+        assert!(reader.stream.reads < 10_000 * 104 / READ_BUFFER_SIZE * 2);
+    }
+
+    #[test]
+    fn decodes_partial_frames() {
+        let mut codec = KafkaFrameCodec::default();
+        let mut buf = BytesMut::new();
+
+        buf.put_slice(&[0, 0]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.put_slice(&[0, 3, 1, 2]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        buf.put_slice(&[3, 0, 0, 0, 1, 9]);
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap().unwrap().as_ref(),
+            &[1, 2, 3]
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap().as_ref(), &[9]);
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        let mut codec = KafkaFrameCodec::new(16);
+
+        let mut buf = BytesMut::from(&[0xFF, 0xFF, 0xFF, 0xFF][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(FrameError::NegativeSize(-1))
+        ));
+
+        let mut buf = BytesMut::from(&[0x80, 0, 0, 0][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(FrameError::NegativeSize(i32::MIN))
+        ));
+        assert_eq!(buf.capacity(), 4);
+
+        let mut buf = BytesMut::from(&[0, 0, 0, 0, 0, 0, 0, 1, 9][..]);
+        assert!(matches!(codec.decode(&mut buf), Err(FrameError::Empty)));
+
+        let mut buf = BytesMut::from(&[0, 0, 0, 17][..]);
+        assert!(matches!(
+            codec.decode(&mut buf),
+            Err(FrameError::TooLarge { size: 17, max: 16 })
+        ));
+    }
+
+    #[test]
+    fn eof_in_the_middle_of_frame() {
+        let mut codec = KafkaFrameCodec::default();
+        let mut buf = BytesMut::from(&[0, 0, 0, 5, 1][..]);
+        assert!(matches!(
+            codec.decode_eof(&mut buf),
+            Err(FrameError::UnexpectedEof { buffered: 5 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn reads_size_prefixes_split_across_reads() {
+        let read_all = |chunks: &'static [&'static [u8]]| async move {
+            let (mut peer, stream) = tokio::io::duplex(64);
+            let writer = tokio::spawn(async move {
+                for chunk in chunks {
+                    peer.write_all(chunk).await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            });
+            let mut reader = FrameReader::new(stream, KafkaFrameCodec::default());
+            let mut frames = Vec::new();
+            let result = loop {
+                match reader.next_frame().await {
+                    Ok(Some(frame)) => frames.push(frame),
+                    Ok(None) => break Ok(()),
+                    Err(err) => break Err(err),
+                }
+            };
+            writer.await.unwrap();
+            (frames, result)
+        };
+
+        let (frames, result) = read_all(&[&[0, 0], &[0], &[1, 7, 0, 0, 0], &[1, 8]]).await;
+        assert_eq!(frames, [&[7][..], &[8][..]]);
+        assert!(result.is_ok());
+
+        // closed after a part of the size prefix
+        let (frames, result) = read_all(&[&[0, 0, 0, 1, 7], &[0, 0]]).await;
+        assert_eq!(frames, [&[7][..]]);
+        assert!(matches!(
+            result,
+            Err(FrameError::UnexpectedEof { buffered: 2 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn times_out_in_the_middle_of_messages() {
+        let timeout = Duration::from_millis(50);
+        let (mut peer, stream) = tokio::io::duplex(16);
+        let mut framed = Framed::new(stream, KafkaFrameCodec::default());
+        framed.timeouts(Some(timeout), Some(timeout));
+
+        // the wait for the next message is not bounded
+        assert!(tokio::time::timeout(3 * timeout, framed.next_frame())
+            .await
+            .is_err());
+
+        // the client sent only the size of the message
+        peer.write_all(&[0, 0, 0, 5]).await.unwrap();
+        assert!(matches!(
+            framed.next_frame().await,
+            Err(FrameError::ReadTimeout { buffered: 4, .. })
+        ));
+
+        // the client does not read the response
+        let written = async {
+            framed.send(&[0; 64]).await?;
+            framed.flush().await
+        };
+        assert_eq!(written.await.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn traces_requests_and_their_responses() {
+        let mut trace = WireTrace::new("127.0.0.1:50000", TraceWire::Hexdump);
+        // Produce v9 with acks 0 without a response, then Metadata v12
+        let produce = [0, 0, 0, 9, 0, 0, 0, 1];
+        let metadata = [0, 3, 0, 12, 0, 0, 0, 2, b'i', b'd'];
+
+        assert_eq!(
+            trace.request(&produce).lines().next().unwrap(),
+            "wire 127.0.0.1:50000 <- Produce v9 correlation_id=1 size=12"
+        );
+        let request = trace.request(&metadata);
+        assert_eq!(
+            request.lines().nth(1).unwrap(),
+            "  00000000  00 03 00 0c 00 00 00 02 69 64                    |........id|"
+        );
+
+        let response = trace.response(&[0, 0, 0, 5, 0, 0, 0, 2, 0]);
+        assert_eq!(
+            response.lines().next().unwrap(),
+            "wire 127.0.0.1:50000 -> Metadata v12 correlation_id=2 size=9"
+        );
+        assert!(trace.requests.is_empty());
+    }
+}
@@ -0,0 +1,916 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::storage::Storage;
+
+pub use crate::logic::authorizer::Acl;
+
+const USAGE: &str = "\
+Usage: kafka-starter-rust [OPTIONS] [SERVER_PROPERTIES]
+
+Options:
+      --bind <ADDRS>    Comma-separated addresses to listen on, e.g. 127.0.0.1,::1 for both loopbacks,
+                        the first one is advertised to the clients. :: accepts IPv4 connections too
+                        where the OS maps them, e.g. on Linux, 0.0.0.0 next to it is not bound
+                        [default: 127.0.0.1]
+      --port <PORT>     Port to listen on [default: 9092]
+      --node-id <ID>    Id of this broker, Fetch of partitions led by other brokers is answered with
+                        NOT_LEADER_OR_FOLLOWER [default: lead all partitions]
+      --broker-rack <RACK>
+                        Rack of this broker, shown to clients in the Metadata response [default: none]
+      --log-dir <DIRS>  Comma-separated directories with the topic logs, the metadata log
+                        is in the first one [default: /tmp/kraft-combined-logs]
+      --metadata-log-dir <DIR>
+                        Directory of the metadata log [default: the first log directory]
+      --connections-max-idle-ms <MS>
+                        Close connections idle for this long [default: 600000]
+      --request-timeout-ms <MS>
+                        Answer requests not processed in this time with REQUEST_TIMED_OUT [default: 30000]
+      --socket-read-timeout-ms <MS>
+                        Close connections whose client sent a part of a request but not the rest
+                        of it in this time, 0 to wait forever [default: 30000]
+      --socket-write-timeout-ms <MS>
+                        Close connections whose client does not read its responses, so that they
+                        cannot be written in this time, 0 to wait forever [default: 30000]
+      --max-in-flight-requests <N>
+                        Stop reading requests of a connection while N are being processed [default: 5]
+      --tcp-nodelay <BOOL>
+                        Disable Nagle's algorithm on accepted connections [default: true]
+      --tcp-keepalive <BOOL>
+                        Enable TCP keepalive on accepted connections [default: true]
+      --socket-send-buffer-bytes <BYTES>
+                        SO_SNDBUF of the sockets, -1 for the OS default [default: 102400]
+      --socket-receive-buffer-bytes <BYTES>
+                        SO_RCVBUF of the sockets, -1 for the OS default [default: 102400]
+      --acl <PRINCIPAL,OPERATION,RESOURCE_TYPE,NAME>
+                        Allow the principal the operation, e.g. User:alice,Read,Topic,payments.
+                        Resources without ACLs are accessible by everyone [repeatable]
+      --sasl-plain-user <USER:PASSWORD>
+                        Require clients to authenticate with SASL/PLAIN as one of these users,
+                        without users the listener is PLAINTEXT [repeatable]
+      --connections-max-reauth-ms <MS>
+                        Close SASL connections that do not reauthenticate within this time,
+                        0 to never expire the sessions [default: 0]
+      --delegation-token-secret-key <KEY>
+                        Secret key of the delegation token HMACs, tokens are disabled without it.
+                        Clients log in with a token over SASL/PLAIN with authorization id
+                        tokenauth, the token id as username and the hex HMAC as password
+      --delegation-token-max-lifetime-ms <MS>
+                        Maximum lifetime of delegation tokens [default: 604800000]
+      --delegation-token-expiry-time-ms <MS>
+                        Renewal period of delegation tokens [default: 86400000]
+      --broker-session-timeout-ms <MS>
+                        Fence registered brokers without a heartbeat for this long [default: 9000]
+      --replica-selector-class <CLASS>
+                        RackAwareReplicaSelector lets consumers with a rack id fetch from
+                        a replica in their rack instead of the leader [default: LeaderSelector]
+      --metrics-port <PORT>
+                        Serve Prometheus metrics on http://<bind>:<PORT>/metrics [default: disabled]
+      --admin-port <PORT>
+                        Serve JSON views of the topics, partitions and connections for debugging
+                        on http://<bind>:<PORT>/ [default: disabled]
+      --message-max-bytes <BYTES>
+                        Largest record batch a producer may append, topics may override it with
+                        max.message.bytes [default: 1048588]
+      --log-message-timestamp-type <TYPE>
+                        CreateTime keeps the timestamps of the producers, LogAppendTime sets them
+                        to the time of the append [default: CreateTime]
+      --log-message-timestamp-difference-max-ms <MS>
+                        Reject records with CreateTime timestamps this far from the broker time
+                        with INVALID_TIMESTAMP [default: unlimited]
+      --compression-type <TYPE>
+                        Compression of the appended batches, gzip and uncompressed recompress the
+                        batches of other codecs, topics may override it with compression.type,
+                        producer keeps the producer's one [default: producer]
+      --max-incremental-fetch-session-cache-slots <SESSIONS>
+                        Fetch sessions kept for incremental fetches, 0 disables them [default: 1000]
+      --fetch-compression-type <TYPE>
+                        gzip compresses the uncompressed batches of the Fetch responses, for
+                        slow links to the consumers, none sends them as they are [default: none]
+      --quota-byte-rate <BYTES>
+                        Bytes per second a client id may send and receive [default: unlimited]
+      --quota-request-rate <REQUESTS>
+                        Requests per second a client id may send [default: unlimited]
+      --trace-wire[=hex]
+                        Log the API key, version, correlation id and size of every request and
+                        response frame, with hex also their hexdump [default: disabled]
+      --replay <FILE>   Process the request frames of the file instead of listening, a capture of
+                        the frames with their size prefixes or a --trace-wire=hex log
+      --replay-output <FILE>
+                        Write the response frames of the replayed requests to this file
+                        [default: <FILE>.responses]
+  -h, --help            Print help
+
+The server.properties file passed by the tester is accepted and ignored.
+
+Environment variables override the options:
+  KAFKA_LISTENERS       Listeners to bind, e.g. PLAINTEXT://0.0.0.0:9092 (only the first one and
+                        the ones on its port, e.g. PLAINTEXT6://[::1]:9092, are used)
+  KAFKA_LOG_DIRS        Comma-separated directories with the topic logs
+  KAFKA_METADATA_LOG_DIR
+  KAFKA_CONNECTIONS_MAX_IDLE_MS
+  KAFKA_METRICS_PORT";
+
+/// Broker configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Addresses listened on, all on `port`. The first one is advertised in the Metadata responses
+    /// and serves the metrics and admin endpoints.
+    pub bind: Vec<IpAddr>,
+    pub port: u16,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_node.id, a single broker leading all partitions
+    /// if `None`
+    pub node_id: Option<u32>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_broker.rack
+    pub broker_rack: Option<String>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_log.dirs
+    pub log_dirs: Vec<PathBuf>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_metadata.log.dir, the first log directory
+    /// if `None`
+    pub metadata_log_dir: Option<PathBuf>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_connections.max.idle.ms
+    pub connections_max_idle: Duration,
+    /// Requests taking longer are answered with REQUEST_TIMED_OUT error
+    pub request_timeout: Duration,
+    /// Time from the first byte of a request to its last one, waiting for the request is unbounded if `None`
+    pub socket_read_timeout: Option<Duration>,
+    /// Time a response may take to be written to the socket, unbounded if `None`
+    pub socket_write_timeout: Option<Duration>,
+    /// Requests of one connection processed at the same time, further requests are left in the socket
+    pub max_in_flight_requests: usize,
+    /// TCP_NODELAY of accepted connections
+    pub tcp_nodelay: bool,
+    /// SO_KEEPALIVE of accepted connections
+    pub tcp_keepalive: bool,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_socket.send.buffer.bytes
+    pub socket_send_buffer_bytes: Option<u32>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_socket.receive.buffer.bytes
+    pub socket_receive_buffer_bytes: Option<u32>,
+    /// ACLs of the authorizer
+    pub acls: Vec<Acl>,
+    /// Usernames and passwords of the SASL/PLAIN users, authentication is not required if there are none
+    pub sasl_plain_users: Vec<(String, String)>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_connections.max.reauth.ms, sessions do not expire
+    /// if `None`
+    pub connections_max_reauth: Option<Duration>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_delegation.token.secret.key
+    pub delegation_token_secret_key: Option<String>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_delegation.token.max.lifetime.ms
+    pub delegation_token_max_lifetime: Duration,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_delegation.token.expiry.time.ms
+    pub delegation_token_expiry_time: Duration,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_broker.session.timeout.ms
+    pub broker_session_timeout: Duration,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_message.max.bytes
+    pub message_max_bytes: usize,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_log.message.timestamp.type
+    pub log_message_timestamp_type: TimestampType,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_log.message.timestamp.difference.max.ms,
+    /// unlimited if `None`
+    pub log_message_timestamp_difference_max: Option<Duration>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_replica.selector.class
+    pub replica_selector: ReplicaSelector,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_compression.type, the producer's compression
+    /// if `None`
+    pub compression_type: Option<CompressionType>,
+    /// https://kafka.apache.org/documentation/#brokerconfigs_max.incremental.fetch.session.cache.slots
+    pub max_incremental_fetch_session_cache_slots: usize,
+    /// Compression of the batches sent in the Fetch responses that are not compressed in the log
+    pub fetch_compression_type: CompressionType,
+    /// Port of the HTTP endpoint with Prometheus metrics, disabled if `None`
+    pub metrics_port: Option<u16>,
+    /// Port of the HTTP endpoint with JSON views of the broker state, disabled if `None`
+    pub admin_port: Option<u16>,
+    /// Per client id byte rate quota (bytes per second), unlimited if `None`
+    pub quota_byte_rate: Option<u64>,
+    /// Per client id request rate quota (requests per second), unlimited if `None`
+    pub quota_request_rate: Option<u64>,
+    /// Request and response frames are logged if set
+    pub trace_wire: Option<TraceWire>,
+    /// Requests processed instead of listening, see [`crate::replay`]
+    pub replay: Option<PathBuf>,
+    /// File the responses of the replayed requests are written to, `<replay>.responses` if `None`
+    pub replay_output: Option<PathBuf>,
+}
+
+/// What is logged of the frames with `--trace-wire`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceWire {
+    /// API key, version, correlation id and size
+    Headers,
+    /// The headers and a hexdump of the whole frame
+    Hexdump,
+}
+
+/// Which time the timestamps of the appended records are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampType {
+    /// Set by the producer when the record was created
+    CreateTime,
+    /// Set by the broker when the record was appended to the log
+    LogAppendTime,
+}
+
+impl FromStr for TimestampType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CreateTime" => Ok(Self::CreateTime),
+            "LogAppendTime" => Ok(Self::LogAppendTime),
+            _ => bail!("invalid timestamp type `{s}`"),
+        }
+    }
+}
+
+/// Compression codec of record batches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionType {
+    None,
+    Gzip,
+}
+
+impl FromStr for CompressionType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" | "uncompressed" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            _ => bail!("invalid compression type `{s}`, only none and gzip are supported"),
+        }
+    }
+}
+
+impl CompressionType {
+    /// Compression of a `compression.type` config, `None` for the producer's compression
+    pub fn from_config(s: &str) -> Result<Option<Self>> {
+        match s {
+            "producer" => Ok(None),
+            _ => s.parse().map(Some),
+        }
+    }
+}
+
+/// Replica the consumers are asked to fetch from, as the selectors of Kafka's `org.apache.kafka.common.replica`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicaSelector {
+    /// Consumers fetch from the leader
+    Leader,
+    /// Consumers with a rack id fetch from an in-sync replica in their rack, if the leader is not in it
+    RackAware,
+}
+
+impl FromStr for ReplicaSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // the class name, with or without its package
+        match s
+            .strip_prefix("org.apache.kafka.common.replica.")
+            .unwrap_or(s)
+        {
+            "LeaderSelector" => Ok(Self::Leader),
+            "RackAwareReplicaSelector" => Ok(Self::RackAware),
+            _ => bail!("invalid replica selector `{s}`"),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            port: 9092,
+            node_id: None,
+            broker_rack: None,
+            log_dirs: vec![PathBuf::from("/tmp/kraft-combined-logs")],
+            metadata_log_dir: None,
+            connections_max_idle: Duration::from_millis(600_000),
+            request_timeout: Duration::from_millis(30_000),
+            socket_read_timeout: Some(Duration::from_millis(30_000)),
+            socket_write_timeout: Some(Duration::from_millis(30_000)),
+            max_in_flight_requests: 5,
+            tcp_nodelay: true,
+            tcp_keepalive: true,
+            socket_send_buffer_bytes: Some(102_400),
+            socket_receive_buffer_bytes: Some(102_400),
+            acls: Vec::new(),
+            sasl_plain_users: Vec::new(),
+            connections_max_reauth: None,
+            delegation_token_secret_key: None,
+            delegation_token_max_lifetime: Duration::from_millis(604_800_000),
+            delegation_token_expiry_time: Duration::from_millis(86_400_000),
+            broker_session_timeout: Duration::from_millis(9_000),
+            message_max_bytes: 1_048_588,
+            log_message_timestamp_type: TimestampType::CreateTime,
+            log_message_timestamp_difference_max: None,
+            replica_selector: ReplicaSelector::Leader,
+            compression_type: None,
+            max_incremental_fetch_session_cache_slots: 1000,
+            fetch_compression_type: CompressionType::None,
+            metrics_port: None,
+            admin_port: None,
+            quota_byte_rate: None,
+            quota_request_rate: None,
+            trace_wire: None,
+            replay: None,
+            replay_output: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parses command line arguments (without the program name).
+    /// Returns `None` if help was requested.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let mut config = Self::default();
+        let mut properties_file = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // both `--flag value` and `--flag=value` forms
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String> {
+                match inline_value {
+                    Some(v) => Ok(v.to_string()),
+                    None => args
+                        .next()
+                        .with_context(|| format!("missing value of `{flag}`\n\n{USAGE}")),
+                }
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--bind" => config.bind = parse_bind(&value()?)?,
+                "--port" => {
+                    let v = value()?;
+                    config.port = v.parse().with_context(|| format!("invalid port `{v}`"))?;
+                }
+                "--node-id" => {
+                    let v = value()?;
+                    config.node_id = Some(
+                        v.parse()
+                            .with_context(|| format!("invalid node id `{v}`"))?,
+                    );
+                }
+                "--broker-rack" => config.broker_rack = Some(value()?),
+                "--log-dir" => config.log_dirs = parse_log_dirs(&value()?)?,
+                "--metadata-log-dir" => config.metadata_log_dir = Some(parse_dir(&value()?)?),
+                "--connections-max-idle-ms" => {
+                    config.connections_max_idle = parse_millis(&value()?)?;
+                }
+                "--request-timeout-ms" => config.request_timeout = parse_millis(&value()?)?,
+                "--socket-read-timeout-ms" => {
+                    let timeout = parse_millis(&value()?)?;
+                    config.socket_read_timeout = Some(timeout).filter(|d| !d.is_zero());
+                }
+                "--socket-write-timeout-ms" => {
+                    let timeout = parse_millis(&value()?)?;
+                    config.socket_write_timeout = Some(timeout).filter(|d| !d.is_zero());
+                }
+                "--max-in-flight-requests" => {
+                    let v = value()?;
+                    config.max_in_flight_requests = match v.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => bail!("invalid number of requests `{v}`"),
+                    };
+                }
+                "--tcp-nodelay" => config.tcp_nodelay = parse_bool(&value()?)?,
+                "--tcp-keepalive" => config.tcp_keepalive = parse_bool(&value()?)?,
+                "--socket-send-buffer-bytes" => {
+                    config.socket_send_buffer_bytes = parse_buffer_size(&value()?)?;
+                }
+                "--socket-receive-buffer-bytes" => {
+                    config.socket_receive_buffer_bytes = parse_buffer_size(&value()?)?;
+                }
+                "--acl" => config.acls.push(value()?.parse()?),
+                "--sasl-plain-user" => {
+                    let v = value()?;
+                    let (user, password) =
+                        v.split_once(':')
+                            .filter(|(user, _)| !user.is_empty())
+                            .with_context(|| format!("invalid SASL/PLAIN user `{v}`"))?;
+                    config
+                        .sasl_plain_users
+                        .push((user.to_string(), password.to_string()));
+                }
+                "--connections-max-reauth-ms" => {
+                    let max_reauth = parse_millis(&value()?)?;
+                    config.connections_max_reauth = Some(max_reauth).filter(|d| !d.is_zero());
+                }
+                "--delegation-token-secret-key" => {
+                    config.delegation_token_secret_key = Some(value()?);
+                }
+                "--delegation-token-max-lifetime-ms" => {
+                    config.delegation_token_max_lifetime = parse_millis(&value()?)?;
+                }
+                "--delegation-token-expiry-time-ms" => {
+                    config.delegation_token_expiry_time = parse_millis(&value()?)?;
+                }
+                "--broker-session-timeout-ms" => {
+                    config.broker_session_timeout = parse_millis(&value()?)?;
+                }
+                "--message-max-bytes" => {
+                    let v = value()?;
+                    config.message_max_bytes = match v.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => bail!("invalid message size `{v}`"),
+                    };
+                }
+                "--log-message-timestamp-type" => {
+                    config.log_message_timestamp_type = value()?.parse()?;
+                }
+                "--log-message-timestamp-difference-max-ms" => {
+                    config.log_message_timestamp_difference_max = Some(parse_millis(&value()?)?);
+                }
+                "--replica-selector-class" => config.replica_selector = value()?.parse()?,
+                "--max-incremental-fetch-session-cache-slots" => {
+                    let v = value()?;
+                    config.max_incremental_fetch_session_cache_slots = v
+                        .parse()
+                        .with_context(|| format!("invalid number of sessions `{v}`"))?;
+                }
+                "--compression-type" => {
+                    config.compression_type = CompressionType::from_config(&value()?)?
+                }
+                "--fetch-compression-type" => {
+                    config.fetch_compression_type = value()?.parse()?;
+                }
+                "--metrics-port" => {
+                    let v = value()?;
+                    let port = v.parse().with_context(|| format!("invalid port `{v}`"))?;
+                    config.metrics_port = Some(port);
+                }
+                "--admin-port" => {
+                    let v = value()?;
+                    let port = v.parse().with_context(|| format!("invalid port `{v}`"))?;
+                    config.admin_port = Some(port);
+                }
+                "--quota-byte-rate" => config.quota_byte_rate = Some(parse_rate(&value()?)?),
+                "--quota-request-rate" => {
+                    config.quota_request_rate = Some(parse_rate(&value()?)?);
+                }
+                // the hexdump is optional, so the flag has only the inline value
+                "--trace-wire" => {
+                    config.trace_wire = match inline_value {
+                        None => Some(TraceWire::Headers),
+                        Some("hex") => Some(TraceWire::Hexdump),
+                        Some(v) => bail!("invalid wire trace `{v}`, only `hex` is supported"),
+                    };
+                }
+                "--replay" => config.replay = Some(PathBuf::from(value()?)),
+                "--replay-output" => config.replay_output = Some(PathBuf::from(value()?)),
+                _ if flag.starts_with('-') => bail!("unknown option `{flag}`\n\n{USAGE}"),
+                _ if properties_file.is_none() => properties_file = Some(arg),
+                _ => bail!("unexpected argument `{arg}`\n\n{USAGE}"),
+            }
+        }
+
+        Ok(Some(config))
+    }
+
+    /// Overrides the configuration with `KAFKA_*` environment variables, in the format of the corresponding
+    /// broker configs, so the broker can be configured in containers without changing the command line.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        // https://kafka.apache.org/documentation/#brokerconfigs_listeners
+        if let Some(listeners) = var("KAFKA_LISTENERS") {
+            let invalid = || format!("invalid KAFKA_LISTENERS `{listeners}`");
+            let mut entries = listeners.split(',').map(str::trim);
+            let first = parse_listener(entries.next().unwrap_or_default()).with_context(invalid)?;
+            self.bind = vec![first.ip()];
+            self.port = first.port();
+            // the listeners on the port of the first one are dual-stack ones, as KIP-797, e.g. an IPv6
+            // address next to an IPv4 one. The others, e.g. CONTROLLER, are not served.
+            for listener in entries {
+                let port = listener.rsplit_once(':').map(|(_, port)| port);
+                if port.and_then(|port| port.parse().ok()) == Some(first.port()) {
+                    self.bind
+                        .push(parse_listener(listener).with_context(invalid)?.ip());
+                }
+            }
+        }
+
+        // https://kafka.apache.org/documentation/#brokerconfigs_log.dirs
+        if let Some(log_dirs) = var("KAFKA_LOG_DIRS") {
+            self.log_dirs = parse_log_dirs(&log_dirs).context("invalid KAFKA_LOG_DIRS")?;
+        }
+
+        // https://kafka.apache.org/documentation/#brokerconfigs_metadata.log.dir
+        if let Some(dir) = var("KAFKA_METADATA_LOG_DIR") {
+            self.metadata_log_dir =
+                Some(parse_dir(&dir).context("invalid KAFKA_METADATA_LOG_DIR")?);
+        }
+
+        if let Some(ms) = var("KAFKA_CONNECTIONS_MAX_IDLE_MS") {
+            self.connections_max_idle =
+                parse_millis(&ms).context("invalid KAFKA_CONNECTIONS_MAX_IDLE_MS")?;
+        }
+
+        if let Some(port) = var("KAFKA_METRICS_PORT") {
+            let port = port
+                .parse()
+                .with_context(|| format!("invalid KAFKA_METRICS_PORT `{port}`"))?;
+            self.metrics_port = Some(port);
+        }
+
+        Ok(())
+    }
+
+    pub fn usage() -> &'static str {
+        USAGE
+    }
+
+    /// Address of the first bind address, the one advertised to the clients
+    pub fn listen_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.advertised_ip(), self.port)
+    }
+
+    /// Addresses of all bind addresses, the first one is `listen_addr`.
+    ///
+    /// The IPv6 wildcard `::` is bound dual-stack, it accepts the IPv4 connections too. The IPv4 wildcard
+    /// next to it is left out, on Linux it would collide with it on the same port.
+    pub fn listen_addrs(&self) -> Vec<SocketAddr> {
+        if self.bind.is_empty() {
+            return vec![self.listen_addr()];
+        }
+        let dual_stack = self
+            .bind
+            .iter()
+            .any(|ip| ip.is_ipv6() && ip.is_unspecified());
+        self.bind
+            .iter()
+            .filter(|ip| !(dual_stack && ip.is_ipv4() && ip.is_unspecified()))
+            .map(|&ip| SocketAddr::new(ip, self.port))
+            .collect()
+    }
+
+    pub fn metrics_addr(&self) -> Option<SocketAddr> {
+        self.metrics_port
+            .map(|port| SocketAddr::new(self.advertised_ip(), port))
+    }
+
+    pub fn admin_addr(&self) -> Option<SocketAddr> {
+        self.admin_port
+            .map(|port| SocketAddr::new(self.advertised_ip(), port))
+    }
+
+    fn advertised_ip(&self) -> IpAddr {
+        self.bind
+            .first()
+            .copied()
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    /// https://kafka.apache.org/documentation/#log, the metadata log is in `metadata.log.dir`,
+    /// by default in the first log directory as with Kafka
+    pub fn metadata_log_file(&self) -> PathBuf {
+        self.metadata_partition_dir()
+            .join("00000000000000000000.log")
+    }
+
+    /// Directory of the metadata log partition, with its log segments and snapshots
+    pub fn metadata_partition_dir(&self) -> PathBuf {
+        self.metadata_log_dir().join("__cluster_metadata-0")
+    }
+
+    fn metadata_log_dir(&self) -> &Path {
+        self.metadata_log_dir
+            .as_deref()
+            .unwrap_or_else(|| self.log_dirs.first().expect("there is a log directory"))
+    }
+
+    /// Properties of the log directory written by `kafka-storage.sh format`, has the `cluster.id`
+    pub fn meta_properties_file(&self) -> PathBuf {
+        self.metadata_log_dir().join("meta.properties")
+    }
+
+    /// First log segment of the topic partition, see [`Config::partition_dir`]
+    pub fn partition_log_file(
+        &self,
+        storage: &dyn Storage,
+        topic_name: &str,
+        partition: u32,
+    ) -> PathBuf {
+        self.partition_dir(storage, topic_name, partition)
+            .join("00000000000000000000.log")
+    }
+
+    /// Directory of the topic partition in the log directory of the storage that has it. A partition that does
+    /// not exist yet is placed in the log directory with the fewest partitions.
+    pub fn partition_dir(
+        &self,
+        storage: &dyn Storage,
+        topic_name: &str,
+        partition: u32,
+    ) -> PathBuf {
+        let name = format!("{}-{}", topic_name, partition);
+        let dirs = |log_dir: &Path| {
+            storage
+                .list(log_dir)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|entry| entry.is_dir)
+        };
+        if let Some(log_dir) = self
+            .log_dirs
+            .iter()
+            .find(|log_dir| dirs(log_dir).any(|entry| entry.name == name))
+        {
+            return log_dir.join(name);
+        }
+
+        let partition_count = |log_dir: &Path| dirs(log_dir).count();
+        self.log_dirs
+            .iter()
+            .min_by_key(|log_dir| partition_count(log_dir))
+            .expect("there is a log directory")
+            .join(name)
+    }
+
+    pub fn log_dirs(&self) -> &[PathBuf] {
+        &self.log_dirs
+    }
+}
+
+fn parse_millis(ms: &str) -> Result<Duration> {
+    let ms: u64 = ms
+        .parse()
+        .with_context(|| format!("invalid milliseconds `{ms}`"))?;
+    Ok(Duration::from_millis(ms))
+}
+
+/// Comma-separated IP addresses, IPv6 ones with or without brackets
+fn parse_bind(addrs: &str) -> Result<Vec<IpAddr>> {
+    addrs
+        .split(',')
+        .map(|addr| {
+            let addr = addr.trim();
+            let ip = addr
+                .strip_prefix('[')
+                .and_then(|ip| ip.strip_suffix(']'))
+                .unwrap_or(addr);
+            ip.parse()
+                .with_context(|| format!("invalid address `{addr}`"))
+        })
+        .collect()
+}
+
+/// Comma-separated directories, as Kafka's `log.dirs`
+fn parse_log_dirs(dirs: &str) -> Result<Vec<PathBuf>> {
+    let log_dirs: Vec<_> = dirs.split(',').map(str::trim).collect();
+    if log_dirs.iter().any(|dir| dir.is_empty()) {
+        bail!("invalid log directories `{dirs}`");
+    }
+    Ok(log_dirs.into_iter().map(PathBuf::from).collect())
+}
+
+fn parse_dir(dir: &str) -> Result<PathBuf> {
+    let dir = dir.trim();
+    if dir.is_empty() {
+        bail!("empty directory");
+    }
+    Ok(PathBuf::from(dir))
+}
+
+fn parse_bool(b: &str) -> Result<bool> {
+    b.parse().with_context(|| format!("invalid boolean `{b}`"))
+}
+
+/// Buffer size in bytes, -1 means the default of the OS
+fn parse_buffer_size(size: &str) -> Result<Option<u32>> {
+    match size.parse::<i64>() {
+        Ok(-1) => Ok(None),
+        Ok(size) if size > 0 && size <= u32::MAX as i64 => Ok(Some(size as u32)),
+        _ => bail!("invalid buffer size `{size}`"),
+    }
+}
+
+fn parse_rate(rate: &str) -> Result<u64> {
+    match rate.parse() {
+        Ok(rate) if rate > 0 => Ok(rate),
+        _ => bail!("invalid quota `{rate}`"),
+    }
+}
+
+/// Parses `LISTENER_NAME://host:port`, empty host means all interfaces
+fn parse_listener(listener: &str) -> Result<SocketAddr> {
+    let (name, host_port) = listener
+        .split_once("://")
+        .context("missing listener name")?;
+    // there is no TLS implementation, encrypted listeners must not silently accept plaintext
+    if matches!(name, "SSL" | "SASL_SSL") {
+        bail!("{name} listeners are not supported, only PLAINTEXT");
+    }
+    let (host, port) = host_port.rsplit_once(':').context("missing port")?;
+    let port: u16 = port.parse().context("invalid port")?;
+
+    if host.is_empty() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port));
+    }
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("resolve `{host}`"))?
+        .next()
+        .with_context(|| format!("no address for `{host}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{CompressionType, Config, ReplicaSelector, TimestampType, TraceWire};
+
+    fn parse(args: &[&str]) -> anyhow::Result<Option<Config>> {
+        Config::from_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parses_flags() {
+        let config = parse(&[
+            "/tmp/server.properties",
+            "--bind",
+            "0.0.0.0",
+            "--port=19092",
+            "--node-id=2",
+            "--broker-rack=eu-west-1a",
+            "--log-dir",
+            "/var/lib/kafka",
+            "--connections-max-idle-ms=1500",
+            "--request-timeout-ms=100",
+            "--socket-read-timeout-ms=200",
+            "--socket-write-timeout-ms=0",
+            "--max-in-flight-requests=1",
+            "--tcp-nodelay=false",
+            "--socket-send-buffer-bytes",
+            "-1",
+            "--socket-receive-buffer-bytes=65536",
+            "--acl",
+            "User:alice,Read,Topic,payments",
+            "--sasl-plain-user=alice:alice-secret",
+            "--connections-max-reauth-ms=60000",
+            "--quota-byte-rate",
+            "1048576",
+            "--broker-session-timeout-ms=18000",
+            "--message-max-bytes=2048",
+            "--log-message-timestamp-type=LogAppendTime",
+            "--log-message-timestamp-difference-max-ms",
+            "3600000",
+            "--replica-selector-class=org.apache.kafka.common.replica.RackAwareReplicaSelector",
+            "--compression-type=uncompressed",
+            "--max-incremental-fetch-session-cache-slots=10",
+            "--fetch-compression-type=gzip",
+            "--trace-wire",
+            "--admin-port=9101",
+            "--replay=/tmp/requests.bin",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.listen_addr().to_string(), "0.0.0.0:19092");
+        let both = parse(&["--bind=127.0.0.1,[::1]"]).unwrap().unwrap();
+        let addrs: Vec<_> = both.listen_addrs().iter().map(|a| a.to_string()).collect();
+        assert_eq!(addrs, ["127.0.0.1:9092", "[::1]:9092"]);
+        let wildcards = parse(&["--bind=0.0.0.0,::"]).unwrap().unwrap();
+        assert_eq!(wildcards.listen_addrs(), ["[::]:9092".parse().unwrap()]);
+        assert_eq!(config.node_id, Some(2));
+        assert_eq!(config.broker_rack.as_deref(), Some("eu-west-1a"));
+        assert_eq!(
+            config.metadata_log_file(),
+            PathBuf::from("/var/lib/kafka/__cluster_metadata-0/00000000000000000000.log")
+        );
+        let separate = parse(&["--log-dir=/data/a,/data/b", "--metadata-log-dir=/data/meta"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            separate.metadata_log_file(),
+            PathBuf::from("/data/meta/__cluster_metadata-0/00000000000000000000.log")
+        );
+        assert_eq!(
+            separate.meta_properties_file(),
+            PathBuf::from("/data/meta/meta.properties")
+        );
+
+        assert_eq!(config.connections_max_idle, Duration::from_millis(1500));
+        assert_eq!(config.request_timeout, Duration::from_millis(100));
+        assert_eq!(config.socket_read_timeout, Some(Duration::from_millis(200)));
+        assert_eq!(config.socket_write_timeout, None);
+        assert_eq!(config.max_in_flight_requests, 1);
+        assert!(!config.tcp_nodelay);
+        assert!(config.tcp_keepalive);
+        assert_eq!(config.socket_send_buffer_bytes, None);
+        assert_eq!(config.socket_receive_buffer_bytes, Some(65536));
+        assert_eq!(config.acls.len(), 1);
+        assert_eq!(
+            config.sasl_plain_users,
+            [("alice".to_string(), "alice-secret".to_string())]
+        );
+        assert_eq!(config.connections_max_reauth, Some(Duration::from_secs(60)));
+        assert_eq!(config.quota_byte_rate, Some(1048576));
+        assert_eq!(config.quota_request_rate, None);
+        assert_eq!(config.broker_session_timeout, Duration::from_secs(18));
+        assert_eq!(config.message_max_bytes, 2048);
+        assert_eq!(
+            config.log_message_timestamp_type,
+            TimestampType::LogAppendTime
+        );
+        assert_eq!(
+            config.log_message_timestamp_difference_max,
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(config.replica_selector, ReplicaSelector::RackAware);
+        assert_eq!(config.max_incremental_fetch_session_cache_slots, 10);
+        assert_eq!(config.compression_type, Some(CompressionType::None));
+        assert_eq!(config.fetch_compression_type, CompressionType::Gzip);
+        assert_eq!(config.trace_wire, Some(TraceWire::Headers));
+        assert_eq!(config.admin_addr().unwrap().to_string(), "0.0.0.0:9101");
+        assert_eq!(config.replay, Some(PathBuf::from("/tmp/requests.bin")));
+        assert_eq!(config.replay_output, None);
+        assert_eq!(
+            parse(&["--trace-wire=hex"]).unwrap().unwrap().trace_wire,
+            Some(TraceWire::Hexdump)
+        );
+
+        assert_eq!(parse(&[]).unwrap(), Some(Config::default()));
+        assert_eq!(parse(&["--help"]).unwrap(), None);
+    }
+
+    #[test]
+    fn environment_overrides() {
+        let env = |name: &str| match name {
+            "KAFKA_LISTENERS" => Some("PLAINTEXT://:29092,CONTROLLER://:9093".to_string()),
+            "KAFKA_LOG_DIRS" => Some("/data/a,/data/b".to_string()),
+            "KAFKA_METADATA_LOG_DIR" => Some("/data/meta".to_string()),
+            _ => None,
+        };
+        let mut config = parse(&["--port", "19092"]).unwrap().unwrap();
+        config.apply_env(env).unwrap();
+        assert_eq!(config.listen_addr().to_string(), "0.0.0.0:29092");
+        assert_eq!(
+            config.log_dirs,
+            vec![PathBuf::from("/data/a"), PathBuf::from("/data/b")]
+        );
+        assert_eq!(config.metadata_log_dir, Some(PathBuf::from("/data/meta")));
+
+        let listeners = |value: &'static str| {
+            move |name: &str| (name == "KAFKA_LISTENERS").then(|| value.to_string())
+        };
+        let mut config = Config::default();
+        config
+            .apply_env(listeners("PLAINTEXT://[::1]:9092"))
+            .unwrap();
+        assert_eq!(config.listen_addr().to_string(), "[::1]:9092");
+
+        // a dual-stack listener on the same port
+        let mut config = Config::default();
+        config
+            .apply_env(listeners(
+                "PLAINTEXT://127.0.0.1:9092,PLAINTEXT6://[::1]:9092,CONTROLLER://controller:9093",
+            ))
+            .unwrap();
+        let addrs: Vec<_> = config
+            .listen_addrs()
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(addrs, ["127.0.0.1:9092", "[::1]:9092"]);
+
+        // listener name is required
+        assert!(Config::default()
+            .apply_env(listeners("localhost:9092"))
+            .is_err());
+        assert!(Config::default()
+            .apply_env(listeners("SSL://:9093"))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse(&["--port", "abc"]).is_err());
+        assert!(parse(&["--port"]).is_err());
+        assert!(parse(&["--trace-wire=bytes"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["a.properties", "b.properties"]).is_err());
+        assert!(parse(&["--max-in-flight-requests=0"]).is_err());
+        assert!(parse(&["--tcp-keepalive=yes"]).is_err());
+        assert!(parse(&["--bind=127.0.0.1,"]).is_err());
+        assert!(parse(&["--fetch-compression-type=zstd"]).is_err());
+        assert!(parse(&["--compression-type=lz4"]).is_err());
+        assert!(parse(&["--socket-send-buffer-bytes=0"]).is_err());
+        assert!(parse(&["--quota-byte-rate=0"]).is_err());
+        assert!(parse(&["--log-dir=/data/a,"]).is_err());
+        assert!(parse(&["--metadata-log-dir="]).is_err());
+        assert!(parse(&["--log-message-timestamp-type=NoTimestamp"]).is_err());
+        assert!(parse(&["--message-max-bytes=0"]).is_err());
+        assert!(parse(&["--sasl-plain-user=alice"]).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Minimal HTTP/1.1 server of the metrics and admin endpoints, which answers one GET request per connection
+
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::{Context, Result};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Response to a request
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain",
+            body: "not found\n".to_string(),
+        }
+    }
+}
+
+/// Responses to the paths requested with GET, other requests are not found
+pub type Respond = Arc<dyn Fn(&str) -> Response + Send + Sync>;
+
+/// Serves the endpoint on the given address, `name` is the endpoint in the errors.
+///
+/// `respond` runs on the blocking thread pool, so that it may read the logs.
+pub async fn serve(addr: SocketAddr, name: &'static str, respond: Respond) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("bind {name} endpoint {addr}"))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let respond = respond.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_request(stream, respond).await {
+                eprintln!("Error: {name} request: {:?}", e);
+            }
+        });
+    }
+}
+
+/// Answers a single HTTP request and closes the connection
+async fn serve_request(mut stream: TcpStream, respond: Respond) -> Result<()> {
+    // only the request line is needed, the headers are read just to not reset the connection
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(path)) => {
+            let path = path.to_string();
+            tokio::task::spawn_blocking(move || respond(&path))
+                .await
+                .context("join response task")?
+        }
+        _ => Response::not_found(),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
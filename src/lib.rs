@@ -0,0 +1,28 @@
+//! Kafka broker implementing a subset of the Kafka wire protocol.
+//!
+//! [`protocol`] holds the wire format: request and response headers, the generated message structs
+//! that both serialize and deserialize every version, and the record batches of the logs.
+//! It can be used on its own to talk to any Kafka broker, see [`protocol::encode_request`]
+//! and [`protocol::decode_response`], or through the minimal [`client`].
+//! The broker itself is started with [`run`], or in the background with [`Broker::start`],
+//! and captured requests are processed without it listening with [`replay`].
+//! It keeps its logs in the [`storage`], the file system by default.
+
+pub mod client;
+pub mod config;
+pub mod protocol;
+pub mod storage;
+
+mod admin;
+mod broker;
+mod codec;
+mod http;
+mod logic;
+mod metrics;
+mod replay;
+mod scheduler;
+mod server;
+
+pub use broker::{Broker, BrokerHandle};
+pub use replay::replay;
+pub use server::run;
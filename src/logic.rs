@@ -1,49 +1,517 @@
+pub mod api_versions;
+pub mod authorizer;
+pub mod broker_registrations;
+pub mod configs;
+pub mod delegation_tokens;
+pub mod envelope;
 pub mod fetch_responses;
+pub mod fetch_sessions;
+pub mod handler;
+pub mod list_offsets;
+pub mod log_dirs;
+pub mod metadata;
+pub mod partitions;
+pub mod produce;
+pub mod quota;
+pub mod sasl;
 pub mod topic_partitions;
 
-use anyhow::{bail, Context, Result};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use authorizer::{AclAuthorizer, KafkaPrincipal};
+use broker_registrations::ClusterControl;
 use bytes::Bytes;
+use delegation_tokens::TokenStore;
+use fetch_sessions::FetchSessions;
+use handler::Handler;
+use partitions::Partitions;
+use quota::ClientQuotas;
+use sasl::Authentication;
 use thiserror::Error;
 
-use crate::protocol::{
-    request::{
-        api_versions::ApiVersionsRequest,
-        describe_topic_partitions::DescribeTopicPartitionsRequestV0, fetch::FetchRequestV16,
+use crate::{
+    config::Config,
+    metrics::{metrics, CLIENT_EXPIRY},
+    protocol::{
+        reader::ByteReader, record_batch::RecordBatches, request::RequestHeader, ApiKey, ErrorCode,
+        ProtocolError,
     },
-    ApiKey, Response,
+    scheduler::Scheduler,
+    storage::{Entry, Storage, StorageError},
 };
 
-pub fn process(request_api_key: i16, msg: &mut Bytes) -> Result<Box<dyn Response + Send>> {
-    // https://kafka.apache.org/protocol.html#protocol_api_keys
-    let request_api_key = match ApiKey::try_from(request_api_key) {
-        Ok(key) => key,
-        Err(_) => {
-            bail!(UnsupportedApiKeyError(request_api_key));
+/// State of the broker shared by the requests of all connections, created when the broker starts.
+///
+/// The handlers get the configuration, the storage and the state they keep in memory from it instead of
+/// globals, most of them through [`RequestContext`], so that they can be called with another context.
+/// The connections share it as an `Arc`, so its state is locked by itself: a `Mutex` for state that is
+/// changed by most of its uses, as the stores below that expire their entries whenever they are used,
+/// and a `RwLock` for the metadata records, which are only replaced when the metadata log changes.
+/// The partition logs are owned by actors instead, see [`partitions`].
+///
+/// The context owns all the state of its broker: the configuration and the storage it is created with, as
+/// `Arc`s the partition actors share, the metadata loaded from the storage, and the authorizer and
+/// the stores it creates from the configuration.
+/// Brokers of one process do not share any of it. Only the metrics, counted for the whole process, and the
+/// handler registry, which has no state, are global.
+pub struct BrokerContext {
+    /// Configuration the broker was started with
+    pub config: Arc<Config>,
+    /// Storage of the log directories
+    pub storage: Arc<dyn Storage>,
+    /// Directory of the log of the cluster metadata records: the topics, their partitions and the registered
+    /// brokers. See [`BrokerContext::metadata`].
+    pub metadata_partition_dir: PathBuf,
+    /// Metadata records last loaded from the directory
+    metadata: RwLock<Option<MetadataCache>>,
+    /// Authorizer with the ACLs of the configuration
+    pub authorizer: AclAuthorizer,
+    /// Usage of the clients against the quotas of the configuration
+    pub quotas: ClientQuotas,
+    /// Brokers registered with this node as the controller
+    pub cluster_control: ClusterControl,
+    /// Delegation tokens issued by the broker
+    pub tokens: TokenStore,
+    /// Actors of the partition logs, which append the produced batches and read the fetched ones
+    pub partitions: Partitions,
+    /// Sessions of the incremental fetches
+    pub fetch_sessions: FetchSessions,
+}
+
+impl BrokerContext {
+    pub fn new(config: Arc<Config>, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            metadata_partition_dir: config.metadata_partition_dir(),
+            metadata: RwLock::default(),
+            authorizer: AclAuthorizer::new(config.acls.clone()),
+            quotas: ClientQuotas::new(config.quota_byte_rate, config.quota_request_rate),
+            cluster_control: ClusterControl::new(
+                broker_registrations::read_cluster_id(&config, &*storage),
+                config.broker_session_timeout,
+            ),
+            tokens: TokenStore::new(
+                config.delegation_token_secret_key.as_deref(),
+                config.delegation_token_max_lifetime,
+                config.delegation_token_expiry_time,
+            ),
+            partitions: Partitions::new(storage.clone()),
+            fetch_sessions: FetchSessions::new(config.max_incremental_fetch_session_cache_slots),
+            config,
+            storage,
         }
-    };
+    }
+}
+
+/// Metadata records loaded from the files of the metadata log directory
+struct MetadataCache {
+    /// Names and sizes of the snapshots and log segments when they were loaded
+    files: Vec<Entry>,
+    record_batches: Arc<RecordBatches>,
+}
 
-    let response: Box<dyn Response + Send> = match request_api_key {
-        ApiKey::ApiVersions => {
-            let req =
-                ApiVersionsRequest::from_bytes(msg).context("deserialize ApiVersionsRequest")?;
-            let resp = req.process();
-            Box::new(resp)
+impl BrokerContext {
+    /// Metadata of the cluster, from the latest snapshot and the metadata log after it.
+    ///
+    /// The records are loaded once and again only when the files of the directory change, records are
+    /// appended to the log and snapshots are added, so a request only lists the directory.
+    pub fn metadata(&self) -> Result<Arc<RecordBatches>, StorageError> {
+        let files = self.storage.list(&self.metadata_partition_dir)?;
+        let cached = self.metadata.read().expect("metadata lock is not poisoned");
+        if let Some(cache) = cached.as_ref().filter(|cache| cache.files == files) {
+            return Ok(cache.record_batches.clone());
         }
-        ApiKey::DescribeTopicPartitions => {
-            let req = DescribeTopicPartitionsRequestV0::from_bytes(msg);
-            let resp = topic_partitions::process(req)?;
-            Box::new(resp)
+        drop(cached);
+
+        // the files may have changed since they were listed, then they are loaded again by the next request
+        let record_batches = Arc::new(RecordBatches::load_metadata(
+            &*self.storage,
+            &self.metadata_partition_dir,
+        )?);
+        *self
+            .metadata
+            .write()
+            .expect("metadata lock is not poisoned") = Some(MetadataCache {
+            files,
+            record_batches: record_batches.clone(),
+        });
+        Ok(record_batches)
+    }
+
+    /// First log segment of the topic partition in the log directories of the storage
+    pub fn partition_log_file(&self, topic_name: &str, partition: u32) -> PathBuf {
+        self.config
+            .partition_log_file(&*self.storage, topic_name, partition)
+    }
+}
+
+#[cfg(test)]
+impl BrokerContext {
+    /// Context of the default configuration with the files of the storage
+    pub fn with_storage(storage: crate::storage::MemoryStorage) -> Arc<Self> {
+        Arc::new(Self::new(Arc::default(), Arc::new(storage)))
+    }
+}
+
+/// Request being processed
+pub struct RequestContext {
+    pub broker: Arc<BrokerContext>,
+    pub header: RequestHeader,
+    /// Identity of the client the authorization is decided for
+    pub principal: KafkaPrincipal,
+    /// How the principal authenticated
+    pub authentication: Authentication,
+    /// Quota throttle time reported to the client in the response
+    pub throttle_time_ms: i32,
+}
+
+#[cfg(test)]
+impl RequestContext {
+    /// Request of the anonymous principal, without a client id
+    pub fn for_request(broker: Arc<BrokerContext>, api_key: ApiKey, version: i16) -> Self {
+        Self {
+            broker,
+            header: RequestHeader {
+                request_api_key: api_key.into(),
+                request_api_version: version,
+                correlation_id: 1,
+                client_id: None,
+            },
+            principal: KafkaPrincipal::anonymous(),
+            authentication: Authentication::None,
+            throttle_time_ms: 0,
         }
-        ApiKey::Fetch => {
-            let req = FetchRequestV16::from_bytes(msg);
-            let resp = fetch_responses::process(req)?;
-            Box::new(resp)
+    }
+}
+
+/// Response message to a processed request
+pub struct ProcessedRequest {
+    pub response: Bytes,
+    /// The client exceeded its quota, the response has to be delayed by this time
+    pub throttle: Duration,
+}
+
+/// Wall-clock time of the broker in milliseconds since the Unix epoch, e.g. of the record timestamps
+/// and the expiry of tokens and sessions
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after the Unix epoch")
+        .as_millis() as i64
+}
+
+/// Interval of removing the expired delegation tokens, Kafka's default `delegation.token.expiry.check.interval.ms`
+const TOKEN_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Schedules the expiry of the state the request handlers keep in memory
+pub fn schedule_tasks(scheduler: &Scheduler, broker: &Arc<BrokerContext>) {
+    let b = Arc::clone(broker);
+    scheduler.schedule("quota-window-expiry", Duration::from_secs(30), move || {
+        b.quotas.expire_windows(Instant::now());
+    });
+    // at least twice per session, so that a broker is fenced soon after its session expired
+    let session_timeout = broker.config.broker_session_timeout;
+    let b = Arc::clone(broker);
+    scheduler.schedule("broker-session-expiry", session_timeout / 2, move || {
+        b.cluster_control.fence_expired(Instant::now());
+    });
+    let b = Arc::clone(broker);
+    scheduler.schedule(
+        "delegation-token-expiry",
+        TOKEN_EXPIRY_CHECK_INTERVAL,
+        move || b.tokens.remove_expired(now_ms()),
+    );
+    scheduler.schedule("client-metrics-expiry", CLIENT_EXPIRY / 12, || {
+        metrics().expire_clients(now_ms());
+    });
+}
+
+/// Passes the request body to the handler of its API.
+///
+/// Versions the handler does not support are answered with UNSUPPORTED_VERSION error, their body is not parsed.
+///
+/// Requests that cannot be deserialized are answered with INVALID_REQUEST error and the correlation id
+/// of the request, so that the client can match the error to the request. Other errors are returned
+/// and the connection is closed.
+pub fn process(
+    broker: Arc<BrokerContext>,
+    header: RequestHeader,
+    principal: KafkaPrincipal,
+    authentication: Authentication,
+    body: Bytes,
+) -> Result<ProcessedRequest> {
+    let start = Instant::now();
+    let api_key = header.request_api_key;
+    record_client(&header);
+    // quotas apply to each user and client id pair, as Kafka's `<user, client-id>` quotas
+    let quota_entity = format!(
+        "{principal}/{}",
+        header.client_id.as_deref().unwrap_or_default()
+    );
+
+    let throttle = broker.quotas.throttle_time(&quota_entity, start);
+    if !throttle.is_zero() {
+        metrics().request_throttled(throttle);
+    }
+    let ctx = RequestContext {
+        broker,
+        header,
+        principal,
+        authentication,
+        throttle_time_ms: throttle.as_millis() as i32,
+    };
+    let request_size = body.len();
+
+    // https://kafka.apache.org/protocol.html#protocol_api_keys
+    let result = match handler::registry().get(api_key) {
+        Some(handler)
+            if !handler
+                .version_range()
+                .contains(&ctx.header.request_api_version) =>
+        {
+            handler
+                .error_response(&ctx, ErrorCode::UnsupportedVersion)
+                .with_context(|| {
+                    format!(
+                        "unsupported version {} of {:?}",
+                        ctx.header.request_api_version,
+                        handler.api_key()
+                    )
+                })
         }
+        Some(handler) => handler
+            .handle(&ctx, body)
+            .or_else(|err| invalid_request(handler, &ctx, err)),
+        None => Err(UnsupportedApiKeyError(api_key).into()),
     };
+    metrics().request_processed(api_key, start.elapsed(), result.is_err());
 
-    Ok(response)
+    let response = result?;
+    ctx.broker
+        .quotas
+        .record(&quota_entity, request_size + response.len(), Instant::now());
+
+    Ok(ProcessedRequest { response, throttle })
 }
 
+/// Records the request in the metrics of its client id
+fn record_client(header: &RequestHeader) {
+    metrics().client_request(
+        header.client_id.as_deref().unwrap_or_default(),
+        header.request_api_key,
+        header.request_api_version,
+        now_ms(),
+    );
+}
+
+/// Deserializes the request body with the parser of the API.
+///
+/// The message has to end where the parser finished. Bytes left in it mean that its size disagrees with
+/// the schema of the request version the client sent, so the request is rejected as invalid instead of
+/// processing fields that may be misread. The parser cannot read past the end of the message,
+/// as the reader is bounded by the message size. The next message starts after the size of this one
+/// in either case, so the connection stays in sync.
+pub fn deserialize<T>(
+    api_key: ApiKey,
+    ctx: &RequestContext,
+    body: Bytes,
+    parse: impl FnOnce(RequestHeader, &mut ByteReader) -> Result<T, ProtocolError>,
+) -> Result<T, InvalidRequestError> {
+    let mut reader = ByteReader::new(body);
+    let req =
+        parse(ctx.header.clone(), &mut reader).map_err(|err| InvalidRequestError(api_key, err))?;
+
+    if reader.remaining() > 0 {
+        let err = ProtocolError::TrailingBytes {
+            message: "request",
+            remaining: reader.remaining(),
+        };
+        return Err(InvalidRequestError(api_key, err));
+    }
+
+    Ok(req)
+}
+
+/// Turns the error of a request that could not be deserialized into INVALID_REQUEST error response
+fn invalid_request(
+    handler: &dyn Handler,
+    ctx: &RequestContext,
+    err: anyhow::Error,
+) -> Result<Bytes> {
+    if err.downcast_ref::<InvalidRequestError>().is_none() {
+        return Err(err);
+    }
+
+    eprintln!(
+        "Error: {err:#} (client '{}')",
+        ctx.header.client_id.as_deref().unwrap_or_default()
+    );
+    handler
+        .error_response(ctx, ErrorCode::InvalidRequest)
+        .ok_or(err)
+}
+
+/// Response with the error code to a request that could not be processed, `None` if the API is unsupported
+/// or its response has no top-level error code
+pub fn error_response(
+    broker: Arc<BrokerContext>,
+    header: RequestHeader,
+    principal: KafkaPrincipal,
+    authentication: Authentication,
+    error_code: ErrorCode,
+) -> Option<ProcessedRequest> {
+    let handler = handler::registry().get(header.request_api_key)?;
+    let ctx = RequestContext {
+        broker,
+        header,
+        principal,
+        authentication,
+        throttle_time_ms: 0,
+    };
+
+    handler
+        .error_response(&ctx, error_code)
+        .map(|response| ProcessedRequest {
+            response,
+            throttle: Duration::ZERO,
+        })
+}
+
+/// The request message could not be deserialized
+#[derive(Debug, Error)]
+#[error("deserialize {0:?} request")]
+pub struct InvalidRequestError(pub ApiKey, #[source] pub ProtocolError);
+
 #[derive(Debug, Error)]
 #[error("Unsupported api key `{0}`")]
 pub struct UnsupportedApiKeyError(i16);
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use super::{
+        authorizer::{Authorizer, KafkaPrincipal, Operation, Resource},
+        deserialize, process,
+        sasl::Authentication,
+        BrokerContext, InvalidRequestError, RequestContext,
+    };
+    use crate::{
+        config::Config,
+        protocol::{
+            generated::api_versions_request::ApiVersionsRequestData,
+            reader::ByteReader,
+            record_batch::{RecordBatch, RecordValue, TopicValue},
+            request::RequestHeader,
+            types::Serialize,
+            ApiKey, ProtocolError,
+        },
+        storage::{MemoryStorage, Storage},
+    };
+
+    #[test]
+    fn rejects_requests_longer_than_their_fields() {
+        let ctx = RequestContext::for_request(
+            BrokerContext::with_storage(MemoryStorage::default()),
+            ApiKey::ApiVersions,
+            3,
+        );
+        let req = ApiVersionsRequestData {
+            client_software_name: "test".to_string(),
+            client_software_version: "1.0".to_string(),
+        };
+        let parse = |header: crate::protocol::request::RequestHeader, src: &mut _| {
+            ApiVersionsRequestData::deserialize(src, header.request_api_version)
+        };
+
+        let body = req.serialize(3);
+        assert!(deserialize(ApiKey::ApiVersions, &ctx, body.clone(), parse).is_ok());
+
+        let mut longer = body.to_vec();
+        longer.extend_from_slice(&[0; 20]);
+        let err = deserialize(ApiKey::ApiVersions, &ctx, Bytes::from(longer), parse).unwrap_err();
+        assert!(matches!(
+            err,
+            InvalidRequestError(
+                ApiKey::ApiVersions,
+                ProtocolError::TrailingBytes { remaining: 20, .. }
+            )
+        ));
+    }
+
+    #[test]
+    fn skips_the_tagged_fields_of_requests() {
+        // ApiVersions v3 with header v2, both with a tagged field the broker does not know
+        let mut frame = vec![0, 18, 0, 3, 0, 0, 0, 7, 0, 1, b'c', 1, 0, 2, b'x', b'y'];
+        let body = ApiVersionsRequestData {
+            client_software_name: "test".to_string(),
+            client_software_version: "1.0".to_string(),
+        }
+        .serialize(3);
+        frame.extend_from_slice(&body[..body.len() - 1]);
+        frame.extend_from_slice(&[1, 3, 1, 0]);
+
+        let mut src = ByteReader::new(Bytes::from(frame));
+        let header = RequestHeader::from_bytes(&mut src).unwrap();
+        let body = src.get_bytes("body", src.remaining()).unwrap();
+        let broker = BrokerContext::with_storage(MemoryStorage::default());
+        let processed = process(
+            broker,
+            header,
+            KafkaPrincipal::anonymous(),
+            Authentication::None,
+            body,
+        )
+        .unwrap();
+        // the correlation id and the error code after the size of the response
+        assert_eq!(processed.response[4..10], [0, 0, 0, 7, 0, 0]);
+    }
+
+    #[test]
+    fn metadata_is_loaded_again_when_the_log_changes() {
+        let topic = |offset, name: &str| {
+            let value = RecordValue::Topic(TopicValue {
+                topic_name: name.to_string(),
+                topic_id: format!("00000000-0000-0000-0000-00000000000{offset}"),
+            });
+            RecordBatch::of_values(offset, vec![value]).serialize()
+        };
+        let log = Config::default().metadata_log_file();
+        let storage = Arc::new(MemoryStorage::with_files([(log.clone(), topic(0, "foo"))]));
+        let broker = BrokerContext::new(Arc::default(), storage.clone());
+
+        let metadata = broker.metadata().unwrap();
+        assert!(Arc::ptr_eq(&metadata, &broker.metadata().unwrap()));
+
+        storage.append(&log, &topic(1, "bar")).unwrap();
+        let reloaded = broker.metadata().unwrap();
+        assert!(!Arc::ptr_eq(&metadata, &reloaded));
+        assert!(reloaded.topic_id("bar").is_some());
+    }
+
+    #[test]
+    fn contexts_authorize_with_the_acls_of_their_config() {
+        let context = |acls: &[&str]| {
+            let config = Config {
+                acls: acls.iter().map(|acl| acl.parse().unwrap()).collect(),
+                ..Config::default()
+            };
+            BrokerContext::new(Arc::new(config), Arc::new(MemoryStorage::default()))
+        };
+        let open = context(&[]);
+        let restricted = context(&["User:alice,Read,Topic,payments"]);
+        let bob = KafkaPrincipal::user("bob");
+        let payments = Resource::Topic("payments");
+
+        assert!(open.authorizer.authorize(&bob, Operation::Read, payments));
+        assert!(!restricted
+            .authorizer
+            .authorize(&bob, Operation::Read, payments));
+    }
+}
@@ -0,0 +1,79 @@
+use std::{ops::RangeInclusive, sync::OnceLock};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::{
+    metrics::metrics,
+    protocol::{
+        request::api_versions::ApiVersionsRequest, response::api_versions::ApiVersionsResponse,
+        ApiKey, ErrorCode, Response,
+    },
+};
+
+use super::{
+    deserialize,
+    handler::{registry, Handler},
+    RequestContext,
+};
+
+/// Response of the supported version, serialized once.
+///
+/// Clients send ApiVersions first on every connection and the response differs only in the correlation id
+/// and throttle time, which are patched in.
+fn serialized_response(version: i16) -> &'static Bytes {
+    static RESPONSES: OnceLock<Vec<Bytes>> = OnceLock::new();
+    let responses = RESPONSES.get_or_init(|| {
+        (ApiVersionsResponse::LOWEST_SUPPORTED_VERSION
+            ..=ApiVersionsResponse::HIGHEST_SUPPORTED_VERSION)
+            .map(|version| {
+                ApiVersionsResponse::new(0, version, registry().api_keys(), 0).into_bytes()
+            })
+            .collect()
+    });
+    &responses[(version - ApiVersionsResponse::LOWEST_SUPPORTED_VERSION) as usize]
+}
+
+pub struct ApiVersionsHandler;
+
+impl Handler for ApiVersionsHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::ApiVersions
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        ApiVersionsResponse::LOWEST_SUPPORTED_VERSION
+            ..=ApiVersionsResponse::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, ApiVersionsRequest::from_bytes)?;
+        // the software of the client is sent from version 3
+        if ctx.header.request_api_version >= 3 {
+            metrics().client_software(
+                ctx.header.client_id.as_deref().unwrap_or_default(),
+                &req.body().client_software_name,
+                &req.body().client_software_version,
+            );
+        }
+
+        let version = ctx.header.request_api_version;
+        Ok(ApiVersionsResponse::patch(
+            serialized_response(version),
+            version,
+            ctx.header.correlation_id,
+            ctx.throttle_time_ms,
+        ))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = ApiVersionsResponse::with_error_code(
+            ctx.header.correlation_id,
+            ctx.header.request_api_version,
+            error_code,
+            registry().api_keys(),
+            ctx.throttle_time_ms,
+        );
+        Some(resp.into_bytes())
+    }
+}
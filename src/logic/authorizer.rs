@@ -0,0 +1,230 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context};
+
+/// Identity of the client of a connection, e.g. `User:alice`
+// https://github.com/apache/kafka/blob/trunk/clients/src/main/java/org/apache/kafka/common/security/auth/KafkaPrincipal.java
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaPrincipal {
+    pub principal_type: String,
+    pub name: String,
+}
+
+impl KafkaPrincipal {
+    pub fn user(name: impl Into<String>) -> Self {
+        Self {
+            principal_type: "User".to_string(),
+            name: name.into(),
+        }
+    }
+
+    /// Principal of PLAINTEXT connections and of clients that did not authenticate
+    pub fn anonymous() -> Self {
+        Self::user("ANONYMOUS")
+    }
+
+    /// Principal of a SASL connection, the authenticated username
+    pub fn from_sasl(username: &str) -> Self {
+        Self::user(username)
+    }
+
+    /// Principal of a TLS connection with client authentication, the distinguished name
+    /// of the client certificate, e.g. `User:CN=alice,OU=dev`
+    #[allow(dead_code)]
+    pub fn from_certificate(distinguished_name: &str) -> Self {
+        Self::user(distinguished_name)
+    }
+}
+
+impl fmt::Display for KafkaPrincipal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.principal_type, self.name)
+    }
+}
+
+/// https://github.com/apache/kafka/blob/trunk/clients/src/main/java/org/apache/kafka/common/acl/AclOperation.java
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    All,
+    Read,
+    Write,
+    Create,
+    Delete,
+    Alter,
+    Describe,
+    ClusterAction,
+    DescribeConfigs,
+    AlterConfigs,
+    IdempotentWrite,
+}
+
+impl FromStr for Operation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "All" => Self::All,
+            "Read" => Self::Read,
+            "Write" => Self::Write,
+            "Create" => Self::Create,
+            "Delete" => Self::Delete,
+            "Alter" => Self::Alter,
+            "Describe" => Self::Describe,
+            "ClusterAction" => Self::ClusterAction,
+            "DescribeConfigs" => Self::DescribeConfigs,
+            "AlterConfigs" => Self::AlterConfigs,
+            "IdempotentWrite" => Self::IdempotentWrite,
+            _ => bail!("unknown operation `{s}`"),
+        })
+    }
+}
+
+/// Resource the operation is performed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Resource<'a> {
+    Topic(&'a str),
+    Group(&'a str),
+    Cluster,
+}
+
+/// Decides whether a principal may perform an operation on a resource
+pub trait Authorizer: Send + Sync {
+    fn authorize(
+        &self,
+        principal: &KafkaPrincipal,
+        operation: Operation,
+        resource: Resource,
+    ) -> bool;
+}
+
+/// ACL allowing a principal an operation on resources, `*` matches any principal or resource name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    principal: String,
+    operation: Operation,
+    resource_type: String,
+    resource_name: String,
+}
+
+impl Acl {
+    fn matches_resource(&self, resource: Resource) -> bool {
+        let (resource_type, name) = match resource {
+            Resource::Topic(name) => ("Topic", name),
+            Resource::Group(name) => ("Group", name),
+            Resource::Cluster => ("Cluster", "kafka-cluster"),
+        };
+        self.resource_type == resource_type
+            && (self.resource_name == "*" || self.resource_name == name)
+    }
+
+    fn allows(&self, principal: &str, operation: Operation) -> bool {
+        (self.principal == "*" || self.principal == principal)
+            && (self.operation == Operation::All
+                || self.operation == operation
+                // like in Kafka, operations that change a resource imply describing it
+                || (operation == Operation::Describe
+                    && matches!(
+                        self.operation,
+                        Operation::Read | Operation::Write | Operation::Delete | Operation::Alter
+                    )))
+    }
+}
+
+/// Parses `<principal>,<operation>,<resource type>,<resource name>`, e.g. `User:alice,Read,Topic,payments`
+impl FromStr for Acl {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [principal, operation, resource_type, resource_name] = s
+            .split(',')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .try_into()
+            .ok()
+            .with_context(|| format!("invalid ACL `{s}`"))?;
+
+        if !matches!(resource_type, "Topic" | "Group" | "Cluster") {
+            bail!("unknown resource type `{resource_type}` in ACL `{s}`");
+        }
+
+        Ok(Self {
+            principal: principal.to_string(),
+            operation: operation
+                .parse()
+                .with_context(|| format!("invalid ACL `{s}`"))?,
+            resource_type: resource_type.to_string(),
+            resource_name: resource_name.to_string(),
+        })
+    }
+}
+
+/// Authorizer with a static list of allowing ACLs.
+///
+/// Resources without any ACL are accessible by everyone, as with Kafka's `allow.everyone.if.no.acl.found=true`.
+/// Once a resource has an ACL, only the principals it names are allowed.
+pub struct AclAuthorizer {
+    acls: Vec<Acl>,
+}
+
+impl AclAuthorizer {
+    pub fn new(acls: Vec<Acl>) -> Self {
+        Self { acls }
+    }
+
+    /// An ACL allows the principal the operation on the resource, resources without any ACL are not
+    /// accessible. For the operations that must never be allowed by default, e.g. the ClusterAction of brokers.
+    pub fn grants(
+        &self,
+        principal: &KafkaPrincipal,
+        operation: Operation,
+        resource: Resource,
+    ) -> bool {
+        let principal = principal.to_string();
+        self.acls
+            .iter()
+            .any(|acl| acl.matches_resource(resource) && acl.allows(&principal, operation))
+    }
+}
+
+impl Authorizer for AclAuthorizer {
+    fn authorize(
+        &self,
+        principal: &KafkaPrincipal,
+        operation: Operation,
+        resource: Resource,
+    ) -> bool {
+        let no_acl_found = !self.acls.iter().any(|acl| acl.matches_resource(resource));
+        no_acl_found || self.grants(principal, operation, resource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Acl, AclAuthorizer, Authorizer, KafkaPrincipal, Operation, Resource};
+
+    #[test]
+    fn allows_only_principals_with_acl() {
+        let acls = ["User:alice,Read,Topic,payments", "*,Describe,Topic,*"]
+            .iter()
+            .map(|acl| acl.parse::<Acl>().unwrap())
+            .collect();
+        let authorizer = AclAuthorizer::new(acls);
+        let payments = Resource::Topic("payments");
+        let alice = KafkaPrincipal::from_sasl("alice");
+        let bob = KafkaPrincipal::user("bob");
+
+        assert!(authorizer.authorize(&alice, Operation::Read, payments));
+        assert!(!authorizer.authorize(&bob, Operation::Read, payments));
+        assert!(authorizer.authorize(&bob, Operation::Describe, payments));
+        assert!(!authorizer.authorize(&alice, Operation::Write, payments));
+
+        // no ACL for groups
+        assert!(authorizer.authorize(&bob, Operation::Read, Resource::Group("g")));
+        assert!(!authorizer.grants(&bob, Operation::Read, Resource::Group("g")));
+        assert!(authorizer.grants(&alice, Operation::Read, payments));
+
+        assert!("User:alice,Read,Topic".parse::<Acl>().is_err());
+        assert!("User:alice,Fly,Topic,payments".parse::<Acl>().is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    ops::RangeInclusive,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::{
+    config::Config,
+    protocol::{
+        generated::{
+            broker_heartbeat_request::BrokerHeartbeatRequestData,
+            broker_heartbeat_response::BrokerHeartbeatResponseData,
+            broker_registration_request::BrokerRegistrationRequestData,
+            broker_registration_response::BrokerRegistrationResponseData,
+        },
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode,
+    },
+    storage::Storage,
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    RequestContext,
+};
+
+/// Broker registered with the controller
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisteredBroker {
+    pub broker_id: i32,
+    /// Random id of the broker process, a restarted broker registers with a new one
+    pub incarnation_id: String,
+    /// Epoch of the registration, heartbeats with another epoch are stale
+    pub epoch: i64,
+    pub rack: Option<String>,
+    /// Fenced brokers may not lead or host partitions in sync
+    pub fenced: bool,
+    /// Highest offset of the metadata log the broker has reached
+    pub metadata_offset: i64,
+    last_heartbeat: Instant,
+}
+
+/// State of the broker returned in the heartbeat response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatState {
+    pub caught_up: bool,
+    pub fenced: bool,
+    pub should_shut_down: bool,
+}
+
+struct Brokers {
+    registered: Vec<RegisteredBroker>,
+    next_epoch: i64,
+}
+
+/// Controller side of the broker lifecycle, as Kafka's `ClusterControlManager` and `BrokerHeartbeatManager`.
+///
+/// A broker registers and starts fenced. It is unfenced by the first heartbeat that reports it has caught up
+/// with the metadata log and does not want to be fenced. Brokers that do not send a heartbeat within the session
+/// timeout are fenced again. A broker asking to shut down is fenced and told to proceed at once, as it cannot
+/// lead any partitions to move away first.
+///
+/// Registrations are not appended to the metadata log, they are kept in memory and do not survive a restart.
+pub struct ClusterControl {
+    /// Brokers of another cluster are rejected, any cluster id is accepted if the log directory has none
+    cluster_id: Option<String>,
+    session_timeout: Duration,
+    brokers: Mutex<Brokers>,
+}
+
+impl ClusterControl {
+    pub fn new(cluster_id: Option<String>, session_timeout: Duration) -> Self {
+        Self {
+            cluster_id,
+            session_timeout,
+            brokers: Mutex::new(Brokers {
+                registered: Vec::new(),
+                next_epoch: 1,
+            }),
+        }
+    }
+
+    /// Cluster id of the log directory, if it has one
+    pub fn cluster_id(&self) -> Option<&str> {
+        self.cluster_id.as_deref()
+    }
+
+    /// Locks the brokers, the ones whose session expired are fenced
+    fn brokers(&self, now: Instant) -> MutexGuard<'_, Brokers> {
+        let mut brokers = self.brokers.lock().expect("broker lock is not poisoned");
+        for broker in &mut brokers.registered {
+            if !broker.fenced && now.duration_since(broker.last_heartbeat) > self.session_timeout {
+                eprintln!(
+                    "fencing broker {} without a heartbeat for {} ms",
+                    broker.broker_id,
+                    self.session_timeout.as_millis()
+                );
+                broker.fenced = true;
+            }
+        }
+        brokers
+    }
+
+    /// Fences the brokers whose session expired, without waiting for the next registration or heartbeat
+    pub fn fence_expired(&self, now: Instant) {
+        drop(self.brokers(now));
+    }
+
+    /// Registers the broker fenced with a new epoch, replacing the registration of its previous incarnation.
+    /// Returns the epoch.
+    pub fn register(
+        &self,
+        broker_id: i32,
+        cluster_id: &str,
+        incarnation_id: &str,
+        rack: Option<String>,
+        now: Instant,
+    ) -> Result<i64, ErrorCode> {
+        if self
+            .cluster_id
+            .as_deref()
+            .is_some_and(|id| id != cluster_id)
+        {
+            return Err(ErrorCode::InconsistentClusterId);
+        }
+
+        let session_timeout = self.session_timeout;
+        let mut brokers = self.brokers(now);
+        // another process with the same id is still sending heartbeats
+        if brokers.registered.iter().any(|b| {
+            b.broker_id == broker_id
+                && b.incarnation_id != incarnation_id
+                && now.duration_since(b.last_heartbeat) <= session_timeout
+        }) {
+            return Err(ErrorCode::DuplicateBrokerRegistration);
+        }
+
+        let epoch = brokers.next_epoch;
+        brokers.next_epoch += 1;
+        brokers.registered.retain(|b| b.broker_id != broker_id);
+        brokers.registered.push(RegisteredBroker {
+            broker_id,
+            incarnation_id: incarnation_id.to_string(),
+            epoch,
+            rack,
+            fenced: true,
+            metadata_offset: -1,
+            last_heartbeat: now,
+        });
+
+        Ok(epoch)
+    }
+
+    /// Renews the session of the broker and moves it between the fenced and unfenced state.
+    ///
+    /// The broker is caught up when it has reached `metadata_end_offset`, the end of the metadata log.
+    pub fn heartbeat(
+        &self,
+        req: &BrokerHeartbeatRequestData,
+        metadata_end_offset: i64,
+        now: Instant,
+    ) -> Result<HeartbeatState, ErrorCode> {
+        let mut brokers = self.brokers(now);
+        let broker = brokers
+            .registered
+            .iter_mut()
+            .find(|b| b.broker_id == req.broker_id)
+            .ok_or(ErrorCode::BrokerIdNotRegistered)?;
+        if broker.epoch != req.broker_epoch {
+            return Err(ErrorCode::StaleBrokerEpoch);
+        }
+
+        broker.last_heartbeat = now;
+        broker.metadata_offset = req.current_metadata_offset;
+        let caught_up = req.current_metadata_offset >= metadata_end_offset;
+        if req.want_fence || req.want_shut_down {
+            broker.fenced = true;
+        } else if caught_up {
+            broker.fenced = false;
+        }
+
+        Ok(HeartbeatState {
+            caught_up,
+            fenced: broker.fenced,
+            should_shut_down: req.want_shut_down,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn registered_brokers(&self, now: Instant) -> Vec<RegisteredBroker> {
+        self.brokers(now).registered.clone()
+    }
+}
+
+/// `cluster.id` from `meta.properties` of the log directory
+pub fn read_cluster_id(config: &Config, storage: &dyn Storage) -> Option<String> {
+    let properties = storage.read(&config.meta_properties_file()).ok()?;
+    let properties = std::str::from_utf8(&properties).ok()?;
+    properties.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "cluster.id").then(|| value.trim().to_string())
+    })
+}
+
+/// Brokers have to be allowed to act as a part of the cluster
+fn authorize_cluster_action(ctx: &RequestContext) -> Result<(), ErrorCode> {
+    if ctx
+        .broker
+        .authorizer
+        .authorize(&ctx.principal, Operation::ClusterAction, Resource::Cluster)
+    {
+        Ok(())
+    } else {
+        Err(ErrorCode::ClusterAuthorizationFailed)
+    }
+}
+
+fn message(ctx: &RequestContext, api_key: ApiKey, body: Bytes) -> Bytes {
+    let version = ctx.header.request_api_version;
+    let header = ResponseHeader::new(api_key, version, ctx.header.correlation_id);
+    response::message(header, body)
+}
+
+pub struct BrokerRegistrationHandler;
+
+impl Handler for BrokerRegistrationHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::BrokerRegistration
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        BrokerRegistrationRequestData::LOWEST_SUPPORTED_VERSION
+            ..=BrokerRegistrationRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            BrokerRegistrationRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        let result = authorize_cluster_action(ctx).and_then(|()| {
+            ctx.broker.cluster_control.register(
+                req.broker_id,
+                &req.cluster_id,
+                &req.incarnation_id,
+                req.rack,
+                Instant::now(),
+            )
+        });
+        let resp = BrokerRegistrationResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            error_code: result.err().unwrap_or(ErrorCode::None).into(),
+            broker_epoch: result.unwrap_or(-1),
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = BrokerRegistrationResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            error_code: error_code.into(),
+            broker_epoch: -1,
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+pub struct BrokerHeartbeatHandler;
+
+impl Handler for BrokerHeartbeatHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::BrokerHeartbeat
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        BrokerHeartbeatRequestData::LOWEST_SUPPORTED_VERSION
+            ..=BrokerHeartbeatRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            BrokerHeartbeatRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        // a missing or unreadable metadata log is empty
+        let metadata_end_offset = ctx
+            .broker
+            .metadata()
+            .map_or(0, |batches| batches.end_offset());
+
+        let result = authorize_cluster_action(ctx).and_then(|()| {
+            ctx.broker
+                .cluster_control
+                .heartbeat(&req, metadata_end_offset, Instant::now())
+        });
+        let resp = match result {
+            Ok(state) => BrokerHeartbeatResponseData {
+                throttle_time_ms: ctx.throttle_time_ms,
+                error_code: ErrorCode::None.into(),
+                is_caught_up: state.caught_up,
+                is_fenced: state.fenced,
+                should_shut_down: state.should_shut_down,
+            },
+            Err(error_code) => return Ok(self.error_response(ctx, error_code).unwrap_or_default()),
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = BrokerHeartbeatResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            error_code: error_code.into(),
+            ..Default::default()
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ClusterControl, HeartbeatState};
+    use crate::protocol::{
+        generated::broker_heartbeat_request::BrokerHeartbeatRequestData, ErrorCode,
+    };
+
+    fn heartbeat(broker_id: i32, epoch: i64, offset: i64) -> BrokerHeartbeatRequestData {
+        BrokerHeartbeatRequestData {
+            broker_id,
+            broker_epoch: epoch,
+            current_metadata_offset: offset,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn broker_lifecycle() {
+        let control = ClusterControl::new(Some("cluster".to_string()), Duration::from_secs(9));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(
+            control.register(1, "other", "a", None, start),
+            Err(ErrorCode::InconsistentClusterId)
+        );
+        let epoch = control.register(1, "cluster", "a", None, start).unwrap();
+        assert_eq!(
+            control.register(1, "cluster", "b", None, at(1)),
+            Err(ErrorCode::DuplicateBrokerRegistration)
+        );
+
+        // unfenced once caught up with the metadata log
+        let state = control.heartbeat(&heartbeat(1, epoch, 5), 10, at(1));
+        assert_eq!(
+            state,
+            Ok(HeartbeatState {
+                caught_up: false,
+                fenced: true,
+                should_shut_down: false
+            })
+        );
+        let state = control.heartbeat(&heartbeat(1, epoch, 10), 10, at(2));
+        assert!(!state.unwrap().fenced);
+        assert_eq!(
+            control.heartbeat(&heartbeat(1, epoch + 1, 10), 10, at(2)),
+            Err(ErrorCode::StaleBrokerEpoch)
+        );
+        assert_eq!(
+            control.heartbeat(&heartbeat(2, epoch, 10), 10, at(2)),
+            Err(ErrorCode::BrokerIdNotRegistered)
+        );
+
+        // fenced after the session expired, a new incarnation may register then
+        assert!(control.registered_brokers(at(12))[0].fenced);
+        let new_epoch = control.register(1, "cluster", "b", None, at(12)).unwrap();
+        assert!(new_epoch > epoch);
+
+        let shut_down = BrokerHeartbeatRequestData {
+            want_shut_down: true,
+            ..heartbeat(1, new_epoch, 10)
+        };
+        let state = control.heartbeat(&shut_down, 10, at(13));
+        assert!(state.unwrap().should_shut_down);
+        assert!(control.registered_brokers(at(13))[0].fenced);
+    }
+}
@@ -0,0 +1,520 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+
+use crate::{
+    config::{CompressionType, Config, TimestampType},
+    protocol::{
+        generated::{
+            describe_configs_request::{DescribeConfigsRequestData, DescribeConfigsResource},
+            describe_configs_response::{
+                DescribeConfigsResourceResult, DescribeConfigsResponseData, DescribeConfigsResult,
+                DescribeConfigsSynonym,
+            },
+        },
+        record_batch::{ConfigValue, RecordBatches},
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode,
+    },
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    metadata::broker_id,
+    RequestContext,
+};
+
+/// Resource type of the configs of a broker, Kafka's `ConfigResource.Type.BROKER`
+const BROKER_RESOURCE: i8 = 4;
+
+/// Where the value of a config comes from, Kafka's `DescribeConfigsResponse.ConfigSource`
+const TOPIC_CONFIG: i8 = 1;
+const STATIC_BROKER_CONFIG: i8 = 4;
+const DEFAULT_CONFIG: i8 = 5;
+
+/// Data types of the config values, Kafka's `DescribeConfigsResponse.ConfigType`
+const STRING: i8 = 2;
+const INT: i8 = 3;
+const LONG: i8 = 5;
+const LIST: i8 = 7;
+
+/// Config a topic may override, with the broker config it defaults to
+struct TopicConfig {
+    name: &'static str,
+    broker_name: &'static str,
+    config_type: i8,
+    documentation: &'static str,
+    /// Value of the broker config
+    value: fn(&Config) -> String,
+}
+
+/// Topic configs of the log, the ones the broker does not apply yet are described with Kafka's defaults
+const TOPIC_CONFIGS: &[TopicConfig] = &[
+    TopicConfig {
+        name: "cleanup.policy",
+        broker_name: "log.cleanup.policy",
+        config_type: LIST,
+        documentation: "The retention policy to use on log segments, delete or compact.",
+        value: |_| "delete".to_string(),
+    },
+    TopicConfig {
+        name: "compression.type",
+        broker_name: "compression.type",
+        config_type: STRING,
+        documentation: "The final compression type of the batches appended to the topic, producer keeps the codec of the producer.",
+        value: |config| {
+            match config.compression_type {
+                None => "producer",
+                Some(CompressionType::None) => "uncompressed",
+                Some(CompressionType::Gzip) => "gzip",
+            }
+            .to_string()
+        },
+    },
+    TopicConfig {
+        name: "max.message.bytes",
+        broker_name: "message.max.bytes",
+        config_type: INT,
+        documentation: "The largest record batch size allowed by Kafka, after compression if compression is enabled.",
+        value: |config| config.message_max_bytes.to_string(),
+    },
+    TopicConfig {
+        name: "message.timestamp.difference.max.ms",
+        broker_name: "log.message.timestamp.difference.max.ms",
+        config_type: LONG,
+        documentation: "The maximum difference allowed between the timestamp of a CreateTime record and the time of the broker.",
+        value: |config| {
+            config
+                .log_message_timestamp_difference_max
+                .map_or(i64::MAX, |max| max.as_millis() as i64)
+                .to_string()
+        },
+    },
+    TopicConfig {
+        name: "message.timestamp.type",
+        broker_name: "log.message.timestamp.type",
+        config_type: STRING,
+        documentation: "Whether the timestamp of the records is the create time or the log append time.",
+        value: |config| {
+            match config.log_message_timestamp_type {
+                TimestampType::CreateTime => "CreateTime",
+                TimestampType::LogAppendTime => "LogAppendTime",
+            }
+            .to_string()
+        },
+    },
+    TopicConfig {
+        name: "retention.bytes",
+        broker_name: "log.retention.bytes",
+        config_type: LONG,
+        documentation: "The maximum size a partition can grow to before old log segments are discarded, -1 for no limit.",
+        value: |_| "-1".to_string(),
+    },
+    TopicConfig {
+        name: "retention.ms",
+        broker_name: "log.retention.ms",
+        config_type: LONG,
+        documentation: "The maximum time a log segment is retained before it is discarded, -1 for no limit.",
+        value: |_| "604800000".to_string(),
+    },
+    TopicConfig {
+        name: "segment.bytes",
+        broker_name: "log.segment.bytes",
+        config_type: INT,
+        documentation: "The segment file size of the log.",
+        value: |_| "1073741824".to_string(),
+    },
+];
+
+/// What of the configs is described
+#[derive(Debug, Clone, Copy)]
+struct Describe {
+    include_synonyms: bool,
+    include_documentation: bool,
+}
+
+/// Value of the broker config and its source, set on the command line if it is not the default
+fn broker_value(topic_config: &TopicConfig, config: &Config) -> (String, i8) {
+    let value = (topic_config.value)(config);
+    let source = if value == (topic_config.value)(&Config::default()) {
+        DEFAULT_CONFIG
+    } else {
+        STATIC_BROKER_CONFIG
+    };
+    (value, source)
+}
+
+/// The requested configs, all of them if no keys are requested, unknown keys are left out
+fn requested<'a>(
+    resource: &'a DescribeConfigsResource,
+    name: impl Fn(&TopicConfig) -> &'static str + 'a,
+) -> impl Iterator<Item = &'static TopicConfig> + 'a {
+    TOPIC_CONFIGS
+        .iter()
+        .filter(move |topic_config| match &resource.configuration_keys {
+            Some(keys) => keys.iter().any(|key| key == name(topic_config)),
+            None => true,
+        })
+}
+
+fn entry(
+    name: &str,
+    (value, source): (String, i8),
+    read_only: bool,
+    topic_config: &TopicConfig,
+    synonyms: Vec<DescribeConfigsSynonym>,
+    describe: Describe,
+) -> DescribeConfigsResourceResult {
+    DescribeConfigsResourceResult {
+        name: name.to_string(),
+        value: Some(value),
+        read_only,
+        is_default: source == DEFAULT_CONFIG,
+        config_source: source,
+        is_sensitive: false,
+        synonyms: if describe.include_synonyms {
+            synonyms
+        } else {
+            Vec::new()
+        },
+        config_type: topic_config.config_type,
+        documentation: describe
+            .include_documentation
+            .then(|| topic_config.documentation.to_string()),
+    }
+}
+
+/// Configs of the topic, its overrides from the config records and otherwise the broker configs.
+///
+/// The synonyms are the override and the broker config, in the order of precedence.
+fn describe_topic(
+    config: &Config,
+    record_batches: &RecordBatches,
+    resource: &DescribeConfigsResource,
+    describe: Describe,
+) -> Vec<DescribeConfigsResourceResult> {
+    requested(resource, |topic_config| topic_config.name)
+        .map(|topic_config| {
+            let (broker_value, broker_source) = broker_value(topic_config, config);
+            let mut synonyms = vec![DescribeConfigsSynonym {
+                name: topic_config.broker_name.to_string(),
+                value: Some(broker_value.clone()),
+                source: broker_source,
+            }];
+            let value =
+                match record_batches.topic_config(&resource.resource_name, topic_config.name) {
+                    Some(value) => {
+                        synonyms.insert(
+                            0,
+                            DescribeConfigsSynonym {
+                                name: topic_config.name.to_string(),
+                                value: Some(value.to_string()),
+                                source: TOPIC_CONFIG,
+                            },
+                        );
+                        (value.to_string(), TOPIC_CONFIG)
+                    }
+                    None => (broker_value, broker_source),
+                };
+            entry(
+                topic_config.name,
+                value,
+                false,
+                topic_config,
+                synonyms,
+                describe,
+            )
+        })
+        .collect()
+}
+
+/// Configs of the broker the topic configs default to, they are read-only without dynamic broker configs
+fn describe_broker(
+    config: &Config,
+    resource: &DescribeConfigsResource,
+    describe: Describe,
+) -> Vec<DescribeConfigsResourceResult> {
+    requested(resource, |topic_config| topic_config.broker_name)
+        .map(|topic_config| {
+            let value = broker_value(topic_config, config);
+            let synonyms = vec![DescribeConfigsSynonym {
+                name: topic_config.broker_name.to_string(),
+                value: Some(value.0.clone()),
+                source: value.1,
+            }];
+            entry(
+                topic_config.broker_name,
+                value,
+                true,
+                topic_config,
+                synonyms,
+                describe,
+            )
+        })
+        .collect()
+}
+
+pub struct DescribeConfigsHandler;
+
+impl DescribeConfigsHandler {
+    fn describe(
+        &self,
+        ctx: &RequestContext,
+        record_batches: &RecordBatches,
+        resource: &DescribeConfigsResource,
+        describe: Describe,
+    ) -> Result<Vec<DescribeConfigsResourceResult>, (ErrorCode, String)> {
+        let config = &ctx.broker.config;
+        match resource.resource_type {
+            ConfigValue::TOPIC_RESOURCE => {
+                let topic = resource.resource_name.as_str();
+                // checked first, a denied principal must not learn whether the topic exists
+                if !ctx.broker.authorizer.authorize(
+                    &ctx.principal,
+                    Operation::DescribeConfigs,
+                    Resource::Topic(topic),
+                ) {
+                    return Err((
+                        ErrorCode::TopicAuthorizationFailed,
+                        format!("Not authorized to describe the configs of topic {topic}"),
+                    ));
+                }
+                if record_batches.topic_id(topic).is_none() {
+                    return Err((
+                        ErrorCode::UnknownTopicOrPartition,
+                        format!("Topic {topic} does not exist"),
+                    ));
+                }
+                Ok(describe_topic(config, record_batches, resource, describe))
+            }
+            BROKER_RESOURCE => {
+                // an empty name is the default config of all brokers
+                let name = resource.resource_name.as_str();
+                if !name.is_empty() && name != broker_id(config).to_string() {
+                    return Err((
+                        ErrorCode::InvalidRequest,
+                        format!("Unexpected broker id, expected {}", broker_id(config)),
+                    ));
+                }
+                if !ctx.broker.authorizer.authorize(
+                    &ctx.principal,
+                    Operation::DescribeConfigs,
+                    Resource::Cluster,
+                ) {
+                    return Err((
+                        ErrorCode::ClusterAuthorizationFailed,
+                        "Not authorized to describe the configs of the cluster".to_string(),
+                    ));
+                }
+                Ok(describe_broker(config, resource, describe))
+            }
+            resource_type => Err((
+                ErrorCode::InvalidRequest,
+                format!("Unsupported resource type {resource_type}"),
+            )),
+        }
+    }
+}
+
+impl Handler for DescribeConfigsHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::DescribeConfigs
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        DescribeConfigsRequestData::LOWEST_SUPPORTED_VERSION
+            ..=DescribeConfigsRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            DescribeConfigsRequestData::deserialize(src, header.request_api_version)
+        })?;
+        let version = ctx.header.request_api_version;
+        let describe = Describe {
+            include_synonyms: req.include_synonyms,
+            include_documentation: req.include_documentation,
+        };
+
+        let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
+        let results = req
+            .resources
+            .iter()
+            .map(|resource| {
+                let (error_code, error_message, configs) =
+                    match self.describe(ctx, &record_batches, resource, describe) {
+                        Ok(configs) => (ErrorCode::None, None, configs),
+                        Err((error_code, message)) => (error_code, Some(message), Vec::new()),
+                    };
+                DescribeConfigsResult {
+                    error_code: error_code.into(),
+                    error_message,
+                    resource_type: resource.resource_type,
+                    resource_name: resource.resource_name.clone(),
+                    configs,
+                }
+            })
+            .collect();
+
+        let resp = DescribeConfigsResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            results,
+        };
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Describe, DescribeConfigsHandler, BROKER_RESOURCE, DEFAULT_CONFIG, TOPIC_CONFIG};
+    use crate::{
+        config::Config,
+        logic::{BrokerContext, RequestContext},
+        protocol::{
+            generated::describe_configs_request::DescribeConfigsResource,
+            record_batch::{ConfigValue, RecordBatch, RecordValue, TopicValue},
+            types::Serialize,
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    fn resource(resource_type: i8, name: &str, keys: Option<&[&str]>) -> DescribeConfigsResource {
+        DescribeConfigsResource {
+            resource_type,
+            resource_name: name.to_string(),
+            configuration_keys: keys.map(|keys| keys.iter().map(|k| k.to_string()).collect()),
+        }
+    }
+
+    #[test]
+    fn describes_topic_overrides_and_broker_defaults() {
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-0000-0000-000000000001".to_string(),
+        });
+        let config = RecordValue::Config(ConfigValue {
+            resource_type: ConfigValue::TOPIC_RESOURCE,
+            resource_name: "foo".to_string(),
+            name: "max.message.bytes".to_string(),
+            value: Some("2048".to_string()),
+        });
+        let metadata = RecordBatch::of_values(0, vec![topic, config]).serialize();
+        let storage =
+            MemoryStorage::with_files([(Config::default().metadata_log_file(), metadata)]);
+        let ctx = RequestContext::for_request(
+            BrokerContext::with_storage(storage),
+            ApiKey::DescribeConfigs,
+            4,
+        );
+        let record_batches = ctx.broker.metadata().unwrap();
+        let describe = Describe {
+            include_synonyms: true,
+            include_documentation: false,
+        };
+
+        let keys = ["max.message.bytes", "retention.ms", "unknown"];
+        let configs = DescribeConfigsHandler
+            .describe(
+                &ctx,
+                &record_batches,
+                &resource(ConfigValue::TOPIC_RESOURCE, "foo", Some(&keys)),
+                describe,
+            )
+            .unwrap();
+        let described: Vec<_> = configs
+            .iter()
+            .map(|c| (c.name.as_str(), c.value.as_deref(), c.config_source))
+            .collect();
+        assert_eq!(
+            described,
+            [
+                ("max.message.bytes", Some("2048"), TOPIC_CONFIG),
+                ("retention.ms", Some("604800000"), DEFAULT_CONFIG),
+            ]
+        );
+        let synonyms: Vec<_> = configs[0]
+            .synonyms
+            .iter()
+            .map(|s| (s.name.as_str(), s.value.as_deref()))
+            .collect();
+        assert_eq!(
+            synonyms,
+            [
+                ("max.message.bytes", Some("2048")),
+                ("message.max.bytes", Some("1048588"))
+            ]
+        );
+
+        // all configs of the broker, without a topic override
+        let configs = DescribeConfigsHandler
+            .describe(
+                &ctx,
+                &record_batches,
+                &resource(BROKER_RESOURCE, "1", None),
+                describe,
+            )
+            .unwrap();
+        let max = configs
+            .iter()
+            .find(|c| c.name == "message.max.bytes")
+            .unwrap();
+        assert_eq!(max.value.as_deref(), Some("1048588"));
+        assert!(max.read_only && max.is_default);
+
+        let err = |resource| {
+            DescribeConfigsHandler
+                .describe(&ctx, &record_batches, &resource, describe)
+                .unwrap_err()
+                .0
+        };
+        assert_eq!(
+            err(resource(ConfigValue::TOPIC_RESOURCE, "bar", None)),
+            ErrorCode::UnknownTopicOrPartition
+        );
+        assert_eq!(
+            err(resource(BROKER_RESOURCE, "2", None)),
+            ErrorCode::InvalidRequest
+        );
+        assert_eq!(err(resource(8, "", None)), ErrorCode::InvalidRequest);
+    }
+
+    #[test]
+    fn unauthorized_topics_are_not_told_apart_from_missing_ones() {
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-0000-0000-000000000001".to_string(),
+        });
+        let config = Config {
+            acls: vec!["User:alice,DescribeConfigs,Topic,*".parse().unwrap()],
+            ..Config::default()
+        };
+        let metadata = RecordBatch::of_values(0, vec![topic]).serialize();
+        let storage = MemoryStorage::with_files([(config.metadata_log_file(), metadata)]);
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(storage));
+        let ctx = RequestContext::for_request(Arc::new(broker), ApiKey::DescribeConfigs, 4);
+        let record_batches = ctx.broker.metadata().unwrap();
+        let describe = Describe {
+            include_synonyms: false,
+            include_documentation: false,
+        };
+
+        for name in ["foo", "missing"] {
+            let resource = resource(ConfigValue::TOPIC_RESOURCE, name, None);
+            let (error_code, message) = DescribeConfigsHandler
+                .describe(&ctx, &record_batches, &resource, describe)
+                .unwrap_err();
+            assert_eq!(error_code, ErrorCode::TopicAuthorizationFailed);
+            assert_eq!(
+                message,
+                format!("Not authorized to describe the configs of topic {name}")
+            );
+        }
+    }
+}
@@ -0,0 +1,643 @@
+mod hmac;
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    ops::RangeInclusive,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::protocol::{
+    generated::{
+        create_delegation_token_request::CreateDelegationTokenRequestData,
+        create_delegation_token_response::CreateDelegationTokenResponseData,
+        describe_delegation_token_request::DescribeDelegationTokenRequestData,
+        describe_delegation_token_response::{
+            DescribeDelegationTokenResponseData, DescribedDelegationToken,
+            DescribedDelegationTokenRenewer,
+        },
+        expire_delegation_token_request::ExpireDelegationTokenRequestData,
+        expire_delegation_token_response::ExpireDelegationTokenResponseData,
+        renew_delegation_token_request::RenewDelegationTokenRequestData,
+        renew_delegation_token_response::RenewDelegationTokenResponseData,
+    },
+    response::{self, ResponseHeader},
+    ApiKey, ErrorCode,
+};
+
+use super::{
+    authorizer::KafkaPrincipal,
+    deserialize,
+    handler::Handler,
+    now_ms,
+    sasl::{constant_time_eq, Authentication},
+    RequestContext,
+};
+
+/// Delegation token, its HMAC is the password of the token
+// https://kafka.apache.org/documentation/#security_delegation_token
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelegationToken {
+    pub token_id: String,
+    pub hmac: Vec<u8>,
+    pub owner: KafkaPrincipal,
+    pub requester: KafkaPrincipal,
+    pub renewers: Vec<KafkaPrincipal>,
+    pub issue_timestamp_ms: i64,
+    pub expiry_timestamp_ms: i64,
+    pub max_timestamp_ms: i64,
+}
+
+impl DelegationToken {
+    fn may_renew(&self, principal: &KafkaPrincipal) -> bool {
+        self.owner == *principal || self.renewers.contains(principal)
+    }
+
+    fn may_describe(&self, principal: &KafkaPrincipal) -> bool {
+        self.may_renew(principal) || self.requester == *principal
+    }
+
+    fn is_expired(&self, now_ms: i64) -> bool {
+        self.expiry_timestamp_ms < now_ms || self.max_timestamp_ms < now_ms
+    }
+}
+
+/// Store of the delegation tokens issued by the broker.
+///
+/// Tokens are enabled only when the secret key the HMACs are computed with is configured, as with
+/// Kafka's `delegation.token.secret.key`. They are kept in memory and do not survive a restart.
+pub struct TokenStore {
+    secret_key: Option<Vec<u8>>,
+    max_lifetime: Duration,
+    expiry_time: Duration,
+    tokens: Mutex<Vec<DelegationToken>>,
+}
+
+impl TokenStore {
+    pub fn new(secret_key: Option<&str>, max_lifetime: Duration, expiry_time: Duration) -> Self {
+        Self {
+            secret_key: secret_key.map(|key| key.as_bytes().to_vec()),
+            max_lifetime,
+            expiry_time,
+            tokens: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Locks the tokens, the expired ones are removed
+    fn tokens(
+        &self,
+        now_ms: i64,
+    ) -> Result<std::sync::MutexGuard<'_, Vec<DelegationToken>>, ErrorCode> {
+        if self.secret_key.is_none() {
+            return Err(ErrorCode::DelegationTokenAuthDisabled);
+        }
+        let mut tokens = self.tokens.lock().expect("token lock is not poisoned");
+        tokens.retain(|token| !token.is_expired(now_ms));
+        Ok(tokens)
+    }
+
+    /// Removes the expired tokens, as Kafka does every `delegation.token.expiry.check.interval.ms`
+    pub fn remove_expired(&self, now_ms: i64) {
+        // nothing to remove when tokens are disabled
+        if let Ok(tokens) = self.tokens(now_ms) {
+            drop(tokens);
+        }
+    }
+
+    /// Issues a token, its lifetime is capped by `delegation.token.max.lifetime.ms`
+    pub fn create(
+        &self,
+        requester: &KafkaPrincipal,
+        owner: Option<KafkaPrincipal>,
+        renewers: Vec<KafkaPrincipal>,
+        max_lifetime_ms: i64,
+        now_ms: i64,
+    ) -> Result<DelegationToken, ErrorCode> {
+        let mut tokens = self.tokens(now_ms)?;
+
+        // there are no ACLs for users, a token can be created only for oneself
+        let owner = owner.unwrap_or_else(|| requester.clone());
+        if owner != *requester {
+            return Err(ErrorCode::DelegationTokenAuthorizationFailed);
+        }
+
+        let server_max_lifetime_ms = self.max_lifetime.as_millis() as i64;
+        let max_lifetime_ms = if max_lifetime_ms <= 0 {
+            server_max_lifetime_ms
+        } else {
+            max_lifetime_ms.min(server_max_lifetime_ms)
+        };
+        let max_timestamp_ms = now_ms + max_lifetime_ms;
+
+        let token_id = random_token_id();
+        let secret_key = self.secret_key.as_deref().unwrap_or_default();
+        let token = DelegationToken {
+            hmac: hmac::hmac_sha256(secret_key, token_id.as_bytes()).to_vec(),
+            token_id,
+            owner,
+            requester: requester.clone(),
+            renewers,
+            issue_timestamp_ms: now_ms,
+            expiry_timestamp_ms: max_timestamp_ms.min(now_ms + self.expiry_time.as_millis() as i64),
+            max_timestamp_ms,
+        };
+        tokens.push(token.clone());
+
+        Ok(token)
+    }
+
+    /// Extends the expiry of the token by the period, `delegation.token.expiry.time.ms` if it is negative.
+    /// Returns the new expiry timestamp.
+    pub fn renew(
+        &self,
+        principal: &KafkaPrincipal,
+        hmac: &[u8],
+        renew_period_ms: i64,
+        now_ms: i64,
+    ) -> Result<i64, ErrorCode> {
+        let mut tokens = self.tokens(now_ms)?;
+        let token = find_token(&mut tokens, principal, hmac)?;
+
+        let renew_period_ms = if renew_period_ms < 0 {
+            self.expiry_time.as_millis() as i64
+        } else {
+            renew_period_ms
+        };
+        // the period of the client may be as long as it likes, the expiry is capped anyway
+        token.expiry_timestamp_ms = token
+            .max_timestamp_ms
+            .min(now_ms.saturating_add(renew_period_ms));
+
+        Ok(token.expiry_timestamp_ms)
+    }
+
+    /// Token of the id with the HMAC, the password of a client logging in with the token.
+    /// `None` if there is no such token, it expired or tokens are disabled.
+    pub fn authenticate(
+        &self,
+        token_id: &str,
+        hmac: &[u8],
+        now_ms: i64,
+    ) -> Option<DelegationToken> {
+        let tokens = self.tokens(now_ms).ok()?;
+        tokens
+            .iter()
+            .find(|token| token.token_id == token_id && constant_time_eq(&token.hmac, hmac))
+            .cloned()
+    }
+
+    /// Sets the expiry of the token to the period from now, a negative period expires it immediately.
+    /// Returns the new expiry timestamp.
+    pub fn expire(
+        &self,
+        principal: &KafkaPrincipal,
+        hmac: &[u8],
+        expiry_period_ms: i64,
+        now_ms: i64,
+    ) -> Result<i64, ErrorCode> {
+        let mut tokens = self.tokens(now_ms)?;
+        let token = find_token(&mut tokens, principal, hmac)?;
+
+        if expiry_period_ms < 0 {
+            let token_id = token.token_id.clone();
+            tokens.retain(|token| token.token_id != token_id);
+            return Ok(now_ms);
+        }
+        token.expiry_timestamp_ms = token
+            .max_timestamp_ms
+            .min(now_ms.saturating_add(expiry_period_ms));
+
+        Ok(token.expiry_timestamp_ms)
+    }
+
+    /// Tokens of the owners the principal may see, tokens of all owners if there are none
+    pub fn describe(
+        &self,
+        principal: &KafkaPrincipal,
+        owners: &[KafkaPrincipal],
+        now_ms: i64,
+    ) -> Result<Vec<DelegationToken>, ErrorCode> {
+        let tokens = self.tokens(now_ms)?;
+
+        Ok(tokens
+            .iter()
+            .filter(|token| owners.is_empty() || owners.contains(&token.owner))
+            .filter(|token| token.may_describe(principal))
+            .cloned()
+            .collect())
+    }
+}
+
+fn find_token<'a>(
+    tokens: &'a mut [DelegationToken],
+    principal: &KafkaPrincipal,
+    hmac: &[u8],
+) -> Result<&'a mut DelegationToken, ErrorCode> {
+    let token = tokens
+        .iter_mut()
+        .find(|token| constant_time_eq(&token.hmac, hmac))
+        .ok_or(ErrorCode::DelegationTokenNotFound)?;
+
+    if !token.may_renew(principal) {
+        return Err(ErrorCode::DelegationTokenOwnerMismatch);
+    }
+    Ok(token)
+}
+
+/// Unpredictable token id, the HMAC of the id is the password of the token
+fn random_token_id() -> String {
+    let random_u64 = || {
+        let mut hasher = RandomState::new().build_hasher();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        hasher.write_u128(now.unwrap_or_default().as_nanos());
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", random_u64(), random_u64())
+}
+
+fn principal(principal_type: &str, name: &str) -> KafkaPrincipal {
+    KafkaPrincipal {
+        principal_type: principal_type.to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn message(ctx: &RequestContext, api_key: ApiKey, body: Bytes) -> Bytes {
+    let version = ctx.header.request_api_version;
+    let header = ResponseHeader::new(api_key, version, ctx.header.correlation_id);
+    response::message(header, body)
+}
+
+pub struct CreateDelegationTokenHandler;
+
+impl Handler for CreateDelegationTokenHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::CreateDelegationToken
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        CreateDelegationTokenRequestData::LOWEST_SUPPORTED_VERSION
+            ..=CreateDelegationTokenRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            CreateDelegationTokenRequestData::deserialize(src, header.request_api_version)
+        })?;
+        // as in Kafka, a token is issued only to a client that authenticated with its own credentials,
+        // a token cannot be created with another token
+        if ctx.authentication != Authentication::Password {
+            let error_code = ErrorCode::DelegationTokenRequestNotAllowed;
+            return Ok(self.error_response(ctx, error_code).unwrap_or_default());
+        }
+
+        let owner = match (req.owner_principal_type, req.owner_principal_name) {
+            (Some(principal_type), Some(name)) => Some(principal(&principal_type, &name)),
+            _ => None,
+        };
+        let renewers = req
+            .renewers
+            .iter()
+            .map(|r| principal(&r.principal_type, &r.principal_name))
+            .collect();
+
+        let resp = match ctx.broker.tokens.create(
+            &ctx.principal,
+            owner,
+            renewers,
+            req.max_lifetime_ms,
+            now_ms(),
+        ) {
+            Ok(token) => CreateDelegationTokenResponseData {
+                error_code: ErrorCode::None.into(),
+                principal_type: token.owner.principal_type,
+                principal_name: token.owner.name,
+                token_requester_principal_type: token.requester.principal_type,
+                token_requester_principal_name: token.requester.name,
+                issue_timestamp_ms: token.issue_timestamp_ms,
+                expiry_timestamp_ms: token.expiry_timestamp_ms,
+                max_timestamp_ms: token.max_timestamp_ms,
+                token_id: token.token_id,
+                hmac: token.hmac,
+                throttle_time_ms: ctx.throttle_time_ms,
+            },
+            Err(error_code) => return Ok(self.error_response(ctx, error_code).unwrap_or_default()),
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = CreateDelegationTokenResponseData {
+            error_code: error_code.into(),
+            principal_type: ctx.principal.principal_type.clone(),
+            principal_name: ctx.principal.name.clone(),
+            token_requester_principal_type: ctx.principal.principal_type.clone(),
+            token_requester_principal_name: ctx.principal.name.clone(),
+            throttle_time_ms: ctx.throttle_time_ms,
+            ..Default::default()
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+pub struct RenewDelegationTokenHandler;
+
+impl Handler for RenewDelegationTokenHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::RenewDelegationToken
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        RenewDelegationTokenRequestData::LOWEST_SUPPORTED_VERSION
+            ..=RenewDelegationTokenRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            RenewDelegationTokenRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        let result =
+            ctx.broker
+                .tokens
+                .renew(&ctx.principal, &req.hmac, req.renew_period_ms, now_ms());
+        let resp = RenewDelegationTokenResponseData {
+            error_code: result.err().unwrap_or(ErrorCode::None).into(),
+            expiry_timestamp_ms: result.unwrap_or_default(),
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = RenewDelegationTokenResponseData {
+            error_code: error_code.into(),
+            expiry_timestamp_ms: 0,
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+pub struct ExpireDelegationTokenHandler;
+
+impl Handler for ExpireDelegationTokenHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::ExpireDelegationToken
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        ExpireDelegationTokenRequestData::LOWEST_SUPPORTED_VERSION
+            ..=ExpireDelegationTokenRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            ExpireDelegationTokenRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        let result = ctx.broker.tokens.expire(
+            &ctx.principal,
+            &req.hmac,
+            req.expiry_time_period_ms,
+            now_ms(),
+        );
+        let resp = ExpireDelegationTokenResponseData {
+            error_code: result.err().unwrap_or(ErrorCode::None).into(),
+            expiry_timestamp_ms: result.unwrap_or_default(),
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = ExpireDelegationTokenResponseData {
+            error_code: error_code.into(),
+            expiry_timestamp_ms: 0,
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+pub struct DescribeDelegationTokenHandler;
+
+impl Handler for DescribeDelegationTokenHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::DescribeDelegationToken
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        DescribeDelegationTokenRequestData::LOWEST_SUPPORTED_VERSION
+            ..=DescribeDelegationTokenRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            DescribeDelegationTokenRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        // a null array of owners and an empty one both describe the tokens of all owners
+        let owners: Vec<_> = req
+            .owners
+            .iter()
+            .flatten()
+            .map(|o| principal(&o.principal_type, &o.principal_name))
+            .collect();
+
+        let tokens = match ctx
+            .broker
+            .tokens
+            .describe(&ctx.principal, &owners, now_ms())
+        {
+            Ok(tokens) => tokens,
+            Err(error_code) => return Ok(self.error_response(ctx, error_code).unwrap_or_default()),
+        };
+        let resp = DescribeDelegationTokenResponseData {
+            error_code: ErrorCode::None.into(),
+            tokens: tokens
+                .into_iter()
+                .map(|token| DescribedDelegationToken {
+                    principal_type: token.owner.principal_type,
+                    principal_name: token.owner.name,
+                    token_requester_principal_type: token.requester.principal_type,
+                    token_requester_principal_name: token.requester.name,
+                    issue_timestamp: token.issue_timestamp_ms,
+                    expiry_timestamp: token.expiry_timestamp_ms,
+                    max_timestamp: token.max_timestamp_ms,
+                    token_id: token.token_id,
+                    hmac: token.hmac,
+                    renewers: token
+                        .renewers
+                        .into_iter()
+                        .map(|r| DescribedDelegationTokenRenewer {
+                            principal_type: r.principal_type,
+                            principal_name: r.name,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+
+        let version = ctx.header.request_api_version;
+        Ok(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = DescribeDelegationTokenResponseData {
+            error_code: error_code.into(),
+            tokens: Vec::new(),
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+        let version = ctx.header.request_api_version;
+        Some(message(ctx, self.api_key(), resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::{CreateDelegationTokenHandler, TokenStore};
+    use crate::{
+        config::Config,
+        logic::{
+            authorizer::KafkaPrincipal, handler::Handler, sasl::Authentication, BrokerContext,
+            RequestContext,
+        },
+        protocol::{
+            generated::create_delegation_token_request::CreateDelegationTokenRequestData, ApiKey,
+            ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    #[test]
+    fn token_lifecycle() {
+        let store = TokenStore::new(
+            Some("secret"),
+            Duration::from_millis(24 * HOUR_MS as u64),
+            Duration::from_millis(HOUR_MS as u64),
+        );
+        let alice = KafkaPrincipal::user("alice");
+        let bob = KafkaPrincipal::user("bob");
+        let carol = KafkaPrincipal::user("carol");
+
+        let token = store
+            .create(&alice, None, vec![bob.clone()], -1, 0)
+            .unwrap();
+        assert_eq!(token.owner, alice);
+        assert_eq!(token.expiry_timestamp_ms, HOUR_MS);
+        assert_eq!(token.max_timestamp_ms, 24 * HOUR_MS);
+        assert_eq!(token.hmac.len(), 32);
+        assert_eq!(
+            store.create(&alice, Some(bob.clone()), vec![], -1, 0),
+            Err(ErrorCode::DelegationTokenAuthorizationFailed)
+        );
+
+        // renewers may extend the expiry up to the max lifetime
+        assert_eq!(
+            store.renew(&bob, &token.hmac, 2 * HOUR_MS, 0),
+            Ok(2 * HOUR_MS)
+        );
+        assert_eq!(
+            store.renew(&bob, &token.hmac, 48 * HOUR_MS, 0),
+            Ok(24 * HOUR_MS)
+        );
+        assert_eq!(
+            store.renew(&carol, &token.hmac, -1, 0),
+            Err(ErrorCode::DelegationTokenOwnerMismatch)
+        );
+        assert_eq!(
+            store.renew(&bob, b"unknown", -1, 0),
+            Err(ErrorCode::DelegationTokenNotFound)
+        );
+
+        let described = store.describe(&alice, &[], 0).unwrap();
+        assert_eq!(described.len(), 1);
+        assert_eq!(described[0].token_id, token.token_id);
+        assert!(store.describe(&carol, &[], 0).unwrap().is_empty());
+        assert!(store.describe(&alice, &[carol], 0).unwrap().is_empty());
+
+        // expired tokens are removed
+        assert_eq!(store.expire(&alice, &token.hmac, 0, HOUR_MS), Ok(HOUR_MS));
+        assert!(store.describe(&alice, &[], HOUR_MS + 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn periods_are_capped_by_the_max_lifetime() {
+        let store = TokenStore::new(
+            Some("secret"),
+            Duration::from_millis(24 * HOUR_MS as u64),
+            Duration::from_millis(HOUR_MS as u64),
+        );
+        let alice = KafkaPrincipal::user("alice");
+        let token = store.create(&alice, None, vec![], -1, 0).unwrap();
+
+        assert_eq!(
+            store.renew(&alice, &token.hmac, i64::MAX, HOUR_MS),
+            Ok(24 * HOUR_MS)
+        );
+        assert_eq!(
+            store.expire(&alice, &token.hmac, i64::MAX, HOUR_MS),
+            Ok(24 * HOUR_MS)
+        );
+    }
+
+    #[test]
+    fn disabled_without_secret_key() {
+        let store = TokenStore::new(None, Duration::from_secs(60), Duration::from_secs(60));
+        let alice = KafkaPrincipal::user("alice");
+        assert_eq!(
+            store.create(&alice, None, vec![], -1, 0),
+            Err(ErrorCode::DelegationTokenAuthDisabled)
+        );
+    }
+
+    #[test]
+    fn tokens_are_created_only_for_password_logins() {
+        let config = Config {
+            delegation_token_secret_key: Some("secret".to_string()),
+            ..Config::default()
+        };
+        let broker = Arc::new(BrokerContext::new(
+            Arc::new(config),
+            Arc::new(MemoryStorage::default()),
+        ));
+        let create = |authentication| {
+            let mut ctx =
+                RequestContext::for_request(broker.clone(), ApiKey::CreateDelegationToken, 1);
+            ctx.principal = KafkaPrincipal::user("alice");
+            ctx.authentication = authentication;
+            let body = CreateDelegationTokenRequestData {
+                max_lifetime_ms: -1,
+                ..Default::default()
+            }
+            .serialize(1);
+            let response = CreateDelegationTokenHandler.handle(&ctx, body).unwrap();
+            // the error code after the size and the correlation id of the response
+            i16::from_be_bytes([response[8], response[9]])
+        };
+
+        assert_eq!(create(Authentication::Password), ErrorCode::None.into());
+        let not_allowed = i16::from(ErrorCode::DelegationTokenRequestNotAllowed);
+        assert_eq!(create(Authentication::None), not_allowed);
+        assert_eq!(create(Authentication::DelegationToken), not_allowed);
+    }
+}
@@ -0,0 +1,255 @@
+use std::ops::RangeInclusive;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::protocol::{
+    generated::{
+        default_principal_data::DefaultPrincipalData, envelope_request::EnvelopeRequestData,
+        envelope_response::EnvelopeResponseData,
+    },
+    reader::ByteReader,
+    request::RequestHeader,
+    response::{self, ResponseHeader},
+    ApiKey, ErrorCode, ProtocolError,
+};
+
+use super::{
+    authorizer::{KafkaPrincipal, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    process,
+    sasl::Authentication,
+    RequestContext,
+};
+
+/// Principal of the client serialized by the forwarding broker, as Kafka's `DefaultKafkaPrincipalBuilder`,
+/// the `DefaultPrincipalData` prefixed with its version. Returns the principal and whether the client
+/// authenticated with a delegation token.
+pub fn deserialize_principal(bytes: Bytes) -> Result<(KafkaPrincipal, bool), ProtocolError> {
+    let mut src = ByteReader::new(bytes);
+    let version = src.get_i16("principal_data_version")?;
+    let data = DefaultPrincipalData::deserialize(&mut src, version)?;
+
+    let principal = KafkaPrincipal {
+        principal_type: data.r#type,
+        name: data.name,
+    };
+    Ok((principal, data.token_authenticated))
+}
+
+/// Requests forwarded by another broker, e.g. admin requests relayed to the controller in KRaft mode.
+///
+/// The embedded request is processed as if the client sent it, with the client's principal for
+/// the authorization. An ACL has to allow the forwarding broker the ClusterAction on the cluster, even when
+/// the cluster has no ACLs at all, so that no client can act as another principal.
+pub struct EnvelopeHandler;
+
+impl EnvelopeHandler {
+    fn forward(&self, ctx: &RequestContext, req: EnvelopeRequestData) -> Result<Bytes, ErrorCode> {
+        if !ctx.broker.authorizer.grants(
+            &ctx.principal,
+            Operation::ClusterAction,
+            Resource::Cluster,
+        ) {
+            return Err(ErrorCode::ClusterAuthorizationFailed);
+        }
+
+        let (principal, token_authenticated) =
+            deserialize_principal(Bytes::from(req.request_principal)).map_err(|err| {
+                eprintln!("Error: deserialize principal of Envelope request: {err}");
+                ErrorCode::PrincipalDeserializationFailure
+            })?;
+
+        let mut reader = ByteReader::new(Bytes::from(req.request_data));
+        let header = RequestHeader::from_bytes(&mut reader).map_err(|err| {
+            eprintln!("Error: deserialize header of request in Envelope: {err}");
+            ErrorCode::InvalidRequest
+        })?;
+        // envelopes are not forwarded again
+        if header.request_api_key == i16::from(ApiKey::Envelope) {
+            return Err(ErrorCode::InvalidRequest);
+        }
+
+        // the client authenticated with the forwarding broker, which tells whether it used a token
+        let authentication = if token_authenticated {
+            Authentication::DelegationToken
+        } else {
+            ctx.authentication
+        };
+        let processed = process(
+            ctx.broker.clone(),
+            header,
+            principal,
+            authentication,
+            reader.into_bytes(),
+        )
+        .map_err(|err| {
+            eprintln!("Error: process request in Envelope: {err:#}");
+            ErrorCode::InvalidRequest
+        })?;
+
+        // the embedded response has no size, a request without a response has none at all
+        Ok(processed.response.slice(processed.response.len().min(4)..))
+    }
+}
+
+impl Handler for EnvelopeHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::Envelope
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        EnvelopeRequestData::LOWEST_SUPPORTED_VERSION
+            ..=EnvelopeRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            EnvelopeRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        let resp = match self.forward(ctx, req) {
+            Ok(response_data) => EnvelopeResponseData {
+                response_data: response_data.to_vec(),
+                error_code: ErrorCode::None.into(),
+            },
+            Err(error_code) => return Ok(self.error_response(ctx, error_code).unwrap_or_default()),
+        };
+
+        let version = ctx.header.request_api_version;
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = EnvelopeResponseData {
+            response_data: Vec::new(),
+            error_code: error_code.into(),
+        };
+        let version = ctx.header.request_api_version;
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Some(response::message(header, resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use super::{deserialize_principal, EnvelopeHandler};
+    use crate::{
+        config::Config,
+        logic::{authorizer::KafkaPrincipal, sasl::Authentication, BrokerContext, RequestContext},
+        protocol::{
+            generated::{
+                create_delegation_token_request::CreateDelegationTokenRequestData,
+                default_principal_data::DefaultPrincipalData,
+                envelope_request::EnvelopeRequestData,
+            },
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    /// Principal data of the client, as the forwarding broker serializes it
+    fn principal_data(name: &str, token_authenticated: bool) -> Vec<u8> {
+        let data = DefaultPrincipalData {
+            r#type: "User".to_string(),
+            name: name.to_string(),
+            token_authenticated,
+        };
+        let mut bytes = BytesMut::new();
+        bytes.put_i16(0);
+        bytes.put(data.serialize(0));
+        bytes.to_vec()
+    }
+
+    /// Context of the Envelope request of the forwarding principal, with the ACLs
+    fn context(acls: &[&str], principal: KafkaPrincipal) -> RequestContext {
+        let config = Config {
+            acls: acls.iter().map(|acl| acl.parse().unwrap()).collect(),
+            delegation_token_secret_key: Some("secret".to_string()),
+            ..Config::default()
+        };
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(MemoryStorage::default()));
+        let mut ctx = RequestContext::for_request(Arc::new(broker), ApiKey::Envelope, 0);
+        ctx.principal = principal;
+        ctx
+    }
+
+    #[test]
+    fn forwarding_requires_an_acl_for_the_cluster_action() {
+        // ApiVersions v0 request of correlation id 7, without a client id
+        let mut request_data = vec![0, 18, 0, 0, 0, 0, 0, 7];
+        request_data.extend_from_slice(&(-1i16).to_be_bytes());
+        let envelope = || EnvelopeRequestData {
+            request_data: request_data.clone(),
+            request_principal: principal_data("alice", false),
+            client_host_address: vec![127, 0, 0, 1],
+        };
+
+        // a cluster without ACLs for the cluster does not allow anyone to act as alice
+        let ctx = context(
+            &["User:alice,Read,Topic,payments"],
+            KafkaPrincipal::anonymous(),
+        );
+        assert_eq!(
+            EnvelopeHandler.forward(&ctx, envelope()),
+            Err(ErrorCode::ClusterAuthorizationFailed)
+        );
+
+        let ctx = context(
+            &["User:broker,ClusterAction,Cluster,kafka-cluster"],
+            KafkaPrincipal::user("broker"),
+        );
+        let response = EnvelopeHandler.forward(&ctx, envelope()).unwrap();
+        // the correlation id and the error code of the embedded response
+        assert_eq!(response[..6], [0, 0, 0, 7, 0, 0]);
+    }
+
+    #[test]
+    fn forwarded_token_logins_may_not_create_tokens() {
+        // CreateDelegationToken v1 request of correlation id 7, without a client id
+        let mut request_data = vec![0, 38, 0, 1, 0, 0, 0, 7];
+        request_data.extend_from_slice(&(-1i16).to_be_bytes());
+        let body = CreateDelegationTokenRequestData {
+            max_lifetime_ms: -1,
+            ..Default::default()
+        };
+        request_data.extend_from_slice(&body.serialize(1));
+
+        let mut ctx = context(
+            &["User:broker,ClusterAction,Cluster,kafka-cluster"],
+            KafkaPrincipal::user("broker"),
+        );
+        ctx.authentication = Authentication::Password;
+        let create = |token_authenticated| {
+            let envelope = EnvelopeRequestData {
+                request_data: request_data.clone(),
+                request_principal: principal_data("alice", token_authenticated),
+                client_host_address: vec![127, 0, 0, 1],
+            };
+            let response = EnvelopeHandler.forward(&ctx, envelope).unwrap();
+            // the error code after the correlation id of the embedded response
+            i16::from_be_bytes([response[4], response[5]])
+        };
+
+        assert_eq!(create(false), i16::from(ErrorCode::None));
+        assert_eq!(
+            create(true),
+            i16::from(ErrorCode::DelegationTokenRequestNotAllowed)
+        );
+    }
+
+    #[test]
+    fn deserializes_forwarded_principal() {
+        assert_eq!(
+            deserialize_principal(Bytes::from(principal_data("alice", false))).unwrap(),
+            (KafkaPrincipal::user("alice"), false)
+        );
+        assert!(deserialize_principal(Bytes::from_static(b"\x00\x00\x05")).is_err());
+    }
+}
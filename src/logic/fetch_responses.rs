@@ -1,67 +1,322 @@
+use std::{
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
 use anyhow::{Context, Result};
+use bytes::Bytes;
+
+use crate::{
+    config::{CompressionType, ReplicaSelector},
+    protocol::{
+        generated::metadata_response::MetadataResponseBroker,
+        record_batch::{gzip_batches, LogSlice},
+        request::fetch::FetchRequestV16,
+        response::fetch::{FetchResponseV16, TopicPartition, TopicResponse},
+        types::Records,
+        ApiKey, ErrorCode, Response,
+    },
+    storage::StorageError,
+};
 
-use crate::protocol::{
-    record_batch::RecordBatches,
-    request::fetch::FetchRequestV16,
-    response::fetch::{BatchBytes, FetchResponseV16, TopicPartition, TopicResponse},
-    ErrorCode,
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    fetch_sessions::{self, FetchSessions, SessionPartition},
+    handler::Handler,
+    metadata,
+    partitions::Partitions,
+    RequestContext,
 };
 
-/// https://kafka.apache.org/documentation/#log
-const CLUSTER_METADATA_LOG_FILE: &str =
-    "/tmp/kraft-combined-logs/__cluster_metadata-0/00000000000000000000.log";
-
-pub fn process(req: FetchRequestV16) -> Result<FetchResponseV16> {
-    if req.topics.is_empty() {
-        let responses = vec![];
-        return Ok(FetchResponseV16::new(
-            req.header.correlation_id,
-            req.session_id,
-            responses,
-        ));
+/// Session epoch of a full fetch request that creates a new session, Kafka's `FetchMetadata.INITIAL_EPOCH`
+const INITIAL_EPOCH: i32 = 0;
+/// Session epoch of a full fetch request that closes the session or uses none, Kafka's `FetchMetadata.FINAL_EPOCH`
+const FINAL_EPOCH: i32 = -1;
+
+/// How a fetch uses the fetch sessions, see [`fetch_sessions`]
+#[derive(Debug)]
+enum SessionFetch {
+    /// Full fetch without a session, it closes the session of its id if it has one
+    Sessionless,
+    /// Full fetch that creates a new session with its partitions, instead of the session of its id
+    New,
+    /// Incremental fetch of all partitions of the session
+    Incremental {
+        session_id: u32,
+        partitions: Vec<SessionPartition>,
+    },
+}
+
+/// Checks the fetch session the request belongs to and applies an incremental fetch to it
+fn session_fetch(
+    sessions: &FetchSessions,
+    req: &FetchRequestV16,
+    now: Instant,
+) -> Result<SessionFetch, ErrorCode> {
+    match req.session_epoch {
+        INITIAL_EPOCH | FINAL_EPOCH => {
+            if req.session_id != 0 {
+                sessions.remove(req.session_id);
+            }
+            Ok(if req.session_epoch == INITIAL_EPOCH {
+                SessionFetch::New
+            } else {
+                SessionFetch::Sessionless
+            })
+        }
+        epoch if epoch < FINAL_EPOCH => Err(ErrorCode::InvalidFetchSessionEpoch),
+        _ => {
+            let partitions = sessions.update(
+                req.session_id,
+                req.session_epoch,
+                &req.topics,
+                &req.forgotten_topics_data,
+                now,
+            )?;
+            Ok(SessionFetch::Incremental {
+                session_id: req.session_id,
+                partitions,
+            })
+        }
+    }
+}
+
+/// Replica a consumer in the rack should fetch the partition from instead of the leader, as Kafka's
+/// `RackAwareReplicaSelector`. `None` if the leader is in the rack or no in-sync replica is.
+///
+/// The log end offsets of the followers are not known, the replica with the lowest id is preferred.
+fn preferred_read_replica(
+    client_rack: &str,
+    leader_id: i32,
+    in_sync_replicas: &[u32],
+    brokers: &[MetadataResponseBroker],
+) -> Option<i32> {
+    let in_rack = |id: i32| {
+        brokers
+            .iter()
+            .any(|b| b.node_id == id && b.rack.as_deref() == Some(client_rack))
+    };
+    if client_rack.is_empty() || in_rack(leader_id) {
+        return None;
+    }
+    in_sync_replicas
+        .iter()
+        .map(|&id| id as i32)
+        .filter(|&id| in_rack(id))
+        .min()
+}
+
+/// "No preferred read replica" of the partition response
+const NO_PREFERRED_READ_REPLICA: i32 = -1;
+
+/// Partition log a Fetch reads, from the fetch offset up to the size limit of the partition
+struct LogRead {
+    file: PathBuf,
+    fetch_offset: i64,
+    max_bytes: usize,
+}
+
+/// Reads the logs of the partitions, the results are in the order of the partitions.
+///
+/// The actors of the partitions read their logs at the same time, so that a slow log does not delay
+/// the reads of the others.
+fn read_logs(partitions: &Partitions, logs: &[LogRead]) -> Vec<Result<LogSlice, StorageError>> {
+    let reads: Vec<_> = logs
+        .iter()
+        .map(|log| partitions.read(&log.file, log.fetch_offset, log.max_bytes))
+        .collect();
+    reads
+        .into_iter()
+        .map(|read| match read.wait() {
+            // nothing was produced to the partition yet, its log is empty
+            Err(StorageError::NotFound(_)) => Ok(LogSlice::default()),
+            result => result,
+        })
+        .collect()
+}
+
+/// Reads the logs again whenever records are appended to them, until they have `min_bytes` of records
+/// after the fetch offsets or the deadline passes, as Kafka's `DelayedFetch`.
+///
+/// A fetch with a partition that cannot be read from its offset is not delayed, the error is returned
+/// right away.
+fn wait_for_records(
+    partitions: &Partitions,
+    logs: &[LogRead],
+    mut slices: Vec<Result<LogSlice, StorageError>>,
+    min_bytes: usize,
+    deadline: Instant,
+) -> Vec<Result<LogSlice, StorageError>> {
+    loop {
+        let mut bytes = 0;
+        let mut end_offsets = Vec::new();
+        for (log, slice) in logs.iter().zip(&slices) {
+            match slice {
+                Ok(slice)
+                    if (slice.log_start_offset..=slice.log_end_offset)
+                        .contains(&log.fetch_offset) =>
+                {
+                    bytes += slice.records.len();
+                    end_offsets.push(slice.log_end_offset);
+                }
+                _ => return slices,
+            }
+        }
+        let now = Instant::now();
+        if bytes >= min_bytes || now >= deadline {
+            return slices;
+        }
+
+        // notified by the first partition a batch is appended to, also if it was appended since the read
+        let (notify, notified) = mpsc::channel();
+        for (log, end_offset) in logs.iter().zip(end_offsets) {
+            partitions.watch(&log.file, end_offset, deadline, notify.clone());
+        }
+        if notified.recv_timeout(deadline - now).is_err() {
+            return slices;
+        }
+        slices = read_logs(partitions, logs);
+    }
+}
+
+pub struct FetchHandler;
+
+impl Handler for FetchHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::Fetch
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        // only the v16 request is parsed, the earlier versions have other fields, e.g. topic names
+        // instead of topic ids before v13
+        16..=16
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, FetchRequestV16::from_bytes)?;
+        let resp = process(req, ctx)?;
+
+        Ok(resp.into_bytes())
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = FetchResponseV16::error(
+            ctx.header.correlation_id,
+            ctx.throttle_time_ms,
+            0,
+            error_code,
+        );
+        Some(resp.into_bytes())
+    }
+}
+
+pub fn process(req: FetchRequestV16, ctx: &RequestContext) -> Result<FetchResponseV16> {
+    let throttle_time_ms = ctx.throttle_time_ms;
+
+    // session errors are top-level, no partition is fetched
+    let sessions = &ctx.broker.fetch_sessions;
+    let session = match session_fetch(sessions, &req, Instant::now()) {
+        Ok(session) => session,
+        Err(error_code) => {
+            return Ok(FetchResponseV16::error(
+                req.header.correlation_id,
+                throttle_time_ms,
+                0,
+                error_code,
+            ))
+        }
+    };
+    // an incremental fetch fetches all partitions of its session, also when it sends none of them
+    let (session_id, topics) = match &session {
+        SessionFetch::Sessionless => (0, req.topics),
+        SessionFetch::New => (sessions.create(&req.topics, Instant::now()), req.topics),
+        SessionFetch::Incremental {
+            session_id,
+            partitions,
+        } => (*session_id, fetch_sessions::topics(partitions)),
+    };
+
+    let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
+
+    // consumers with a rack id may be sent to a follower, the racks of the brokers are in their registrations
+    let rack_aware =
+        ctx.broker.config.replica_selector == ReplicaSelector::RackAware && !req.rack_id.is_empty();
+    let brokers = if rack_aware {
+        metadata::brokers(&record_batches.registered_brokers(), &ctx.broker.config)
+    } else {
+        Vec::new()
     };
 
     let mut responses = Vec::new();
+    // indexes of the topic responses and partitions in them that are read from their logs
+    let mut reads = Vec::new();
 
     // iterate through all requested topics
-    for topic_request in req.topics {
-        // default - topic does not exist
-        let mut error_code = ErrorCode::UnknownTopicId;
-
+    for topic_request in topics {
         let topic_id = topic_request.topic_id.clone();
+        let topic_name = record_batches.topic_name(&topic_id);
+
+        // topics are fetched by id, an unknown id has no name to authorize and is reported as unknown
+        let denied = topic_name.is_some_and(|name| {
+            !ctx.broker
+                .authorizer
+                .authorize(&ctx.principal, Operation::Read, Resource::Topic(name))
+        });
 
         // iterate through requested partitions for the topic
         let mut partitions = Vec::new();
         for partition in topic_request.partitions {
             let partition_id = partition.partition;
 
-            let record_batches = RecordBatches::from_file(CLUSTER_METADATA_LOG_FILE)
-                .context("read record batches from file")?;
-
-            let mut partition_record_batches = Vec::new();
-            if let Some(raw_batch) = record_batches
-                .raw_batch_for_topic(&topic_id, partition_id)
-                .with_context(|| {
-                    format!(
-                        "read messages for topic '{}' in partition '{}'",
-                        topic_id, partition_id
-                    )
-                })?
-            {
-                error_code = ErrorCode::None;
-                let batch_bytes = BatchBytes { bytes: raw_batch };
-                partition_record_batches.push(batch_bytes);
-            }
+            let partition_record = record_batches.partition(&topic_id, partition_id);
+            // in multi-broker mode, followers and other brokers do not serve the partition
+            let not_leader = partition_record
+                .zip(ctx.broker.config.node_id)
+                .is_some_and(|(p, node_id)| p.leader_id != node_id);
+            let preferred_replica = partition_record.filter(|_| rack_aware).and_then(|p| {
+                preferred_read_replica(
+                    &req.rack_id,
+                    metadata::leader_id(p, &ctx.broker.config),
+                    &p.in_sync_replicas,
+                    &brokers,
+                )
+            });
+
+            let error_code = if topic_name.is_none() {
+                // the topic id is not in the metadata, e.g. the topic was deleted and created again
+                ErrorCode::UnknownTopicId
+            } else if denied {
+                ErrorCode::TopicAuthorizationFailed
+            } else if partition_record.is_none() {
+                ErrorCode::UnknownTopicOrPartition
+            } else if not_leader {
+                ErrorCode::NotLeaderOrFollower
+            } else {
+                // the consumer fetches the records from the preferred replica,
+                // the other logs are read after all partitions are checked, see `read_logs`
+                if let (None, Some(topic_name)) = (preferred_replica, topic_name) {
+                    let log = LogRead {
+                        file: ctx.broker.partition_log_file(topic_name, partition_id),
+                        fetch_offset: partition.fetch_offset as i64,
+                        max_bytes: partition.partition_max_bytes as usize,
+                    };
+                    reads.push((responses.len(), partitions.len(), log));
+                }
+                ErrorCode::None
+            };
 
             let partition = TopicPartition {
-                partition_index: 0,
+                partition_index: partition_id,
                 error_code,
                 high_watermark: 0,
                 last_stable_offset: 0,
                 log_start_offset: 0,
                 aborted_transactions: Vec::new(),
-                preferred_read_replica: 0,
-                record_batches: partition_record_batches,
+                preferred_read_replica: preferred_replica.unwrap_or(NO_PREFERRED_READ_REPLICA),
+                records: Records::default(),
             };
             partitions.push(partition);
         }
@@ -70,9 +325,408 @@ pub fn process(req: FetchRequestV16) -> Result<FetchResponseV16> {
         responses.push(topic_response);
     }
 
+    let (positions, logs): (Vec<_>, Vec<_>) =
+        reads.into_iter().map(|(t, p, log)| ((t, p), log)).unzip();
+    let mut slices = read_logs(&ctx.broker.partitions, &logs);
+    // only a fetch of partitions that are all read from their logs waits for records
+    let partitions_count: usize = responses.iter().map(|t| t.partitions.len()).sum();
+    if !logs.is_empty() && logs.len() == partitions_count {
+        let deadline = Instant::now() + Duration::from_millis(req.max_wait_ms.into());
+        slices = wait_for_records(
+            &ctx.broker.partitions,
+            &logs,
+            slices,
+            req.min_bytes as usize,
+            deadline,
+        );
+    }
+
+    for (((t, p), log), slice) in positions.into_iter().zip(&logs).zip(slices) {
+        let topic = &mut responses[t];
+        let partition = &mut topic.partitions[p];
+        let slice = match slice {
+            Ok(slice) => slice,
+            Err(err) => {
+                eprintln!(
+                    "Error: read messages for topic '{}' in partition '{}': {err}",
+                    topic.topic_id, partition.partition_index
+                );
+                partition.error_code = ErrorCode::KafkaStorageError;
+                continue;
+            }
+        };
+        // the partitions have no followers, everything in the log is committed
+        partition.high_watermark = slice.log_end_offset;
+        partition.last_stable_offset = slice.log_end_offset;
+        partition.log_start_offset = slice.log_start_offset;
+        if !(slice.log_start_offset..=slice.log_end_offset).contains(&log.fetch_offset) {
+            partition.error_code = ErrorCode::OffsetOutOfRange;
+            continue;
+        }
+        partition
+            .records
+            .push(match ctx.broker.config.fetch_compression_type {
+                CompressionType::None => slice.records,
+                CompressionType::Gzip => gzip_batches(&slice.records),
+            });
+    }
+
+    if let SessionFetch::Incremental { partitions, .. } = &session {
+        fetch_sessions::leave_out_unchanged(partitions, &mut responses);
+    }
+    if session_id != 0 {
+        sessions.sent(session_id, &responses);
+    }
+
     Ok(FetchResponseV16::new(
         req.header.correlation_id,
-        req.session_id,
+        throttle_time_ms,
+        session_id,
         responses,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use super::{preferred_read_replica, process, read_logs, LogRead};
+    use crate::{
+        config::Config,
+        logic::{partitions::Partitions, BrokerContext, RequestContext},
+        protocol::{
+            generated::metadata_response::MetadataResponseBroker,
+            record_batch::{LogSlice, PartitionValue, RecordBatch, RecordValue, TopicValue},
+            request::fetch::{FetchRequestV16, ForgottenTopicData, Partition, TopicRequest},
+            response::fetch::FetchResponseV16,
+            types::Serialize,
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    const TOPIC_ID: &str = "0b8c1a2d-3e4f-4a5b-8c6d-7e8f9a0b1c2d";
+
+    /// Broker with the topic foo of one partition, nothing was produced to it
+    fn broker() -> std::sync::Arc<BrokerContext> {
+        let partition = PartitionValue {
+            partition_id: 0,
+            topic_id: TOPIC_ID.to_string(),
+            replicas: vec![1],
+            in_sync_replicas: vec![1],
+            removing_replicas: Vec::new(),
+            adding_replicas: Vec::new(),
+            leader_id: 1,
+            leader_epoch: 0,
+            partition_epoch: 0,
+            directories: Vec::new(),
+        };
+        let topic = TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: TOPIC_ID.to_string(),
+        };
+        let metadata = RecordBatch::of_values(
+            0,
+            vec![RecordValue::Topic(topic), RecordValue::Partition(partition)],
+        )
+        .serialize();
+        let storage =
+            MemoryStorage::with_files([(Config::default().metadata_log_file(), metadata)]);
+        BrokerContext::with_storage(storage)
+    }
+
+    fn fetch(ctx: &RequestContext, topics: Vec<TopicRequest>) -> FetchRequestV16 {
+        FetchRequestV16 {
+            header: ctx.header.clone(),
+            max_wait_ms: 0,
+            min_bytes: 1,
+            max_bytes: 1024,
+            isolation_level: 0,
+            session_id: 0,
+            session_epoch: -1,
+            topics,
+            forgotten_topics_data: Vec::new(),
+            rack_id: String::new(),
+        }
+    }
+
+    fn topic(topic_id: &str, partitions: &[u32]) -> TopicRequest {
+        TopicRequest {
+            topic_id: topic_id.to_string(),
+            partitions: partitions
+                .iter()
+                .map(|&partition| Partition {
+                    partition,
+                    current_leader_epoch: 0,
+                    fetch_offset: 0,
+                    last_fetched_epoch: 0,
+                    log_start_offset: 0,
+                    partition_max_bytes: 1024,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn unknown_topic_ids_and_partitions() {
+        let ctx = RequestContext::for_request(broker(), ApiKey::Fetch, 16);
+        let req = fetch(
+            &ctx,
+            vec![
+                topic(TOPIC_ID, &[0, 1]),
+                topic("00000000-0000-0000-0000-00000000abcd", &[0]),
+            ],
+        );
+        let resp = process(req, &ctx).unwrap();
+
+        let errors: Vec<_> = resp
+            .responses
+            .iter()
+            .flat_map(|t| {
+                t.partitions
+                    .iter()
+                    .map(|p| (t.topic_id.as_str(), p.partition_index, p.error_code))
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (TOPIC_ID, 0, ErrorCode::None),
+                (TOPIC_ID, 1, ErrorCode::UnknownTopicOrPartition),
+                (
+                    "00000000-0000-0000-0000-00000000abcd",
+                    0,
+                    ErrorCode::UnknownTopicId
+                ),
+            ]
+        );
+        assert!(resp.responses[0].partitions[0].records.is_empty());
+    }
+
+    #[test]
+    fn reads_the_partition_logs_in_order() {
+        let config = Config::default();
+        // the partitions are in the only log directory
+        let file = |i| config.partition_log_file(&MemoryStorage::default(), "foo", i);
+        let topic = TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: TOPIC_ID.to_string(),
+        };
+        let metadata =
+            RecordBatch::of_values(0, vec![RecordValue::Topic(topic.clone())]).serialize();
+        // nothing was produced to partition 7
+        let log = |i: u8| {
+            RecordBatch::of_values(i.into(), vec![RecordValue::Topic(topic.clone())]).serialize()
+        };
+        let logs = (0..20u8)
+            .filter(|&i| i != 7)
+            .map(|i| (file(i.into()), log(i)))
+            .chain([(config.metadata_log_file(), metadata)]);
+        let storage = MemoryStorage::with_files(logs);
+        let partitions = Partitions::new(Arc::new(storage));
+
+        let reads: Vec<_> = (0..20)
+            .map(|i| LogRead {
+                file: file(i),
+                fetch_offset: 0,
+                max_bytes: 1024,
+            })
+            .collect();
+        let logs = read_logs(&partitions, &reads);
+        assert_eq!(logs.len(), 20);
+        for (i, slice) in logs.into_iter().enumerate() {
+            let slice = slice.unwrap();
+            if i == 7 {
+                assert_eq!(slice, LogSlice::default());
+            } else {
+                assert_eq!(slice.records, log(i as u8));
+                assert_eq!(slice.offsets, Some((i as i64, i as i64)));
+            }
+        }
+    }
+
+    #[test]
+    fn waits_for_records_up_to_max_wait() {
+        let ctx = RequestContext::for_request(broker(), ApiKey::Fetch, 16);
+        let fetch_waiting = |max_wait_ms| {
+            let mut req = fetch(&ctx, vec![topic(TOPIC_ID, &[0])]);
+            req.max_wait_ms = max_wait_ms;
+            let started = Instant::now();
+            let mut resp = process(req, &ctx).unwrap();
+            (
+                started.elapsed(),
+                resp.responses.remove(0).partitions.remove(0),
+            )
+        };
+
+        // nothing is produced
+        let (waited, partition) = fetch_waiting(50);
+        assert!(waited >= Duration::from_millis(50));
+        assert_eq!(partition.error_code, ErrorCode::None);
+        assert!(partition.records.is_empty());
+
+        // a batch is produced while the fetch waits
+        let log = RecordBatch::of_values(
+            0,
+            vec![RecordValue::Topic(TopicValue {
+                topic_name: "foo".to_string(),
+                topic_id: TOPIC_ID.to_string(),
+            })],
+        )
+        .serialize();
+        let file = ctx.broker.partition_log_file("foo", 0);
+        let (waited, partition) = std::thread::scope(|scope| {
+            let fetch = scope.spawn(|| fetch_waiting(10_000));
+            std::thread::sleep(Duration::from_millis(50));
+            ctx.broker.partitions.append(&file, log).wait().unwrap();
+            fetch.join().unwrap()
+        });
+        assert!(waited < Duration::from_secs(10));
+        assert_eq!(partition.high_watermark, 1);
+        assert!(!partition.records.is_empty());
+    }
+
+    #[test]
+    fn watermarks_and_offsets_out_of_range() {
+        let ctx = RequestContext::for_request(broker(), ApiKey::Fetch, 16);
+        let log = RecordBatch::of_values(
+            0,
+            vec![RecordValue::Topic(TopicValue {
+                topic_name: "foo".to_string(),
+                topic_id: TOPIC_ID.to_string(),
+            })],
+        )
+        .serialize();
+        let file = ctx.broker.partition_log_file("foo", 0);
+        ctx.broker.storage.append(&file, &log).unwrap();
+
+        let fetch_from = |offset| {
+            let mut topic = topic(TOPIC_ID, &[0]);
+            topic.partitions[0].fetch_offset = offset;
+            let mut resp = process(fetch(&ctx, vec![topic]), &ctx).unwrap();
+            resp.responses.remove(0).partitions.remove(0)
+        };
+        let partition = fetch_from(0);
+        assert_eq!(partition.error_code, ErrorCode::None);
+        assert_eq!(
+            (partition.log_start_offset, partition.high_watermark),
+            (0, 1)
+        );
+        assert!(!partition.records.is_empty());
+
+        // the log end offset is where the next record is produced, fetching from it gets nothing yet
+        let partition = fetch_from(1);
+        assert_eq!(partition.error_code, ErrorCode::None);
+        assert!(partition.records.is_empty());
+
+        let partition = fetch_from(2);
+        assert_eq!(partition.error_code, ErrorCode::OffsetOutOfRange);
+        assert_eq!(partition.high_watermark, 1);
+    }
+
+    #[test]
+    fn incremental_fetches_send_the_changed_partitions_of_the_session() {
+        let ctx = RequestContext::for_request(broker(), ApiKey::Fetch, 16);
+        let partitions = |resp: &FetchResponseV16| -> Vec<u32> {
+            resp.responses
+                .iter()
+                .flat_map(|t| t.partitions.iter().map(|p| p.partition_index))
+                .collect()
+        };
+
+        // the full fetch creates the session, partition 1 of foo does not exist
+        let mut req = fetch(&ctx, vec![topic(TOPIC_ID, &[0, 1])]);
+        req.session_epoch = 0;
+        let resp = process(req, &ctx).unwrap();
+        assert_ne!(resp.session_id, 0);
+        assert_eq!(partitions(&resp), [0, 1]);
+        let session_id = resp.session_id;
+
+        // nothing changed, only the erroneous partition is sent again
+        let mut req = fetch(&ctx, Vec::new());
+        (req.session_id, req.session_epoch) = (session_id, 1);
+        let resp = process(req, &ctx).unwrap();
+        assert_eq!(resp.session_id, session_id);
+        assert_eq!(partitions(&resp), [1]);
+
+        // a batch is produced to partition 0 and partition 1 is forgotten
+        let log = RecordBatch::of_values(
+            0,
+            vec![RecordValue::Topic(TopicValue {
+                topic_name: "foo".to_string(),
+                topic_id: TOPIC_ID.to_string(),
+            })],
+        )
+        .serialize();
+        let file = ctx.broker.partition_log_file("foo", 0);
+        ctx.broker.storage.append(&file, &log).unwrap();
+        let mut req = fetch(&ctx, Vec::new());
+        (req.session_id, req.session_epoch) = (session_id, 2);
+        req.forgotten_topics_data = vec![ForgottenTopicData {
+            topic_id: TOPIC_ID.to_string(),
+            partitions: vec![1],
+        }];
+        let resp = process(req, &ctx).unwrap();
+        assert_eq!(partitions(&resp), [0]);
+        assert_eq!(resp.responses[0].partitions[0].high_watermark, 1);
+
+        // the final fetch closes the session and is a full fetch without one
+        let mut req = fetch(&ctx, Vec::new());
+        (req.session_id, req.session_epoch) = (session_id, -1);
+        let resp = process(req, &ctx).unwrap();
+        assert_eq!((resp.error_code, resp.session_id), (ErrorCode::None, 0));
+        assert!(resp.responses.is_empty());
+
+        let mut req = fetch(&ctx, Vec::new());
+        (req.session_id, req.session_epoch) = (session_id, 3);
+        let resp = process(req, &ctx).unwrap();
+        assert_eq!(resp.error_code, ErrorCode::FetchSessionIdNotFound);
+
+        // a fetch of a session sent again
+        let mut req = fetch(&ctx, vec![topic(TOPIC_ID, &[0])]);
+        req.session_epoch = 0;
+        let session_id = process(req, &ctx).unwrap().session_id;
+        for (epoch, error_code) in [
+            (1, ErrorCode::None),
+            (1, ErrorCode::InvalidFetchSessionEpoch),
+        ] {
+            let mut req = fetch(&ctx, Vec::new());
+            (req.session_id, req.session_epoch) = (session_id, epoch);
+            assert_eq!(process(req, &ctx).unwrap().error_code, error_code);
+        }
+
+        let mut req = fetch(&ctx, Vec::new());
+        req.session_epoch = -2;
+        let resp = process(req, &ctx).unwrap();
+        assert_eq!(resp.error_code, ErrorCode::InvalidFetchSessionEpoch);
+    }
+
+    #[test]
+    fn prefers_in_sync_replica_in_client_rack() {
+        let broker = |node_id, rack: &str| MetadataResponseBroker {
+            node_id,
+            host: "localhost".to_string(),
+            port: 9092,
+            rack: Some(rack.to_string()),
+        };
+        let brokers = [
+            broker(1, "a"),
+            broker(2, "b"),
+            broker(3, "b"),
+            broker(4, "c"),
+        ];
+
+        assert_eq!(
+            preferred_read_replica("b", 1, &[1, 3, 2], &brokers),
+            Some(2)
+        );
+        assert_eq!(preferred_read_replica("a", 1, &[1, 2, 3], &brokers), None);
+        // out of sync replicas and unknown racks fall back to the leader
+        assert_eq!(preferred_read_replica("c", 1, &[1, 2, 3], &brokers), None);
+        assert_eq!(preferred_read_replica("d", 1, &[1, 2, 3], &brokers), None);
+        assert_eq!(preferred_read_replica("", 1, &[1, 2, 3], &brokers), None);
+    }
+}
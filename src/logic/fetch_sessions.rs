@@ -0,0 +1,327 @@
+//! Fetch sessions of incremental fetch requests, as Kafka's `FetchSessionCache` of KIP-227.
+//!
+//! A full fetch with the initial epoch creates a session with its partitions. The incremental fetches of
+//! the session then send only the partitions whose fetch offsets changed and the ones to forget, and are
+//! answered with only the partitions whose records or offsets changed. Sessions are kept in memory.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{
+    request::fetch::{ForgottenTopicData, Partition, TopicRequest},
+    response::fetch::TopicResponse,
+    ErrorCode,
+};
+
+use super::now_ms;
+
+/// "No preferred read replica" of the partition response
+const NO_PREFERRED_READ_REPLICA: i32 = -1;
+
+/// Sessions used more recently are not evicted for a new session, Kafka's
+/// `min.incremental.fetch.session.eviction.ms`
+const MIN_EVICTION_AGE: Duration = Duration::from_secs(120);
+
+/// Partition fetched in a session
+#[derive(Debug, Clone)]
+pub struct SessionPartition {
+    pub topic_id: String,
+    /// The partition as the last request that sent it, with its fetch offset
+    pub partition: Partition,
+    /// High watermark and log start offset of the last response the partition was in, `None` if it
+    /// was not sent yet
+    pub sent: Option<(i64, i64)>,
+}
+
+impl SessionPartition {
+    fn new(topic_id: &str, partition: &Partition) -> Self {
+        Self {
+            topic_id: topic_id.to_string(),
+            partition: partition.clone(),
+            sent: None,
+        }
+    }
+
+    fn is(&self, topic_id: &str, partition: u32) -> bool {
+        self.topic_id == topic_id && self.partition.partition == partition
+    }
+}
+
+#[derive(Debug)]
+struct FetchSession {
+    /// The partitions in the order they were added
+    partitions: Vec<SessionPartition>,
+    /// Epoch of the next incremental fetch of the session
+    epoch: i32,
+    last_used: Instant,
+}
+
+/// Fetch session as the admin endpoint lists it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub session_id: u32,
+    /// Epoch of the next incremental fetch of the session
+    pub epoch: i32,
+    pub partitions: usize,
+    /// Time the session was last used, in milliseconds since the Unix epoch
+    pub last_used_ms: i64,
+}
+
+/// Fetch sessions of the broker by their ids, at most `max_sessions` of them
+pub struct FetchSessions {
+    max_sessions: usize,
+    sessions: Mutex<HashMap<u32, FetchSession>>,
+}
+
+impl FetchSessions {
+    pub fn new(max_sessions: usize) -> Self {
+        Self {
+            max_sessions,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a session with the partitions of a full fetch and returns its id.
+    ///
+    /// If the cache is full, the least recently used session is evicted if it was not used for
+    /// `MIN_EVICTION_AGE`, otherwise no session is created and the id is 0.
+    pub fn create(&self, topics: &[TopicRequest], now: Instant) -> u32 {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= self.max_sessions {
+            let evictable = sessions
+                .iter()
+                .filter(|(_, session)| {
+                    now.saturating_duration_since(session.last_used) >= MIN_EVICTION_AGE
+                })
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(&id, _)| id);
+            match evictable {
+                Some(id) => _ = sessions.remove(&id),
+                None => return 0,
+            }
+        }
+
+        let session_id = loop {
+            let id = random_session_id();
+            if !sessions.contains_key(&id) {
+                break id;
+            }
+        };
+        let partitions = topics
+            .iter()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .iter()
+                    .map(|partition| SessionPartition::new(&topic.topic_id, partition))
+            })
+            .collect();
+        sessions.insert(
+            session_id,
+            FetchSession {
+                partitions,
+                epoch: 1,
+                last_used: now,
+            },
+        );
+        session_id
+    }
+
+    /// The sessions in the cache, by their ids
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let sessions = self.sessions.lock().unwrap();
+        let (now, now_ms) = (Instant::now(), now_ms());
+        let mut list: Vec<_> = sessions
+            .iter()
+            .map(|(&session_id, session)| SessionInfo {
+                session_id,
+                epoch: session.epoch,
+                partitions: session.partitions.len(),
+                last_used_ms: now_ms
+                    - now.saturating_duration_since(session.last_used).as_millis() as i64,
+            })
+            .collect();
+        list.sort_by_key(|session| session.session_id);
+        list
+    }
+
+    pub fn remove(&self, session_id: u32) {
+        self.sessions.lock().unwrap().remove(&session_id);
+    }
+
+    /// Applies an incremental fetch to its session and returns the partitions of the session to fetch.
+    ///
+    /// The epoch of the fetch must be the next one of the session, the epoch of a fetch sent again or out
+    /// of order leaves the session as it was. The partitions of the request are added to the session, or
+    /// replace the ones of the session with their new fetch offsets, and the forgotten ones are removed.
+    pub fn update(
+        &self,
+        session_id: u32,
+        epoch: i32,
+        topics: &[TopicRequest],
+        forgotten: &[ForgottenTopicData],
+        now: Instant,
+    ) -> Result<Vec<SessionPartition>, ErrorCode> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or(ErrorCode::FetchSessionIdNotFound)?;
+        if epoch != session.epoch {
+            return Err(ErrorCode::InvalidFetchSessionEpoch);
+        }
+        // the epoch after the last one is 1, 0 is the initial epoch of a full fetch
+        session.epoch = if epoch == i32::MAX { 1 } else { epoch + 1 };
+        session.last_used = now;
+
+        for topic in forgotten {
+            session.partitions.retain(|p| {
+                !topic
+                    .partitions
+                    .iter()
+                    .any(|&partition| p.is(&topic.topic_id, partition))
+            });
+        }
+        for topic in topics {
+            for partition in &topic.partitions {
+                match session
+                    .partitions
+                    .iter_mut()
+                    .find(|p| p.is(&topic.topic_id, partition.partition))
+                {
+                    Some(p) => p.partition = partition.clone(),
+                    None => session
+                        .partitions
+                        .push(SessionPartition::new(&topic.topic_id, partition)),
+                }
+            }
+        }
+        Ok(session.partitions.clone())
+    }
+
+    /// Keeps the offsets of the partitions that were sent in the response of the session, the next
+    /// incremental fetch leaves them out if they do not change
+    pub fn sent(&self, session_id: u32, responses: &[TopicResponse]) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(&session_id) else {
+            return;
+        };
+        for topic in responses {
+            for sent in &topic.partitions {
+                if let Some(p) = session
+                    .partitions
+                    .iter_mut()
+                    .find(|p| p.is(&topic.topic_id, sent.partition_index))
+                {
+                    p.sent = Some((sent.high_watermark, sent.log_start_offset));
+                }
+            }
+        }
+    }
+}
+
+/// Topics with the partitions of the session to fetch, in the order the topics were added
+pub fn topics(partitions: &[SessionPartition]) -> Vec<TopicRequest> {
+    let mut topics: Vec<TopicRequest> = Vec::new();
+    for p in partitions {
+        match topics.iter_mut().find(|t| t.topic_id == p.topic_id) {
+            Some(topic) => topic.partitions.push(p.partition.clone()),
+            None => topics.push(TopicRequest {
+                topic_id: p.topic_id.clone(),
+                partitions: vec![p.partition.clone()],
+            }),
+        }
+    }
+    topics
+}
+
+/// Removes the partitions from the response of an incremental fetch that did not change since the last
+/// response they were in: without records, errors or a preferred read replica, and with the same offsets.
+/// Topics left without partitions are removed too.
+pub fn leave_out_unchanged(partitions: &[SessionPartition], responses: &mut Vec<TopicResponse>) {
+    for topic in responses.iter_mut() {
+        topic.partitions.retain(|response| {
+            let sent = partitions
+                .iter()
+                .find(|p| p.is(&topic.topic_id, response.partition_index))
+                .and_then(|p| p.sent);
+            !response.records.is_empty()
+                || response.error_code != ErrorCode::None
+                || response.preferred_read_replica != NO_PREFERRED_READ_REPLICA
+                || sent != Some((response.high_watermark, response.log_start_offset))
+        });
+    }
+    responses.retain(|topic| !topic.partitions.is_empty());
+}
+
+/// Positive id of a new session, 0 is no session
+fn random_session_id() -> u32 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_i64(now_ms());
+    (hasher.finish() as u32 & i32::MAX as u32).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::FetchSessions;
+    use crate::protocol::{
+        request::fetch::{Partition, TopicRequest},
+        ErrorCode,
+    };
+
+    const TOPIC_ID: &str = "0b8c1a2d-3e4f-4a5b-8c6d-7e8f9a0b1c2d";
+
+    fn topic(partitions: &[u32]) -> TopicRequest {
+        TopicRequest {
+            topic_id: TOPIC_ID.to_string(),
+            partitions: partitions
+                .iter()
+                .map(|&partition| Partition {
+                    partition,
+                    current_leader_epoch: 0,
+                    fetch_offset: 0,
+                    last_fetched_epoch: 0,
+                    log_start_offset: 0,
+                    partition_max_bytes: 1024,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn incremental_fetches_follow_the_epochs_of_the_session() {
+        let sessions = FetchSessions::new(1);
+        let now = Instant::now();
+        let session_id = sessions.create(&[topic(&[0])], now);
+        assert_ne!(session_id, 0);
+        // the cache is full and the session was just used
+        assert_eq!(sessions.create(&[topic(&[0])], now), 0);
+
+        let partitions = |epoch, topics: &[TopicRequest]| {
+            sessions
+                .update(session_id, epoch, topics, &[], now)
+                .map(|partitions| partitions.len())
+        };
+        assert_eq!(partitions(1, &[]), Ok(1));
+        // sent again, or ahead of a lost fetch
+        assert_eq!(partitions(1, &[]), Err(ErrorCode::InvalidFetchSessionEpoch));
+        assert_eq!(partitions(3, &[]), Err(ErrorCode::InvalidFetchSessionEpoch));
+        // the stale fetches did not add their partitions
+        assert_eq!(partitions(2, &[topic(&[1])]), Ok(2));
+        let listed: Vec<_> = sessions
+            .list()
+            .iter()
+            .map(|session| (session.session_id, session.epoch, session.partitions))
+            .collect();
+        assert_eq!(listed, [(session_id, 3, 2)]);
+        assert_eq!(
+            sessions.update(7, 3, &[], &[], now).unwrap_err(),
+            ErrorCode::FetchSessionIdNotFound
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use std::{ops::RangeInclusive, sync::OnceLock};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::protocol::{response::api_versions::ApiVersionsApiKeys, ApiKey, ErrorCode};
+
+use super::{
+    api_versions::ApiVersionsHandler,
+    broker_registrations::{BrokerHeartbeatHandler, BrokerRegistrationHandler},
+    configs::DescribeConfigsHandler,
+    delegation_tokens::{
+        CreateDelegationTokenHandler, DescribeDelegationTokenHandler, ExpireDelegationTokenHandler,
+        RenewDelegationTokenHandler,
+    },
+    envelope::EnvelopeHandler,
+    fetch_responses::FetchHandler,
+    list_offsets::ListOffsetsHandler,
+    log_dirs::DescribeLogDirsHandler,
+    metadata::MetadataHandler,
+    produce::ProduceHandler,
+    sasl::{SaslAuthenticateHandler, SaslHandshakeHandler},
+    topic_partitions::DescribeTopicPartitionsHandler,
+    RequestContext,
+};
+
+/// Handler of the requests of one API
+pub trait Handler: Send + Sync {
+    fn api_key(&self) -> ApiKey;
+
+    /// Versions of the API the handler supports, advertised in the ApiVersions response
+    fn version_range(&self) -> RangeInclusive<i16>;
+
+    /// Processes the request body and returns the response message including its size
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes>;
+
+    /// Response with a top-level error code to a request that could not be processed,
+    /// `None` if the response of the API has no such error code
+    fn error_response(&self, _ctx: &RequestContext, _error_code: ErrorCode) -> Option<Bytes> {
+        None
+    }
+}
+
+/// Handlers of all supported APIs
+#[derive(Default)]
+pub struct Registry {
+    handlers: Vec<Box<dyn Handler>>,
+}
+
+impl Registry {
+    pub fn register(&mut self, handler: impl Handler + 'static) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    pub fn get(&self, api_key: i16) -> Option<&dyn Handler> {
+        self.handlers
+            .iter()
+            .find(|h| i16::from(h.api_key()) == api_key)
+            .map(|h| h.as_ref())
+    }
+
+    /// Supported APIs in the order they were registered
+    pub fn api_keys(&self) -> Vec<ApiVersionsApiKeys> {
+        self.handlers
+            .iter()
+            .map(|h| ApiVersionsApiKeys {
+                api_key: h.api_key(),
+                min_version: *h.version_range().start(),
+                max_version: *h.version_range().end(),
+            })
+            .collect()
+    }
+}
+
+/// Registry with the handlers of all APIs the broker supports
+pub fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        registry.register(ApiVersionsHandler);
+        registry.register(DescribeTopicPartitionsHandler);
+        registry.register(FetchHandler);
+        registry.register(CreateDelegationTokenHandler);
+        registry.register(RenewDelegationTokenHandler);
+        registry.register(ExpireDelegationTokenHandler);
+        registry.register(DescribeDelegationTokenHandler);
+        registry.register(BrokerRegistrationHandler);
+        registry.register(BrokerHeartbeatHandler);
+        registry.register(EnvelopeHandler);
+        registry.register(DescribeLogDirsHandler);
+        registry.register(DescribeConfigsHandler);
+        registry.register(ListOffsetsHandler);
+        registry.register(ProduceHandler);
+        registry.register(MetadataHandler);
+        registry.register(SaslHandshakeHandler);
+        registry.register(SaslAuthenticateHandler);
+        registry
+    })
+}
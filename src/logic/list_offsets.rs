@@ -0,0 +1,244 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+
+use crate::protocol::{
+    generated::{
+        list_offsets_request::{ListOffsetsPartition, ListOffsetsRequestData},
+        list_offsets_response::{
+            ListOffsetsPartitionResponse, ListOffsetsResponseData, ListOffsetsTopicResponse,
+        },
+    },
+    record_batch::LogOffsets,
+    response::{self, ResponseHeader},
+    ApiKey, ErrorCode,
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    RequestContext,
+};
+
+/// Special timestamps of the request, as in Kafka's `ListOffsetsRequest`
+const LATEST_TIMESTAMP: i64 = -1;
+const EARLIEST_TIMESTAMP: i64 = -2;
+const MAX_TIMESTAMP: i64 = -3;
+const EARLIEST_LOCAL_TIMESTAMP: i64 = -4;
+
+/// Offset and its timestamp for the requested timestamp of the partition request.
+///
+/// There is no remote storage, all the segments of the log are local, so the earliest local offset
+/// is the log start offset. Timestamps that match no record give the offset and timestamp -1.
+fn offset_for(
+    log: &LogOffsets,
+    partition: &ListOffsetsPartition,
+    version: i16,
+) -> Result<(i64, i64), ErrorCode> {
+    match partition.timestamp {
+        LATEST_TIMESTAMP => Ok((log.log_end_offset, -1)),
+        EARLIEST_TIMESTAMP => Ok((log.log_start_offset, -1)),
+        MAX_TIMESTAMP if version < 7 => Err(ErrorCode::UnsupportedVersion),
+        MAX_TIMESTAMP => Ok(log
+            .max_timestamp()
+            .map_or((-1, -1), |p| (p.offset, p.timestamp))),
+        EARLIEST_LOCAL_TIMESTAMP if version < 8 => Err(ErrorCode::UnsupportedVersion),
+        EARLIEST_LOCAL_TIMESTAMP => Ok((log.log_start_offset, -1)),
+        timestamp => Ok(log
+            .offset_for_timestamp(timestamp)
+            .map_or((-1, -1), |p| (p.offset, p.timestamp))),
+    }
+}
+
+pub struct ListOffsetsHandler;
+
+impl Handler for ListOffsetsHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::ListOffsets
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        ListOffsetsRequestData::LOWEST_SUPPORTED_VERSION
+            ..=ListOffsetsRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            ListOffsetsRequestData::deserialize(src, header.request_api_version)
+        })?;
+        let version = ctx.header.request_api_version;
+
+        let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
+
+        let mut topics = Vec::new();
+        for topic in req.topics {
+            // denied topics get the same answer whether they exist or not
+            let denied = !ctx.broker.authorizer.authorize(
+                &ctx.principal,
+                Operation::Describe,
+                Resource::Topic(&topic.name),
+            );
+            let topic_id = record_batches.topic_id(&topic.name);
+
+            let mut partitions = Vec::new();
+            for partition in &topic.partitions {
+                let partition_record = topic_id.zip(u32::try_from(partition.partition_index).ok());
+                let partition_record = partition_record.and_then(|(topic_id, partition_id)| {
+                    record_batches.partition(topic_id, partition_id)
+                });
+
+                let result = if denied {
+                    Err(ErrorCode::TopicAuthorizationFailed)
+                } else if let Some(partition_record) = partition_record {
+                    // in multi-broker mode, only the leader answers for the partition
+                    if ctx
+                        .broker
+                        .config
+                        .node_id
+                        .is_some_and(|node_id| partition_record.leader_id != node_id)
+                    {
+                        Err(ErrorCode::NotLeaderOrFollower)
+                    } else {
+                        let file = ctx
+                            .broker
+                            .partition_log_file(&topic.name, partition_record.partition_id);
+                        match ctx.broker.partitions.offsets(&file).wait() {
+                            Ok(log) => offset_for(&log, partition, version),
+                            Err(err) => {
+                                eprintln!("Error: list offsets of {}: {err:#}", file.display());
+                                Err(ErrorCode::KafkaStorageError)
+                            }
+                        }
+                    }
+                } else {
+                    Err(ErrorCode::UnknownTopicOrPartition)
+                };
+
+                let (error_code, (offset, timestamp)) = match result {
+                    Ok(found) => (ErrorCode::None, found),
+                    Err(error_code) => (error_code, (-1, -1)),
+                };
+                partitions.push(ListOffsetsPartitionResponse {
+                    partition_index: partition.partition_index,
+                    error_code: error_code.into(),
+                    // version 0 may return several offsets, there is one offset for every timestamp
+                    old_style_offsets: if offset >= 0 {
+                        vec![offset]
+                    } else {
+                        Vec::new()
+                    },
+                    timestamp,
+                    offset,
+                    leader_epoch: -1,
+                });
+            }
+            topics.push(ListOffsetsTopicResponse {
+                name: topic.name,
+                partitions,
+            });
+        }
+
+        let resp = ListOffsetsResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            topics,
+        };
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+
+    use super::{offset_for, ListOffsetsHandler};
+    use crate::{
+        config::Config,
+        logic::{handler::Handler, BrokerContext, RequestContext},
+        protocol::{
+            generated::{
+                list_offsets_request::{
+                    ListOffsetsPartition, ListOffsetsRequestData, ListOffsetsTopic,
+                },
+                list_offsets_response::ListOffsetsResponseData,
+            },
+            reader::ByteReader,
+            record_batch::{LogOffsets, RecordBatch, RecordValue, TopicValue},
+            types::Serialize,
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    #[test]
+    fn special_timestamps_of_empty_log() {
+        let log = LogOffsets::scan(Bytes::new()).unwrap();
+        let partition = |timestamp| ListOffsetsPartition {
+            partition_index: 0,
+            current_leader_epoch: -1,
+            timestamp,
+            max_num_offsets: 1,
+        };
+
+        assert_eq!(offset_for(&log, &partition(-1), 8), Ok((0, -1)));
+        assert_eq!(offset_for(&log, &partition(-2), 8), Ok((0, -1)));
+        assert_eq!(offset_for(&log, &partition(-3), 8), Ok((-1, -1)));
+        assert_eq!(offset_for(&log, &partition(-4), 8), Ok((0, -1)));
+        assert_eq!(offset_for(&log, &partition(1000), 8), Ok((-1, -1)));
+        assert_eq!(
+            offset_for(&log, &partition(-3), 6),
+            Err(ErrorCode::UnsupportedVersion)
+        );
+        assert_eq!(
+            offset_for(&log, &partition(-4), 7),
+            Err(ErrorCode::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn unauthorized_topics_are_not_told_apart_from_missing_ones() {
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-0000-0000-000000000001".to_string(),
+        });
+        let config = Config {
+            acls: vec!["User:alice,Describe,Topic,*".parse().unwrap()],
+            ..Config::default()
+        };
+        let metadata = RecordBatch::of_values(0, vec![topic]).serialize();
+        let storage = MemoryStorage::with_files([(config.metadata_log_file(), metadata)]);
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(storage));
+        let ctx = RequestContext::for_request(Arc::new(broker), ApiKey::ListOffsets, 5);
+
+        let req = ListOffsetsRequestData {
+            replica_id: -1,
+            isolation_level: 0,
+            topics: ["foo", "missing"]
+                .map(|name| ListOffsetsTopic {
+                    name: name.to_string(),
+                    partitions: vec![ListOffsetsPartition {
+                        partition_index: 0,
+                        current_leader_epoch: -1,
+                        timestamp: -1,
+                        max_num_offsets: 1,
+                    }],
+                })
+                .to_vec(),
+        };
+        let response = ListOffsetsHandler.handle(&ctx, req.serialize(5)).unwrap();
+        // the body after the size and the correlation id of the response
+        let resp =
+            ListOffsetsResponseData::deserialize(&mut ByteReader::new(response.slice(8..)), 5)
+                .unwrap();
+        let errors: Vec<_> = resp
+            .topics
+            .iter()
+            .flat_map(|t| t.partitions.iter().map(|p| p.error_code))
+            .collect();
+        assert_eq!(errors, [i16::from(ErrorCode::TopicAuthorizationFailed); 2]);
+    }
+}
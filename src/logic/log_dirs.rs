@@ -0,0 +1,200 @@
+use std::{collections::BTreeMap, ops::RangeInclusive, path::Path};
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::{
+    protocol::{
+        generated::{
+            describe_log_dirs_request::{DescribableLogDirTopic, DescribeLogDirsRequestData},
+            describe_log_dirs_response::{
+                DescribeLogDirsPartition, DescribeLogDirsResponseData, DescribeLogDirsResult,
+                DescribeLogDirsTopic,
+            },
+        },
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode,
+    },
+    storage::{Storage, StorageError},
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    RequestContext,
+};
+
+/// Partitions in the log directory and the size of their segments, grouped by topic.
+///
+/// Only the requested partitions are described, all of them if no topic is requested.
+/// The metadata log is not a partition of a topic and is skipped.
+fn describe_log_dir(
+    storage: &dyn Storage,
+    log_dir: &Path,
+    requested: &[DescribableLogDirTopic],
+) -> Result<Vec<DescribeLogDirsTopic>, StorageError> {
+    let is_requested = |topic: &str, partition: i32| {
+        requested.is_empty()
+            || requested
+                .iter()
+                .any(|t| t.topic == topic && t.partitions.contains(&partition))
+    };
+
+    let mut topics: BTreeMap<String, Vec<DescribeLogDirsPartition>> = BTreeMap::new();
+    for entry in storage.list(log_dir)? {
+        let Some((topic, partition)) = entry
+            .name
+            .rsplit_once('-')
+            .and_then(|(topic, partition)| Some((topic, partition.parse::<i32>().ok()?)))
+        else {
+            continue;
+        };
+        if !entry.is_dir || topic == "__cluster_metadata" || !is_requested(topic, partition) {
+            continue;
+        }
+
+        let mut partition_size = 0;
+        for segment in storage.list(&log_dir.join(&entry.name))? {
+            partition_size += segment.len as i64;
+        }
+        topics
+            .entry(topic.to_string())
+            .or_default()
+            .push(DescribeLogDirsPartition {
+                partition_index: partition,
+                partition_size,
+                // there are no replicas that could lag behind
+                offset_lag: 0,
+                is_future_key: false,
+            });
+    }
+
+    Ok(topics
+        .into_iter()
+        .map(|(name, mut partitions)| {
+            partitions.sort_by_key(|p| p.partition_index);
+            DescribeLogDirsTopic { name, partitions }
+        })
+        .collect())
+}
+
+pub struct DescribeLogDirsHandler;
+
+impl Handler for DescribeLogDirsHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::DescribeLogDirs
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        DescribeLogDirsRequestData::LOWEST_SUPPORTED_VERSION
+            ..=DescribeLogDirsRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            DescribeLogDirsRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        if !ctx
+            .broker
+            .authorizer
+            .authorize(&ctx.principal, Operation::Describe, Resource::Cluster)
+        {
+            return Ok(self
+                .error_response(ctx, ErrorCode::ClusterAuthorizationFailed)
+                .unwrap_or_default());
+        }
+
+        // a null array of topics and an empty one both describe all topics
+        let requested = req.topics.as_deref().unwrap_or_default();
+        let results = ctx
+            .broker
+            .config
+            .log_dirs()
+            .iter()
+            .map(|log_dir| {
+                let (error_code, topics) =
+                    match describe_log_dir(&*ctx.broker.storage, log_dir, requested) {
+                        Ok(topics) => (ErrorCode::None, topics),
+                        Err(err) => {
+                            eprintln!("Error: describe log directory {}: {err}", log_dir.display());
+                            (ErrorCode::KafkaStorageError, Vec::new())
+                        }
+                    };
+                DescribeLogDirsResult {
+                    error_code: error_code.into(),
+                    log_dir: log_dir.display().to_string(),
+                    topics,
+                    // the size of the volume is not known
+                    total_bytes: -1,
+                    usable_bytes: -1,
+                }
+            })
+            .collect();
+
+        let resp = DescribeLogDirsResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            error_code: ErrorCode::None.into(),
+            results,
+        };
+        let version = ctx.header.request_api_version;
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = DescribeLogDirsResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            error_code: error_code.into(),
+            results: Vec::new(),
+        };
+        let version = ctx.header.request_api_version;
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Some(response::message(header, resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::describe_log_dir;
+    use crate::{
+        protocol::generated::describe_log_dirs_request::DescribableLogDirTopic,
+        storage::MemoryStorage,
+    };
+
+    #[test]
+    fn describes_partitions_in_log_dir() {
+        let log_dir = Path::new("/logs");
+        let storage = MemoryStorage::with_files([
+            ("/logs/foo-0/0.log", vec![0; 10]),
+            ("/logs/foo-1/0.log", vec![0; 20]),
+            ("/logs/bar-0/0.log", vec![0; 5]),
+            ("/logs/__cluster_metadata-0/0.log", Vec::new()),
+            ("/logs/meta.properties", b"cluster.id=abc".to_vec()),
+        ]);
+
+        let topics = describe_log_dir(&storage, log_dir, &[]).unwrap();
+        let described: Vec<_> = topics
+            .iter()
+            .flat_map(|t| {
+                t.partitions
+                    .iter()
+                    .map(|p| (t.name.as_str(), p.partition_index, p.partition_size))
+            })
+            .collect();
+        assert_eq!(described, [("bar", 0, 5), ("foo", 0, 10), ("foo", 1, 20)]);
+
+        let requested = [DescribableLogDirTopic {
+            topic: "foo".to_string(),
+            partitions: vec![1],
+        }];
+        let topics = describe_log_dir(&storage, log_dir, &requested).unwrap();
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].partitions[0].partition_index, 1);
+
+        assert!(describe_log_dir(&MemoryStorage::default(), log_dir, &[]).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::{net::IpAddr, ops::RangeInclusive};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+
+use crate::{
+    config::Config,
+    protocol::{
+        generated::{
+            metadata_request::MetadataRequestData,
+            metadata_response::{
+                MetadataResponseBroker, MetadataResponseData, MetadataResponsePartition,
+                MetadataResponseTopic,
+            },
+        },
+        record_batch::{PartitionValue, RegisterBrokerValue},
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode,
+    },
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    RequestContext,
+};
+
+/// Id of this broker when it is the single broker leading all partitions, as `node.id` of Kafka's
+/// example `server.properties`
+const SINGLE_BROKER_ID: i32 = 1;
+
+const UNKNOWN_TOPIC_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Authorized operations that were not requested, Kafka's `MetadataResponse.AUTHORIZED_OPERATIONS_OMITTED`
+const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+/// Operations on topics and clusters with their bits in the authorized operations,
+/// the codes of Kafka's `AclOperation`
+const TOPIC_OPERATIONS: [(Operation, u32); 8] = [
+    (Operation::Read, 3),
+    (Operation::Write, 4),
+    (Operation::Create, 5),
+    (Operation::Delete, 6),
+    (Operation::Alter, 7),
+    (Operation::Describe, 8),
+    (Operation::DescribeConfigs, 10),
+    (Operation::AlterConfigs, 11),
+];
+const CLUSTER_OPERATIONS: [(Operation, u32); 7] = [
+    (Operation::Create, 5),
+    (Operation::Alter, 7),
+    (Operation::Describe, 8),
+    (Operation::ClusterAction, 9),
+    (Operation::DescribeConfigs, 10),
+    (Operation::AlterConfigs, 11),
+    (Operation::IdempotentWrite, 12),
+];
+
+fn authorized_operations(
+    ctx: &RequestContext,
+    operations: &[(Operation, u32)],
+    resource: Resource,
+) -> i32 {
+    operations
+        .iter()
+        .filter(|(operation, _)| {
+            ctx.broker
+                .authorizer
+                .authorize(&ctx.principal, *operation, resource)
+        })
+        .fold(0, |bits, (_, bit)| bits | 1 << bit)
+}
+
+/// Id this broker is shown with
+pub fn broker_id(config: &Config) -> i32 {
+    config.node_id.map_or(SINGLE_BROKER_ID, |id| id as i32)
+}
+
+/// Unfenced brokers with the first listener of their registration in the metadata log.
+///
+/// This broker is always shown, with its own address and `broker.rack` if it has no registration,
+/// so clients can connect to it. An unspecified first bind address is advertised as `localhost`.
+pub fn brokers(registered: &[RegisterBrokerValue], config: &Config) -> Vec<MetadataResponseBroker> {
+    let mut brokers: Vec<_> = registered
+        .iter()
+        .filter(|b| !b.fenced)
+        .filter_map(|b| {
+            let end_point = b.end_points.first()?;
+            Some(MetadataResponseBroker {
+                node_id: b.broker_id,
+                host: end_point.host.clone(),
+                port: end_point.port.into(),
+                rack: b.rack.clone(),
+            })
+        })
+        .collect();
+
+    let id = broker_id(config);
+    if !brokers.iter().any(|b| b.node_id == id) {
+        let host = match config.listen_addr().ip() {
+            ip if ip.is_unspecified() => "localhost".to_string(),
+            IpAddr::V6(ip) => format!("[{ip}]"),
+            ip => ip.to_string(),
+        };
+        brokers.push(MetadataResponseBroker {
+            node_id: id,
+            host,
+            port: config.port.into(),
+            rack: config.broker_rack.clone(),
+        });
+        brokers.sort_by_key(|b| b.node_id);
+    }
+    brokers
+}
+
+/// Leader of the partition, the single broker leads all partitions
+pub fn leader_id(p: &PartitionValue, config: &Config) -> i32 {
+    match config.node_id {
+        None => SINGLE_BROKER_ID,
+        Some(_) => p.leader_id as i32,
+    }
+}
+
+/// Partition with its leader, a leader that is not among the shown brokers is not available
+fn partition(
+    p: &PartitionValue,
+    brokers: &[MetadataResponseBroker],
+    config: &Config,
+) -> MetadataResponsePartition {
+    let leader_id = leader_id(p, config);
+    let (error_code, leader_id) = if brokers.iter().any(|b| b.node_id == leader_id) {
+        (ErrorCode::None, leader_id)
+    } else {
+        (ErrorCode::LeaderNotAvailable, -1)
+    };
+    MetadataResponsePartition {
+        error_code: error_code.into(),
+        partition_index: p.partition_id as i32,
+        leader_id,
+        leader_epoch: p.leader_epoch as i32,
+        replica_nodes: p.replicas.iter().map(|&id| id as i32).collect(),
+        isr_nodes: p.in_sync_replicas.iter().map(|&id| id as i32).collect(),
+        offline_replicas: Vec::new(),
+    }
+}
+
+pub struct MetadataHandler;
+
+impl Handler for MetadataHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::Metadata
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        MetadataRequestData::LOWEST_SUPPORTED_VERSION
+            ..=MetadataRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            MetadataRequestData::deserialize(src, header.request_api_version)
+        })?;
+        let version = ctx.header.request_api_version;
+        let config = &ctx.broker.config;
+
+        let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
+        let brokers = brokers(&record_batches.registered_brokers(), config);
+
+        let topic_operations = |name: &str| {
+            if req.include_topic_authorized_operations {
+                authorized_operations(ctx, &TOPIC_OPERATIONS, Resource::Topic(name))
+            } else {
+                AUTHORIZED_OPERATIONS_OMITTED
+            }
+        };
+        let topic = |name: &str, topic_id: &str| MetadataResponseTopic {
+            error_code: ErrorCode::None.into(),
+            name: Some(name.to_string()),
+            topic_id: topic_id.to_string(),
+            is_internal: false,
+            partitions: record_batches
+                .partitions(topic_id)
+                .iter()
+                .map(|p| partition(p, &brokers, config))
+                .collect(),
+            topic_authorized_operations: topic_operations(name),
+        };
+        let error =
+            |error_code: ErrorCode, name: Option<String>, topic_id: String| MetadataResponseTopic {
+                error_code: error_code.into(),
+                name,
+                topic_id,
+                is_internal: false,
+                partitions: Vec::new(),
+                topic_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+            };
+
+        let topics = match req.topics {
+            // all topics, in version 0 the empty array, without the ones the principal may not describe
+            None => None,
+            Some(topics) if topics.is_empty() && version == 0 => None,
+            Some(topics) => Some(topics),
+        };
+        let may_describe = |name: &str| {
+            ctx.broker.authorizer.authorize(
+                &ctx.principal,
+                Operation::Describe,
+                Resource::Topic(name),
+            )
+        };
+        let topics = match topics {
+            None => record_batches
+                .topics()
+                .filter(|t| may_describe(&t.topic_name))
+                .map(|t| topic(&t.topic_name, &t.topic_id))
+                .collect(),
+            Some(topics) => topics
+                .into_iter()
+                .map(|requested| match requested.name {
+                    // authorized before it is looked up, so that the principal cannot tell which topics exist
+                    Some(name) if !may_describe(&name) => error(
+                        ErrorCode::TopicAuthorizationFailed,
+                        Some(name),
+                        UNKNOWN_TOPIC_ID.to_string(),
+                    ),
+                    Some(name) => match record_batches.topic_id(&name) {
+                        Some(topic_id) => topic(&name, topic_id),
+                        None => error(
+                            ErrorCode::UnknownTopicOrPartition,
+                            Some(name),
+                            UNKNOWN_TOPIC_ID.to_string(),
+                        ),
+                    },
+                    // topics are requested by id only if they have no name, since version 12. As in Kafka,
+                    // the name of a topic the principal may not describe is not sent.
+                    None => match record_batches.topic_name(&requested.topic_id) {
+                        None => error(ErrorCode::UnknownTopicId, None, requested.topic_id),
+                        Some(name) if !may_describe(name) => error(
+                            ErrorCode::TopicAuthorizationFailed,
+                            None,
+                            requested.topic_id,
+                        ),
+                        Some(name) => topic(name, &requested.topic_id),
+                    },
+                })
+                .collect(),
+        };
+
+        let resp = MetadataResponseData {
+            throttle_time_ms: ctx.throttle_time_ms,
+            brokers,
+            cluster_id: ctx.broker.cluster_control.cluster_id().map(str::to_string),
+            // controllers are not shown to clients, a broker is named instead, as Kafka does in KRaft mode
+            controller_id: broker_id(config),
+            topics,
+            cluster_authorized_operations: if req.include_cluster_authorized_operations {
+                authorized_operations(ctx, &CLUSTER_OPERATIONS, Resource::Cluster)
+            } else {
+                AUTHORIZED_OPERATIONS_OMITTED
+            },
+        };
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+    };
+
+    use super::{brokers, MetadataHandler, UNKNOWN_TOPIC_ID};
+    use crate::{
+        config::Config,
+        logic::{handler::Handler, BrokerContext, RequestContext},
+        protocol::{
+            generated::{
+                metadata_request::{MetadataRequestData, MetadataRequestTopic},
+                metadata_response::MetadataResponseData,
+            },
+            reader::ByteReader,
+            record_batch::{
+                BrokerEndpoint, RecordBatch, RecordValue, RegisterBrokerValue, TopicValue,
+            },
+            types::Serialize,
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    #[test]
+    fn unauthorized_topics_are_not_told_apart_from_missing_ones() {
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-0000-0000-000000000001".to_string(),
+        });
+        let config = Config {
+            acls: vec!["User:alice,Describe,Topic,*".parse().unwrap()],
+            ..Config::default()
+        };
+        let metadata = RecordBatch::of_values(0, vec![topic]).serialize();
+        let storage = MemoryStorage::with_files([(config.metadata_log_file(), metadata)]);
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(storage));
+        let ctx = RequestContext::for_request(Arc::new(broker), ApiKey::Metadata, 4);
+
+        let req = MetadataRequestData {
+            topics: Some(
+                ["foo", "missing"]
+                    .map(|name| MetadataRequestTopic {
+                        topic_id: UNKNOWN_TOPIC_ID.to_string(),
+                        name: Some(name.to_string()),
+                    })
+                    .to_vec(),
+            ),
+            allow_auto_topic_creation: false,
+            include_cluster_authorized_operations: false,
+            include_topic_authorized_operations: false,
+        };
+        let response = MetadataHandler.handle(&ctx, req.serialize(4)).unwrap();
+        // the body after the size and the correlation id of the response
+        let resp = MetadataResponseData::deserialize(&mut ByteReader::new(response.slice(8..)), 4)
+            .unwrap();
+        let errors: Vec<_> = resp.topics.iter().map(|t| t.error_code).collect();
+        assert_eq!(errors, [i16::from(ErrorCode::TopicAuthorizationFailed); 2]);
+    }
+
+    #[test]
+    fn shows_unfenced_brokers_with_racks() {
+        let registered = |broker_id, rack: &str, fenced| RegisterBrokerValue {
+            broker_id,
+            incarnation_id: String::new(),
+            broker_epoch: 1,
+            end_points: vec![BrokerEndpoint {
+                name: "PLAINTEXT".to_string(),
+                host: format!("broker{broker_id}"),
+                port: 9092,
+                security_protocol: 0,
+            }],
+            rack: Some(rack.to_string()),
+            fenced,
+        };
+        let config = Config {
+            bind: vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)],
+            node_id: Some(2),
+            broker_rack: Some("rack-b".to_string()),
+            ..Config::default()
+        };
+
+        let shown = brokers(
+            &[
+                registered(1, "rack-a", false),
+                registered(3, "rack-c", true),
+            ],
+            &config,
+        );
+        let shown: Vec<_> = shown
+            .iter()
+            .map(|b| (b.node_id, b.host.as_str(), b.rack.as_deref()))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                (1, "broker1", Some("rack-a")),
+                (2, "localhost", Some("rack-b"))
+            ]
+        );
+
+        // the registration of this broker is shown as it is
+        let shown = brokers(&[registered(2, "rack-a", false)], &config);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].host, "broker2");
+        assert_eq!(shown[0].rack.as_deref(), Some("rack-a"));
+    }
+}
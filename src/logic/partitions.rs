@@ -0,0 +1,493 @@
+//! Actors of the partition logs.
+//!
+//! Every partition log is owned by an actor, a thread that appends the produced batches to the log, reads it
+//! for the fetches and the offset lookups and keeps its watermarks, one request after the other. The handlers
+//! send it their requests over a channel and wait for the reply, so the appends to a partition get the next
+//! offsets without a lock, while the actors of other partitions work at the same time. Fetches waiting for
+//! records park a waiter in the actor, it is notified when a batch is appended.
+//!
+//! The actors are threads rather than tasks, as the storage and the handlers are synchronous. Their queues
+//! are bounded, a handler waits for a busy actor to catch up, and an actor without requests for a while stops,
+//! so there are only threads for the partitions in use.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    protocol::{
+        reader::ByteReader,
+        record_batch::{LogOffsets, LogSlice, RecordBatch},
+    },
+    storage::{Storage, StorageError},
+};
+
+/// Log start and end offsets of a partition.
+///
+/// The partitions have no followers, everything in the log is committed: the end offset is the high watermark
+/// and the last stable offset too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Watermarks {
+    pub log_start_offset: i64,
+    pub log_end_offset: i64,
+}
+
+/// Where a batch was appended to the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Appended {
+    pub base_offset: i64,
+    pub log_start_offset: i64,
+}
+
+/// Requests queued for an actor before the handlers sending it more wait
+const QUEUE_CAPACITY: usize = 64;
+
+/// Time without requests after which an actor without waiting fetches stops
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Request queues of the running actors, by the path of the log
+type Actors = Mutex<HashMap<PathBuf, Arc<mpsc::SyncSender<Command>>>>;
+
+/// Reply of a partition actor, received once the actor got to the request
+#[must_use = "the request waits in the actor until its reply is received"]
+pub struct Reply<T> {
+    file: PathBuf,
+    reply: mpsc::Receiver<Result<T, StorageError>>,
+}
+
+impl<T> Reply<T> {
+    /// Waits for the actor to process the request
+    pub fn wait(self) -> Result<T, StorageError> {
+        self.reply.recv().unwrap_or_else(|_| {
+            // the actor panicked, it is started again for the next request
+            Err(StorageError::Io {
+                path: self.file.clone(),
+                source: io::Error::other("partition actor stopped"),
+            })
+        })
+    }
+}
+
+enum Command {
+    Append {
+        batch: Bytes,
+        reply: mpsc::Sender<Result<Appended, StorageError>>,
+    },
+    Read {
+        fetch_offset: i64,
+        max_bytes: usize,
+        reply: mpsc::Sender<Result<LogSlice, StorageError>>,
+    },
+    Offsets {
+        reply: mpsc::Sender<Result<LogOffsets, StorageError>>,
+    },
+    Watch(Waiter),
+    Watermarks {
+        reply: mpsc::Sender<Result<Watermarks, StorageError>>,
+    },
+}
+
+/// Fetch waiting for the log to grow past an offset, until its deadline
+struct Waiter {
+    offset: i64,
+    deadline: Instant,
+    notify: mpsc::Sender<()>,
+}
+
+/// Actors of the partition logs, by the path of the log.
+///
+/// An actor is started for the first request to a partition, and again for the next request after it stopped
+/// being idle or panicked. The offsets are then read from the log again.
+pub struct Partitions {
+    storage: Arc<dyn Storage>,
+    actors: Arc<Actors>,
+    idle_timeout: Duration,
+}
+
+impl Partitions {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            actors: Arc::default(),
+            idle_timeout: IDLE_TIMEOUT,
+        }
+    }
+
+    /// Appends the record batch to the log at its end offset, the base offset of the batch is set to it.
+    ///
+    /// The offsets of the records are relative to the base offset, the CRC of the batch does not change.
+    pub fn append(&self, file: &Path, batch: Bytes) -> Reply<Appended> {
+        self.request(file, |reply| Command::Append { batch, reply })
+    }
+
+    /// Reads the batches of the log from the fetch offset, see [`LogSlice::read`]
+    pub fn read(&self, file: &Path, fetch_offset: i64, max_bytes: usize) -> Reply<LogSlice> {
+        self.request(file, |reply| Command::Read {
+            fetch_offset,
+            max_bytes,
+            reply,
+        })
+    }
+
+    /// Log start and end offsets of the log, zero if nothing was produced to it yet
+    pub fn watermarks(&self, file: &Path) -> Reply<Watermarks> {
+        self.request(file, |reply| Command::Watermarks { reply })
+    }
+
+    /// Offsets and timestamps of the batches of the log, read after the appends queued before
+    pub fn offsets(&self, file: &Path) -> Reply<LogOffsets> {
+        self.request(file, |reply| Command::Offsets { reply })
+    }
+
+    /// Notifies the waiter once the log end offset is past the offset, right away if it already is.
+    /// The waiter is dropped after the deadline.
+    pub fn watch(&self, file: &Path, offset: i64, deadline: Instant, notify: mpsc::Sender<()>) {
+        self.send(
+            file,
+            Command::Watch(Waiter {
+                offset,
+                deadline,
+                notify,
+            }),
+        );
+    }
+
+    fn request<T>(
+        &self,
+        file: &Path,
+        command: impl FnOnce(mpsc::Sender<Result<T, StorageError>>) -> Command,
+    ) -> Reply<T> {
+        let (reply, receiver) = mpsc::channel();
+        self.send(file, command(reply));
+        Reply {
+            file: file.to_path_buf(),
+            reply: receiver,
+        }
+    }
+
+    fn send(&self, file: &Path, mut command: Command) {
+        loop {
+            // the actors of the other partitions are not held up while the queue is full
+            let actor = self.actor(file);
+            match actor.send(command) {
+                Ok(()) => return,
+                Err(mpsc::SendError(unsent)) => command = unsent,
+            }
+            // the actor panicked, unless another handler started it again already
+            let mut actors = self
+                .actors
+                .lock()
+                .expect("partition actors are not poisoned");
+            if actors.get(file).is_some_and(|a| Arc::ptr_eq(a, &actor)) {
+                actors.remove(file);
+            }
+        }
+    }
+
+    /// Queue of the actor of the log, which is started if it is not running
+    fn actor(&self, file: &Path) -> Arc<mpsc::SyncSender<Command>> {
+        let mut actors = self
+            .actors
+            .lock()
+            .expect("partition actors are not poisoned");
+        if let Some(actor) = actors.get(file) {
+            return actor.clone();
+        }
+
+        let (actor, commands) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let actor = Arc::new(actor);
+        let log = PartitionLog {
+            storage: self.storage.clone(),
+            file: file.to_path_buf(),
+            watermarks: None,
+            waiters: Vec::new(),
+            actors: Arc::downgrade(&self.actors),
+            queue: Arc::downgrade(&actor),
+            idle_timeout: self.idle_timeout,
+        };
+        std::thread::Builder::new()
+            .name(format!("partition {}", file.display()))
+            .spawn(move || log.run(commands))
+            .expect("start partition actor");
+        actors.insert(file.to_path_buf(), actor.clone());
+        actor
+    }
+}
+
+/// State of the partition log owned by its actor
+struct PartitionLog {
+    storage: Arc<dyn Storage>,
+    file: PathBuf,
+    /// Read from the log by the first request, and again after a failed append
+    watermarks: Option<Watermarks>,
+    waiters: Vec<Waiter>,
+    /// The actor removes its queue when it stops, the actors are dropped with the broker
+    actors: Weak<Actors>,
+    queue: Weak<mpsc::SyncSender<Command>>,
+    idle_timeout: Duration,
+}
+
+impl PartitionLog {
+    /// Processes the requests until the actor is idle, or the broker stops and the senders are dropped
+    fn run(mut self, commands: mpsc::Receiver<Command>) {
+        loop {
+            let command = match commands.recv_timeout(self.idle_timeout) {
+                Ok(command) => command,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    // the waiters past their deadline are dropped
+                    self.notify_waiters();
+                    if !self.waiters.is_empty() {
+                        continue;
+                    }
+                    let Some(actors) = self.actors.upgrade() else {
+                        return;
+                    };
+                    let mut actors = actors.lock().expect("partition actors are not poisoned");
+                    // no handler holds the queue, and none gets it while the lock is held
+                    let idle = actors.get(&self.file).is_some_and(|queue| {
+                        std::ptr::eq(Arc::as_ptr(queue), self.queue.as_ptr())
+                            && Arc::strong_count(queue) == 1
+                    });
+                    if !idle {
+                        continue;
+                    }
+                    match commands.try_recv() {
+                        // sent after the timeout, before the handler dropped the queue
+                        Ok(command) => command,
+                        Err(_) => {
+                            actors.remove(&self.file);
+                            return;
+                        }
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            };
+            self.process(command);
+        }
+    }
+
+    fn process(&mut self, command: Command) {
+        // the handler may have given up on the reply, e.g. its connection was closed
+        match command {
+            Command::Append { batch, reply } => _ = reply.send(self.append(batch)),
+            Command::Read {
+                fetch_offset,
+                max_bytes,
+                reply,
+            } => {
+                _ = reply.send(LogSlice::read(
+                    &*self.storage,
+                    &self.file,
+                    fetch_offset,
+                    max_bytes,
+                ))
+            }
+            Command::Offsets { reply } => _ = reply.send(self.offsets()),
+            Command::Watermarks { reply } => _ = reply.send(self.watermarks()),
+            Command::Watch(waiter) => {
+                self.waiters.push(waiter);
+                // the offsets of a log not read yet are read first
+                if let Err(err) = self.watermarks() {
+                    eprintln!("Error: read offsets of {}: {err}", self.file.display());
+                }
+                self.notify_waiters();
+            }
+        }
+    }
+
+    fn watermarks(&mut self) -> Result<Watermarks, StorageError> {
+        if let Some(watermarks) = self.watermarks {
+            return Ok(watermarks);
+        }
+        let (_, watermarks) = self.read_offsets()?;
+        Ok(watermarks)
+    }
+
+    fn offsets(&mut self) -> Result<LogOffsets, StorageError> {
+        self.read_offsets().map(|(log, _)| log)
+    }
+
+    /// Reads the offsets of the log, its watermarks are kept
+    fn read_offsets(&mut self) -> Result<(LogOffsets, Watermarks), StorageError> {
+        let log = LogOffsets::from_file(&*self.storage, &self.file)?;
+        let watermarks = Watermarks {
+            log_start_offset: log.log_start_offset,
+            log_end_offset: log.log_end_offset,
+        };
+        self.watermarks = Some(watermarks);
+        Ok((log, watermarks))
+    }
+
+    fn append(&mut self, batch: Bytes) -> Result<Appended, StorageError> {
+        let watermarks = self.watermarks()?;
+        let base_offset = watermarks.log_end_offset;
+        let mut batch = BytesMut::from(&batch[..]);
+        batch[..8].copy_from_slice(&base_offset.to_be_bytes());
+        let batch = batch.freeze();
+        let last_offset = RecordBatch::from_bytes_lazy(&mut ByteReader::new(batch.clone()))
+            .map_err(|source| StorageError::Corrupt {
+                path: self.file.clone(),
+                source,
+            })?
+            .last_offset();
+
+        if let Err(err) = self.storage.append(&self.file, &batch) {
+            // a part of the batch may have been written, the offsets are read from the log again
+            self.watermarks = None;
+            return Err(err);
+        }
+        self.watermarks = Some(Watermarks {
+            log_start_offset: watermarks.log_start_offset,
+            log_end_offset: last_offset + 1,
+        });
+        self.notify_waiters();
+
+        Ok(Appended {
+            base_offset,
+            log_start_offset: watermarks.log_start_offset,
+        })
+    }
+
+    /// Notifies the waiters the log grew past the offset of, and drops the waiters past their deadline
+    fn notify_waiters(&mut self) {
+        let Some(watermarks) = self.watermarks else {
+            return;
+        };
+        let now = Instant::now();
+        self.waiters.retain(|waiter| {
+            if watermarks.log_end_offset > waiter.offset {
+                // the fetch may have stopped waiting already
+                _ = waiter.notify.send(());
+                return false;
+            }
+            waiter.deadline > now
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::Path,
+        sync::{mpsc, Arc},
+        time::{Duration, Instant},
+    };
+
+    use bytes::Bytes;
+
+    use super::{Appended, Partitions};
+    use crate::{
+        protocol::{
+            record_batch::{LogOffsets, RecordBatch, RecordValue, TopicValue},
+            types::Serialize,
+        },
+        storage::MemoryStorage,
+    };
+
+    fn partitions() -> Partitions {
+        Partitions::new(Arc::new(MemoryStorage::default()))
+    }
+
+    fn topic() -> RecordValue {
+        RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-4000-8000-000000000001".to_string(),
+        })
+    }
+
+    /// Batch of the given number of records, at base offset 0
+    fn batch(records: usize) -> Bytes {
+        RecordBatch::of_values(0, vec![topic(); records]).serialize()
+    }
+
+    #[test]
+    fn appends_are_read_at_their_offsets() {
+        let partitions = partitions();
+        let file = Path::new("/logs/foo-0/00000000000000000000.log");
+
+        let appended = [3, 1, 2].map(|records| partitions.append(file, batch(records)));
+        let offsets = appended.map(|reply| reply.wait().unwrap().base_offset);
+        assert_eq!(offsets, [0, 3, 4]);
+
+        let slice = partitions.read(file, 3, usize::MAX).wait().unwrap();
+        assert_eq!(slice.offsets, Some((3, 5)));
+        assert_eq!((slice.log_start_offset, slice.log_end_offset), (0, 6));
+        let log = LogOffsets::from_file(&*partitions.storage, file).unwrap();
+        assert_eq!(log.log_end_offset, 6);
+
+        // the offsets are read after the append queued before them
+        let appended = partitions.append(file, batch(1));
+        let log = partitions.offsets(file).wait().unwrap();
+        assert_eq!(appended.wait().unwrap().base_offset, 6);
+        assert_eq!((log.log_start_offset, log.log_end_offset), (0, 7));
+    }
+
+    #[test]
+    fn appends_continue_the_log_written_before() {
+        let file = Path::new("/logs/foo-0/00000000000000000000.log");
+        // the log starts at offset 5, e.g. its first segments were deleted
+        let log = RecordBatch::of_values(5, vec![topic(); 2]).serialize();
+        let storage = MemoryStorage::with_files([(file, log)]);
+        let partitions = Partitions::new(Arc::new(storage));
+
+        let appended = partitions.append(file, batch(1)).wait().unwrap();
+        assert_eq!(
+            appended,
+            Appended {
+                base_offset: 7,
+                log_start_offset: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn waiters_are_notified_of_appends() {
+        let partitions = partitions();
+        let file = Path::new("/logs/foo-0/00000000000000000000.log");
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        let (notify, notified) = mpsc::channel();
+        partitions.watch(file, 0, deadline, notify.clone());
+        assert_eq!(
+            notified.recv_timeout(Duration::from_millis(10)),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        );
+        partitions.append(file, batch(1)).wait().unwrap();
+        assert_eq!(notified.recv_timeout(Duration::from_secs(1)), Ok(()));
+
+        // the log is past the offset already
+        partitions.watch(file, 0, deadline, notify);
+        assert_eq!(notified.recv_timeout(Duration::from_secs(1)), Ok(()));
+    }
+
+    #[test]
+    fn idle_actors_stop() {
+        let mut partitions = partitions();
+        partitions.idle_timeout = Duration::from_millis(10);
+        let file = Path::new("/logs/foo-0/00000000000000000000.log");
+        let running = |partitions: &Partitions| !partitions.actors.lock().unwrap().is_empty();
+
+        // the actor of a waiting fetch keeps running until its deadline
+        let (notify, _notified) = mpsc::channel();
+        let deadline = Instant::now() + Duration::from_millis(100);
+        partitions.watch(file, 0, deadline, notify);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(running(&partitions));
+
+        partitions.append(file, batch(2)).wait().unwrap();
+        let timeout = Instant::now() + Duration::from_secs(5);
+        while running(&partitions) {
+            assert!(Instant::now() < timeout, "idle actor did not stop");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // the offsets are read from the log by the next actor
+        let appended = partitions.append(file, batch(1)).wait().unwrap();
+        assert_eq!(appended.base_offset, 2);
+    }
+}
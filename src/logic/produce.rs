@@ -0,0 +1,1047 @@
+use std::{borrow::Cow, ops::RangeInclusive, path::Path};
+
+use anyhow::{Context, Result};
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    config::{CompressionType, Config, TimestampType},
+    protocol::{
+        crc32c::crc32c,
+        generated::{
+            produce_request::{PartitionProduceData, ProduceRequestData},
+            produce_response::{
+                BatchIndexAndErrorMessage, PartitionProduceResponse, ProduceResponseData,
+                TopicProduceResponse,
+            },
+        },
+        gzip::{self, GzipError},
+        reader::ByteReader,
+        record_batch::RecordBatches,
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode, ProtocolError,
+    },
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    now_ms,
+    partitions::Partitions,
+    RequestContext,
+};
+
+/// Attributes of the record batch the broker checks or sets
+const COMPRESSION_MASK: i16 = 0x07;
+const GZIP: i16 = 1;
+const SNAPPY: i16 = 2;
+const LZ4: i16 = 3;
+const ZSTD: i16 = 4;
+const LOG_APPEND_TIME: i16 = 0x08;
+const CONTROL_BATCH: i16 = 0x20;
+/// Timestamp of a record created without one, Kafka's `RecordBatch.NO_TIMESTAMP`
+const NO_TIMESTAMP: i64 = -1;
+
+/// Size of the record batch header up to the records, Kafka's `DefaultRecordBatch.RECORD_BATCH_OVERHEAD`
+const BATCH_HEADER_SIZE: usize = 61;
+/// Position of the fields of the record batch header the broker checks or sets
+const PARTITION_LEADER_EPOCH_OFFSET: usize = 12;
+const MAGIC_OFFSET: usize = 16;
+const CRC_OFFSET: usize = 17;
+const ATTRIBUTES_OFFSET: usize = 21;
+const MAX_TIMESTAMP_OFFSET: usize = 35;
+/// Largest size of the decompressed records of a batch, a limit of the memory a produced batch takes
+const MAX_DECOMPRESSED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Configuration of the log the batches produced to a topic are checked against, as Kafka's `LogConfig`.
+///
+/// The broker configuration is the default, topics override it with the configs set in the metadata log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LogConfig {
+    max_message_bytes: usize,
+    timestamp_type: TimestampType,
+    /// Largest difference of a CreateTime timestamp from the broker time, unlimited if `None`
+    timestamp_difference_max_ms: Option<i64>,
+    /// Compression of the appended batches, the producer's one if `None`
+    compression_type: Option<CompressionType>,
+}
+
+impl LogConfig {
+    /// Log configuration of the topic with the `topic_config` overrides of the broker configuration
+    fn new<'a>(config: &Config, topic_config: impl Fn(&str) -> Option<&'a str>) -> Self {
+        let mut log_config = Self {
+            max_message_bytes: config.message_max_bytes,
+            timestamp_type: config.log_message_timestamp_type,
+            timestamp_difference_max_ms: config
+                .log_message_timestamp_difference_max
+                .map(|max| max.as_millis() as i64),
+            compression_type: config.compression_type,
+        };
+
+        // invalid overrides are not accepted by Kafka, keep the broker configuration if one got in anyway
+        let invalid = |name: &str, value: &str| {
+            eprintln!("Warning: ignoring invalid topic config {name}={value}");
+        };
+        if let Some(value) = topic_config("max.message.bytes") {
+            match value.parse() {
+                Ok(max) => log_config.max_message_bytes = max,
+                Err(_) => invalid("max.message.bytes", value),
+            }
+        }
+        if let Some(value) = topic_config("message.timestamp.type") {
+            match value.parse() {
+                Ok(timestamp_type) => log_config.timestamp_type = timestamp_type,
+                Err(_) => invalid("message.timestamp.type", value),
+            }
+        }
+        if let Some(value) = topic_config("message.timestamp.difference.max.ms") {
+            match value.parse::<i64>() {
+                // the default of Kafka is Long.MAX_VALUE, no limit
+                Ok(i64::MAX) => log_config.timestamp_difference_max_ms = None,
+                Ok(max) if max >= 0 => log_config.timestamp_difference_max_ms = Some(max),
+                _ => invalid("message.timestamp.difference.max.ms", value),
+            }
+        }
+        if let Some(value) = topic_config("compression.type") {
+            match CompressionType::from_config(value) {
+                Ok(compression_type) => log_config.compression_type = compression_type,
+                // snappy, lz4 and zstd are valid in Kafka, but the broker cannot compress with them
+                Err(_) => invalid("compression.type", value),
+            }
+        }
+        log_config
+    }
+
+    /// Checks the timestamp of a record against the broker time, the message of the error if it is out of range
+    fn validate_timestamp(&self, timestamp: i64, now_ms: i64) -> Result<(), String> {
+        match self.timestamp_difference_max_ms {
+            Some(max)
+                if self.timestamp_type == TimestampType::CreateTime
+                    && timestamp != NO_TIMESTAMP
+                    && timestamp.abs_diff(now_ms) > max as u64 =>
+            {
+                Err(format!(
+                    "Timestamp {timestamp} is out of range. The timestamp should be within [{}, {}]",
+                    now_ms.saturating_sub(max),
+                    now_ms.saturating_add(max)
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Where the produced batch was appended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AppendInfo {
+    base_offset: i64,
+    /// Time the broker set as the timestamp of the records, -1 for CreateTime
+    log_append_time_ms: i64,
+    log_start_offset: i64,
+}
+
+/// Why the records produced to a partition were rejected.
+///
+/// Record errors point to the records of the batch by their index, as in Kafka's `RecordValidationException`.
+#[derive(Debug, PartialEq)]
+struct ProduceError {
+    error_code: ErrorCode,
+    message: Option<String>,
+    record_errors: Vec<(i32, String)>,
+}
+
+impl ProduceError {
+    fn new(error_code: ErrorCode) -> Self {
+        Self {
+            error_code,
+            message: None,
+            record_errors: Vec::new(),
+        }
+    }
+
+    fn with_message(error_code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error_code,
+            message: Some(message.into()),
+            record_errors: Vec::new(),
+        }
+    }
+
+    /// The records that failed the validation, summarized in the message as Kafka does
+    fn invalid_records(record_errors: Vec<(i32, String)>, invalid_timestamp: bool) -> Self {
+        if invalid_timestamp {
+            return Self {
+                error_code: ErrorCode::InvalidTimestamp,
+                message: Some(
+                    "One or more records have been rejected due to invalid timestamp".to_string(),
+                ),
+                record_errors,
+            };
+        }
+
+        let shown: Vec<_> = record_errors
+            .iter()
+            .take(3)
+            .map(|(index, message)| format!("{index}: {message}"))
+            .collect();
+        Self {
+            error_code: ErrorCode::InvalidRecord,
+            message: Some(format!(
+                "One or more records have been rejected due to {} record errors in total, and only showing the first three errors at most: [{}]",
+                record_errors.len(),
+                shown.join(", ")
+            )),
+            record_errors,
+        }
+    }
+}
+
+/// Checks the record batch produced to a partition before it is appended to the log.
+///
+/// As Kafka requires for produce requests of version 3 and later, the records have to be exactly one batch
+/// of magic 2 with base offset 0, the broker assigns the offsets. The batch must not be larger than
+/// `max.message.bytes` and its CRC has to match. The records of uncompressed and gzip batches are decoded
+/// to check their framing, count, offsets and timestamps, the decompressed records of gzip batches are
+/// returned. Snappy, lz4 and zstd batches are checked only by their CRC and the timestamps in the header,
+/// other compression ids are rejected.
+///
+/// CreateTime timestamps have to be within `timestamp.difference.max.ms` of the broker time. With LogAppendTime
+/// the broker sets the timestamps, the producer's ones are not checked.
+fn validate_batch(
+    records: &[u8],
+    log_config: &LogConfig,
+    now_ms: i64,
+) -> Result<Option<Vec<u8>>, ProduceError> {
+    if records.is_empty() {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            "Produce requests must have at least one record batch per partition",
+        ));
+    }
+    if records.len() < BATCH_HEADER_SIZE {
+        return Err(ProduceError::with_message(
+            ErrorCode::CorruptMessage,
+            format!("Record batch of {} bytes is truncated", records.len()),
+        ));
+    }
+
+    let mut header = ByteReader::new(Bytes::copy_from_slice(&records[..BATCH_HEADER_SIZE]));
+    let corrupt = |_: ProtocolError| ProduceError::new(ErrorCode::CorruptMessage);
+    let base_offset = header.get_i64("base_offset").map_err(corrupt)?;
+    let batch_length = header.get_i32("batch_length").map_err(corrupt)?;
+    _ = header.get_i32("partition_leader_epoch").map_err(corrupt)?;
+    let magic = header.get_i8("magic").map_err(corrupt)?;
+    let crc = header.get_u32("crc").map_err(corrupt)?;
+    let attributes = header.get_i16("attributes").map_err(corrupt)?;
+    let last_offset_delta = header.get_i32("last_offset_delta").map_err(corrupt)?;
+    let base_timestamp = header.get_i64("base_timestamp").map_err(corrupt)?;
+    let max_timestamp = header.get_i64("max_timestamp").map_err(corrupt)?;
+    _ = header.get_i64("producer_id").map_err(corrupt)?;
+    _ = header.get_i16("producer_epoch").map_err(corrupt)?;
+    _ = header.get_i32("base_sequence").map_err(corrupt)?;
+    let records_count = header.get_i32("records_count").map_err(corrupt)?;
+
+    let batch_size = 12 + batch_length.max(0) as usize;
+    if magic != 2 {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            format!("Produce requests are only allowed to contain record batches with magic version 2, not {magic}"),
+        ));
+    }
+    if batch_size < BATCH_HEADER_SIZE || batch_size > records.len() {
+        return Err(ProduceError::with_message(
+            ErrorCode::CorruptMessage,
+            format!(
+                "Record batch length {batch_length} does not match the {} bytes of the records",
+                records.len()
+            ),
+        ));
+    }
+    if batch_size < records.len() {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            "Produce requests must have exactly one record batch per partition",
+        ));
+    }
+    if batch_size > log_config.max_message_bytes {
+        return Err(ProduceError::with_message(
+            ErrorCode::MessageTooLarge,
+            format!(
+                "The record batch size is {batch_size} bytes which exceeds the maximum configured value of {}",
+                log_config.max_message_bytes
+            ),
+        ));
+    }
+    let computed_crc = crc32c(&records[ATTRIBUTES_OFFSET..]);
+    if computed_crc != crc {
+        return Err(ProduceError::with_message(
+            ErrorCode::CorruptMessage,
+            format!("Record batch is corrupt (stored crc = {crc}, computed crc = {computed_crc})"),
+        ));
+    }
+    if base_offset != 0 {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            format!("The base offset of the record batch should be 0, but it is {base_offset}"),
+        ));
+    }
+    if attributes & CONTROL_BATCH != 0 {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            "Clients are not allowed to write control records",
+        ));
+    }
+    if records_count < 1 || i64::from(records_count) != i64::from(last_offset_delta) + 1 {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidRecord,
+            format!("Inconsistent batch offset range [0, {last_offset_delta}] and count of records {records_count}"),
+        ));
+    }
+    if attributes & LOG_APPEND_TIME != 0 {
+        return Err(ProduceError::with_message(
+            ErrorCode::InvalidTimestamp,
+            "Producer should not set timestamp type to LogAppendTime",
+        ));
+    }
+    let decompressed = match attributes & COMPRESSION_MASK {
+        0 => None,
+        GZIP => match gzip::decompress(&records[BATCH_HEADER_SIZE..], MAX_DECOMPRESSED_SIZE) {
+            Ok(decompressed) => Some(decompressed),
+            Err(err @ GzipError::TooLarge(_)) => {
+                return Err(ProduceError::with_message(
+                    ErrorCode::MessageTooLarge,
+                    format!("Records of the record batch are too large: {err}"),
+                ))
+            }
+            Err(err) => {
+                return Err(ProduceError::with_message(
+                    ErrorCode::CorruptMessage,
+                    format!("Record batch is corrupt: {err}"),
+                ))
+            }
+        },
+        SNAPPY | LZ4 | ZSTD => {
+            return [base_timestamp, max_timestamp]
+                .into_iter()
+                .try_for_each(|timestamp| log_config.validate_timestamp(timestamp, now_ms))
+                .map(|()| None)
+                .map_err(|message| {
+                    ProduceError::with_message(ErrorCode::InvalidTimestamp, message)
+                });
+        }
+        codec => {
+            return Err(ProduceError::with_message(
+                ErrorCode::CorruptMessage,
+                format!("Unknown compression type id {codec} of the record batch"),
+            ))
+        }
+    };
+
+    let batch_records = decompressed
+        .as_deref()
+        .unwrap_or(&records[BATCH_HEADER_SIZE..]);
+    let mut src = ByteReader::new(Bytes::copy_from_slice(batch_records));
+    let mut record_errors = Vec::new();
+    let mut invalid_timestamp = false;
+    for index in 0..records_count {
+        let (timestamp_delta, offset_delta) = match read_record(&mut src) {
+            Ok(deltas) => deltas,
+            Err(err) => {
+                // the next records cannot be found without the length of this one
+                record_errors.push((index, format!("Record is corrupt: {err}")));
+                break;
+            }
+        };
+        if offset_delta != i64::from(index) {
+            record_errors.push((
+                index,
+                format!("Record has offset delta {offset_delta}, expected {index}"),
+            ));
+        } else if base_timestamp != NO_TIMESTAMP {
+            // both come from the producer, their sum may not be a timestamp
+            let checked = match base_timestamp.checked_add(timestamp_delta) {
+                Some(timestamp) => log_config.validate_timestamp(timestamp, now_ms),
+                None => Err(format!(
+                    "Record has timestamp delta {timestamp_delta}, its timestamp overflows"
+                )),
+            };
+            if let Err(message) = checked {
+                record_errors.push((index, message));
+                invalid_timestamp = true;
+            }
+        }
+    }
+    if record_errors.is_empty() && src.remaining() > 0 {
+        return Err(ProduceError::with_message(
+            ErrorCode::CorruptMessage,
+            format!(
+                "{} bytes follow the last record of the batch",
+                src.remaining()
+            ),
+        ));
+    }
+    if !record_errors.is_empty() {
+        return Err(ProduceError::invalid_records(
+            record_errors,
+            invalid_timestamp,
+        ));
+    }
+    Ok(decompressed)
+}
+
+/// The validated batch as it is appended, with its records recompressed if its codec is not the
+/// `compression.type` of the topic.
+///
+/// Batches are recompressed from and to uncompressed and gzip, the broker cannot decompress the batches
+/// of the other codecs to convert them. `decompressed` are the records of a gzip batch.
+fn recompress<'a>(
+    batch: &'a [u8],
+    decompressed: Option<Vec<u8>>,
+    compression_type: Option<CompressionType>,
+) -> Result<Cow<'a, [u8]>, ProduceError> {
+    let attributes = i16::from_be_bytes([batch[ATTRIBUTES_OFFSET], batch[ATTRIBUTES_OFFSET + 1]]);
+    let compression = attributes & COMPRESSION_MASK;
+    let target = match compression_type {
+        None => return Ok(Cow::Borrowed(batch)),
+        Some(CompressionType::None) => 0,
+        Some(CompressionType::Gzip) => GZIP,
+    };
+    if compression == target {
+        return Ok(Cow::Borrowed(batch));
+    }
+
+    let records = match (compression, &decompressed) {
+        (0, _) => &batch[BATCH_HEADER_SIZE..],
+        (GZIP, Some(decompressed)) => decompressed,
+        _ => {
+            return Err(ProduceError::with_message(
+                ErrorCode::UnsupportedCompressionType,
+                format!("Record batches of compression type {compression} cannot be recompressed"),
+            ))
+        }
+    };
+    let records = match target {
+        GZIP => Cow::Owned(gzip::compress(records)),
+        _ => Cow::Borrowed(records),
+    };
+
+    let mut recompressed = Vec::with_capacity(BATCH_HEADER_SIZE + records.len());
+    recompressed.extend_from_slice(&batch[..BATCH_HEADER_SIZE]);
+    recompressed.extend_from_slice(&records);
+    let batch_length = (recompressed.len() - 12) as i32;
+    recompressed[8..12].copy_from_slice(&batch_length.to_be_bytes());
+    recompressed[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 2]
+        .copy_from_slice(&(attributes & !COMPRESSION_MASK | target).to_be_bytes());
+    let crc = crc32c(&recompressed[ATTRIBUTES_OFFSET..]);
+    recompressed[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&crc.to_be_bytes());
+    Ok(Cow::Owned(recompressed))
+}
+
+/// Reads one record of an uncompressed batch and returns its timestamp and offset deltas
+fn read_record(src: &mut ByteReader) -> Result<(i64, i64), ProtocolError> {
+    let length = src.get_varlong("length")?;
+    let invalid_length = |field, value: i64| ProtocolError::UnexpectedValue { field, value };
+    if length < 0 {
+        return Err(invalid_length("length", length));
+    }
+    let mut record = ByteReader::new(src.get_bytes("record", length as usize)?);
+    _ = record.get_i8("attributes")?;
+    let timestamp_delta = record.get_varlong("timestamp_delta")?;
+    let offset_delta = record.get_varlong("offset_delta")?;
+
+    // null key and value have length -1
+    for field in ["key", "value"] {
+        let len = record.get_varlong(field)?;
+        if len >= 0 {
+            _ = record.get_bytes(field, len as usize)?;
+        } else if len < -1 {
+            return Err(invalid_length(field, len));
+        }
+    }
+    let headers_count = record.get_varlong("headers_count")?;
+    if headers_count < 0 {
+        return Err(invalid_length("headers_count", headers_count));
+    }
+    for _ in 0..headers_count {
+        let key_len = record.get_varlong("header_key")?;
+        if key_len < 0 {
+            return Err(invalid_length("header_key", key_len));
+        }
+        _ = record.get_bytes("header_key", key_len as usize)?;
+        let value_len = record.get_varlong("header_value")?;
+        if value_len >= 0 {
+            _ = record.get_bytes("header_value", value_len as usize)?;
+        }
+    }
+    if record.remaining() > 0 {
+        return Err(invalid_length("length", length));
+    }
+    Ok((timestamp_delta, offset_delta))
+}
+
+/// Appends the validated batch to the partition log.
+///
+/// The actor of the partition assigns the offsets of the records, the base offset of the batch is the end
+/// offset of the log. With the log append time, the max timestamp of the batch is set to the time of
+/// the append and marked as LogAppendTime, and the CRC is computed again. The records keep their timestamps,
+/// consumers take the max timestamp of a LogAppendTime batch as the timestamp of every record, as Kafka does
+/// for the compressed batches it does not recompress.
+fn append(
+    partitions: &Partitions,
+    file: &Path,
+    records: &[u8],
+    leader_epoch: i32,
+    log_append_time: Option<i64>,
+) -> Result<AppendInfo> {
+    let mut batch = BytesMut::from(records);
+    batch[PARTITION_LEADER_EPOCH_OFFSET..MAGIC_OFFSET].copy_from_slice(&leader_epoch.to_be_bytes());
+    if let Some(now_ms) = log_append_time {
+        let attributes =
+            i16::from_be_bytes([batch[ATTRIBUTES_OFFSET], batch[ATTRIBUTES_OFFSET + 1]]);
+        batch[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 2]
+            .copy_from_slice(&(attributes | LOG_APPEND_TIME).to_be_bytes());
+        batch[MAX_TIMESTAMP_OFFSET..MAX_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&now_ms.to_be_bytes());
+        let crc = crc32c(&batch[ATTRIBUTES_OFFSET..]);
+        batch[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    let appended = partitions
+        .append(file, batch.freeze())
+        .wait()
+        .context("append record batch")?;
+
+    Ok(AppendInfo {
+        base_offset: appended.base_offset,
+        log_append_time_ms: log_append_time.unwrap_or(-1),
+        log_start_offset: appended.log_start_offset,
+    })
+}
+
+pub struct ProduceHandler;
+
+impl ProduceHandler {
+    fn produce(
+        &self,
+        ctx: &RequestContext,
+        record_batches: &RecordBatches,
+        topic: &str,
+        partition: &PartitionProduceData,
+    ) -> Result<AppendInfo, ProduceError> {
+        // authorized before the topic is looked up, so that the principal cannot tell which topics exist
+        if !ctx.broker.authorizer.authorize(
+            &ctx.principal,
+            Operation::Write,
+            Resource::Topic(topic),
+        ) {
+            return Err(ProduceError::new(ErrorCode::TopicAuthorizationFailed));
+        }
+        let partition_record = record_batches
+            .topic_id(topic)
+            .zip(u32::try_from(partition.index).ok())
+            .and_then(|(topic_id, partition_id)| record_batches.partition(topic_id, partition_id))
+            .ok_or(ProduceError::new(ErrorCode::UnknownTopicOrPartition))?;
+        // in multi-broker mode, only the leader appends to the partition
+        if ctx
+            .broker
+            .config
+            .node_id
+            .is_some_and(|node_id| partition_record.leader_id != node_id)
+        {
+            return Err(ProduceError::new(ErrorCode::NotLeaderOrFollower));
+        }
+
+        let log_config = LogConfig::new(&ctx.broker.config, |name| {
+            record_batches.topic_config(topic, name)
+        });
+        let now_ms = now_ms();
+        let decompressed = validate_batch(&partition.records, &log_config, now_ms)?;
+        let batch = recompress(
+            &partition.records,
+            decompressed,
+            log_config.compression_type,
+        )?;
+
+        let log_append_time =
+            (log_config.timestamp_type == TimestampType::LogAppendTime).then_some(now_ms);
+        let file = ctx
+            .broker
+            .partition_log_file(topic, partition_record.partition_id);
+        append(
+            &ctx.broker.partitions,
+            &file,
+            &batch,
+            partition_record.leader_epoch as i32,
+            log_append_time,
+        )
+        .map_err(|err| {
+            eprintln!("Error: produce to {}: {err:#}", file.display());
+            ProduceError::new(ErrorCode::KafkaStorageError)
+        })
+    }
+}
+
+impl Handler for ProduceHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::Produce
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        // versions before 3 carry message sets of magic 0 and 1
+        3..=ProduceRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(self.api_key(), ctx, body, |header, src| {
+            ProduceRequestData::deserialize(src, header.request_api_version)
+        })?;
+
+        let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
+
+        let responses = req
+            .topic_data
+            .iter()
+            .map(|topic| TopicProduceResponse {
+                name: topic.name.clone(),
+                partition_responses: topic
+                    .partition_data
+                    .iter()
+                    .map(|partition| {
+                        let result = if matches!(req.acks, -1..=1) {
+                            self.produce(ctx, &record_batches, &topic.name, partition)
+                        } else {
+                            Err(ProduceError::new(ErrorCode::InvalidRequiredAcks))
+                        };
+                        partition_response(partition.index, result)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // the producer does not wait for any acknowledgment
+        if req.acks == 0 {
+            return Ok(Bytes::new());
+        }
+
+        let resp = ProduceResponseData {
+            responses,
+            throttle_time_ms: ctx.throttle_time_ms,
+        };
+        let version = ctx.header.request_api_version;
+        let header = ResponseHeader::new(self.api_key(), version, ctx.header.correlation_id);
+        Ok(response::message(header, resp.serialize(version)))
+    }
+}
+
+fn partition_response(
+    index: i32,
+    result: Result<AppendInfo, ProduceError>,
+) -> PartitionProduceResponse {
+    match result {
+        Ok(info) => PartitionProduceResponse {
+            index,
+            error_code: ErrorCode::None.into(),
+            base_offset: info.base_offset,
+            log_append_time_ms: info.log_append_time_ms,
+            log_start_offset: info.log_start_offset,
+            record_errors: Vec::new(),
+            error_message: None,
+        },
+        Err(err) => PartitionProduceResponse {
+            index,
+            error_code: err.error_code.into(),
+            base_offset: -1,
+            log_append_time_ms: -1,
+            log_start_offset: -1,
+            record_errors: err
+                .record_errors
+                .into_iter()
+                .map(|(batch_index, message)| BatchIndexAndErrorMessage {
+                    batch_index,
+                    batch_index_error_message: Some(message),
+                })
+                .collect(),
+            error_message: err.message,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, BytesMut};
+
+    use std::{path::Path, sync::Arc};
+
+    use super::{
+        append, recompress, validate_batch, LogConfig, Partitions, ProduceHandler,
+        ATTRIBUTES_OFFSET, BATCH_HEADER_SIZE, CRC_OFFSET,
+    };
+    use crate::{
+        config::{CompressionType, Config, TimestampType},
+        logic::{BrokerContext, RequestContext},
+        protocol::{
+            crc32c::crc32c,
+            generated::produce_request::PartitionProduceData,
+            gzip,
+            record_batch::{LogOffsets, RecordBatch, RecordValue, TopicValue},
+            types::{Serialize, VarLong},
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    const NOW: i64 = 1_000_000;
+
+    fn log_config(max_message_bytes: usize) -> LogConfig {
+        LogConfig {
+            max_message_bytes,
+            timestamp_type: TimestampType::CreateTime,
+            timestamp_difference_max_ms: None,
+            compression_type: None,
+        }
+    }
+
+    /// Uncompressed batch with records of the offset deltas, created at the base timestamp 1000,
+    /// 10 ms apart, and a valid CRC
+    fn batch(offset_deltas: &[i64]) -> BytesMut {
+        let deltas: Vec<_> = offset_deltas
+            .iter()
+            .map(|&delta| (delta * 10, delta))
+            .collect();
+        batch_of(&deltas)
+    }
+
+    /// Uncompressed batch with records of the timestamp and offset deltas, at the base timestamp 1000
+    fn batch_of(deltas: &[(i64, i64)]) -> BytesMut {
+        let mut b = BytesMut::new();
+        b.put_i64(0);
+        b.put_i32(0); // batch length
+        b.put_i32(-1);
+        b.put_i8(2);
+        b.put_u32(0); // crc
+        b.put_i16(0);
+        b.put_i32(deltas.len() as i32 - 1);
+        b.put_i64(1000);
+        b.put_i64(1000);
+        b.put_i64(-1);
+        b.put_i16(-1);
+        b.put_i32(-1);
+        b.put_i32(deltas.len() as i32);
+        for &(timestamp_delta, offset_delta) in deltas {
+            let mut record = BytesMut::new();
+            record.put_i8(0);
+            VarLong::serialize_into(timestamp_delta, &mut record);
+            VarLong::serialize_into(offset_delta, &mut record);
+            VarLong::serialize_into(-1, &mut record); // null key
+            VarLong::serialize_into(5, &mut record);
+            record.put_slice(b"hello");
+            VarLong::serialize_into(0, &mut record);
+            VarLong::serialize_into(record.len() as i64, &mut b);
+            b.put(record);
+        }
+        let batch_length = b.len() as i32 - 12;
+        b[8..12].copy_from_slice(&batch_length.to_be_bytes());
+        fix_crc(&mut b);
+        b
+    }
+
+    fn fix_crc(b: &mut BytesMut) {
+        let crc = crc32c(&b[ATTRIBUTES_OFFSET..]);
+        b[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    /// The batch with the records compressed with gzip
+    fn gzip_batch(offset_deltas: &[i64]) -> BytesMut {
+        let uncompressed = batch(offset_deltas);
+        let mut b = BytesMut::from(&uncompressed[..BATCH_HEADER_SIZE]);
+        b.extend_from_slice(&gzip::compress(&uncompressed[BATCH_HEADER_SIZE..]));
+        let batch_length = b.len() as i32 - 12;
+        b[8..12].copy_from_slice(&batch_length.to_be_bytes());
+        b[ATTRIBUTES_OFFSET + 1] |= 0x01;
+        fix_crc(&mut b);
+        b
+    }
+
+    #[test]
+    fn validates_produced_batch() {
+        assert_eq!(
+            validate_batch(&batch(&[0, 1]), &log_config(1000), NOW),
+            Ok(None)
+        );
+
+        let err = validate_batch(&batch(&[0, 1]), &log_config(50), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::MessageTooLarge);
+
+        let mut corrupt = batch(&[0, 1]);
+        let last = corrupt.len() - 2;
+        corrupt[last] ^= 0xff;
+        let err = validate_batch(&corrupt, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::CorruptMessage);
+        assert!(err.message.unwrap().contains("crc"));
+
+        let mut two_batches = batch(&[0]);
+        two_batches.extend_from_slice(&batch(&[0]));
+        let err = validate_batch(&two_batches, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidRecord);
+    }
+
+    #[test]
+    fn reports_invalid_records_by_index() {
+        let err = validate_batch(&batch(&[0, 5, 2]), &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidRecord);
+        assert_eq!(err.record_errors.len(), 1);
+        assert_eq!(err.record_errors[0].0, 1);
+        assert!(err.message.unwrap().contains("1 record errors"));
+
+        // a record longer than the batch
+        let mut truncated = batch(&[0, 1]);
+        truncated[BATCH_HEADER_SIZE] = VarLong::serialize(60)[0];
+        fix_crc(&mut truncated);
+        let err = validate_batch(&truncated, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.record_errors[0].0, 0);
+        assert!(err.record_errors[0].1.starts_with("Record is corrupt"));
+    }
+
+    #[test]
+    fn validates_create_time_timestamps() {
+        let mut config = log_config(1000);
+        config.timestamp_difference_max_ms = Some(NOW - 1010);
+        // the records are at 1000, 1010 and 1020
+        let err = validate_batch(&batch(&[0, 1, 2]), &config, NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidTimestamp);
+        let indexes: Vec<_> = err.record_errors.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indexes, [0]);
+
+        // the broker sets the timestamps
+        config.timestamp_type = TimestampType::LogAppendTime;
+        assert_eq!(validate_batch(&batch(&[0, 1, 2]), &config, NOW), Ok(None));
+
+        // producers must not set LogAppendTime
+        let mut log_append_time = batch(&[0]);
+        log_append_time[ATTRIBUTES_OFFSET + 1] |= 0x08;
+        fix_crc(&mut log_append_time);
+        let err = validate_batch(&log_append_time, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidTimestamp);
+    }
+
+    #[test]
+    fn rejects_timestamps_that_overflow() {
+        let err = validate_batch(&batch_of(&[(0, 0), (i64::MAX, 1)]), &log_config(1000), NOW)
+            .unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidTimestamp);
+        let indexes: Vec<_> = err.record_errors.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indexes, [1]);
+
+        // the range of the message is clamped to the timestamps there are
+        let mut config = log_config(1000);
+        config.timestamp_difference_max_ms = Some(i64::MAX - 1);
+        let err = validate_batch(&batch_of(&[(i64::MIN, 0)]), &config, NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidTimestamp);
+        assert!(err.record_errors[0].1.ends_with(&format!(
+            "[{}, {}]",
+            NOW - (i64::MAX - 1),
+            i64::MAX
+        )));
+    }
+
+    #[test]
+    fn validates_the_decompressed_records_of_gzip_batches() {
+        let uncompressed = batch(&[0, 1]);
+        assert_eq!(
+            validate_batch(&gzip_batch(&[0, 1]), &log_config(1000), NOW),
+            Ok(Some(uncompressed[BATCH_HEADER_SIZE..].to_vec()))
+        );
+
+        // the header counts 3 records
+        let mut missing_record = gzip_batch(&[0, 1]);
+        missing_record[57..61].copy_from_slice(&3i32.to_be_bytes());
+        missing_record[26] = 2; // last offset delta
+        fix_crc(&mut missing_record);
+        let err = validate_batch(&missing_record, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::InvalidRecord);
+        assert_eq!(err.record_errors[0].0, 2);
+
+        // the records are not gzip, with a valid batch CRC
+        let mut not_gzip = batch(&[0, 1]);
+        not_gzip[ATTRIBUTES_OFFSET + 1] |= 0x01;
+        fix_crc(&mut not_gzip);
+        let err = validate_batch(&not_gzip, &log_config(1000), NOW).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::CorruptMessage);
+
+        // snappy batches are checked only by their header
+        let mut snappy = batch(&[0, 1]);
+        snappy[ATTRIBUTES_OFFSET + 1] |= 0x02;
+        fix_crc(&mut snappy);
+        assert_eq!(validate_batch(&snappy, &log_config(1000), NOW), Ok(None));
+
+        // ids 5 to 7 are no codec
+        for codec in 5..=7 {
+            let mut unknown = batch(&[0, 1]);
+            unknown[ATTRIBUTES_OFFSET + 1] |= codec;
+            fix_crc(&mut unknown);
+            let err = validate_batch(&unknown, &log_config(1000), NOW).unwrap_err();
+            assert_eq!(err.error_code, ErrorCode::CorruptMessage);
+        }
+    }
+
+    #[test]
+    fn recompresses_to_the_compression_type_of_the_topic() {
+        let uncompressed = batch(&[0, 1]);
+        let gzipped = gzip_batch(&[0, 1]);
+        let decompressed = || Some(uncompressed[BATCH_HEADER_SIZE..].to_vec());
+
+        // as received
+        for compression_type in [None, Some(CompressionType::None)] {
+            assert_eq!(
+                recompress(&uncompressed, None, compression_type).unwrap(),
+                &uncompressed[..]
+            );
+        }
+        assert_eq!(
+            recompress(&gzipped, decompressed(), Some(CompressionType::Gzip)).unwrap(),
+            &gzipped[..]
+        );
+
+        // recompressed, with the batch length and CRC of the new records
+        assert_eq!(
+            recompress(&gzipped, decompressed(), Some(CompressionType::None)).unwrap(),
+            &uncompressed[..]
+        );
+        assert_eq!(
+            recompress(&uncompressed, None, Some(CompressionType::Gzip)).unwrap(),
+            &gzipped[..]
+        );
+
+        let mut snappy = batch(&[0, 1]);
+        snappy[ATTRIBUTES_OFFSET + 1] |= 0x02;
+        fix_crc(&mut snappy);
+        let err = recompress(&snappy, None, Some(CompressionType::Gzip)).unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::UnsupportedCompressionType);
+        assert_eq!(recompress(&snappy, None, None).unwrap(), &snappy[..]);
+    }
+
+    #[test]
+    fn topic_configs_override_broker_config() {
+        let config = Config::default();
+        let log_config = LogConfig::new(&config, |name| match name {
+            "max.message.bytes" => Some("2048"),
+            "message.timestamp.type" => Some("LogAppendTime"),
+            "message.timestamp.difference.max.ms" => Some("invalid"),
+            "compression.type" => Some("gzip"),
+            _ => None,
+        });
+        assert_eq!(
+            log_config,
+            LogConfig {
+                max_message_bytes: 2048,
+                timestamp_type: TimestampType::LogAppendTime,
+                timestamp_difference_max_ms: None,
+                compression_type: Some(CompressionType::Gzip),
+            }
+        );
+
+        let log_config = LogConfig::new(&config, |_| None);
+        assert_eq!(log_config.max_message_bytes, config.message_max_bytes);
+        assert_eq!(log_config.compression_type, None);
+        let err = validate_batch(
+            &batch(&[0, 1]),
+            &LogConfig {
+                max_message_bytes: 80,
+                ..log_config
+            },
+            NOW,
+        )
+        .unwrap_err();
+        assert_eq!(err.error_code, ErrorCode::MessageTooLarge);
+    }
+
+    #[test]
+    fn appended_batches_get_the_next_offsets() {
+        let storage = Arc::new(MemoryStorage::default());
+        let partitions = Partitions::new(storage.clone());
+        let file = Path::new("/logs/foo-0/00000000000000000000.log");
+
+        let first = append(&partitions, file, &batch(&[0, 1]), 3, None).unwrap();
+        assert_eq!((first.base_offset, first.log_start_offset), (0, 0));
+        assert_eq!(first.log_append_time_ms, -1);
+
+        let second = append(&partitions, file, &batch(&[0]), 3, Some(NOW)).unwrap();
+        assert_eq!((second.base_offset, second.log_start_offset), (2, 0));
+        assert_eq!(second.log_append_time_ms, NOW);
+
+        let log = LogOffsets::from_file(&*storage, file).unwrap();
+        assert_eq!((log.log_start_offset, log.log_end_offset), (0, 3));
+    }
+
+    #[test]
+    fn concurrent_appends_to_a_partition_get_the_next_offsets() {
+        let storage = Arc::new(MemoryStorage::default());
+        let partitions = Partitions::new(storage.clone());
+        let files = [
+            Path::new("/logs/foo-0/00000000000000000000.log"),
+            Path::new("/logs/foo-1/00000000000000000000.log"),
+        ];
+
+        // 8 threads append 50 batches of 2 records each, 7 of them to the first partition
+        let appended: Vec<_> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..8)
+                .map(|i| {
+                    let (partitions, file) = (&partitions, files[(i == 7) as usize]);
+                    scope.spawn(move || {
+                        (0..50)
+                            .map(|_| {
+                                let info =
+                                    append(partitions, file, &batch(&[0, 1]), 0, None).unwrap();
+                                (file, info.base_offset)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .flat_map(|thread| thread.join().unwrap())
+                .collect()
+        });
+
+        for (file, batches) in [(files[0], 7 * 50), (files[1], 50)] {
+            let mut offsets: Vec<_> = appended
+                .iter()
+                .filter(|(f, _)| *f == file)
+                .map(|&(_, offset)| offset)
+                .collect();
+            offsets.sort();
+            assert_eq!(offsets, (0..batches).map(|i| i * 2).collect::<Vec<_>>());
+            let log = LogOffsets::from_file(&*storage, file).unwrap();
+            assert_eq!(log.log_end_offset, batches * 2);
+        }
+    }
+
+    #[test]
+    fn unauthorized_topics_are_not_told_apart_from_missing_ones() {
+        let topic = RecordValue::Topic(TopicValue {
+            topic_name: "foo".to_string(),
+            topic_id: "00000000-0000-0000-0000-000000000001".to_string(),
+        });
+        let config = Config {
+            acls: vec!["User:alice,Write,Topic,*".parse().unwrap()],
+            ..Config::default()
+        };
+        let metadata = RecordBatch::of_values(0, vec![topic]).serialize();
+        let storage = MemoryStorage::with_files([(config.metadata_log_file(), metadata)]);
+        let broker = BrokerContext::new(Arc::new(config), Arc::new(storage));
+        let ctx = RequestContext::for_request(Arc::new(broker), ApiKey::Produce, 9);
+        let record_batches = ctx.broker.metadata().unwrap();
+
+        let partition = PartitionProduceData {
+            index: 0,
+            records: batch(&[0]).to_vec(),
+        };
+        for topic in ["foo", "missing"] {
+            let error = ProduceHandler
+                .produce(&ctx, &record_batches, topic, &partition)
+                .unwrap_err();
+            assert_eq!(error.error_code, ErrorCode::TopicAuthorizationFailed);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Length of one sample window of the rates, as Kafka's default `quota.window.size.seconds`
+const QUOTA_WINDOW: Duration = Duration::from_secs(1);
+/// Number of sample windows the rates are measured over, as Kafka's default `quota.window.num`
+const QUOTA_WINDOW_NUM: u32 = 11;
+/// Throttle time is at most the time covered by all the sample windows
+const MAX_THROTTLE: Duration = QUOTA_WINDOW.saturating_mul(QUOTA_WINDOW_NUM);
+
+/// Byte-rate and request-rate quotas applied to every client, identified by the user and the client id.
+///
+/// Usage is counted in sliding windows made of `QUOTA_WINDOW_NUM` samples, the oldest sample is dropped
+/// when a new one starts. The rate is the usage of the samples over the time they cover, but at least
+/// over all but one of the windows, so that a burst of a new client is not measured over a few milliseconds.
+/// When a client exceeded a quota, its requests are throttled for the time the rate needs to fall under
+/// the quota, the same way Kafka's `ClientQuotaManager` computes the delay.
+// https://kafka.apache.org/documentation/#design_quotas
+pub struct ClientQuotas {
+    /// Bytes of requests and responses per second
+    byte_rate: Option<u64>,
+    /// Requests per second
+    request_rate: Option<u64>,
+    clients: Mutex<HashMap<String, Usage>>,
+}
+
+/// Usage of a client, the samples from the oldest to the current one
+#[derive(Default)]
+struct Usage {
+    samples: VecDeque<Sample>,
+}
+
+struct Sample {
+    start: Instant,
+    bytes: u64,
+    requests: u64,
+}
+
+impl Usage {
+    /// Drops the samples that ended before the sliding window
+    fn purge(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| now.duration_since(s.start) >= MAX_THROTTLE)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Time the rates are measured over, Kafka's `SampledStat` window size
+    fn window_size(&self, now: Instant) -> Duration {
+        let elapsed = self
+            .samples
+            .front()
+            .map_or(Duration::ZERO, |s| now.duration_since(s.start));
+        elapsed.max(QUOTA_WINDOW * (QUOTA_WINDOW_NUM - 1))
+    }
+}
+
+impl ClientQuotas {
+    pub fn new(byte_rate: Option<u64>, request_rate: Option<u64>) -> Self {
+        Self {
+            byte_rate,
+            request_rate,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.byte_rate.is_some() || self.request_rate.is_some()
+    }
+
+    /// Time the next request of the client has to be delayed by
+    pub fn throttle_time(&self, client_id: &str, now: Instant) -> Duration {
+        if !self.is_enabled() {
+            return Duration::ZERO;
+        }
+
+        let mut clients = self.clients.lock().expect("quota lock is not poisoned");
+        let Some(usage) = clients.get_mut(client_id) else {
+            return Duration::ZERO;
+        };
+        usage.purge(now);
+
+        // (rate - quota) / quota * window size
+        let window_size = usage.window_size(now);
+        let over = |observed: u64, quota: Option<u64>| match quota {
+            Some(quota) => {
+                let rate = observed as f64 / window_size.as_secs_f64();
+                let quota = quota.max(1) as f64;
+                if rate > quota {
+                    window_size.mul_f64((rate - quota) / quota)
+                } else {
+                    Duration::ZERO
+                }
+            }
+            None => Duration::ZERO,
+        };
+        let bytes = usage.samples.iter().map(|s| s.bytes).sum();
+        let requests = usage.samples.iter().map(|s| s.requests).sum();
+        over(bytes, self.byte_rate)
+            .max(over(requests, self.request_rate))
+            .min(MAX_THROTTLE)
+    }
+
+    /// Counts a processed request and the bytes of the request and the response
+    pub fn record(&self, client_id: &str, bytes: usize, now: Instant) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut clients = self.clients.lock().expect("quota lock is not poisoned");
+        let usage = clients.entry(client_id.to_string()).or_default();
+        usage.purge(now);
+        let current = match usage.samples.back_mut() {
+            Some(sample) if now.duration_since(sample.start) < QUOTA_WINDOW => sample,
+            _ => {
+                usage.samples.push_back(Sample {
+                    start: now,
+                    bytes: 0,
+                    requests: 0,
+                });
+                usage.samples.back_mut().expect("sample was just added")
+            }
+        };
+        current.bytes += bytes as u64;
+        current.requests += 1;
+    }
+
+    /// Forgets the usage of clients whose samples all ended, so that clients that went away take no memory
+    pub fn expire_windows(&self, now: Instant) {
+        let mut clients = self.clients.lock().expect("quota lock is not poisoned");
+        clients.retain(|_, usage| {
+            usage.purge(now);
+            !usage.samples.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ClientQuotas;
+
+    #[test]
+    fn throttles_client_over_quota() {
+        let quotas = ClientQuotas::new(Some(1000), Some(2));
+        let start = Instant::now();
+
+        // rates are measured over at least 10 windows, 20 requests and 10000 bytes are within the quotas
+        for _ in 0..20 {
+            quotas.record("a", 500, start);
+        }
+        assert_eq!(quotas.throttle_time("a", start), Duration::ZERO);
+
+        // 30 requests in 10 s with quota 2 per second
+        for _ in 0..10 {
+            quotas.record("a", 0, start);
+        }
+        assert_eq!(quotas.throttle_time("a", start), Duration::from_secs(5));
+        // 22000 bytes in 10 s with quota 1000 per second would be 12 s, more than the whole window
+        quotas.record("a", 12000, start);
+        assert_eq!(quotas.throttle_time("a", start), Duration::from_secs(11));
+
+        // other clients are not throttled
+        assert_eq!(quotas.throttle_time("b", start), Duration::ZERO);
+
+        // the usage slides out of the window, sample by sample
+        let later = start + Duration::from_secs(5);
+        quotas.record("a", 0, later);
+        assert!(quotas.throttle_time("a", later) > Duration::ZERO);
+        let after_window = start + Duration::from_secs(11);
+        assert_eq!(quotas.throttle_time("a", after_window), Duration::ZERO);
+        quotas.expire_windows(after_window + Duration::from_secs(5));
+        assert!(quotas.clients.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn disabled_without_quotas() {
+        let quotas = ClientQuotas::new(None, None);
+        let now = Instant::now();
+        for _ in 0..10 {
+            quotas.record("a", 1 << 20, now);
+        }
+        assert_eq!(quotas.throttle_time("a", now), Duration::ZERO);
+    }
+}
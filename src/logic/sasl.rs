@@ -0,0 +1,506 @@
+use std::{
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::{
+    config::Config,
+    protocol::{
+        generated::{
+            sasl_authenticate_request::SaslAuthenticateRequestData,
+            sasl_authenticate_response::SaslAuthenticateResponseData,
+            sasl_handshake_request::SaslHandshakeRequestData,
+            sasl_handshake_response::SaslHandshakeResponseData,
+        },
+        request::RequestHeader,
+        response::{self, ResponseHeader},
+        ApiKey, ErrorCode,
+    },
+};
+
+use super::{
+    authorizer::KafkaPrincipal, delegation_tokens::TokenStore, deserialize, handler::Handler,
+    BrokerContext, RequestContext,
+};
+
+/// The only SASL mechanism of the broker, the users are configured with `--sasl-plain-user`
+const PLAIN_MECHANISM: &str = "PLAIN";
+
+/// Authorization id of the PLAIN messages that log in with a delegation token, with the token id as
+/// the username and the hex HMAC of the token as the password. Kafka logs in with tokens over SCRAM
+/// with the `tokenauth` extension, which the broker does not support.
+const TOKEN_AUTH: &str = "tokenauth";
+
+/// Request that may not be processed in the SASL state of the connection, the connection is closed
+#[derive(Debug, Error)]
+pub enum AuthenticationError {
+    #[error("unexpected request of API key {0} before SASL authentication")]
+    NotAuthenticated(i16),
+    #[error("unexpected request of API key {0} during SASL reauthentication")]
+    Reauthenticating(i16),
+    #[error("SASL session expired, the client did not reauthenticate")]
+    SessionExpired,
+}
+
+/// How the client of a connection authenticated, Kafka decides it by the security protocol of the listener
+/// and the `tokenAuthenticated` flag of the principal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Authentication {
+    /// The client did not authenticate, e.g. on a PLAINTEXT listener
+    None,
+    /// SASL with the password of a user
+    Password,
+    /// SASL with the HMAC of a delegation token, see [`TOKEN_AUTH`]
+    DelegationToken,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Waiting for the handshake that starts the first authentication
+    Handshake,
+    /// The mechanism was chosen, waiting for the authentication bytes
+    Authenticate,
+    Authenticated,
+}
+
+/// Response to a SASL request
+pub struct SaslResponse {
+    pub response: Bytes,
+    /// The authentication failed, the connection is closed after the response is written
+    pub failed: bool,
+}
+
+/// SASL state of a connection, as Kafka's `SaslServerAuthenticator`.
+///
+/// Before the client authenticated only ApiVersions and the SASL requests are accepted. With
+/// `connections.max.reauth.ms` the session expires, the client has to reauthenticate (KIP-368) with a new
+/// handshake and the same principal before it, otherwise the connection is closed at its next request.
+/// Other requests are not accepted while the client reauthenticates.
+pub struct Authenticator {
+    state: State,
+    /// Principal of the last authentication, `None` before the first one
+    principal: Option<KafkaPrincipal>,
+    authentication: Authentication,
+    session_expiry: Option<Instant>,
+}
+
+/// Client that authenticated
+#[derive(Debug, PartialEq)]
+struct Login {
+    principal: KafkaPrincipal,
+    authentication: Authentication,
+    /// Expiry of the token the client logged in with, in milliseconds since the Unix epoch
+    expiry_timestamp_ms: Option<i64>,
+}
+
+impl Authenticator {
+    /// Authenticator of a new connection, `None` if the listener does not require authentication
+    pub fn new(config: &Config) -> Option<Self> {
+        if config.sasl_plain_users.is_empty() {
+            return None;
+        }
+        Some(Self {
+            state: State::Handshake,
+            principal: None,
+            authentication: Authentication::None,
+            session_expiry: None,
+        })
+    }
+
+    /// Authenticated principal the requests are processed for
+    pub fn principal(&self) -> KafkaPrincipal {
+        self.principal
+            .clone()
+            .unwrap_or_else(KafkaPrincipal::anonymous)
+    }
+
+    /// How the requests of the principal were authenticated
+    pub fn authentication(&self) -> Authentication {
+        self.authentication
+    }
+
+    /// The request is a part of the SASL exchange, it is processed by the authenticator
+    pub fn is_sasl_request(api_key: i16) -> bool {
+        matches!(
+            ApiKey::try_from(api_key),
+            Ok(ApiKey::SaslHandshake | ApiKey::SaslAuthenticate)
+        )
+    }
+
+    /// Checks whether a request of the API may be processed in the current state
+    pub fn check(&self, api_key: i16, now: Instant) -> Result<(), AuthenticationError> {
+        match (self.state, ApiKey::try_from(api_key).ok()) {
+            (State::Handshake, Some(ApiKey::ApiVersions | ApiKey::SaslHandshake)) => Ok(()),
+            (State::Handshake, _) => Err(AuthenticationError::NotAuthenticated(api_key)),
+            (State::Authenticate, Some(ApiKey::SaslAuthenticate)) => Ok(()),
+            (State::Authenticate, _) if self.principal.is_none() => {
+                Err(AuthenticationError::NotAuthenticated(api_key))
+            }
+            (State::Authenticate, _) => Err(AuthenticationError::Reauthenticating(api_key)),
+            (State::Authenticated, Some(ApiKey::SaslHandshake)) => Ok(()),
+            (State::Authenticated, _) if self.session_expiry.is_some_and(|e| now >= e) => {
+                Err(AuthenticationError::SessionExpired)
+            }
+            (State::Authenticated, _) => Ok(()),
+        }
+    }
+
+    /// Processes SaslHandshake and SaslAuthenticate requests
+    pub fn process(
+        &mut self,
+        broker: Arc<BrokerContext>,
+        header: RequestHeader,
+        body: Bytes,
+        now: Instant,
+    ) -> Result<SaslResponse> {
+        super::record_client(&header);
+        let ctx = RequestContext {
+            broker,
+            header,
+            principal: self.principal(),
+            authentication: self.authentication(),
+            throttle_time_ms: 0,
+        };
+        let version = ctx.header.request_api_version;
+        let handler: &dyn Handler = match ApiKey::try_from(ctx.header.request_api_key) {
+            Ok(ApiKey::SaslHandshake) => &SaslHandshakeHandler,
+            _ => &SaslAuthenticateHandler,
+        };
+        if !handler.version_range().contains(&version) {
+            return Ok(failure(&ctx, handler, ErrorCode::UnsupportedVersion));
+        }
+
+        if handler.api_key() == ApiKey::SaslHandshake {
+            let req = deserialize(handler.api_key(), &ctx, body, |header, src| {
+                SaslHandshakeRequestData::deserialize(src, header.request_api_version)
+            })?;
+            // a handshake starts the first authentication or a reauthentication
+            if self.state == State::Authenticate {
+                return Ok(failure(&ctx, handler, ErrorCode::IllegalSaslState));
+            }
+            if req.mechanism != PLAIN_MECHANISM {
+                eprintln!("Error: unsupported SASL mechanism `{}`", req.mechanism);
+                return Ok(failure(&ctx, handler, ErrorCode::UnsupportedSaslMechanism));
+            }
+            self.state = State::Authenticate;
+            return Ok(SaslResponse {
+                response: handshake_response(&ctx, ErrorCode::None),
+                failed: false,
+            });
+        }
+
+        let req = deserialize(handler.api_key(), &ctx, body, |header, src| {
+            SaslAuthenticateRequestData::deserialize(src, header.request_api_version)
+        })?;
+        if self.state != State::Authenticate {
+            return Ok(failure(&ctx, handler, ErrorCode::IllegalSaslState));
+        }
+        let now_ms = super::now_ms();
+        let login = match authenticate_plain(
+            &ctx.broker.config.sasl_plain_users,
+            &ctx.broker.tokens,
+            &req.auth_bytes,
+            now_ms,
+        ) {
+            Ok(login) => login,
+            Err(message) => {
+                return Ok(authenticate_failure(&ctx, message));
+            }
+        };
+        let principal = login.principal;
+        if let Some(previous) = self.principal.as_ref().filter(|p| **p != principal) {
+            let message = format!(
+                "Cannot change principals during re-authentication from {previous} to {principal}"
+            );
+            return Ok(authenticate_failure(&ctx, message));
+        }
+
+        // the session of a token login ends when the token expires, as in Kafka
+        let token_lifetime = login
+            .expiry_timestamp_ms
+            .map(|expiry_ms| Duration::from_millis(expiry_ms.saturating_sub(now_ms).max(0) as u64));
+        let lifetime = match (ctx.broker.config.connections_max_reauth, token_lifetime) {
+            (Some(max_reauth), Some(token_lifetime)) => Some(max_reauth.min(token_lifetime)),
+            (max_reauth, token_lifetime) => max_reauth.or(token_lifetime),
+        };
+        self.state = State::Authenticated;
+        self.principal = Some(principal);
+        self.authentication = login.authentication;
+        self.session_expiry = lifetime.map(|lifetime| now + lifetime);
+        let resp = SaslAuthenticateResponseData {
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            auth_bytes: Vec::new(),
+            session_lifetime_ms: lifetime.as_ref().map_or(0, Duration::as_millis) as i64,
+        };
+        Ok(SaslResponse {
+            response: message(&ctx, resp.serialize(version)),
+            failed: false,
+        })
+    }
+}
+
+/// Checks the `authzid NUL authcid NUL passwd` message of the PLAIN mechanism (RFC 4616)
+/// against the users and their passwords, or the delegation tokens if the authorization id is [`TOKEN_AUTH`].
+/// Returns the login of the user or the token owner, or the error message.
+fn authenticate_plain(
+    users: &[(String, String)],
+    delegation_tokens: &TokenStore,
+    auth_bytes: &[u8],
+    now_ms: i64,
+) -> Result<Login, String> {
+    let invalid = || "Authentication failed: Invalid username or password".to_string();
+
+    let message = std::str::from_utf8(auth_bytes).map_err(|_| invalid())?;
+    let mut tokens = message.split('\0');
+    let (Some(authorization_id), Some(username), Some(password), None) =
+        (tokens.next(), tokens.next(), tokens.next(), tokens.next())
+    else {
+        return Err("Invalid SASL/PLAIN response: expected 3 tokens".to_string());
+    };
+    if authorization_id == TOKEN_AUTH {
+        let hmac = hex::decode(password).map_err(|_| invalid())?;
+        let token = delegation_tokens
+            .authenticate(username, &hmac, now_ms)
+            .ok_or_else(invalid)?;
+        return Ok(Login {
+            principal: token.owner,
+            authentication: Authentication::DelegationToken,
+            expiry_timestamp_ms: Some(token.expiry_timestamp_ms),
+        });
+    }
+    if !authorization_id.is_empty() && authorization_id != username {
+        return Err("Authentication failed: Client requested an authorization id that is different from username".to_string());
+    }
+
+    let known = users.iter().any(|(user, secret)| {
+        user == username && constant_time_eq(secret.as_bytes(), password.as_bytes())
+    });
+    if !known {
+        return Err(invalid());
+    }
+    Ok(Login {
+        principal: KafkaPrincipal::from_sasl(username),
+        authentication: Authentication::Password,
+        expiry_timestamp_ms: None,
+    })
+}
+
+/// Compares the passwords in time that does not depend on the position of the first difference
+pub(super) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Error response of the API, after which the connection is closed
+fn failure(ctx: &RequestContext, handler: &dyn Handler, error_code: ErrorCode) -> SaslResponse {
+    SaslResponse {
+        response: handler.error_response(ctx, error_code).unwrap_or_default(),
+        failed: true,
+    }
+}
+
+fn authenticate_failure(ctx: &RequestContext, error_message: String) -> SaslResponse {
+    eprintln!("Error: SASL authentication failed: {error_message}");
+    let resp = SaslAuthenticateResponseData {
+        error_code: ErrorCode::SaslAuthenticationFailed.into(),
+        error_message: Some(error_message),
+        auth_bytes: Vec::new(),
+        session_lifetime_ms: 0,
+    };
+    SaslResponse {
+        response: message(ctx, resp.serialize(ctx.header.request_api_version)),
+        failed: true,
+    }
+}
+
+fn handshake_response(ctx: &RequestContext, error_code: ErrorCode) -> Bytes {
+    // a listener without authentication has no mechanisms
+    let mechanisms = if ctx.broker.config.sasl_plain_users.is_empty() {
+        Vec::new()
+    } else {
+        vec![PLAIN_MECHANISM.to_string()]
+    };
+    let resp = SaslHandshakeResponseData {
+        error_code: error_code.into(),
+        mechanisms,
+    };
+    message(ctx, resp.serialize(ctx.header.request_api_version))
+}
+
+fn message(ctx: &RequestContext, body: Bytes) -> Bytes {
+    let version = ctx.header.request_api_version;
+    let api_key = ApiKey::try_from(ctx.header.request_api_key).unwrap_or(ApiKey::SaslAuthenticate);
+    let header = ResponseHeader::new(api_key, version, ctx.header.correlation_id);
+    response::message(header, body)
+}
+
+/// SaslHandshake of connections that need no authentication, the SASL exchange of the others
+/// is processed by their `Authenticator`
+pub struct SaslHandshakeHandler;
+
+impl Handler for SaslHandshakeHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::SaslHandshake
+    }
+
+    /// Version 0 sends the authentication bytes without Kafka request headers, it is not supported
+    fn version_range(&self) -> RangeInclusive<i16> {
+        1..=SaslHandshakeRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, _body: Bytes) -> Result<Bytes> {
+        Ok(handshake_response(ctx, ErrorCode::UnsupportedSaslMechanism))
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        Some(handshake_response(ctx, error_code))
+    }
+}
+
+/// SaslAuthenticate of connections that need no authentication, the SASL exchange of the others
+/// is processed by their `Authenticator`
+pub struct SaslAuthenticateHandler;
+
+impl Handler for SaslAuthenticateHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::SaslAuthenticate
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        SaslAuthenticateRequestData::LOWEST_SUPPORTED_VERSION
+            ..=SaslAuthenticateRequestData::HIGHEST_SUPPORTED_VERSION
+    }
+
+    fn handle(&self, ctx: &RequestContext, _body: Bytes) -> Result<Bytes> {
+        Ok(self
+            .error_response(ctx, ErrorCode::IllegalSaslState)
+            .unwrap_or_default())
+    }
+
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let resp = SaslAuthenticateResponseData {
+            error_code: error_code.into(),
+            ..Default::default()
+        };
+        Some(message(ctx, resp.serialize(ctx.header.request_api_version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{authenticate_plain, Authentication, Authenticator, Login, State, TOKEN_AUTH};
+    use crate::{
+        logic::{authorizer::KafkaPrincipal, delegation_tokens::TokenStore},
+        protocol::ApiKey,
+    };
+
+    fn tokens() -> TokenStore {
+        TokenStore::new(
+            Some("secret"),
+            Duration::from_secs(3600),
+            Duration::from_secs(60),
+        )
+    }
+
+    fn password_login(username: &str) -> Login {
+        Login {
+            principal: KafkaPrincipal::from_sasl(username),
+            authentication: Authentication::Password,
+            expiry_timestamp_ms: None,
+        }
+    }
+
+    #[test]
+    fn plain_messages() {
+        let users = [("alice".to_string(), "secret".to_string())];
+        let tokens = tokens();
+        let authenticate = |users: &[(String, String)], auth_bytes: &[u8]| {
+            authenticate_plain(users, &tokens, auth_bytes, 0)
+        };
+        assert_eq!(
+            authenticate(&users, b"\0alice\0secret"),
+            Ok(password_login("alice"))
+        );
+        assert_eq!(
+            authenticate(&users, b"alice\0alice\0secret"),
+            Ok(password_login("alice"))
+        );
+        assert!(authenticate(&users, b"\0alice\0secreT").is_err());
+        assert!(authenticate(&[], b"\0alice\0secret").is_err());
+        assert_eq!(
+            authenticate(&users, b"alice\0alice").unwrap_err(),
+            "Invalid SASL/PLAIN response: expected 3 tokens"
+        );
+        assert!(authenticate(&users, b"bob\0alice\0secret")
+            .unwrap_err()
+            .contains("different from username"));
+    }
+
+    #[test]
+    fn token_logins() {
+        let tokens = tokens();
+        let alice = KafkaPrincipal::from_sasl("alice");
+        let token = tokens.create(&alice, None, vec![], -1, 0).unwrap();
+        let message = |token_id: &str, hmac: &[u8]| {
+            format!("{TOKEN_AUTH}\0{token_id}\0{}", hex::encode(hmac)).into_bytes()
+        };
+
+        // the token owner logs in until the token expires
+        assert_eq!(
+            authenticate_plain(&[], &tokens, &message(&token.token_id, &token.hmac), 0),
+            Ok(Login {
+                principal: alice,
+                authentication: Authentication::DelegationToken,
+                expiry_timestamp_ms: Some(60_000),
+            })
+        );
+        let mut hmac = token.hmac.clone();
+        hmac[0] ^= 1;
+        assert!(authenticate_plain(&[], &tokens, &message(&token.token_id, &hmac), 0).is_err());
+        assert!(authenticate_plain(&[], &tokens, &message("unknown", &token.hmac), 0).is_err());
+        let not_hex = format!("{TOKEN_AUTH}\0{}\0secret", token.token_id).into_bytes();
+        assert!(authenticate_plain(&[], &tokens, &not_hex, 0).is_err());
+        let message = message(&token.token_id, &token.hmac);
+        assert!(authenticate_plain(&[], &tokens, &message, 60_001).is_err());
+    }
+
+    #[test]
+    fn requests_allowed_in_sasl_states() {
+        let now = Instant::now();
+        let mut authenticator = Authenticator {
+            state: State::Handshake,
+            principal: None,
+            authentication: Authentication::None,
+            session_expiry: None,
+        };
+        let check = |a: &Authenticator, api_key: ApiKey, now| a.check(api_key.into(), now).is_ok();
+
+        assert!(check(&authenticator, ApiKey::ApiVersions, now));
+        assert!(check(&authenticator, ApiKey::SaslHandshake, now));
+        assert!(!check(&authenticator, ApiKey::Fetch, now));
+
+        authenticator.state = State::Authenticate;
+        assert!(check(&authenticator, ApiKey::SaslAuthenticate, now));
+        assert!(!check(&authenticator, ApiKey::ApiVersions, now));
+
+        authenticator.state = State::Authenticated;
+        authenticator.principal = Some(KafkaPrincipal::user("alice"));
+        authenticator.session_expiry = Some(now + Duration::from_secs(60));
+        assert!(check(&authenticator, ApiKey::Fetch, now));
+
+        // an expired session only accepts the handshake of the reauthentication
+        let later = now + Duration::from_secs(60);
+        assert!(!check(&authenticator, ApiKey::Fetch, later));
+        assert!(check(&authenticator, ApiKey::SaslHandshake, later));
+
+        authenticator.state = State::Authenticate;
+        assert!(!check(&authenticator, ApiKey::Fetch, now));
+    }
+}
@@ -1,30 +1,73 @@
-use anyhow::Result;
-use bytes::{Buf, BytesMut};
+use std::ops::RangeInclusive;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
 
 use crate::protocol::{
-    record_batch::{RecordBatch, RecordValue},
     request::describe_topic_partitions::DescribeTopicPartitionsRequestV0,
     response::describe_topic_partitions::{DescribeTopicPartitionsResponseV0, Partition, Topic},
-    ErrorCode,
+    ApiKey, ErrorCode, Response,
+};
+
+use super::{
+    authorizer::{Authorizer, Operation, Resource},
+    deserialize,
+    handler::Handler,
+    RequestContext,
 };
 
 const DEFAULT_UNKNOWN_TOPIC_UUID: &str = "00000000-0000-0000-0000-000000000000";
 
-/// https://kafka.apache.org/documentation/#log
-const CLUSTER_METADATA_LOG_FILE: &str =
-    "/tmp/kraft-combined-logs/__cluster_metadata-0/00000000000000000000.log";
+pub struct DescribeTopicPartitionsHandler;
+
+impl Handler for DescribeTopicPartitionsHandler {
+    fn api_key(&self) -> ApiKey {
+        ApiKey::DescribeTopicPartitions
+    }
+
+    fn version_range(&self) -> RangeInclusive<i16> {
+        0..=0
+    }
+
+    fn handle(&self, ctx: &RequestContext, body: Bytes) -> Result<Bytes> {
+        let req = deserialize(
+            self.api_key(),
+            ctx,
+            body,
+            DescribeTopicPartitionsRequestV0::from_bytes,
+        )?;
+        let resp = process(req, ctx)?;
+
+        Ok(resp.into_bytes())
+    }
 
-pub fn process(req: DescribeTopicPartitionsRequestV0) -> Result<DescribeTopicPartitionsResponseV0> {
-    let file_bytes = std::fs::read(CLUSTER_METADATA_LOG_FILE)?;
+    /// The response has no top-level error code, the error is reported in a topic without name,
+    /// as the names of the requested topics are not known
+    fn error_response(&self, ctx: &RequestContext, error_code: ErrorCode) -> Option<Bytes> {
+        let topic = Topic {
+            error_code,
+            name: String::new(),
+            topic_id: DEFAULT_UNKNOWN_TOPIC_UUID.to_string(),
+            is_internal: false,
+            partitions: vec![],
+            topic_authorized_operations: 0,
+        };
+        let resp = DescribeTopicPartitionsResponseV0::new(
+            ctx.header.correlation_id,
+            ctx.throttle_time_ms,
+            vec![topic],
+        );
+        Some(resp.into_bytes())
+    }
+}
 
-    let mut data = BytesMut::with_capacity(file_bytes.len());
-    data.extend_from_slice(&file_bytes);
-    let mut data = data.freeze();
+pub fn process(
+    req: DescribeTopicPartitionsRequestV0,
+    ctx: &RequestContext,
+) -> Result<DescribeTopicPartitionsResponseV0> {
+    let throttle_time_ms = ctx.throttle_time_ms;
 
-    // default response UUID
-    let mut topic_id = DEFAULT_UNKNOWN_TOPIC_UUID.to_string();
-    // default error response
-    let mut topic_error_code = ErrorCode::UnknownTopicOrPartition;
+    let record_batches = ctx.broker.metadata().context("read cluster metadata")?;
 
     let topic_authorized_operations = 0x0DF;
     /*
@@ -44,80 +87,73 @@ pub fn process(req: DescribeTopicPartitionsRequestV0) -> Result<DescribeTopicPar
 
     let mut topics = Vec::new();
 
-    while data.remaining() > 0 {
-        let record_batch = RecordBatch::from_bytes(&mut data);
-
-        for topic_name in &req.topics {
-            topic_id = DEFAULT_UNKNOWN_TOPIC_UUID.to_string();
-            let mut partitions = Vec::new();
-
-            // find topic id and partition info in the records
-            for rec in &record_batch.records {
-                let record_type = &rec.value;
-                if let Some(id) = match record_type {
-                    RecordValue::Topic(ref topic) if topic.topic_name == *topic_name => {
-                        Some(topic.topic_id.clone())
-                    }
-                    _ => None,
-                } {
-                    topic_id = id;
-                    topic_error_code = ErrorCode::None;
-                };
-
-                match record_type {
-                    RecordValue::Partition(p) if p.topic_id == topic_id => {
-                        partitions.push(Partition::new(
+    // unauthorized topics are answered without looking them up, whether they exist or not
+    let (requested_topics, unauthorized_topics): (Vec<_>, Vec<_>) =
+        req.topics.into_iter().partition(|name| {
+            ctx.broker.authorizer.authorize(
+                &ctx.principal,
+                Operation::Describe,
+                Resource::Topic(name),
+            )
+        });
+
+    for name in requested_topics {
+        let topic = match record_batches.topic_id(&name) {
+            Some(topic_id) => {
+                let partitions = record_batches
+                    .partitions(topic_id)
+                    .iter()
+                    // the partition records with the partition changes after them, the eligible leader
+                    // replicas are not kept and the replicas are never offline as the broker has one log dir
+                    .map(|p| {
+                        Partition::new(
                             ErrorCode::None,
                             p.partition_id,
                             p.leader_id,
                             p.leader_epoch,
                             p.replicas.clone(),
                             p.in_sync_replicas.clone(),
-                            p.adding_replicas.clone(),
                             Vec::new(),
-                            p.removing_replicas.clone(),
-                        ));
-                    }
-                    _ => {}
-                }
-            }
-
-            if !partitions.is_empty() {
-                let topic = Topic {
-                    error_code: topic_error_code,
-                    name: topic_name.to_string(),
-                    topic_id: topic_id.clone(),
+                            Vec::new(),
+                            Vec::new(),
+                        )
+                    })
+                    .collect();
+                Topic {
+                    error_code: ErrorCode::None,
+                    name,
+                    topic_id: topic_id.to_string(),
                     is_internal: false,
                     partitions,
                     topic_authorized_operations,
-                };
-                topics.push(topic);
-            }
-        }
-    }
-
-    for requested_topic in req.topics {
-        let mut topic_found = false;
-        for topic in &topics {
-            if topic.name == requested_topic {
-                topic_found = true;
+                }
             }
-        }
-        if !topic_found {
-            let error_topic = Topic {
+            None => Topic {
                 error_code: ErrorCode::UnknownTopicOrPartition,
-                name: requested_topic.to_string(),
-                topic_id: topic_id.clone(),
+                name,
+                topic_id: DEFAULT_UNKNOWN_TOPIC_UUID.to_string(),
                 is_internal: false,
                 partitions: Vec::new(),
                 topic_authorized_operations,
-            };
-            topics.push(error_topic);
-        }
+            },
+        };
+        topics.push(topic);
+    }
+
+    for name in unauthorized_topics {
+        topics.push(Topic {
+            error_code: ErrorCode::TopicAuthorizationFailed,
+            name,
+            topic_id: DEFAULT_UNKNOWN_TOPIC_UUID.to_string(),
+            is_internal: false,
+            partitions: Vec::new(),
+            topic_authorized_operations: 0,
+        });
     }
 
     Ok(DescribeTopicPartitionsResponseV0::new(
         req.header.correlation_id,
+        throttle_time_ms,
         topics,
     ))
 }
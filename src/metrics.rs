@@ -0,0 +1,342 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{http, protocol::ApiKey};
+
+/// Upper bounds of the request latency histogram buckets in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// Time after which the metrics of a client id that sent no request are removed, as Kafka's client sensors
+/// expire after an hour of inactivity
+pub const CLIENT_EXPIRY: Duration = Duration::from_secs(3600);
+
+/// Broker metrics exposed in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    open_connections: AtomicI64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    throttled_requests: AtomicU64,
+    throttle_time_ms: AtomicU64,
+    apis: Mutex<BTreeMap<i16, ApiMetrics>>,
+    clients: Mutex<BTreeMap<String, ClientMetrics>>,
+}
+
+#[derive(Default)]
+struct ApiMetrics {
+    requests: u64,
+    errors: u64,
+    /// Cumulative counts of requests per bucket of `LATENCY_BUCKETS`
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+}
+
+/// Requests of a client id, requests without one are of the empty client id
+#[derive(Default)]
+struct ClientMetrics {
+    /// Software name and version the client sent in ApiVersions, from version 3
+    software: Option<(String, String)>,
+    /// Requests by API key and version
+    requests: BTreeMap<(i16, i16), u64>,
+    last_seen_ms: i64,
+}
+
+impl Metrics {
+    pub fn connection_opened(&self) {
+        self.open_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn connection_closed(&self) {
+        self.open_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn bytes_received(&self, n: usize) {
+        self.bytes_in.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub fn bytes_sent(&self, n: usize) {
+        self.bytes_out.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    /// Records a request of a client over its quota, whose response is delayed by the throttle time
+    pub fn request_throttled(&self, throttle: Duration) {
+        self.throttled_requests.fetch_add(1, Ordering::Relaxed);
+        self.throttle_time_ms
+            .fetch_add(throttle.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Records processed request of the API, `failed` if the handler returned an error
+    pub fn request_processed(&self, api_key: i16, latency: Duration, failed: bool) {
+        let mut apis = self.apis.lock().expect("metrics lock is not poisoned");
+        let api = apis.entry(api_key).or_default();
+        api.requests += 1;
+        if failed {
+            api.errors += 1;
+        }
+        let secs = latency.as_secs_f64();
+        for (bucket, le) in api.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if secs <= le {
+                *bucket += 1;
+            }
+        }
+        api.latency_sum += secs;
+    }
+
+    /// Records a request of the client id, of the API key and version
+    pub fn client_request(&self, client_id: &str, api_key: i16, api_version: i16, now_ms: i64) {
+        let mut clients = self.clients.lock().expect("metrics lock is not poisoned");
+        let client = match clients.get_mut(client_id) {
+            Some(client) => client,
+            None => clients.entry(client_id.to_string()).or_default(),
+        };
+        *client.requests.entry((api_key, api_version)).or_default() += 1;
+        client.last_seen_ms = now_ms;
+    }
+
+    /// Records the software the client id reported, the latest one is kept
+    pub fn client_software(&self, client_id: &str, name: &str, version: &str) {
+        let mut clients = self.clients.lock().expect("metrics lock is not poisoned");
+        clients.entry(client_id.to_string()).or_default().software =
+            Some((name.to_string(), version.to_string()));
+    }
+
+    /// Removes the metrics of the client ids that sent no request for `CLIENT_EXPIRY`
+    pub fn expire_clients(&self, now_ms: i64) {
+        let expiry_ms = CLIENT_EXPIRY.as_millis() as i64;
+        self.clients
+            .lock()
+            .expect("metrics lock is not poisoned")
+            .retain(|_, client| now_ms - client.last_seen_ms < expiry_ms);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    // https://prometheus.io/docs/instrumenting/exposition_formats/#text-based-format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            _ = writeln!(out, "# HELP {name} {help}");
+            _ = writeln!(out, "# TYPE {name} {kind}");
+            _ = writeln!(out, "{name} {value}");
+        };
+        metric(
+            "kafka_connections_open",
+            "gauge",
+            "Open client connections.",
+            self.open_connections.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "kafka_network_bytes_in_total",
+            "counter",
+            "Bytes received from clients.",
+            self.bytes_in.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "kafka_network_bytes_out_total",
+            "counter",
+            "Bytes sent to clients.",
+            self.bytes_out.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "kafka_quota_throttled_requests_total",
+            "counter",
+            "Requests of clients over their quota.",
+            self.throttled_requests.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "kafka_quota_throttle_time_seconds_total",
+            "counter",
+            "Time the responses to clients over their quota were delayed by.",
+            (self.throttle_time_ms.load(Ordering::Relaxed) as f64 / 1000.0).to_string(),
+        );
+
+        let apis = self.apis.lock().expect("metrics lock is not poisoned");
+        let label = |api_key: i16| match ApiKey::try_from(api_key) {
+            Ok(key) => format!("api=\"{:?}\",api_key=\"{}\"", key, api_key),
+            Err(_) => format!("api=\"Unknown\",api_key=\"{}\"", api_key),
+        };
+
+        out.push_str("# HELP kafka_requests_total Processed requests.\n");
+        out.push_str("# TYPE kafka_requests_total counter\n");
+        for (api_key, api) in apis.iter() {
+            _ = writeln!(
+                out,
+                "kafka_requests_total{{{}}} {}",
+                label(*api_key),
+                api.requests
+            );
+        }
+        out.push_str(
+            "# HELP kafka_request_errors_total Requests that failed and closed the connection.\n",
+        );
+        out.push_str("# TYPE kafka_request_errors_total counter\n");
+        for (api_key, api) in apis.iter() {
+            _ = writeln!(
+                out,
+                "kafka_request_errors_total{{{}}} {}",
+                label(*api_key),
+                api.errors
+            );
+        }
+        out.push_str("# HELP kafka_request_duration_seconds Time of request processing.\n");
+        out.push_str("# TYPE kafka_request_duration_seconds histogram\n");
+        for (api_key, api) in apis.iter() {
+            let label = label(*api_key);
+            for (count, le) in api.latency_buckets.iter().zip(LATENCY_BUCKETS) {
+                _ = writeln!(
+                    out,
+                    "kafka_request_duration_seconds_bucket{{{label},le=\"{le}\"}} {count}"
+                );
+            }
+            _ = writeln!(
+                out,
+                "kafka_request_duration_seconds_bucket{{{label},le=\"+Inf\"}} {}",
+                api.requests
+            );
+            _ = writeln!(
+                out,
+                "kafka_request_duration_seconds_sum{{{label}}} {}",
+                api.latency_sum
+            );
+            _ = writeln!(
+                out,
+                "kafka_request_duration_seconds_count{{{label}}} {}",
+                api.requests
+            );
+        }
+        drop(apis);
+
+        let clients = self.clients.lock().expect("metrics lock is not poisoned");
+        out.push_str(
+            "# HELP kafka_client_requests_total Requests of the client id by API version.\n",
+        );
+        out.push_str("# TYPE kafka_client_requests_total counter\n");
+        for (client_id, client) in clients.iter() {
+            let client_id = escape_label(client_id);
+            for ((api_key, version), requests) in &client.requests {
+                _ = writeln!(
+                    out,
+                    "kafka_client_requests_total{{client_id=\"{client_id}\",{},version=\"{version}\"}} {requests}",
+                    label(*api_key)
+                );
+            }
+        }
+        out.push_str(
+            "# HELP kafka_client_info Software of the client id, from its ApiVersions request.\n",
+        );
+        out.push_str("# TYPE kafka_client_info gauge\n");
+        for (client_id, client) in clients.iter() {
+            if let Some((name, version)) = &client.software {
+                _ = writeln!(
+                    out,
+                    "kafka_client_info{{client_id=\"{}\",software_name=\"{}\",software_version=\"{}\"}} 1",
+                    escape_label(client_id),
+                    escape_label(name),
+                    escape_label(version)
+                );
+            }
+        }
+        out.push_str("# HELP kafka_client_last_seen_timestamp_seconds Time of the last request of the client id.\n");
+        out.push_str("# TYPE kafka_client_last_seen_timestamp_seconds gauge\n");
+        for (client_id, client) in clients.iter() {
+            _ = writeln!(
+                out,
+                "kafka_client_last_seen_timestamp_seconds{{client_id=\"{}\"}} {}",
+                escape_label(client_id),
+                client.last_seen_ms as f64 / 1000.0
+            );
+        }
+
+        out
+    }
+}
+
+/// Escapes the backslashes, double quotes and line feeds of a label value, e.g. of a client id
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+/// Serves `GET /metrics` over HTTP on the given address
+pub async fn serve(addr: SocketAddr) -> Result<()> {
+    let respond: http::Respond = Arc::new(|path: &str| match path {
+        "/metrics" => http::Response::ok("text/plain; version=0.0.4", metrics().render()),
+        _ => http::Response::not_found(),
+    });
+    http::serve(addr, "metrics", respond).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Metrics, CLIENT_EXPIRY};
+
+    #[test]
+    fn renders_api_metrics() {
+        let metrics = Metrics::default();
+        metrics.connection_opened();
+        metrics.bytes_received(10);
+        metrics.request_throttled(Duration::from_millis(1500));
+        metrics.request_processed(18, Duration::from_millis(2), false);
+        metrics.request_processed(18, Duration::from_millis(200), true);
+        metrics.request_processed(99, Duration::from_millis(2), true);
+
+        let out = metrics.render();
+        assert!(out.contains("kafka_connections_open 1\n"));
+        assert!(out.contains("kafka_network_bytes_in_total 10\n"));
+        assert!(out.contains("kafka_quota_throttled_requests_total 1\n"));
+        assert!(out.contains("kafka_quota_throttle_time_seconds_total 1.5\n"));
+        assert!(out.contains("kafka_requests_total{api=\"ApiVersions\",api_key=\"18\"} 2\n"));
+        assert!(out.contains("kafka_request_errors_total{api=\"Unknown\",api_key=\"99\"} 1\n"));
+        assert!(out.contains(
+            "kafka_request_duration_seconds_bucket{api=\"ApiVersions\",api_key=\"18\",le=\"0.005\"} 1\n"
+        ));
+        assert!(out.contains(
+            "kafka_request_duration_seconds_bucket{api=\"ApiVersions\",api_key=\"18\",le=\"0.5\"} 2\n"
+        ));
+    }
+
+    #[test]
+    fn renders_client_metrics_until_they_expire() {
+        let metrics = Metrics::default();
+        metrics.client_software("console-consumer", "apache-kafka-java", "3.9.0");
+        metrics.client_request("console-consumer", 18, 4, 1_000);
+        metrics.client_request("console-consumer", 1, 16, 2_500);
+        metrics.client_request("console-consumer", 1, 16, 3_000);
+        metrics.client_request("a\"b", 3, 12, 60_000);
+
+        let out = metrics.render();
+        assert!(out.contains(
+            "kafka_client_requests_total{client_id=\"console-consumer\",api=\"Fetch\",api_key=\"1\",version=\"16\"} 2\n"
+        ));
+        assert!(out.contains(
+            "kafka_client_info{client_id=\"console-consumer\",software_name=\"apache-kafka-java\",software_version=\"3.9.0\"} 1\n"
+        ));
+        assert!(out.contains(
+            "kafka_client_last_seen_timestamp_seconds{client_id=\"console-consumer\"} 3\n"
+        ));
+        assert!(out.contains("kafka_client_last_seen_timestamp_seconds{client_id=\"a\\\"b\"} 60\n"));
+
+        metrics.expire_clients(3_000 + CLIENT_EXPIRY.as_millis() as i64);
+        let out = metrics.render();
+        assert!(!out.contains("console-consumer"));
+        assert!(out.contains("client_id=\"a\\\"b\""));
+    }
+}
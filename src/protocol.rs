@@ -1,70 +1,471 @@
+pub mod crc32c;
+pub mod generated;
+pub mod gzip;
+pub mod reader;
+pub mod record_batch;
 pub mod request;
 pub mod response;
 pub mod types;
-pub mod record_batch;
+
+#[cfg(test)]
+pub(crate) mod testing;
 
 use bytes::{BufMut, Bytes, BytesMut};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
+use thiserror::Error;
+
+use reader::ByteReader;
 
 /// https://kafka.apache.org/protocol.html#protocol_api_keys
-#[derive(Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
 #[repr(i16)]
 pub enum ApiKey {
+    Produce = 0,
     Fetch = 1,
+    ListOffsets = 2,
+    Metadata = 3,
+    LeaderAndIsr = 4,
+    StopReplica = 5,
+    UpdateMetadata = 6,
+    ControlledShutdown = 7,
+    OffsetCommit = 8,
+    OffsetFetch = 9,
+    FindCoordinator = 10,
+    JoinGroup = 11,
+    Heartbeat = 12,
+    LeaveGroup = 13,
+    SyncGroup = 14,
+    DescribeGroups = 15,
+    ListGroups = 16,
+    SaslHandshake = 17,
     ApiVersions = 18,
+    CreateTopics = 19,
+    DeleteTopics = 20,
+    DeleteRecords = 21,
+    InitProducerId = 22,
+    OffsetForLeaderEpoch = 23,
+    AddPartitionsToTxn = 24,
+    AddOffsetsToTxn = 25,
+    EndTxn = 26,
+    WriteTxnMarkers = 27,
+    TxnOffsetCommit = 28,
+    DescribeAcls = 29,
+    CreateAcls = 30,
+    DeleteAcls = 31,
+    DescribeConfigs = 32,
+    AlterConfigs = 33,
+    AlterReplicaLogDirs = 34,
+    DescribeLogDirs = 35,
+    SaslAuthenticate = 36,
+    CreatePartitions = 37,
+    CreateDelegationToken = 38,
+    RenewDelegationToken = 39,
+    ExpireDelegationToken = 40,
+    DescribeDelegationToken = 41,
+    DeleteGroups = 42,
+    ElectLeaders = 43,
+    IncrementalAlterConfigs = 44,
+    AlterPartitionReassignments = 45,
+    ListPartitionReassignments = 46,
+    OffsetDelete = 47,
+    DescribeClientQuotas = 48,
+    AlterClientQuotas = 49,
+    DescribeUserScramCredentials = 50,
+    AlterUserScramCredentials = 51,
+    Vote = 52,
+    BeginQuorumEpoch = 53,
+    EndQuorumEpoch = 54,
+    DescribeQuorum = 55,
+    AlterPartition = 56,
+    UpdateFeatures = 57,
+    Envelope = 58,
+    FetchSnapshot = 59,
+    DescribeCluster = 60,
+    DescribeProducers = 61,
+    BrokerRegistration = 62,
+    BrokerHeartbeat = 63,
+    UnregisterBroker = 64,
+    DescribeTransactions = 65,
+    ListTransactions = 66,
+    AllocateProducerIds = 67,
+    ConsumerGroupHeartbeat = 68,
+    ConsumerGroupDescribe = 69,
+    ControllerRegistration = 70,
+    GetTelemetrySubscriptions = 71,
+    PushTelemetry = 72,
+    AssignReplicasToDirs = 73,
+    ListClientMetricsResources = 74,
     DescribeTopicPartitions = 75,
 }
 
+impl ApiKey {
+    /// First flexible version of the API (KIP-482), `None` if no version is flexible
+    // flexibleVersions of https://github.com/apache/kafka/tree/trunk/clients/src/main/resources/common/message
+    pub fn flexible_since(self) -> Option<i16> {
+        match self {
+            Self::SaslHandshake | Self::OffsetDelete => None,
+            Self::WriteTxnMarkers
+            | Self::IncrementalAlterConfigs
+            | Self::DescribeClientQuotas
+            | Self::AlterClientQuotas
+            | Self::BeginQuorumEpoch
+            | Self::EndQuorumEpoch => Some(1),
+            Self::StopReplica
+            | Self::DeleteRecords
+            | Self::InitProducerId
+            | Self::DescribeAcls
+            | Self::CreateAcls
+            | Self::DeleteAcls
+            | Self::AlterConfigs
+            | Self::AlterReplicaLogDirs
+            | Self::DescribeLogDirs
+            | Self::SaslAuthenticate
+            | Self::CreatePartitions
+            | Self::CreateDelegationToken
+            | Self::RenewDelegationToken
+            | Self::ExpireDelegationToken
+            | Self::DescribeDelegationToken
+            | Self::DeleteGroups
+            | Self::ElectLeaders => Some(2),
+            Self::ControlledShutdown
+            | Self::FindCoordinator
+            | Self::ListGroups
+            | Self::ApiVersions
+            | Self::AddPartitionsToTxn
+            | Self::AddOffsetsToTxn
+            | Self::EndTxn
+            | Self::TxnOffsetCommit => Some(3),
+            Self::LeaderAndIsr
+            | Self::Heartbeat
+            | Self::LeaveGroup
+            | Self::SyncGroup
+            | Self::DeleteTopics
+            | Self::OffsetForLeaderEpoch
+            | Self::DescribeConfigs => Some(4),
+            Self::DescribeGroups | Self::CreateTopics => Some(5),
+            Self::ListOffsets | Self::UpdateMetadata | Self::OffsetFetch | Self::JoinGroup => {
+                Some(6)
+            }
+            Self::OffsetCommit => Some(8),
+            Self::Produce | Self::Metadata => Some(9),
+            Self::Fetch => Some(12),
+            // all APIs added since KIP-482 are flexible from the first version
+            _ => Some(0),
+        }
+    }
+
+    /// Flexible versions use compact encodings and tagged fields, also in the request and response headers
+    pub fn is_flexible(self, version: i16) -> bool {
+        self.flexible_since().is_some_and(|since| version >= since)
+    }
+}
+
 /// https://kafka.apache.org/protocol.html#protocol_error_codes
-#[derive(Clone, Copy, IntoPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoPrimitive, TryFromPrimitive)]
 #[repr(i16)]
 pub enum ErrorCode {
+    UnknownServerError = -1,
     None = 0,
+    OffsetOutOfRange = 1,
+    CorruptMessage = 2,
     UnknownTopicOrPartition = 3,
+    InvalidFetchSize = 4,
+    LeaderNotAvailable = 5,
+    NotLeaderOrFollower = 6,
+    RequestTimedOut = 7,
+    BrokerNotAvailable = 8,
+    ReplicaNotAvailable = 9,
+    MessageTooLarge = 10,
+    StaleControllerEpoch = 11,
+    OffsetMetadataTooLarge = 12,
+    NetworkException = 13,
+    CoordinatorLoadInProgress = 14,
+    CoordinatorNotAvailable = 15,
+    NotCoordinator = 16,
+    InvalidTopicException = 17,
+    RecordListTooLarge = 18,
+    NotEnoughReplicas = 19,
+    NotEnoughReplicasAfterAppend = 20,
+    InvalidRequiredAcks = 21,
+    IllegalGeneration = 22,
+    InconsistentGroupProtocol = 23,
+    InvalidGroupId = 24,
+    UnknownMemberId = 25,
+    InvalidSessionTimeout = 26,
+    RebalanceInProgress = 27,
+    InvalidCommitOffsetSize = 28,
+    TopicAuthorizationFailed = 29,
+    GroupAuthorizationFailed = 30,
+    ClusterAuthorizationFailed = 31,
+    InvalidTimestamp = 32,
+    UnsupportedSaslMechanism = 33,
+    IllegalSaslState = 34,
     UnsupportedVersion = 35,
+    TopicAlreadyExists = 36,
+    InvalidPartitions = 37,
+    InvalidReplicationFactor = 38,
+    InvalidReplicaAssignment = 39,
+    InvalidConfig = 40,
+    NotController = 41,
     InvalidRequest = 42,
+    UnsupportedForMessageFormat = 43,
+    PolicyViolation = 44,
+    OutOfOrderSequenceNumber = 45,
+    DuplicateSequenceNumber = 46,
+    InvalidProducerEpoch = 47,
+    InvalidTxnState = 48,
+    InvalidProducerIdMapping = 49,
+    InvalidTransactionTimeout = 50,
+    ConcurrentTransactions = 51,
+    TransactionCoordinatorFenced = 52,
+    TransactionalIdAuthorizationFailed = 53,
+    SecurityDisabled = 54,
+    OperationNotAttempted = 55,
+    KafkaStorageError = 56,
+    LogDirNotFound = 57,
+    SaslAuthenticationFailed = 58,
+    UnknownProducerId = 59,
+    ReassignmentInProgress = 60,
+    DelegationTokenAuthDisabled = 61,
+    DelegationTokenNotFound = 62,
+    DelegationTokenOwnerMismatch = 63,
+    DelegationTokenRequestNotAllowed = 64,
+    DelegationTokenAuthorizationFailed = 65,
+    DelegationTokenExpired = 66,
+    InvalidPrincipalType = 67,
+    NonEmptyGroup = 68,
+    GroupIdNotFound = 69,
+    FetchSessionIdNotFound = 70,
+    InvalidFetchSessionEpoch = 71,
+    ListenerNotFound = 72,
+    TopicDeletionDisabled = 73,
+    FencedLeaderEpoch = 74,
+    UnknownLeaderEpoch = 75,
+    UnsupportedCompressionType = 76,
+    StaleBrokerEpoch = 77,
+    OffsetNotAvailable = 78,
+    MemberIdRequired = 79,
+    PreferredLeaderNotAvailable = 80,
+    GroupMaxSizeReached = 81,
+    FencedInstanceId = 82,
+    EligibleLeadersNotAvailable = 83,
+    ElectionNotNeeded = 84,
+    NoReassignmentInProgress = 85,
+    GroupSubscribedToTopic = 86,
+    InvalidRecord = 87,
+    UnstableOffsetCommit = 88,
+    ThrottlingQuotaExceeded = 89,
+    ProducerFenced = 90,
+    ResourceNotFound = 91,
+    DuplicateResource = 92,
+    UnacceptableCredential = 93,
+    InconsistentVoterSet = 94,
+    InvalidUpdateVersion = 95,
+    FeatureUpdateFailed = 96,
+    PrincipalDeserializationFailure = 97,
+    SnapshotNotFound = 98,
+    PositionOutOfRange = 99,
     UnknownTopicId = 100,
+    DuplicateBrokerRegistration = 101,
+    BrokerIdNotRegistered = 102,
+    InconsistentTopicId = 103,
+    InconsistentClusterId = 104,
+    TransactionalIdNotFound = 105,
+    FetchSessionTopicIdError = 106,
+    IneligibleReplica = 107,
+    NewLeaderElected = 108,
+    OffsetMovedToTieredStorage = 109,
+    FencedMemberEpoch = 110,
+    UnreleasedInstanceId = 111,
+    UnsupportedAssignor = 112,
+    StaleMemberEpoch = 113,
+    MismatchedEndpointType = 114,
+    UnsupportedEndpointType = 115,
+    UnknownControllerId = 116,
+    UnknownSubscriptionId = 117,
+    TelemetryTooLarge = 118,
+    InvalidRegistration = 119,
+    TransactionAbortable = 120,
 }
 
 impl types::Serialize for ErrorCode {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::with_capacity(2);
-        let val: i16 = <ErrorCode as Into<i16>>::into(*self);
-        b.put_i16(val);
-        b.freeze()
+    fn serialize_into(&mut self, dst: &mut BytesMut) {
+        dst.put_i16((*self).into());
     }
 }
 
-/// Response Message is a wrapper around API response with prepended message size
-// https://kafka.apache.org/protocol.html#protocol_common
-pub struct ResponseMessage {
-    bytes: BytesMut,
+impl types::Encode<ErrorCode> for types::Int16 {
+    fn encode(value: &mut ErrorCode, dst: &mut BytesMut) {
+        dst.put_i16((*value).into());
+    }
 }
 
-impl ResponseMessage {
-    /// Calculates the size of the source API response and prepends it to the response
-    pub fn from_bytes(src: &[u8]) -> Self {
-        let mut bytes = BytesMut::with_capacity(src.len() + 4);
+impl types::Decode<ErrorCode> for types::Int16 {
+    fn decode(src: &mut ByteReader, field: &'static str) -> Result<ErrorCode, ProtocolError> {
+        let code = src.get_i16(field)?;
+        ErrorCode::try_from(code).map_err(|_| ProtocolError::UnexpectedValue {
+            field,
+            value: code.into(),
+        })
+    }
+}
 
-        let msg_size = 0; // placeholder; will be counted later
-        bytes.put_i32(msg_size);
+impl types::Encode<ApiKey> for types::Int16 {
+    fn encode(value: &mut ApiKey, dst: &mut BytesMut) {
+        dst.put_i16((*value).into());
+    }
+}
 
-        bytes.extend_from_slice(src);
+impl types::Decode<ApiKey> for types::Int16 {
+    fn decode(src: &mut ByteReader, field: &'static str) -> Result<ApiKey, ProtocolError> {
+        let key = src.get_i16(field)?;
+        ApiKey::try_from(key).map_err(|_| ProtocolError::UnexpectedValue {
+            field,
+            value: key.into(),
+        })
+    }
+}
 
+/// Errors that can occur when decoding data received from the network or read from the log files
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error(
+        "unexpected end of data when reading {field}: {needed} bytes needed, {remaining} remaining"
+    )]
+    UnexpectedEof {
+        field: &'static str,
+        needed: usize,
+        remaining: usize,
+    },
+    #[error("unexpected value `{value}` of {field}")]
+    UnexpectedValue { field: &'static str, value: i64 },
+    #[error("invalid varint {field}: {source}")]
+    VarInt {
+        field: &'static str,
+        source: types::VarIntError,
+    },
+    /// The message is longer than its fields, its size disagrees with the schema of its version
+    #[error("{remaining} unexpected bytes at the end of {message}")]
+    TrailingBytes {
+        message: &'static str,
+        remaining: usize,
+    },
+}
+
+/// Response Message is API response with prepended message size
+// https://kafka.apache.org/protocol.html#protocol_common
+pub struct ResponseMessage;
+
+impl ResponseMessage {
+    /// Creates a buffer for the response, starting with a placeholder for the message size
+    pub fn buffer() -> BytesMut {
+        let mut bytes = BytesMut::new();
+        let msg_size = 0; // placeholder; will be counted in `finish`
+        bytes.put_i32(msg_size);
+        bytes
+    }
+
+    /// Calculates the size of the API response serialized into the `buffer` and fills it in
+    pub fn finish(mut bytes: BytesMut) -> Bytes {
         let resp_size = bytes.len() as i32 - 4;
 
         let msg_size_ref = bytes
             .first_chunk_mut::<4>()
-            .expect("message size element is present in response header");
+            .expect("message size element is present in response buffer");
         *msg_size_ref = (resp_size).to_be_bytes();
 
-        Self { bytes }
-    }
-    pub fn as_bytes(&self) -> &[u8] {
-        &self.bytes
+        bytes.freeze()
     }
 }
 
 pub trait Response {
-    fn as_bytes(&self) -> &[u8];
+    /// Consumes the response and returns the whole response message, message size included
+    fn into_bytes(self) -> Bytes;
+}
+
+/// Request message to send to a broker: the header and the serialized request body, e.g. of a generated
+/// request, with prepended message size
+pub fn encode_request(header: &request::RequestHeader, body: Bytes) -> Bytes {
+    let mut bytes = ResponseMessage::buffer();
+    header.serialize(&mut bytes);
+    bytes.put(body);
+    ResponseMessage::finish(bytes)
+}
+
+/// Decodes the response to a request of the API and version from a message received from a broker,
+/// without the message size.
+///
+/// The header version follows from the request, the body is read by `decode`, e.g. by the `deserialize`
+/// function of a generated response.
+pub fn decode_response<T>(
+    msg: Bytes,
+    api_key: ApiKey,
+    api_version: i16,
+    decode: impl FnOnce(&mut ByteReader, i16) -> Result<T, ProtocolError>,
+) -> Result<(response::ResponseHeader, T), ProtocolError> {
+    let mut src = ByteReader::new(msg);
+    let header = response::ResponseHeader::from_bytes(&mut src, api_key, api_version)?;
+    let body = decode(&mut src, api_version)?;
+    Ok((header, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{
+        decode_response, encode_request,
+        generated::{
+            api_versions_request::ApiVersionsRequestData,
+            metadata_response::{MetadataResponseBroker, MetadataResponseData},
+        },
+        reader::ByteReader,
+        request::RequestHeader,
+        response::{self, ResponseHeader},
+        ApiKey,
+    };
+
+    #[test]
+    fn encodes_requests_and_decodes_responses() {
+        let header = RequestHeader {
+            request_api_key: ApiKey::ApiVersions.into(),
+            request_api_version: 4,
+            correlation_id: 7,
+            client_id: Some("client".to_string()),
+        };
+        let req = ApiVersionsRequestData {
+            client_software_name: "kafka-starter-rust".to_string(),
+            client_software_version: "0.1.0".to_string(),
+        };
+        let msg = encode_request(&header, req.serialize(4));
+        assert_eq!(
+            msg.len() - 4,
+            i32::from_be_bytes(msg[..4].try_into().unwrap()) as usize
+        );
+
+        let mut src = ByteReader::new(msg.slice(4..));
+        let decoded = RequestHeader::from_bytes(&mut src).unwrap();
+        assert_eq!(decoded.correlation_id, 7);
+        assert_eq!(decoded.client_id.as_deref(), Some("client"));
+        assert_eq!(
+            ApiVersionsRequestData::deserialize(&mut src, 4).unwrap(),
+            req
+        );
+
+        let resp = MetadataResponseData {
+            brokers: vec![MetadataResponseBroker {
+                node_id: 1,
+                host: "localhost".to_string(),
+                port: 9092,
+                rack: None,
+            }],
+            ..MetadataResponseData::default()
+        };
+        let header = ResponseHeader::new(ApiKey::Metadata, 12, 8);
+        let msg: Bytes = response::message(header, resp.serialize(12)).slice(4..);
+        let (header, decoded) =
+            decode_response(msg, ApiKey::Metadata, 12, MetadataResponseData::deserialize).unwrap();
+        assert_eq!(header.correlation_id(), 8);
+        assert_eq!(decoded, resp);
+    }
 }
@@ -0,0 +1,34 @@
+//! Message structs generated from the Kafka message JSON schemas in `schemas/` by `src/bin/codegen.rs`.
+//! Regenerate with `cargo run --bin codegen -- src/protocol/generated schemas/*.json && cargo fmt`.
+
+pub mod api_versions_request;
+pub mod api_versions_response;
+pub mod broker_heartbeat_request;
+pub mod broker_heartbeat_response;
+pub mod broker_registration_request;
+pub mod broker_registration_response;
+pub mod create_delegation_token_request;
+pub mod create_delegation_token_response;
+pub mod default_principal_data;
+pub mod describe_configs_request;
+pub mod describe_configs_response;
+pub mod describe_delegation_token_request;
+pub mod describe_delegation_token_response;
+pub mod describe_log_dirs_request;
+pub mod describe_log_dirs_response;
+pub mod envelope_request;
+pub mod envelope_response;
+pub mod expire_delegation_token_request;
+pub mod expire_delegation_token_response;
+pub mod list_offsets_request;
+pub mod list_offsets_response;
+pub mod metadata_request;
+pub mod metadata_response;
+pub mod produce_request;
+pub mod produce_response;
+pub mod renew_delegation_token_request;
+pub mod renew_delegation_token_response;
+pub mod sasl_authenticate_request;
+pub mod sasl_authenticate_response;
+pub mod sasl_handshake_request;
+pub mod sasl_handshake_response;
@@ -0,0 +1,70 @@
+// Generated by `src/bin/codegen.rs` from `ApiVersionsRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ApiVersionsRequest, versions 0-4
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiVersionsRequestData {
+    /// The name of the client.
+    pub client_software_name: String,
+    /// The version of the client.
+    pub client_software_version: String,
+}
+
+impl ApiVersionsRequestData {
+    pub const API_KEY: i16 = 18;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let client_software_name = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        let client_software_version = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        if version >= 3 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            client_software_name,
+            client_software_version,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 3 {
+            CompactString::serialize_into(&self.client_software_name, b);
+        }
+        if version >= 3 {
+            CompactString::serialize_into(&self.client_software_version, b);
+        }
+        if version >= 3 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Generated by `src/bin/codegen.rs` from `ApiVersionsResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ApiVersionsResponse, versions 0-4
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiVersionsResponseData {
+    /// The top-level error code.
+    pub error_code: i16,
+    /// The APIs supported by the broker.
+    pub api_keys: Vec<ApiVersion>,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+}
+
+impl ApiVersionsResponseData {
+    pub const API_KEY: i16 = 18;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let api_keys = {
+            let len = if version >= 3 {
+                src.get_varint("api_keys")? - 1
+            } else {
+                i64::from(src.get_i32("api_keys")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(ApiVersion::deserialize(src, version)?);
+            }
+            items
+        };
+        let throttle_time_ms = if version >= 1 {
+            src.get_i32("throttle_time_ms")?
+        } else {
+            0
+        };
+        if version >= 3 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            api_keys,
+            throttle_time_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 3 {
+            VarInt::serialize_into(self.api_keys.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.api_keys.len() as i32);
+        }
+        for item in &self.api_keys {
+            item.serialize_into(b, version);
+        }
+        if version >= 1 {
+            b.put_i32(self.throttle_time_ms);
+        }
+        if version >= 3 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiVersion {
+    /// The API index.
+    pub api_key: i16,
+    /// The minimum supported version, inclusive.
+    pub min_version: i16,
+    /// The maximum supported version, inclusive.
+    pub max_version: i16,
+}
+
+impl ApiVersion {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let api_key = src.get_i16("api_key")?;
+        let min_version = src.get_i16("min_version")?;
+        let max_version = src.get_i16("max_version")?;
+        if version >= 3 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            api_key,
+            min_version,
+            max_version,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.api_key);
+        b.put_i16(self.min_version);
+        b.put_i16(self.max_version);
+        if version >= 3 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
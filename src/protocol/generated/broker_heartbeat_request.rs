@@ -0,0 +1,81 @@
+// Generated by `src/bin/codegen.rs` from `BrokerHeartbeatRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// BrokerHeartbeatRequest, versions 0-1
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerHeartbeatRequestData {
+    /// The broker ID.
+    pub broker_id: i32,
+    /// The broker epoch.
+    pub broker_epoch: i64,
+    /// The highest metadata offset which the broker has reached.
+    pub current_metadata_offset: i64,
+    /// True if the broker wants to be fenced, false otherwise.
+    pub want_fence: bool,
+    /// True if the broker wants to be shut down, false otherwise.
+    pub want_shut_down: bool,
+}
+
+impl Default for BrokerHeartbeatRequestData {
+    fn default() -> Self {
+        Self {
+            broker_id: 0,
+            broker_epoch: -1,
+            current_metadata_offset: 0,
+            want_fence: false,
+            want_shut_down: false,
+        }
+    }
+}
+
+impl BrokerHeartbeatRequestData {
+    pub const API_KEY: i16 = 63;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 1;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let broker_id = src.get_i32("broker_id")?;
+        let broker_epoch = src.get_i64("broker_epoch")?;
+        let current_metadata_offset = src.get_i64("current_metadata_offset")?;
+        let want_fence = src.get_u8("want_fence")? != 0;
+        let want_shut_down = src.get_u8("want_shut_down")? != 0;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            broker_id,
+            broker_epoch,
+            current_metadata_offset,
+            want_fence,
+            want_shut_down,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        b.put_i32(self.broker_id);
+        b.put_i64(self.broker_epoch);
+        b.put_i64(self.current_metadata_offset);
+        b.put_u8(self.want_fence.into());
+        b.put_u8(self.want_shut_down.into());
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
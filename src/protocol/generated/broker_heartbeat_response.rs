@@ -0,0 +1,81 @@
+// Generated by `src/bin/codegen.rs` from `BrokerHeartbeatResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// BrokerHeartbeatResponse, versions 0-1
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerHeartbeatResponseData {
+    /// Duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// True if the broker has approximately caught up with the latest metadata.
+    pub is_caught_up: bool,
+    /// True if the broker is fenced.
+    pub is_fenced: bool,
+    /// True if the broker should proceed with its shutdown.
+    pub should_shut_down: bool,
+}
+
+impl Default for BrokerHeartbeatResponseData {
+    fn default() -> Self {
+        Self {
+            throttle_time_ms: 0,
+            error_code: 0,
+            is_caught_up: false,
+            is_fenced: true,
+            should_shut_down: false,
+        }
+    }
+}
+
+impl BrokerHeartbeatResponseData {
+    pub const API_KEY: i16 = 63;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 1;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let error_code = src.get_i16("error_code")?;
+        let is_caught_up = src.get_u8("is_caught_up")? != 0;
+        let is_fenced = src.get_u8("is_fenced")? != 0;
+        let should_shut_down = src.get_u8("should_shut_down")? != 0;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            is_caught_up,
+            is_fenced,
+            should_shut_down,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        b.put_i32(self.throttle_time_ms);
+        b.put_i16(self.error_code);
+        b.put_u8(self.is_caught_up.into());
+        b.put_u8(self.is_fenced.into());
+        b.put_u8(self.should_shut_down.into());
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
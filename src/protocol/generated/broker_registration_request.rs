@@ -0,0 +1,232 @@
+// Generated by `src/bin/codegen.rs` from `BrokerRegistrationRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// BrokerRegistrationRequest, versions 0-3
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerRegistrationRequestData {
+    /// The broker ID.
+    pub broker_id: i32,
+    /// The cluster id of the broker process.
+    pub cluster_id: String,
+    /// The incarnation id of the broker process.
+    pub incarnation_id: String,
+    /// The listeners of this broker
+    pub listeners: Vec<Listener>,
+    /// The features on this broker
+    pub features: Vec<Feature>,
+    /// The rack which this broker is in.
+    pub rack: Option<String>,
+    /// If the required configurations for ZK migration are present, this value is set to true
+    pub is_migrating_zk_broker: bool,
+    /// Log directories configured in this broker which are available.
+    pub log_dirs: Vec<String>,
+    /// The epoch before a clean shutdown.
+    pub previous_broker_epoch: i64,
+}
+
+impl Default for BrokerRegistrationRequestData {
+    fn default() -> Self {
+        Self {
+            broker_id: 0,
+            cluster_id: String::new(),
+            incarnation_id: "00000000-0000-0000-0000-000000000000".to_string(),
+            listeners: Vec::new(),
+            features: Vec::new(),
+            rack: None,
+            is_migrating_zk_broker: false,
+            log_dirs: Vec::new(),
+            previous_broker_epoch: -1,
+        }
+    }
+}
+
+impl BrokerRegistrationRequestData {
+    pub const API_KEY: i16 = 62;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let broker_id = src.get_i32("broker_id")?;
+        let cluster_id = CompactString::deserialize(src)?;
+        let incarnation_id = Uuid::deserialize(src)?;
+        let listeners = {
+            let len = src.get_varint("listeners")? - 1;
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(Listener::deserialize(src, version)?);
+            }
+            items
+        };
+        let features = {
+            let len = src.get_varint("features")? - 1;
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(Feature::deserialize(src, version)?);
+            }
+            items
+        };
+        let rack = CompactNullableString::deserialize(src)?;
+        let is_migrating_zk_broker = if version >= 1 {
+            src.get_u8("is_migrating_zk_broker")? != 0
+        } else {
+            false
+        };
+        let log_dirs = if version >= 2 {
+            {
+                let len = src.get_varint("log_dirs")? - 1;
+                let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(Uuid::deserialize(src)?);
+                }
+                items
+            }
+        } else {
+            Vec::new()
+        };
+        let previous_broker_epoch = if version >= 3 {
+            src.get_i64("previous_broker_epoch")?
+        } else {
+            -1
+        };
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            broker_id,
+            cluster_id,
+            incarnation_id,
+            listeners,
+            features,
+            rack,
+            is_migrating_zk_broker,
+            log_dirs,
+            previous_broker_epoch,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.broker_id);
+        CompactString::serialize_into(&self.cluster_id, b);
+        Uuid::serialize_into(&self.incarnation_id, b);
+        VarInt::serialize_into(self.listeners.len() as u64 + 1, b);
+        for item in &self.listeners {
+            item.serialize_into(b, version);
+        }
+        VarInt::serialize_into(self.features.len() as u64 + 1, b);
+        for item in &self.features {
+            item.serialize_into(b, version);
+        }
+        CompactNullableString::serialize_into(self.rack.as_deref(), b);
+        if version >= 1 {
+            b.put_u8(self.is_migrating_zk_broker.into());
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.log_dirs.len() as u64 + 1, b);
+            for item in &self.log_dirs {
+                Uuid::serialize_into(item, b);
+            }
+        }
+        if version >= 3 {
+            b.put_i64(self.previous_broker_epoch);
+        }
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Listener {
+    /// The name of the endpoint.
+    pub name: String,
+    /// The hostname.
+    pub host: String,
+    /// The port.
+    pub port: u16,
+    /// The security protocol.
+    pub security_protocol: i16,
+}
+
+impl Listener {
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let name = CompactString::deserialize(src)?;
+        let host = CompactString::deserialize(src)?;
+        let port = src.get_u16("port")?;
+        let security_protocol = src.get_i16("security_protocol")?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            name,
+            host,
+            port,
+            security_protocol,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        CompactString::serialize_into(&self.name, b);
+        CompactString::serialize_into(&self.host, b);
+        b.put_u16(self.port);
+        b.put_i16(self.security_protocol);
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Feature {
+    /// The feature name.
+    pub name: String,
+    /// The minimum supported feature level.
+    pub min_supported_version: i16,
+    /// The maximum supported feature level.
+    pub max_supported_version: i16,
+}
+
+impl Feature {
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let name = CompactString::deserialize(src)?;
+        let min_supported_version = src.get_i16("min_supported_version")?;
+        let max_supported_version = src.get_i16("max_supported_version")?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            name,
+            min_supported_version,
+            max_supported_version,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        CompactString::serialize_into(&self.name, b);
+        b.put_i16(self.min_supported_version);
+        b.put_i16(self.max_supported_version);
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
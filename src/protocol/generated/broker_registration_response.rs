@@ -0,0 +1,69 @@
+// Generated by `src/bin/codegen.rs` from `BrokerRegistrationResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// BrokerRegistrationResponse, versions 0-3
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokerRegistrationResponseData {
+    /// Duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The broker's assigned epoch, or -1 if none was assigned.
+    pub broker_epoch: i64,
+}
+
+impl Default for BrokerRegistrationResponseData {
+    fn default() -> Self {
+        Self {
+            throttle_time_ms: 0,
+            error_code: 0,
+            broker_epoch: -1,
+        }
+    }
+}
+
+impl BrokerRegistrationResponseData {
+    pub const API_KEY: i16 = 62;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let error_code = src.get_i16("error_code")?;
+        let broker_epoch = src.get_i64("broker_epoch")?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            broker_epoch,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        b.put_i32(self.throttle_time_ms);
+        b.put_i16(self.error_code);
+        b.put_i64(self.broker_epoch);
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
@@ -0,0 +1,152 @@
+// Generated by `src/bin/codegen.rs` from `CreateDelegationTokenRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// CreateDelegationTokenRequest, versions 0-3
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreateDelegationTokenRequestData {
+    /// The principal type of the owner of the token. If it's null it defaults to the token request principal.
+    pub owner_principal_type: Option<String>,
+    /// The principal name of the owner of the token. If it's null it defaults to the token request principal.
+    pub owner_principal_name: Option<String>,
+    /// A list of those who are allowed to renew this token before it expires.
+    pub renewers: Vec<CreatableRenewers>,
+    /// The maximum lifetime of the token in milliseconds, or -1 to use the server side default.
+    pub max_lifetime_ms: i64,
+}
+
+impl CreateDelegationTokenRequestData {
+    pub const API_KEY: i16 = 38;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let owner_principal_type = if version >= 3 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            None
+        };
+        let owner_principal_name = if version >= 3 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            None
+        };
+        let renewers = {
+            let len = if version >= 2 {
+                src.get_varint("renewers")? - 1
+            } else {
+                i64::from(src.get_i32("renewers")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(CreatableRenewers::deserialize(src, version)?);
+            }
+            items
+        };
+        let max_lifetime_ms = src.get_i64("max_lifetime_ms")?;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            owner_principal_type,
+            owner_principal_name,
+            renewers,
+            max_lifetime_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 3 {
+            CompactNullableString::serialize_into(self.owner_principal_type.as_deref(), b);
+        }
+        if version >= 3 {
+            CompactNullableString::serialize_into(self.owner_principal_name.as_deref(), b);
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.renewers.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.renewers.len() as i32);
+        }
+        for item in &self.renewers {
+            item.serialize_into(b, version);
+        }
+        b.put_i64(self.max_lifetime_ms);
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreatableRenewers {
+    /// The type of the Kafka principal.
+    pub principal_type: String,
+    /// The name of the Kafka principal.
+    pub principal_name: String,
+}
+
+impl CreatableRenewers {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let principal_type = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let principal_name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            principal_type,
+            principal_name,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_type, b);
+        } else {
+            b.put_i16(self.principal_type.len() as i16);
+            b.put_slice(self.principal_type.as_bytes());
+        }
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_name, b);
+        } else {
+            b.put_i16(self.principal_name.len() as i16);
+            b.put_slice(self.principal_name.as_bytes());
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// Generated by `src/bin/codegen.rs` from `CreateDelegationTokenResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// CreateDelegationTokenResponse, versions 0-3
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreateDelegationTokenResponseData {
+    /// The top-level error, or zero if there was no error.
+    pub error_code: i16,
+    /// The principal type of the token owner.
+    pub principal_type: String,
+    /// The name of the token owner.
+    pub principal_name: String,
+    /// The principal type of the requester of the token.
+    pub token_requester_principal_type: String,
+    /// The principal type of the requester of the token.
+    pub token_requester_principal_name: String,
+    /// When this token was generated.
+    pub issue_timestamp_ms: i64,
+    /// When this token expires.
+    pub expiry_timestamp_ms: i64,
+    /// The maximum lifetime of this token.
+    pub max_timestamp_ms: i64,
+    /// The token UUID.
+    pub token_id: String,
+    /// HMAC of the delegation token.
+    pub hmac: Vec<u8>,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+}
+
+impl CreateDelegationTokenResponseData {
+    pub const API_KEY: i16 = 38;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let principal_type = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let principal_name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let token_requester_principal_type = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        let token_requester_principal_name = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        let issue_timestamp_ms = src.get_i64("issue_timestamp_ms")?;
+        let expiry_timestamp_ms = src.get_i64("expiry_timestamp_ms")?;
+        let max_timestamp_ms = src.get_i64("max_timestamp_ms")?;
+        let token_id = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let hmac = if version >= 2 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("hmac")?;
+                src.get_bytes("hmac", len.max(0) as usize)?.to_vec()
+            }
+        };
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            principal_type,
+            principal_name,
+            token_requester_principal_type,
+            token_requester_principal_name,
+            issue_timestamp_ms,
+            expiry_timestamp_ms,
+            max_timestamp_ms,
+            token_id,
+            hmac,
+            throttle_time_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_type, b);
+        } else {
+            b.put_i16(self.principal_type.len() as i16);
+            b.put_slice(self.principal_type.as_bytes());
+        }
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_name, b);
+        } else {
+            b.put_i16(self.principal_name.len() as i16);
+            b.put_slice(self.principal_name.as_bytes());
+        }
+        if version >= 3 {
+            CompactString::serialize_into(&self.token_requester_principal_type, b);
+        }
+        if version >= 3 {
+            CompactString::serialize_into(&self.token_requester_principal_name, b);
+        }
+        b.put_i64(self.issue_timestamp_ms);
+        b.put_i64(self.expiry_timestamp_ms);
+        b.put_i64(self.max_timestamp_ms);
+        if version >= 2 {
+            CompactString::serialize_into(&self.token_id, b);
+        } else {
+            b.put_i16(self.token_id.len() as i16);
+            b.put_slice(self.token_id.as_bytes());
+        }
+        if version >= 2 {
+            CompactNullableBytes::serialize_into(&self.hmac, b);
+        } else {
+            b.put_i32(self.hmac.len() as i32);
+            b.put_slice(&self.hmac);
+        }
+        b.put_i32(self.throttle_time_ms);
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
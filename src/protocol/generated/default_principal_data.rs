@@ -0,0 +1,58 @@
+// Generated by `src/bin/codegen.rs` from `DefaultPrincipalData.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DefaultPrincipalData, versions 0-0
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefaultPrincipalData {
+    /// The principal type
+    pub r#type: String,
+    /// The principal name
+    pub name: String,
+    /// Whether the principal was authenticated by a delegation token on the forwarding broker.
+    pub token_authenticated: bool,
+}
+
+impl DefaultPrincipalData {
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 0;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let r#type = CompactString::deserialize(src)?;
+        let name = CompactString::deserialize(src)?;
+        let token_authenticated = src.get_u8("token_authenticated")? != 0;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            r#type,
+            name,
+            token_authenticated,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        CompactString::serialize_into(&self.r#type, b);
+        CompactString::serialize_into(&self.name, b);
+        b.put_u8(self.token_authenticated.into());
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
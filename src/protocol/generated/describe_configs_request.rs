@@ -0,0 +1,195 @@
+// Generated by `src/bin/codegen.rs` from `DescribeConfigsRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeConfigsRequest, versions 0-4
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeConfigsRequestData {
+    /// The resources whose configurations we want to describe.
+    pub resources: Vec<DescribeConfigsResource>,
+    /// True if we should include all synonyms.
+    pub include_synonyms: bool,
+    /// True if we should include configuration documentation.
+    pub include_documentation: bool,
+}
+
+impl DescribeConfigsRequestData {
+    pub const API_KEY: i16 = 32;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let resources = {
+            let len = if version >= 4 {
+                src.get_varint("resources")? - 1
+            } else {
+                i64::from(src.get_i32("resources")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeConfigsResource::deserialize(src, version)?);
+            }
+            items
+        };
+        let include_synonyms = if version >= 1 {
+            src.get_u8("include_synonyms")? != 0
+        } else {
+            false
+        };
+        let include_documentation = if version >= 3 {
+            src.get_u8("include_documentation")? != 0
+        } else {
+            false
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            resources,
+            include_synonyms,
+            include_documentation,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 4 {
+            VarInt::serialize_into(self.resources.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.resources.len() as i32);
+        }
+        for item in &self.resources {
+            item.serialize_into(b, version);
+        }
+        if version >= 1 {
+            b.put_u8(self.include_synonyms.into());
+        }
+        if version >= 3 {
+            b.put_u8(self.include_documentation.into());
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResource {
+    /// The resource type.
+    pub resource_type: i8,
+    /// The resource name.
+    pub resource_name: String,
+    /// The configuration keys to list, or null to list all configuration keys.
+    pub configuration_keys: Option<Vec<String>>,
+}
+
+impl Default for DescribeConfigsResource {
+    fn default() -> Self {
+        Self {
+            resource_type: 0,
+            resource_name: String::new(),
+            configuration_keys: Some(Vec::new()),
+        }
+    }
+}
+
+impl DescribeConfigsResource {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let resource_type = src.get_i8("resource_type")?;
+        let resource_name = if version >= 4 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let configuration_keys = {
+            let len = if version >= 4 {
+                src.get_varint("configuration_keys")? - 1
+            } else {
+                i64::from(src.get_i32("configuration_keys")?)
+            };
+            if len < 0 {
+                None
+            } else {
+                let mut items = Vec::with_capacity((len as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(if version >= 4 {
+                        CompactString::deserialize(src)?
+                    } else {
+                        NullableString::deserialize(src)?.unwrap_or_default()
+                    });
+                }
+                Some(items)
+            }
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            resource_type,
+            resource_name,
+            configuration_keys,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i8(self.resource_type);
+        if version >= 4 {
+            CompactString::serialize_into(&self.resource_name, b);
+        } else {
+            b.put_i16(self.resource_name.len() as i16);
+            b.put_slice(self.resource_name.as_bytes());
+        }
+        match &self.configuration_keys {
+            None => {
+                if version >= 4 {
+                    VarInt::serialize_into(0, b);
+                } else {
+                    b.put_i32(-1);
+                }
+            }
+            Some(items) => {
+                if version >= 4 {
+                    VarInt::serialize_into(items[..].len() as u64 + 1, b);
+                } else {
+                    b.put_i32(items[..].len() as i32);
+                }
+                for item in &items[..] {
+                    if version >= 4 {
+                        CompactString::serialize_into(item, b);
+                    } else {
+                        b.put_i16(item.len() as i16);
+                        b.put_slice(item.as_bytes());
+                    }
+                }
+            }
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
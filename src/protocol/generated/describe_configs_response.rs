@@ -0,0 +1,398 @@
+// Generated by `src/bin/codegen.rs` from `DescribeConfigsResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeConfigsResponse, versions 0-4
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeConfigsResponseData {
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// The results for each resource.
+    pub results: Vec<DescribeConfigsResult>,
+}
+
+impl DescribeConfigsResponseData {
+    pub const API_KEY: i16 = 32;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let results = {
+            let len = if version >= 4 {
+                src.get_varint("results")? - 1
+            } else {
+                i64::from(src.get_i32("results")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeConfigsResult::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            throttle_time_ms,
+            results,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.throttle_time_ms);
+        if version >= 4 {
+            VarInt::serialize_into(self.results.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.results.len() as i32);
+        }
+        for item in &self.results {
+            item.serialize_into(b, version);
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeConfigsResult {
+    /// The error code, or 0 if we were able to successfully describe the configurations.
+    pub error_code: i16,
+    /// The error message, or null if we were able to successfully describe the configurations.
+    pub error_message: Option<String>,
+    /// The resource type.
+    pub resource_type: i8,
+    /// The resource name.
+    pub resource_name: String,
+    /// Each listed configuration.
+    pub configs: Vec<DescribeConfigsResourceResult>,
+}
+
+impl DescribeConfigsResult {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let error_message = if version >= 4 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?
+        };
+        let resource_type = src.get_i8("resource_type")?;
+        let resource_name = if version >= 4 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let configs = {
+            let len = if version >= 4 {
+                src.get_varint("configs")? - 1
+            } else {
+                i64::from(src.get_i32("configs")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeConfigsResourceResult::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            error_message,
+            resource_type,
+            resource_name,
+            configs,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 4 {
+            CompactNullableString::serialize_into(self.error_message.as_deref(), b);
+        } else {
+            NullableString::serialize_into(self.error_message.as_deref(), b);
+        }
+        b.put_i8(self.resource_type);
+        if version >= 4 {
+            CompactString::serialize_into(&self.resource_name, b);
+        } else {
+            b.put_i16(self.resource_name.len() as i16);
+            b.put_slice(self.resource_name.as_bytes());
+        }
+        if version >= 4 {
+            VarInt::serialize_into(self.configs.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.configs.len() as i32);
+        }
+        for item in &self.configs {
+            item.serialize_into(b, version);
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeConfigsResourceResult {
+    /// The configuration name.
+    pub name: String,
+    /// The configuration value.
+    pub value: Option<String>,
+    /// True if the configuration is read-only.
+    pub read_only: bool,
+    /// True if the configuration is not set.
+    pub is_default: bool,
+    /// The configuration source.
+    pub config_source: i8,
+    /// True if this configuration is sensitive.
+    pub is_sensitive: bool,
+    /// The synonyms for this configuration key.
+    pub synonyms: Vec<DescribeConfigsSynonym>,
+    /// The configuration data type. Type can be one of the following values - BOOLEAN, STRING, INT, SHORT, LONG, DOUBLE, LIST, CLASS, PASSWORD
+    pub config_type: i8,
+    /// The configuration documentation.
+    pub documentation: Option<String>,
+}
+
+impl Default for DescribeConfigsResourceResult {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            value: None,
+            read_only: false,
+            is_default: false,
+            config_source: -1,
+            is_sensitive: false,
+            synonyms: Vec::new(),
+            config_type: 0,
+            documentation: None,
+        }
+    }
+}
+
+impl DescribeConfigsResourceResult {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 4 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let value = if version >= 4 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?
+        };
+        let read_only = src.get_u8("read_only")? != 0;
+        let is_default = if version <= 0 {
+            src.get_u8("is_default")? != 0
+        } else {
+            false
+        };
+        let config_source = if version >= 1 {
+            src.get_i8("config_source")?
+        } else {
+            -1
+        };
+        let is_sensitive = src.get_u8("is_sensitive")? != 0;
+        let synonyms = if version >= 1 {
+            {
+                let len = if version >= 4 {
+                    src.get_varint("synonyms")? - 1
+                } else {
+                    i64::from(src.get_i32("synonyms")?)
+                };
+                let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(DescribeConfigsSynonym::deserialize(src, version)?);
+                }
+                items
+            }
+        } else {
+            Vec::new()
+        };
+        let config_type = if version >= 3 {
+            src.get_i8("config_type")?
+        } else {
+            0
+        };
+        let documentation = if version >= 3 {
+            if version >= 4 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            name,
+            value,
+            read_only,
+            is_default,
+            config_source,
+            is_sensitive,
+            synonyms,
+            config_type,
+            documentation,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 4 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 4 {
+            CompactNullableString::serialize_into(self.value.as_deref(), b);
+        } else {
+            NullableString::serialize_into(self.value.as_deref(), b);
+        }
+        b.put_u8(self.read_only.into());
+        if version <= 0 {
+            b.put_u8(self.is_default.into());
+        }
+        if version >= 1 {
+            b.put_i8(self.config_source);
+        }
+        b.put_u8(self.is_sensitive.into());
+        if version >= 1 {
+            if version >= 4 {
+                VarInt::serialize_into(self.synonyms.len() as u64 + 1, b);
+            } else {
+                b.put_i32(self.synonyms.len() as i32);
+            }
+            for item in &self.synonyms {
+                item.serialize_into(b, version);
+            }
+        }
+        if version >= 3 {
+            b.put_i8(self.config_type);
+        }
+        if version >= 3 {
+            if version >= 4 {
+                CompactNullableString::serialize_into(self.documentation.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.documentation.as_deref(), b);
+            }
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeConfigsSynonym {
+    /// The synonym name.
+    pub name: String,
+    /// The synonym value.
+    pub value: Option<String>,
+    /// The synonym source.
+    pub source: i8,
+}
+
+impl DescribeConfigsSynonym {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 1 {
+            if version >= 4 {
+                CompactString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?.unwrap_or_default()
+            }
+        } else {
+            String::new()
+        };
+        let value = if version >= 1 {
+            if version >= 4 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        let source = if version >= 1 {
+            src.get_i8("source")?
+        } else {
+            0
+        };
+        if version >= 4 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            name,
+            value,
+            source,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 1 {
+            if version >= 4 {
+                CompactString::serialize_into(&self.name, b);
+            } else {
+                b.put_i16(self.name.len() as i16);
+                b.put_slice(self.name.as_bytes());
+            }
+        }
+        if version >= 1 {
+            if version >= 4 {
+                CompactNullableString::serialize_into(self.value.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.value.as_deref(), b);
+            }
+        }
+        if version >= 1 {
+            b.put_i8(self.source);
+        }
+        if version >= 4 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Generated by `src/bin/codegen.rs` from `DescribeDelegationTokenRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeDelegationTokenRequest, versions 0-3
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeDelegationTokenRequestData {
+    /// Each owner that we want to describe delegation tokens for, or null to describe all tokens.
+    pub owners: Option<Vec<DescribeDelegationTokenOwner>>,
+}
+
+impl Default for DescribeDelegationTokenRequestData {
+    fn default() -> Self {
+        Self {
+            owners: Some(Vec::new()),
+        }
+    }
+}
+
+impl DescribeDelegationTokenRequestData {
+    pub const API_KEY: i16 = 41;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let owners = {
+            let len = if version >= 2 {
+                src.get_varint("owners")? - 1
+            } else {
+                i64::from(src.get_i32("owners")?)
+            };
+            if len < 0 {
+                None
+            } else {
+                let mut items = Vec::with_capacity((len as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(DescribeDelegationTokenOwner::deserialize(src, version)?);
+                }
+                Some(items)
+            }
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { owners })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        match &self.owners {
+            None => {
+                if version >= 2 {
+                    VarInt::serialize_into(0, b);
+                } else {
+                    b.put_i32(-1);
+                }
+            }
+            Some(items) => {
+                if version >= 2 {
+                    VarInt::serialize_into(items[..].len() as u64 + 1, b);
+                } else {
+                    b.put_i32(items[..].len() as i32);
+                }
+                for item in &items[..] {
+                    item.serialize_into(b, version);
+                }
+            }
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeDelegationTokenOwner {
+    /// The owner principal type.
+    pub principal_type: String,
+    /// The owner principal name.
+    pub principal_name: String,
+}
+
+impl DescribeDelegationTokenOwner {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let principal_type = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let principal_name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            principal_type,
+            principal_name,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_type, b);
+        } else {
+            b.put_i16(self.principal_type.len() as i16);
+            b.put_slice(self.principal_type.as_bytes());
+        }
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_name, b);
+        } else {
+            b.put_i16(self.principal_name.len() as i16);
+            b.put_slice(self.principal_name.as_bytes());
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// Generated by `src/bin/codegen.rs` from `DescribeDelegationTokenResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeDelegationTokenResponse, versions 0-3
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeDelegationTokenResponseData {
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The tokens.
+    pub tokens: Vec<DescribedDelegationToken>,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+}
+
+impl DescribeDelegationTokenResponseData {
+    pub const API_KEY: i16 = 41;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 3;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let tokens = {
+            let len = if version >= 2 {
+                src.get_varint("tokens")? - 1
+            } else {
+                i64::from(src.get_i32("tokens")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribedDelegationToken::deserialize(src, version)?);
+            }
+            items
+        };
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            tokens,
+            throttle_time_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 2 {
+            VarInt::serialize_into(self.tokens.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.tokens.len() as i32);
+        }
+        for item in &self.tokens {
+            item.serialize_into(b, version);
+        }
+        b.put_i32(self.throttle_time_ms);
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribedDelegationToken {
+    /// The token principal type.
+    pub principal_type: String,
+    /// The token principal name.
+    pub principal_name: String,
+    /// The principal type of the requester of the token.
+    pub token_requester_principal_type: String,
+    /// The principal type of the requester of the token.
+    pub token_requester_principal_name: String,
+    /// The token issue timestamp in milliseconds.
+    pub issue_timestamp: i64,
+    /// The token expiry timestamp in milliseconds.
+    pub expiry_timestamp: i64,
+    /// The token maximum timestamp length in milliseconds.
+    pub max_timestamp: i64,
+    /// The token ID.
+    pub token_id: String,
+    /// The token HMAC.
+    pub hmac: Vec<u8>,
+    /// Those who are able to renew this token before it expires.
+    pub renewers: Vec<DescribedDelegationTokenRenewer>,
+}
+
+impl DescribedDelegationToken {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let principal_type = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let principal_name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let token_requester_principal_type = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        let token_requester_principal_name = if version >= 3 {
+            CompactString::deserialize(src)?
+        } else {
+            String::new()
+        };
+        let issue_timestamp = src.get_i64("issue_timestamp")?;
+        let expiry_timestamp = src.get_i64("expiry_timestamp")?;
+        let max_timestamp = src.get_i64("max_timestamp")?;
+        let token_id = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let hmac = if version >= 2 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("hmac")?;
+                src.get_bytes("hmac", len.max(0) as usize)?.to_vec()
+            }
+        };
+        let renewers = {
+            let len = if version >= 2 {
+                src.get_varint("renewers")? - 1
+            } else {
+                i64::from(src.get_i32("renewers")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribedDelegationTokenRenewer::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            principal_type,
+            principal_name,
+            token_requester_principal_type,
+            token_requester_principal_name,
+            issue_timestamp,
+            expiry_timestamp,
+            max_timestamp,
+            token_id,
+            hmac,
+            renewers,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_type, b);
+        } else {
+            b.put_i16(self.principal_type.len() as i16);
+            b.put_slice(self.principal_type.as_bytes());
+        }
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_name, b);
+        } else {
+            b.put_i16(self.principal_name.len() as i16);
+            b.put_slice(self.principal_name.as_bytes());
+        }
+        if version >= 3 {
+            CompactString::serialize_into(&self.token_requester_principal_type, b);
+        }
+        if version >= 3 {
+            CompactString::serialize_into(&self.token_requester_principal_name, b);
+        }
+        b.put_i64(self.issue_timestamp);
+        b.put_i64(self.expiry_timestamp);
+        b.put_i64(self.max_timestamp);
+        if version >= 2 {
+            CompactString::serialize_into(&self.token_id, b);
+        } else {
+            b.put_i16(self.token_id.len() as i16);
+            b.put_slice(self.token_id.as_bytes());
+        }
+        if version >= 2 {
+            CompactNullableBytes::serialize_into(&self.hmac, b);
+        } else {
+            b.put_i32(self.hmac.len() as i32);
+            b.put_slice(&self.hmac);
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.renewers.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.renewers.len() as i32);
+        }
+        for item in &self.renewers {
+            item.serialize_into(b, version);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribedDelegationTokenRenewer {
+    /// The renewer principal type
+    pub principal_type: String,
+    /// The renewer principal name
+    pub principal_name: String,
+}
+
+impl DescribedDelegationTokenRenewer {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let principal_type = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let principal_name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            principal_type,
+            principal_name,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_type, b);
+        } else {
+            b.put_i16(self.principal_type.len() as i16);
+            b.put_slice(self.principal_type.as_bytes());
+        }
+        if version >= 2 {
+            CompactString::serialize_into(&self.principal_name, b);
+        } else {
+            b.put_i16(self.principal_name.len() as i16);
+            b.put_slice(self.principal_name.as_bytes());
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// Generated by `src/bin/codegen.rs` from `DescribeLogDirsRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeLogDirsRequest, versions 0-4
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeLogDirsRequestData {
+    /// Each topic that we want to describe log directories for, or null for all topics.
+    pub topics: Option<Vec<DescribableLogDirTopic>>,
+}
+
+impl Default for DescribeLogDirsRequestData {
+    fn default() -> Self {
+        Self {
+            topics: Some(Vec::new()),
+        }
+    }
+}
+
+impl DescribeLogDirsRequestData {
+    pub const API_KEY: i16 = 35;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let topics = {
+            let len = if version >= 2 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            if len < 0 {
+                None
+            } else {
+                let mut items = Vec::with_capacity((len as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(DescribableLogDirTopic::deserialize(src, version)?);
+                }
+                Some(items)
+            }
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { topics })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        match &self.topics {
+            None => {
+                if version >= 2 {
+                    VarInt::serialize_into(0, b);
+                } else {
+                    b.put_i32(-1);
+                }
+            }
+            Some(items) => {
+                if version >= 2 {
+                    VarInt::serialize_into(items[..].len() as u64 + 1, b);
+                } else {
+                    b.put_i32(items[..].len() as i32);
+                }
+                for item in &items[..] {
+                    item.serialize_into(b, version);
+                }
+            }
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribableLogDirTopic {
+    /// The topic name
+    pub topic: String,
+    /// The partition indexes.
+    pub partitions: Vec<i32>,
+}
+
+impl DescribableLogDirTopic {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let topic = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partitions = {
+            let len = if version >= 2 {
+                src.get_varint("partitions")? - 1
+            } else {
+                i64::from(src.get_i32("partitions")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(src.get_i32("partitions")?);
+            }
+            items
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { topic, partitions })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.topic, b);
+        } else {
+            b.put_i16(self.topic.len() as i16);
+            b.put_slice(self.topic.as_bytes());
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.partitions.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partitions.len() as i32);
+        }
+        for item in &self.partitions {
+            b.put_i32(*item);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// Generated by `src/bin/codegen.rs` from `DescribeLogDirsResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// DescribeLogDirsResponse, versions 0-4
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeLogDirsResponseData {
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The log directories.
+    pub results: Vec<DescribeLogDirsResult>,
+}
+
+impl DescribeLogDirsResponseData {
+    pub const API_KEY: i16 = 35;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let error_code = if version >= 3 {
+            src.get_i16("error_code")?
+        } else {
+            0
+        };
+        let results = {
+            let len = if version >= 2 {
+                src.get_varint("results")? - 1
+            } else {
+                i64::from(src.get_i32("results")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeLogDirsResult::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            throttle_time_ms,
+            error_code,
+            results,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.throttle_time_ms);
+        if version >= 3 {
+            b.put_i16(self.error_code);
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.results.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.results.len() as i32);
+        }
+        for item in &self.results {
+            item.serialize_into(b, version);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescribeLogDirsResult {
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The absolute log directory path.
+    pub log_dir: String,
+    /// Each topic.
+    pub topics: Vec<DescribeLogDirsTopic>,
+    /// The total size in bytes of the volume the log directory is in. This value does not include the size of data stored in remote storage.
+    pub total_bytes: i64,
+    /// The usable size in bytes of the volume the log directory is in. This value does not include the size of data stored in remote storage.
+    pub usable_bytes: i64,
+}
+
+impl Default for DescribeLogDirsResult {
+    fn default() -> Self {
+        Self {
+            error_code: 0,
+            log_dir: String::new(),
+            topics: Vec::new(),
+            total_bytes: -1,
+            usable_bytes: -1,
+        }
+    }
+}
+
+impl DescribeLogDirsResult {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let log_dir = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let topics = {
+            let len = if version >= 2 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeLogDirsTopic::deserialize(src, version)?);
+            }
+            items
+        };
+        let total_bytes = if version >= 4 {
+            src.get_i64("total_bytes")?
+        } else {
+            -1
+        };
+        let usable_bytes = if version >= 4 {
+            src.get_i64("usable_bytes")?
+        } else {
+            -1
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            log_dir,
+            topics,
+            total_bytes,
+            usable_bytes,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 2 {
+            CompactString::serialize_into(&self.log_dir, b);
+        } else {
+            b.put_i16(self.log_dir.len() as i16);
+            b.put_slice(self.log_dir.as_bytes());
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.topics.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.topics.len() as i32);
+        }
+        for item in &self.topics {
+            item.serialize_into(b, version);
+        }
+        if version >= 4 {
+            b.put_i64(self.total_bytes);
+        }
+        if version >= 4 {
+            b.put_i64(self.usable_bytes);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeLogDirsTopic {
+    /// The topic name.
+    pub name: String,
+    /// The partitions.
+    pub partitions: Vec<DescribeLogDirsPartition>,
+}
+
+impl DescribeLogDirsTopic {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 2 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partitions = {
+            let len = if version >= 2 {
+                src.get_varint("partitions")? - 1
+            } else {
+                i64::from(src.get_i32("partitions")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(DescribeLogDirsPartition::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { name, partitions })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 2 {
+            VarInt::serialize_into(self.partitions.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partitions.len() as i32);
+        }
+        for item in &self.partitions {
+            item.serialize_into(b, version);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DescribeLogDirsPartition {
+    /// The partition index.
+    pub partition_index: i32,
+    /// The size of the log segments in this partition in bytes.
+    pub partition_size: i64,
+    /// The lag of the log's LEO w.r.t. partition's HW (if it is the current log for the partition) or current replica's LEO (if it is the future log for the partition).
+    pub offset_lag: i64,
+    /// True if this log is created by AlterReplicaLogDirsRequest and will replace the current log of the replica in the future.
+    pub is_future_key: bool,
+}
+
+impl DescribeLogDirsPartition {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let partition_index = src.get_i32("partition_index")?;
+        let partition_size = src.get_i64("partition_size")?;
+        let offset_lag = src.get_i64("offset_lag")?;
+        let is_future_key = src.get_u8("is_future_key")? != 0;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            partition_index,
+            partition_size,
+            offset_lag,
+            is_future_key,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.partition_index);
+        b.put_i64(self.partition_size);
+        b.put_i64(self.offset_lag);
+        b.put_u8(self.is_future_key.into());
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// Generated by `src/bin/codegen.rs` from `EnvelopeRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// EnvelopeRequest, versions 0-0
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvelopeRequestData {
+    /// The embedded request header and data.
+    pub request_data: Vec<u8>,
+    /// Value of the initial client principal when the request is redirected by a broker.
+    pub request_principal: Vec<u8>,
+    /// The original client's address in bytes.
+    pub client_host_address: Vec<u8>,
+}
+
+impl EnvelopeRequestData {
+    pub const API_KEY: i16 = 58;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 0;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let request_data = CompactNullableBytes::deserialize(src)?;
+        let request_principal = CompactNullableBytes::deserialize(src)?;
+        let client_host_address = CompactNullableBytes::deserialize(src)?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            request_data,
+            request_principal,
+            client_host_address,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        CompactNullableBytes::serialize_into(&self.request_data, b);
+        CompactNullableBytes::serialize_into(&self.request_principal, b);
+        CompactNullableBytes::serialize_into(&self.client_host_address, b);
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
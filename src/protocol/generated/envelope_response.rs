@@ -0,0 +1,54 @@
+// Generated by `src/bin/codegen.rs` from `EnvelopeResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// EnvelopeResponse, versions 0-0
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvelopeResponseData {
+    /// The embedded response header and data.
+    pub response_data: Vec<u8>,
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+}
+
+impl EnvelopeResponseData {
+    pub const API_KEY: i16 = 58;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 0;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let response_data = CompactNullableBytes::deserialize(src)?;
+        let error_code = src.get_i16("error_code")?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(Self {
+            response_data,
+            error_code,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        CompactNullableBytes::serialize_into(&self.response_data, b);
+        b.put_i16(self.error_code);
+        TaggedFields::serialize_into(b); // tag buffer
+    }
+}
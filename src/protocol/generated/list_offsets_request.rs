@@ -0,0 +1,216 @@
+// Generated by `src/bin/codegen.rs` from `ListOffsetsRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ListOffsetsRequest, versions 0-8
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOffsetsRequestData {
+    /// The broker ID of the requester, or -1 if this request is being made by a normal consumer.
+    pub replica_id: i32,
+    /// This setting controls the visibility of transactional records. Using READ_UNCOMMITTED (isolation_level = 0) makes all records visible. With READ_COMMITTED (isolation_level = 1), non-transactional and COMMITTED transactional records are visible. To be more concrete, READ_COMMITTED returns all data from offsets smaller than the current LSO (last stable offset), and enables the inclusion of the list of aborted transactions in the result, which allows consumers to discard ABORTED transactional records
+    pub isolation_level: i8,
+    /// Each topic in the request.
+    pub topics: Vec<ListOffsetsTopic>,
+}
+
+impl ListOffsetsRequestData {
+    pub const API_KEY: i16 = 2;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 8;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let replica_id = src.get_i32("replica_id")?;
+        let isolation_level = if version >= 2 {
+            src.get_i8("isolation_level")?
+        } else {
+            0
+        };
+        let topics = {
+            let len = if version >= 6 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(ListOffsetsTopic::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            replica_id,
+            isolation_level,
+            topics,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.replica_id);
+        if version >= 2 {
+            b.put_i8(self.isolation_level);
+        }
+        if version >= 6 {
+            VarInt::serialize_into(self.topics.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.topics.len() as i32);
+        }
+        for item in &self.topics {
+            item.serialize_into(b, version);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOffsetsTopic {
+    /// The topic name.
+    pub name: String,
+    /// Each partition in the request.
+    pub partitions: Vec<ListOffsetsPartition>,
+}
+
+impl ListOffsetsTopic {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 6 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partitions = {
+            let len = if version >= 6 {
+                src.get_varint("partitions")? - 1
+            } else {
+                i64::from(src.get_i32("partitions")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(ListOffsetsPartition::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { name, partitions })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 6 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 6 {
+            VarInt::serialize_into(self.partitions.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partitions.len() as i32);
+        }
+        for item in &self.partitions {
+            item.serialize_into(b, version);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsPartition {
+    /// The partition index.
+    pub partition_index: i32,
+    /// The current leader epoch.
+    pub current_leader_epoch: i32,
+    /// The current timestamp.
+    pub timestamp: i64,
+    /// The maximum number of offsets to report.
+    pub max_num_offsets: i32,
+}
+
+impl Default for ListOffsetsPartition {
+    fn default() -> Self {
+        Self {
+            partition_index: 0,
+            current_leader_epoch: -1,
+            timestamp: 0,
+            max_num_offsets: 1,
+        }
+    }
+}
+
+impl ListOffsetsPartition {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let partition_index = src.get_i32("partition_index")?;
+        let current_leader_epoch = if version >= 4 {
+            src.get_i32("current_leader_epoch")?
+        } else {
+            -1
+        };
+        let timestamp = src.get_i64("timestamp")?;
+        let max_num_offsets = if version <= 0 {
+            src.get_i32("max_num_offsets")?
+        } else {
+            1
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            partition_index,
+            current_leader_epoch,
+            timestamp,
+            max_num_offsets,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.partition_index);
+        if version >= 4 {
+            b.put_i32(self.current_leader_epoch);
+        }
+        b.put_i64(self.timestamp);
+        if version <= 0 {
+            b.put_i32(self.max_num_offsets);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,245 @@
+// Generated by `src/bin/codegen.rs` from `ListOffsetsResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ListOffsetsResponse, versions 0-8
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOffsetsResponseData {
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// Each topic in the response.
+    pub topics: Vec<ListOffsetsTopicResponse>,
+}
+
+impl ListOffsetsResponseData {
+    pub const API_KEY: i16 = 2;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 8;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = if version >= 2 {
+            src.get_i32("throttle_time_ms")?
+        } else {
+            0
+        };
+        let topics = {
+            let len = if version >= 6 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(ListOffsetsTopicResponse::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            throttle_time_ms,
+            topics,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            b.put_i32(self.throttle_time_ms);
+        }
+        if version >= 6 {
+            VarInt::serialize_into(self.topics.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.topics.len() as i32);
+        }
+        for item in &self.topics {
+            item.serialize_into(b, version);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOffsetsTopicResponse {
+    /// The topic name.
+    pub name: String,
+    /// Each partition in the response.
+    pub partitions: Vec<ListOffsetsPartitionResponse>,
+}
+
+impl ListOffsetsTopicResponse {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 6 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partitions = {
+            let len = if version >= 6 {
+                src.get_varint("partitions")? - 1
+            } else {
+                i64::from(src.get_i32("partitions")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(ListOffsetsPartitionResponse::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { name, partitions })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 6 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 6 {
+            VarInt::serialize_into(self.partitions.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partitions.len() as i32);
+        }
+        for item in &self.partitions {
+            item.serialize_into(b, version);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListOffsetsPartitionResponse {
+    /// The partition index.
+    pub partition_index: i32,
+    /// The partition error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The result offsets.
+    pub old_style_offsets: Vec<i64>,
+    /// The timestamp associated with the returned offset.
+    pub timestamp: i64,
+    /// The returned offset.
+    pub offset: i64,
+    /// The leader epoch associated with the returned offset.
+    pub leader_epoch: i32,
+}
+
+impl Default for ListOffsetsPartitionResponse {
+    fn default() -> Self {
+        Self {
+            partition_index: 0,
+            error_code: 0,
+            old_style_offsets: Vec::new(),
+            timestamp: -1,
+            offset: -1,
+            leader_epoch: -1,
+        }
+    }
+}
+
+impl ListOffsetsPartitionResponse {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let partition_index = src.get_i32("partition_index")?;
+        let error_code = src.get_i16("error_code")?;
+        let old_style_offsets = if version <= 0 {
+            {
+                let len = i64::from(src.get_i32("old_style_offsets")?);
+                let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(src.get_i64("old_style_offsets")?);
+                }
+                items
+            }
+        } else {
+            Vec::new()
+        };
+        let timestamp = if version >= 1 {
+            src.get_i64("timestamp")?
+        } else {
+            -1
+        };
+        let offset = if version >= 1 {
+            src.get_i64("offset")?
+        } else {
+            -1
+        };
+        let leader_epoch = if version >= 4 {
+            src.get_i32("leader_epoch")?
+        } else {
+            -1
+        };
+        if version >= 6 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            partition_index,
+            error_code,
+            old_style_offsets,
+            timestamp,
+            offset,
+            leader_epoch,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.partition_index);
+        b.put_i16(self.error_code);
+        if version <= 0 {
+            b.put_i32(self.old_style_offsets.len() as i32);
+            for item in &self.old_style_offsets {
+                b.put_i64(*item);
+            }
+        }
+        if version >= 1 {
+            b.put_i64(self.timestamp);
+        }
+        if version >= 1 {
+            b.put_i64(self.offset);
+        }
+        if version >= 4 {
+            b.put_i32(self.leader_epoch);
+        }
+        if version >= 6 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
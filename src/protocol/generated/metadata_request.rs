@@ -0,0 +1,186 @@
+// Generated by `src/bin/codegen.rs` from `MetadataRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// MetadataRequest, versions 0-12
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataRequestData {
+    /// The topics to fetch metadata for.
+    pub topics: Option<Vec<MetadataRequestTopic>>,
+    /// If this is true, the broker may auto-create topics that we requested which do not already exist, if it is configured to do so.
+    pub allow_auto_topic_creation: bool,
+    /// Whether to include cluster authorized operations.
+    pub include_cluster_authorized_operations: bool,
+    /// Whether to include topic authorized operations.
+    pub include_topic_authorized_operations: bool,
+}
+
+impl Default for MetadataRequestData {
+    fn default() -> Self {
+        Self {
+            topics: Some(Vec::new()),
+            allow_auto_topic_creation: true,
+            include_cluster_authorized_operations: false,
+            include_topic_authorized_operations: false,
+        }
+    }
+}
+
+impl MetadataRequestData {
+    pub const API_KEY: i16 = 3;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 12;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let topics = {
+            let len = if version >= 9 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            if len < 0 {
+                None
+            } else {
+                let mut items = Vec::with_capacity((len as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(MetadataRequestTopic::deserialize(src, version)?);
+                }
+                Some(items)
+            }
+        };
+        let allow_auto_topic_creation = if version >= 4 {
+            src.get_u8("allow_auto_topic_creation")? != 0
+        } else {
+            true
+        };
+        let include_cluster_authorized_operations = if (8..=10).contains(&version) {
+            src.get_u8("include_cluster_authorized_operations")? != 0
+        } else {
+            false
+        };
+        let include_topic_authorized_operations = if version >= 8 {
+            src.get_u8("include_topic_authorized_operations")? != 0
+        } else {
+            false
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            topics,
+            allow_auto_topic_creation,
+            include_cluster_authorized_operations,
+            include_topic_authorized_operations,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        match &self.topics {
+            None => {
+                if version >= 9 {
+                    VarInt::serialize_into(0, b);
+                } else {
+                    b.put_i32(-1);
+                }
+            }
+            Some(items) => {
+                if version >= 9 {
+                    VarInt::serialize_into(items[..].len() as u64 + 1, b);
+                } else {
+                    b.put_i32(items[..].len() as i32);
+                }
+                for item in &items[..] {
+                    item.serialize_into(b, version);
+                }
+            }
+        }
+        if version >= 4 {
+            b.put_u8(self.allow_auto_topic_creation.into());
+        }
+        if (8..=10).contains(&version) {
+            b.put_u8(self.include_cluster_authorized_operations.into());
+        }
+        if version >= 8 {
+            b.put_u8(self.include_topic_authorized_operations.into());
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataRequestTopic {
+    /// The topic id.
+    pub topic_id: String,
+    /// The topic name.
+    pub name: Option<String>,
+}
+
+impl Default for MetadataRequestTopic {
+    fn default() -> Self {
+        Self {
+            topic_id: "00000000-0000-0000-0000-000000000000".to_string(),
+            name: None,
+        }
+    }
+}
+
+impl MetadataRequestTopic {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let topic_id = if version >= 10 {
+            Uuid::deserialize(src)?
+        } else {
+            "00000000-0000-0000-0000-000000000000".to_string()
+        };
+        let name = if version >= 9 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { topic_id, name })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 10 {
+            Uuid::serialize_into(&self.topic_id, b);
+        }
+        if version >= 9 {
+            CompactNullableString::serialize_into(self.name.as_deref(), b);
+        } else {
+            NullableString::serialize_into(self.name.as_deref(), b);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
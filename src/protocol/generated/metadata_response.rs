@@ -0,0 +1,482 @@
+// Generated by `src/bin/codegen.rs` from `MetadataResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// MetadataResponse, versions 0-12
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataResponseData {
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+    /// A list of brokers present in the cluster.
+    pub brokers: Vec<MetadataResponseBroker>,
+    /// The cluster ID that responding broker belongs to.
+    pub cluster_id: Option<String>,
+    /// The ID of the controller broker.
+    pub controller_id: i32,
+    /// Each topic in the response.
+    pub topics: Vec<MetadataResponseTopic>,
+    /// 32-bit bitfield to represent authorized operations for this cluster.
+    pub cluster_authorized_operations: i32,
+}
+
+impl Default for MetadataResponseData {
+    fn default() -> Self {
+        Self {
+            throttle_time_ms: 0,
+            brokers: Vec::new(),
+            cluster_id: None,
+            controller_id: -1,
+            topics: Vec::new(),
+            cluster_authorized_operations: -2147483648,
+        }
+    }
+}
+
+impl MetadataResponseData {
+    pub const API_KEY: i16 = 3;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 12;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = if version >= 3 {
+            src.get_i32("throttle_time_ms")?
+        } else {
+            0
+        };
+        let brokers = {
+            let len = if version >= 9 {
+                src.get_varint("brokers")? - 1
+            } else {
+                i64::from(src.get_i32("brokers")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(MetadataResponseBroker::deserialize(src, version)?);
+            }
+            items
+        };
+        let cluster_id = if version >= 2 {
+            if version >= 9 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        let controller_id = if version >= 1 {
+            src.get_i32("controller_id")?
+        } else {
+            -1
+        };
+        let topics = {
+            let len = if version >= 9 {
+                src.get_varint("topics")? - 1
+            } else {
+                i64::from(src.get_i32("topics")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(MetadataResponseTopic::deserialize(src, version)?);
+            }
+            items
+        };
+        let cluster_authorized_operations = if (8..=10).contains(&version) {
+            src.get_i32("cluster_authorized_operations")?
+        } else {
+            -2147483648
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            throttle_time_ms,
+            brokers,
+            cluster_id,
+            controller_id,
+            topics,
+            cluster_authorized_operations,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 3 {
+            b.put_i32(self.throttle_time_ms);
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.brokers.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.brokers.len() as i32);
+        }
+        for item in &self.brokers {
+            item.serialize_into(b, version);
+        }
+        if version >= 2 {
+            if version >= 9 {
+                CompactNullableString::serialize_into(self.cluster_id.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.cluster_id.as_deref(), b);
+            }
+        }
+        if version >= 1 {
+            b.put_i32(self.controller_id);
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.topics.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.topics.len() as i32);
+        }
+        for item in &self.topics {
+            item.serialize_into(b, version);
+        }
+        if (8..=10).contains(&version) {
+            b.put_i32(self.cluster_authorized_operations);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataResponseBroker {
+    /// The broker ID.
+    pub node_id: i32,
+    /// The broker hostname.
+    pub host: String,
+    /// The broker port.
+    pub port: i32,
+    /// The rack of the broker, or null if it has not been assigned to a rack.
+    pub rack: Option<String>,
+}
+
+impl MetadataResponseBroker {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let node_id = src.get_i32("node_id")?;
+        let host = if version >= 9 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let port = src.get_i32("port")?;
+        let rack = if version >= 1 {
+            if version >= 9 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            node_id,
+            host,
+            port,
+            rack,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.node_id);
+        if version >= 9 {
+            CompactString::serialize_into(&self.host, b);
+        } else {
+            b.put_i16(self.host.len() as i16);
+            b.put_slice(self.host.as_bytes());
+        }
+        b.put_i32(self.port);
+        if version >= 1 {
+            if version >= 9 {
+                CompactNullableString::serialize_into(self.rack.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.rack.as_deref(), b);
+            }
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataResponseTopic {
+    /// The topic error, or 0 if there was no error.
+    pub error_code: i16,
+    /// The topic name. Null for non-existing topics queried by ID. This is never null when ErrorCode is zero. One of Name and TopicId is always populated.
+    pub name: Option<String>,
+    /// The topic id. Zero for non-existing topics queried by name. This is never zero when ErrorCode is zero. One of Name and TopicId is always populated.
+    pub topic_id: String,
+    /// True if the topic is internal.
+    pub is_internal: bool,
+    /// Each partition in the topic.
+    pub partitions: Vec<MetadataResponsePartition>,
+    /// 32-bit bitfield to represent authorized operations for this topic.
+    pub topic_authorized_operations: i32,
+}
+
+impl Default for MetadataResponseTopic {
+    fn default() -> Self {
+        Self {
+            error_code: 0,
+            name: None,
+            topic_id: "00000000-0000-0000-0000-000000000000".to_string(),
+            is_internal: false,
+            partitions: Vec::new(),
+            topic_authorized_operations: -2147483648,
+        }
+    }
+}
+
+impl MetadataResponseTopic {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let name = if version >= 9 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?
+        };
+        let topic_id = if version >= 10 {
+            Uuid::deserialize(src)?
+        } else {
+            "00000000-0000-0000-0000-000000000000".to_string()
+        };
+        let is_internal = if version >= 1 {
+            src.get_u8("is_internal")? != 0
+        } else {
+            false
+        };
+        let partitions = {
+            let len = if version >= 9 {
+                src.get_varint("partitions")? - 1
+            } else {
+                i64::from(src.get_i32("partitions")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(MetadataResponsePartition::deserialize(src, version)?);
+            }
+            items
+        };
+        let topic_authorized_operations = if version >= 8 {
+            src.get_i32("topic_authorized_operations")?
+        } else {
+            -2147483648
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            name,
+            topic_id,
+            is_internal,
+            partitions,
+            topic_authorized_operations,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 9 {
+            CompactNullableString::serialize_into(self.name.as_deref(), b);
+        } else {
+            NullableString::serialize_into(self.name.as_deref(), b);
+        }
+        if version >= 10 {
+            Uuid::serialize_into(&self.topic_id, b);
+        }
+        if version >= 1 {
+            b.put_u8(self.is_internal.into());
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.partitions.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partitions.len() as i32);
+        }
+        for item in &self.partitions {
+            item.serialize_into(b, version);
+        }
+        if version >= 8 {
+            b.put_i32(self.topic_authorized_operations);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataResponsePartition {
+    /// The partition error, or 0 if there was no error.
+    pub error_code: i16,
+    /// The partition index.
+    pub partition_index: i32,
+    /// The ID of the leader broker.
+    pub leader_id: i32,
+    /// The leader epoch of this partition.
+    pub leader_epoch: i32,
+    /// The set of all nodes that host this partition.
+    pub replica_nodes: Vec<i32>,
+    /// The set of nodes that are in sync with the leader for this partition.
+    pub isr_nodes: Vec<i32>,
+    /// The set of offline replicas of this partition.
+    pub offline_replicas: Vec<i32>,
+}
+
+impl Default for MetadataResponsePartition {
+    fn default() -> Self {
+        Self {
+            error_code: 0,
+            partition_index: 0,
+            leader_id: 0,
+            leader_epoch: -1,
+            replica_nodes: Vec::new(),
+            isr_nodes: Vec::new(),
+            offline_replicas: Vec::new(),
+        }
+    }
+}
+
+impl MetadataResponsePartition {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let partition_index = src.get_i32("partition_index")?;
+        let leader_id = src.get_i32("leader_id")?;
+        let leader_epoch = if version >= 7 {
+            src.get_i32("leader_epoch")?
+        } else {
+            -1
+        };
+        let replica_nodes = {
+            let len = if version >= 9 {
+                src.get_varint("replica_nodes")? - 1
+            } else {
+                i64::from(src.get_i32("replica_nodes")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(src.get_i32("replica_nodes")?);
+            }
+            items
+        };
+        let isr_nodes = {
+            let len = if version >= 9 {
+                src.get_varint("isr_nodes")? - 1
+            } else {
+                i64::from(src.get_i32("isr_nodes")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(src.get_i32("isr_nodes")?);
+            }
+            items
+        };
+        let offline_replicas = if version >= 5 {
+            {
+                let len = if version >= 9 {
+                    src.get_varint("offline_replicas")? - 1
+                } else {
+                    i64::from(src.get_i32("offline_replicas")?)
+                };
+                let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(src.get_i32("offline_replicas")?);
+                }
+                items
+            }
+        } else {
+            Vec::new()
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            partition_index,
+            leader_id,
+            leader_epoch,
+            replica_nodes,
+            isr_nodes,
+            offline_replicas,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        b.put_i32(self.partition_index);
+        b.put_i32(self.leader_id);
+        if version >= 7 {
+            b.put_i32(self.leader_epoch);
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.replica_nodes.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.replica_nodes.len() as i32);
+        }
+        for item in &self.replica_nodes {
+            b.put_i32(*item);
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.isr_nodes.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.isr_nodes.len() as i32);
+        }
+        for item in &self.isr_nodes {
+            b.put_i32(*item);
+        }
+        if version >= 5 {
+            if version >= 9 {
+                VarInt::serialize_into(self.offline_replicas.len() as u64 + 1, b);
+            } else {
+                b.put_i32(self.offline_replicas.len() as i32);
+            }
+            for item in &self.offline_replicas {
+                b.put_i32(*item);
+            }
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
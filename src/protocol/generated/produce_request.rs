@@ -0,0 +1,208 @@
+// Generated by `src/bin/codegen.rs` from `ProduceRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ProduceRequest, versions 0-10
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProduceRequestData {
+    /// The transactional ID, or null if the producer is not transactional.
+    pub transactional_id: Option<String>,
+    /// The number of acknowledgments the producer requires the leader to have received before considering a request complete. Allowed values: 0 for no acknowledgments, 1 for only the leader and -1 for the full ISR.
+    pub acks: i16,
+    /// The timeout to await a response in milliseconds.
+    pub timeout_ms: i32,
+    /// Each topic to produce to.
+    pub topic_data: Vec<TopicProduceData>,
+}
+
+impl ProduceRequestData {
+    pub const API_KEY: i16 = 0;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 10;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let transactional_id = if version >= 3 {
+            if version >= 9 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        let acks = src.get_i16("acks")?;
+        let timeout_ms = src.get_i32("timeout_ms")?;
+        let topic_data = {
+            let len = if version >= 9 {
+                src.get_varint("topic_data")? - 1
+            } else {
+                i64::from(src.get_i32("topic_data")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(TopicProduceData::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topic_data,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 3 {
+            if version >= 9 {
+                CompactNullableString::serialize_into(self.transactional_id.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.transactional_id.as_deref(), b);
+            }
+        }
+        b.put_i16(self.acks);
+        b.put_i32(self.timeout_ms);
+        if version >= 9 {
+            VarInt::serialize_into(self.topic_data.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.topic_data.len() as i32);
+        }
+        for item in &self.topic_data {
+            item.serialize_into(b, version);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopicProduceData {
+    /// The topic name.
+    pub name: String,
+    /// Each partition to produce to.
+    pub partition_data: Vec<PartitionProduceData>,
+}
+
+impl TopicProduceData {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 9 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partition_data = {
+            let len = if version >= 9 {
+                src.get_varint("partition_data")? - 1
+            } else {
+                i64::from(src.get_i32("partition_data")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(PartitionProduceData::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            name,
+            partition_data,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 9 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.partition_data.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partition_data.len() as i32);
+        }
+        for item in &self.partition_data {
+            item.serialize_into(b, version);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartitionProduceData {
+    /// The partition index.
+    pub index: i32,
+    /// The record data to be produced.
+    pub records: Vec<u8>,
+}
+
+impl PartitionProduceData {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let index = src.get_i32("index")?;
+        let records = if version >= 9 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("records")?;
+                src.get_bytes("records", len.max(0) as usize)?.to_vec()
+            }
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { index, records })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.index);
+        if version >= 9 {
+            CompactNullableBytes::serialize_into(&self.records, b);
+        } else {
+            b.put_i32(self.records.len() as i32);
+            b.put_slice(&self.records);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
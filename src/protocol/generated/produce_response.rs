@@ -0,0 +1,326 @@
+// Generated by `src/bin/codegen.rs` from `ProduceResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// ProduceResponse, versions 0-10
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProduceResponseData {
+    /// Each produce response
+    pub responses: Vec<TopicProduceResponse>,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+}
+
+impl ProduceResponseData {
+    pub const API_KEY: i16 = 0;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 10;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let responses = {
+            let len = if version >= 9 {
+                src.get_varint("responses")? - 1
+            } else {
+                i64::from(src.get_i32("responses")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(TopicProduceResponse::deserialize(src, version)?);
+            }
+            items
+        };
+        let throttle_time_ms = if version >= 1 {
+            src.get_i32("throttle_time_ms")?
+        } else {
+            0
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            responses,
+            throttle_time_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 9 {
+            VarInt::serialize_into(self.responses.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.responses.len() as i32);
+        }
+        for item in &self.responses {
+            item.serialize_into(b, version);
+        }
+        if version >= 1 {
+            b.put_i32(self.throttle_time_ms);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopicProduceResponse {
+    /// The topic name
+    pub name: String,
+    /// Each partition that we produced to within the topic.
+    pub partition_responses: Vec<PartitionProduceResponse>,
+}
+
+impl TopicProduceResponse {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let name = if version >= 9 {
+            CompactString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?.unwrap_or_default()
+        };
+        let partition_responses = {
+            let len = if version >= 9 {
+                src.get_varint("partition_responses")? - 1
+            } else {
+                i64::from(src.get_i32("partition_responses")?)
+            };
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(PartitionProduceResponse::deserialize(src, version)?);
+            }
+            items
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            name,
+            partition_responses,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 9 {
+            CompactString::serialize_into(&self.name, b);
+        } else {
+            b.put_i16(self.name.len() as i16);
+            b.put_slice(self.name.as_bytes());
+        }
+        if version >= 9 {
+            VarInt::serialize_into(self.partition_responses.len() as u64 + 1, b);
+        } else {
+            b.put_i32(self.partition_responses.len() as i32);
+        }
+        for item in &self.partition_responses {
+            item.serialize_into(b, version);
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionProduceResponse {
+    /// The partition index.
+    pub index: i32,
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The base offset.
+    pub base_offset: i64,
+    /// The timestamp returned by broker after appending the messages. If CreateTime is used for the topic, the timestamp will be -1.  If LogAppendTime is used for the topic, the timestamp will be the broker local time when the messages are appended.
+    pub log_append_time_ms: i64,
+    /// The log start offset.
+    pub log_start_offset: i64,
+    /// The batch indices of records that caused the batch to be dropped
+    pub record_errors: Vec<BatchIndexAndErrorMessage>,
+    /// The global error message summarizing the common root cause of the records that caused the batch to be dropped
+    pub error_message: Option<String>,
+}
+
+impl Default for PartitionProduceResponse {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            error_code: 0,
+            base_offset: 0,
+            log_append_time_ms: -1,
+            log_start_offset: -1,
+            record_errors: Vec::new(),
+            error_message: None,
+        }
+    }
+}
+
+impl PartitionProduceResponse {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let index = src.get_i32("index")?;
+        let error_code = src.get_i16("error_code")?;
+        let base_offset = src.get_i64("base_offset")?;
+        let log_append_time_ms = if version >= 2 {
+            src.get_i64("log_append_time_ms")?
+        } else {
+            -1
+        };
+        let log_start_offset = if version >= 5 {
+            src.get_i64("log_start_offset")?
+        } else {
+            -1
+        };
+        let record_errors = if version >= 8 {
+            {
+                let len = if version >= 9 {
+                    src.get_varint("record_errors")? - 1
+                } else {
+                    i64::from(src.get_i32("record_errors")?)
+                };
+                let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+                for _ in 0..len {
+                    items.push(BatchIndexAndErrorMessage::deserialize(src, version)?);
+                }
+                items
+            }
+        } else {
+            Vec::new()
+        };
+        let error_message = if version >= 8 {
+            if version >= 9 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            index,
+            error_code,
+            base_offset,
+            log_append_time_ms,
+            log_start_offset,
+            record_errors,
+            error_message,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i32(self.index);
+        b.put_i16(self.error_code);
+        b.put_i64(self.base_offset);
+        if version >= 2 {
+            b.put_i64(self.log_append_time_ms);
+        }
+        if version >= 5 {
+            b.put_i64(self.log_start_offset);
+        }
+        if version >= 8 {
+            if version >= 9 {
+                VarInt::serialize_into(self.record_errors.len() as u64 + 1, b);
+            } else {
+                b.put_i32(self.record_errors.len() as i32);
+            }
+            for item in &self.record_errors {
+                item.serialize_into(b, version);
+            }
+        }
+        if version >= 8 {
+            if version >= 9 {
+                CompactNullableString::serialize_into(self.error_message.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.error_message.as_deref(), b);
+            }
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchIndexAndErrorMessage {
+    /// The batch index of the record that cause the batch to be dropped
+    pub batch_index: i32,
+    /// The error message of the record that caused the batch to be dropped
+    pub batch_index_error_message: Option<String>,
+}
+
+impl BatchIndexAndErrorMessage {
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let batch_index = if version >= 8 {
+            src.get_i32("batch_index")?
+        } else {
+            0
+        };
+        let batch_index_error_message = if version >= 8 {
+            if version >= 9 {
+                CompactNullableString::deserialize(src)?
+            } else {
+                NullableString::deserialize(src)?
+            }
+        } else {
+            None
+        };
+        if version >= 9 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            batch_index,
+            batch_index_error_message,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 8 {
+            b.put_i32(self.batch_index);
+        }
+        if version >= 8 {
+            if version >= 9 {
+                CompactNullableString::serialize_into(self.batch_index_error_message.as_deref(), b);
+            } else {
+                NullableString::serialize_into(self.batch_index_error_message.as_deref(), b);
+            }
+        }
+        if version >= 9 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Generated by `src/bin/codegen.rs` from `RenewDelegationTokenRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// RenewDelegationTokenRequest, versions 0-2
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenewDelegationTokenRequestData {
+    /// The HMAC of the delegation token to be renewed.
+    pub hmac: Vec<u8>,
+    /// The renewal time period in milliseconds.
+    pub renew_period_ms: i64,
+}
+
+impl RenewDelegationTokenRequestData {
+    pub const API_KEY: i16 = 39;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 2;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let hmac = if version >= 2 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("hmac")?;
+                src.get_bytes("hmac", len.max(0) as usize)?.to_vec()
+            }
+        };
+        let renew_period_ms = src.get_i64("renew_period_ms")?;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            hmac,
+            renew_period_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactNullableBytes::serialize_into(&self.hmac, b);
+        } else {
+            b.put_i32(self.hmac.len() as i32);
+            b.put_slice(&self.hmac);
+        }
+        b.put_i64(self.renew_period_ms);
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Generated by `src/bin/codegen.rs` from `RenewDelegationTokenResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// RenewDelegationTokenResponse, versions 0-2
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenewDelegationTokenResponseData {
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The timestamp in milliseconds at which this token expires.
+    pub expiry_timestamp_ms: i64,
+    /// The duration in milliseconds for which the request was throttled due to a quota violation, or zero if the request did not violate any quota.
+    pub throttle_time_ms: i32,
+}
+
+impl RenewDelegationTokenResponseData {
+    pub const API_KEY: i16 = 39;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 2;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let expiry_timestamp_ms = src.get_i64("expiry_timestamp_ms")?;
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            expiry_timestamp_ms,
+            throttle_time_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        b.put_i64(self.expiry_timestamp_ms);
+        b.put_i32(self.throttle_time_ms);
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Generated by `src/bin/codegen.rs` from `SaslAuthenticateRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// SaslAuthenticateRequest, versions 0-2
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaslAuthenticateRequestData {
+    /// The SASL authentication bytes from the client, as defined by the SASL mechanism.
+    pub auth_bytes: Vec<u8>,
+}
+
+impl SaslAuthenticateRequestData {
+    pub const API_KEY: i16 = 36;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 2;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let auth_bytes = if version >= 2 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("auth_bytes")?;
+                src.get_bytes("auth_bytes", len.max(0) as usize)?.to_vec()
+            }
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self { auth_bytes })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        if version >= 2 {
+            CompactNullableBytes::serialize_into(&self.auth_bytes, b);
+        } else {
+            b.put_i32(self.auth_bytes.len() as i32);
+            b.put_slice(&self.auth_bytes);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Generated by `src/bin/codegen.rs` from `SaslAuthenticateResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// SaslAuthenticateResponse, versions 0-2
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaslAuthenticateResponseData {
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The error message, or null if there was no error.
+    pub error_message: Option<String>,
+    /// The SASL authentication bytes from the server, as defined by the SASL mechanism.
+    pub auth_bytes: Vec<u8>,
+    /// Number of milliseconds after which only re-authentication over the existing connection to create a new session can occur.
+    pub session_lifetime_ms: i64,
+}
+
+impl SaslAuthenticateResponseData {
+    pub const API_KEY: i16 = 36;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 2;
+
+    pub fn deserialize(src: &mut ByteReader, version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let error_message = if version >= 2 {
+            CompactNullableString::deserialize(src)?
+        } else {
+            NullableString::deserialize(src)?
+        };
+        let auth_bytes = if version >= 2 {
+            CompactNullableBytes::deserialize(src)?
+        } else {
+            {
+                let len = src.get_i32("auth_bytes")?;
+                src.get_bytes("auth_bytes", len.max(0) as usize)?.to_vec()
+            }
+        };
+        let session_lifetime_ms = if version >= 1 {
+            src.get_i64("session_lifetime_ms")?
+        } else {
+            0
+        };
+        if version >= 2 {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            error_code,
+            error_message,
+            auth_bytes,
+            session_lifetime_ms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, version: i16) {
+        b.put_i16(self.error_code);
+        if version >= 2 {
+            CompactNullableString::serialize_into(self.error_message.as_deref(), b);
+        } else {
+            NullableString::serialize_into(self.error_message.as_deref(), b);
+        }
+        if version >= 2 {
+            CompactNullableBytes::serialize_into(&self.auth_bytes, b);
+        } else {
+            b.put_i32(self.auth_bytes.len() as i32);
+            b.put_slice(&self.auth_bytes);
+        }
+        if version >= 1 {
+            b.put_i64(self.session_lifetime_ms);
+        }
+        if version >= 2 {
+            TaggedFields::serialize_into(b); // tag buffer
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Generated by `src/bin/codegen.rs` from `SaslHandshakeRequest.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// SaslHandshakeRequest, versions 0-1
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaslHandshakeRequestData {
+    /// The SASL mechanism chosen by the client.
+    pub mechanism: String,
+}
+
+impl SaslHandshakeRequestData {
+    pub const API_KEY: i16 = 17;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 1;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let mechanism = NullableString::deserialize(src)?.unwrap_or_default();
+        Ok(Self { mechanism })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        b.put_i16(self.mechanism.len() as i16);
+        b.put_slice(self.mechanism.as_bytes());
+    }
+}
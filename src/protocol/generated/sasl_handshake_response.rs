@@ -0,0 +1,63 @@
+// Generated by `src/bin/codegen.rs` from `SaslHandshakeResponse.json`. Do not edit by hand.
+
+// Generated code covers the whole message, not every part of it has to be used
+#![allow(dead_code)]
+
+#[allow(unused_imports)]
+use bytes::{BufMut, Bytes, BytesMut};
+
+#[allow(unused_imports)]
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        CompactNullableBytes, CompactNullableString, CompactString, NullableString, TaggedFields,
+        Uuid, VarInt,
+    },
+    ProtocolError,
+};
+
+/// SaslHandshakeResponse, versions 0-1
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaslHandshakeResponseData {
+    /// The error code, or 0 if there was no error.
+    pub error_code: i16,
+    /// The mechanisms enabled in the server.
+    pub mechanisms: Vec<String>,
+}
+
+impl SaslHandshakeResponseData {
+    pub const API_KEY: i16 = 17;
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 1;
+
+    pub fn deserialize(src: &mut ByteReader, _version: i16) -> Result<Self, ProtocolError> {
+        let error_code = src.get_i16("error_code")?;
+        let mechanisms = {
+            let len = i64::from(src.get_i32("mechanisms")?);
+            let mut items = Vec::with_capacity((len.max(0) as usize).min(src.remaining()));
+            for _ in 0..len {
+                items.push(NullableString::deserialize(src)?.unwrap_or_default());
+            }
+            items
+        };
+        Ok(Self {
+            error_code,
+            mechanisms,
+        })
+    }
+
+    pub fn serialize(&self, version: i16) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b, version);
+        b.freeze()
+    }
+
+    pub fn serialize_into(&self, b: &mut BytesMut, _version: i16) {
+        b.put_i16(self.error_code);
+        b.put_i32(self.mechanisms.len() as i32);
+        for item in &self.mechanisms {
+            b.put_i16(item.len() as i16);
+            b.put_slice(item.as_bytes());
+        }
+    }
+}
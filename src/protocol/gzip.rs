@@ -0,0 +1,654 @@
+//! Gzip compression of the records of a batch, compression type 1 of the batch attributes.
+//!
+//! The records are compressed into a single DEFLATE block with the fixed Huffman codes of RFC 1951,
+//! with back references to the longest earlier match of a hash chain. That is less compact than the dynamic
+//! codes of zlib, but needs no code tables in the stream, and any gzip decoder reads it.
+//!
+//! Decompression reads the stored, fixed and dynamic Huffman blocks of any encoder, with the canonical
+//! codes decoded bit by bit as zlib's `puff` does, and checks the CRC and size of every member.
+// https://www.rfc-editor.org/rfc/rfc1951, https://www.rfc-editor.org/rfc/rfc1952
+
+use thiserror::Error;
+
+/// Why gzip data could not be decompressed
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GzipError {
+    #[error("gzip data is truncated")]
+    Truncated,
+    #[error("invalid gzip header")]
+    InvalidHeader,
+    #[error("invalid deflate data: {0}")]
+    InvalidData(&'static str),
+    #[error("gzip checksum or size does not match the decompressed data")]
+    Checksum,
+    #[error("decompressed data is larger than {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Gzip member with the compressed data, without a file name and modification time
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter {
+        bytes: Vec::with_capacity(data.len() / 2 + 32),
+        ..BitWriter::default()
+    };
+    // magic, deflate method, no flags, no modification time, no extra flags, unknown OS
+    out.bytes
+        .extend_from_slice(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff]);
+    out.bits(1, 1); // last block
+    out.bits(1, 2); // fixed Huffman codes
+
+    let mut matches = Matches::new(data);
+    let mut pos = 0;
+    while pos < data.len() {
+        match matches.longest(pos) {
+            Some((length, distance)) => {
+                out.length(length);
+                out.distance(distance);
+                for p in pos..pos + length {
+                    matches.insert(p);
+                }
+                pos += length;
+            }
+            None => {
+                out.symbol(data[pos].into());
+                matches.insert(pos);
+                pos += 1;
+            }
+        }
+    }
+    out.symbol(END_OF_BLOCK);
+    out.flush();
+
+    out.bytes.extend_from_slice(&crc32(data).to_le_bytes());
+    out.bytes
+        .extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.bytes
+}
+
+/// Data of the gzip members, at most `max_size` bytes of it
+pub fn decompress(data: &[u8], max_size: usize) -> Result<Vec<u8>, GzipError> {
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4).min(max_size));
+    let mut rest = data;
+    loop {
+        let start = out.len();
+        let mut reader = BitReader::new(&rest[header_size(rest)?..]);
+        inflate(&mut reader, &mut out, start, max_size)?;
+        let trailer = reader.rest();
+        let (Some(crc), Some(size)) = (trailer.get(..4), trailer.get(4..8)) else {
+            return Err(GzipError::Truncated);
+        };
+        let member = &out[start..];
+        if crc32(member).to_le_bytes() != crc || (member.len() as u32).to_le_bytes() != size {
+            return Err(GzipError::Checksum);
+        }
+        // concatenated members are one stream, as in Java's GZIPInputStream
+        rest = &trailer[8..];
+        if rest.is_empty() {
+            return Ok(out);
+        }
+    }
+}
+
+/// CRC-32 (ISO 3309) checksum of the uncompressed data, computed with a lookup table
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Reversed ISO 3309 polynomial
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+};
+
+const END_OF_BLOCK: u16 = 256;
+/// Shortest and longest match of a back reference
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Farthest back a reference may point
+const WINDOW: usize = 32 * 1024;
+/// Earlier positions of the same hash compared to find the longest match
+const MAX_CHAIN: usize = 32;
+const HASH_BITS: u32 = 15;
+
+/// Shortest length of the length codes 257..=285, whose extra bits are added to it
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Shortest distance of the distance codes 0..=29, whose extra bits are added to it
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order of the code lengths of the code length code in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+/// Longest Huffman code
+const MAX_BITS: usize = 15;
+
+/// Flags of the gzip header, the optional fields follow in this order
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+const RESERVED_FLAGS: u8 = 0xe0;
+
+/// Size of the gzip header at the start of the data, with its optional fields
+fn header_size(data: &[u8]) -> Result<usize, GzipError> {
+    let fixed = data.get(..10).ok_or(GzipError::Truncated)?;
+    let flags = fixed[3];
+    if fixed[..3] != [0x1f, 0x8b, 8] || flags & RESERVED_FLAGS != 0 {
+        return Err(GzipError::InvalidHeader);
+    }
+
+    let mut size = 10;
+    if flags & FEXTRA != 0 {
+        let len = data.get(size..size + 2).ok_or(GzipError::Truncated)?;
+        size += 2 + usize::from(u16::from_le_bytes([len[0], len[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let terminator = data
+                .get(size..)
+                .and_then(|field| field.iter().position(|&b| b == 0))
+                .ok_or(GzipError::Truncated)?;
+            size += terminator + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        size += 2;
+    }
+    if size > data.len() {
+        return Err(GzipError::Truncated);
+    }
+    Ok(size)
+}
+
+/// Decodes the blocks of a DEFLATE stream, back references reach back to `start` of the output
+fn inflate(
+    src: &mut BitReader,
+    out: &mut Vec<u8>,
+    start: usize,
+    max_size: usize,
+) -> Result<(), GzipError> {
+    loop {
+        let last = src.bits(1)? == 1;
+        match src.bits(2)? {
+            0 => {
+                src.align();
+                let len = src.bits(16)?;
+                if src.bits(16)? != !len & 0xffff {
+                    return Err(GzipError::InvalidData("stored block length"));
+                }
+                if out.len() + len as usize > max_size {
+                    return Err(GzipError::TooLarge(max_size));
+                }
+                for _ in 0..len {
+                    out.push(src.bits(8)? as u8);
+                }
+            }
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                codes(src, out, start, max_size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = Huffman::dynamic(src)?;
+                codes(src, out, start, max_size, &literals, &distances)?;
+            }
+            _ => return Err(GzipError::InvalidData("block type 3")),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+/// Decodes the literals and back references of a compressed block up to its end of block code
+fn codes(
+    src: &mut BitReader,
+    out: &mut Vec<u8>,
+    start: usize,
+    max_size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), GzipError> {
+    loop {
+        let symbol = literals.decode(src)?;
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+        if symbol < END_OF_BLOCK {
+            if out.len() == max_size {
+                return Err(GzipError::TooLarge(max_size));
+            }
+            out.push(symbol as u8);
+            continue;
+        }
+
+        let index = usize::from(symbol - 257);
+        let (Some(&base), Some(&extra)) = (LENGTH_BASE.get(index), LENGTH_EXTRA_BITS.get(index))
+        else {
+            return Err(GzipError::InvalidData("length code"));
+        };
+        let length = usize::from(base) + src.bits(extra.into())? as usize;
+        let index = usize::from(distances.decode(src)?);
+        let (Some(&base), Some(&extra)) =
+            (DISTANCE_BASE.get(index), DISTANCE_EXTRA_BITS.get(index))
+        else {
+            return Err(GzipError::InvalidData("distance code"));
+        };
+        let distance = usize::from(base) + src.bits(extra.into())? as usize;
+        if distance > out.len() - start {
+            return Err(GzipError::InvalidData("distance too far back"));
+        }
+        if out.len() + length > max_size {
+            return Err(GzipError::TooLarge(max_size));
+        }
+        // the match may overlap the bytes it copies
+        let from = out.len() - distance;
+        for i in from..from + length {
+            out.push(out[i]);
+        }
+    }
+}
+
+/// Canonical Huffman code by the number of codes of each length and the symbols in code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Code of the code lengths of the symbols, a length of 0 leaves the symbol out
+    fn new(lengths: &[u8]) -> Result<Self, GzipError> {
+        let mut counts = [0; MAX_BITS + 1];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+        // codes left of each length, incomplete codes are allowed as zlib does
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = 2 * left - i32::from(count);
+            if left < 0 {
+                return Err(GzipError::InvalidData("over-subscribed code lengths"));
+            }
+        }
+
+        let mut offsets = [0; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                let offset = &mut offsets[usize::from(length)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    /// The literal/length and distance codes of fixed Huffman blocks
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        let literals = Self::new(&lengths).expect("complete fixed code");
+        let distances = Self::new(&[5; 30]).expect("fixed distance code");
+        (literals, distances)
+    }
+
+    /// The literal/length and distance codes of the header of a dynamic Huffman block
+    fn dynamic(src: &mut BitReader) -> Result<(Self, Self), GzipError> {
+        let literal_count = src.bits(5)? as usize + 257;
+        let distance_count = src.bits(5)? as usize + 1;
+        let code_length_count = src.bits(4)? as usize + 4;
+        if literal_count > 286 || distance_count > 30 {
+            return Err(GzipError::InvalidData("too many codes"));
+        }
+
+        let mut code_lengths = [0; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[symbol] = src.bits(3)? as u8;
+        }
+        let code_length_code = Self::new(&code_lengths)?;
+
+        let mut lengths = Vec::with_capacity(literal_count + distance_count);
+        while lengths.len() < literal_count + distance_count {
+            let (length, repeat) = match code_length_code.decode(src)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths
+                        .last()
+                        .ok_or(GzipError::InvalidData("repeat without a length"))?;
+                    (previous, 3 + src.bits(2)?)
+                }
+                17 => (0, 3 + src.bits(3)?),
+                _ => (0, 11 + src.bits(7)?),
+            };
+            if lengths.len() + repeat as usize > literal_count + distance_count {
+                return Err(GzipError::InvalidData("too many code lengths"));
+            }
+            lengths.resize(lengths.len() + repeat as usize, length);
+        }
+        if lengths[usize::from(END_OF_BLOCK)] == 0 {
+            return Err(GzipError::InvalidData("no end of block code"));
+        }
+
+        let literals = Self::new(&lengths[..literal_count])?;
+        let distances = Self::new(&lengths[literal_count..])?;
+        Ok((literals, distances))
+    }
+
+    /// Reads the next symbol, the codes are stored from their most significant bit
+    fn decode(&self, src: &mut BitReader) -> Result<u16, GzipError> {
+        // first code of the length and index of its symbol
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= src.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(GzipError::InvalidData("invalid code"))
+    }
+}
+
+/// Reads the bits of the stream from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            pending: 0,
+            pending_bits: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, GzipError> {
+        while self.pending_bits < count {
+            let byte = *self.data.get(self.pos).ok_or(GzipError::Truncated)?;
+            self.pos += 1;
+            self.pending |= u64::from(byte) << self.pending_bits;
+            self.pending_bits += 8;
+        }
+        let value = (self.pending & ((1 << count) - 1)) as u32;
+        self.pending >>= count;
+        self.pending_bits -= count;
+        Ok(value)
+    }
+
+    /// Skips the bits left of the current byte
+    fn align(&mut self) {
+        let partial = self.pending_bits % 8;
+        self.pending >>= partial;
+        self.pending_bits -= partial;
+    }
+
+    /// Bytes after the last block, at the next byte boundary
+    fn rest(&mut self) -> &'a [u8] {
+        self.align();
+        &self.data[self.pos - (self.pending_bits / 8) as usize..]
+    }
+}
+
+/// Writes the bits of the stream from the least significant bit of each byte
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.pending |= u64::from(value) << self.pending_bits;
+        self.pending_bits += count;
+        while self.pending_bits >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    /// Huffman codes are written from their most significant bit
+    fn code(&mut self, code: u16, length: u32) {
+        self.bits(u32::from(code.reverse_bits() >> (16 - length)), length);
+    }
+
+    /// Literal byte, end of block or length code with the fixed literal/length code
+    fn symbol(&mut self, symbol: u16) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn length(&mut self, length: usize) {
+        let index = LENGTH_BASE.partition_point(|&base| usize::from(base) <= length) - 1;
+        self.symbol(257 + index as u16);
+        let extra = length - usize::from(LENGTH_BASE[index]);
+        self.bits(extra as u32, LENGTH_EXTRA_BITS[index].into());
+    }
+
+    /// Distance codes are 5 bits with the fixed codes
+    fn distance(&mut self, distance: usize) {
+        let index = DISTANCE_BASE.partition_point(|&base| usize::from(base) <= distance) - 1;
+        self.code(index as u16, 5);
+        let extra = distance - usize::from(DISTANCE_BASE[index]);
+        self.bits(extra as u32, DISTANCE_EXTRA_BITS[index].into());
+    }
+
+    /// Pads the last byte with zero bits
+    fn flush(&mut self) {
+        if self.pending_bits > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+        self.pending = 0;
+        self.pending_bits = 0;
+    }
+}
+
+/// Hash chains of the positions of the data by their next three bytes
+struct Matches<'a> {
+    data: &'a [u8],
+    /// Last position of each hash
+    head: Vec<u32>,
+    /// Previous position of the same hash, by position
+    previous: Vec<u32>,
+}
+
+const NO_POSITION: u32 = u32::MAX;
+
+impl<'a> Matches<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            head: vec![NO_POSITION; 1 << HASH_BITS],
+            previous: vec![NO_POSITION; data.len()],
+        }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let bytes = &self.data[pos..pos + MIN_MATCH];
+        let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        (key.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Adds the position, once the match at it was looked for
+    fn insert(&mut self, pos: usize) {
+        if pos + MIN_MATCH <= self.data.len() {
+            let hash = self.hash(pos);
+            self.previous[pos] = self.head[hash];
+            self.head[hash] = pos as u32;
+        }
+    }
+
+    /// Length and distance of the longest match of the data at the position in the window before it
+    fn longest(&self, pos: usize) -> Option<(usize, usize)> {
+        if pos + MIN_MATCH > self.data.len() {
+            return None;
+        }
+        let max_length = MAX_MATCH.min(self.data.len() - pos);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[self.hash(pos)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == NO_POSITION || pos - candidate as usize > WINDOW {
+                break;
+            }
+            let start = candidate as usize;
+            let length = self.data[start..]
+                .iter()
+                .zip(&self.data[pos..pos + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length >= MIN_MATCH && length > best.map_or(0, |(best, _)| best) {
+                best = Some((length, pos - start));
+                if length == max_length {
+                    break;
+                }
+            }
+            candidate = self.previous[start];
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, crc32, decompress, GzipError};
+
+    #[test]
+    fn computes_iso_checksum() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn compresses_with_back_references() {
+        // checked with Python's gzip.decompress
+        assert_eq!(
+            compress(b"abcabcabcabc, hello hello"),
+            [
+                0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 0x4b, 0x4c, 0x4a, 0x86, 0x23, 0x1d, 0x85,
+                0x8c, 0xd4, 0x9c, 0x9c, 0x7c, 0x08, 0x09, 0x00, 0x47, 0x67, 0x79, 0xcd, 0x19, 0, 0,
+                0
+            ]
+        );
+        assert_eq!(
+            compress(b""),
+            [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let records = b"{\"id\":1,\"name\":\"record\"}".repeat(100);
+        assert!(compress(&records).len() < records.len() / 10);
+    }
+
+    #[test]
+    fn decompresses_the_blocks_of_any_encoder() {
+        // Python's gzip.compress with a dynamic Huffman block, and with compresslevel 0 a stored block
+        let dynamic = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x8d, 0xcb, 0xc9, 0x11,
+            0x80, 0x20, 0x10, 0x44, 0xd1, 0x54, 0x3a, 0x0f, 0xa3, 0x01, 0x65, 0x53, 0x60, 0xd8,
+            0x11, 0xa3, 0x77, 0xca, 0x08, 0x3c, 0x76, 0xfd, 0xd7, 0xcd, 0x2a, 0xe4, 0xee, 0xf6,
+            0x0b, 0xb2, 0xd0, 0x8c, 0xd0, 0x74, 0xe3, 0xec, 0x21, 0x55, 0xd0, 0x50, 0x05, 0x8d,
+            0xb3, 0x17, 0xcf, 0xc2, 0x41, 0x66, 0xfb, 0xd6, 0x6f, 0x9c, 0x04, 0xbb, 0xb0, 0x20,
+            0x19, 0x4d, 0xd7, 0x2c, 0xb4, 0x1b, 0x8a, 0xd3, 0xa3, 0x22, 0xbc, 0xcb, 0x9d, 0x0a,
+            0x7f, 0x4d, 0x7d, 0x01, 0xa0, 0x06, 0x81, 0xf5, 0x81, 0x00, 0x00, 0x00,
+        ];
+        let text = [
+            &b"the quick brown fox jumps over the lazy dog; ".repeat(2)[..],
+            b"pack my box with five dozen liquor jugs",
+        ]
+        .concat();
+        assert_eq!(decompress(&dynamic, 1024).unwrap(), text);
+
+        let stored = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x06, 0x00, 0xf9,
+            0xff, 0x73, 0x74, 0x6f, 0x72, 0x65, 0x64, 0x0b, 0xf9, 0x43, 0x56, 0x06, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(decompress(&stored, 1024).unwrap(), b"stored");
+
+        // with a file name in the header, and two members
+        let named = [
+            &[0x1f, 0x8b, 8, 0x08][..],
+            &stored[4..10],
+            b"records\0",
+            &stored[10..],
+        ]
+        .concat();
+        let records = b"{\"id\":1,\"name\":\"record\"}".repeat(100);
+        let members = [named, compress(&records)].concat();
+        assert_eq!(
+            decompress(&members, 4096).unwrap(),
+            [&b"stored"[..], &records].concat()
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_gzip_data() {
+        let compressed = compress(b"abcabcabcabc, hello hello");
+        assert_eq!(
+            decompress(&compressed[..20], 1024),
+            Err(GzipError::Truncated)
+        );
+        assert_eq!(decompress(&compressed, 10), Err(GzipError::TooLarge(10)));
+
+        let mut corrupt = compressed.clone();
+        corrupt[compressed.len() - 8] ^= 1;
+        assert_eq!(decompress(&corrupt, 1024), Err(GzipError::Checksum));
+        corrupt[0] = 0;
+        assert_eq!(decompress(&corrupt, 1024), Err(GzipError::InvalidHeader));
+        // block type 3
+        let mut reserved = compressed;
+        reserved[10] |= 0x06;
+        assert!(matches!(
+            decompress(&reserved, 1024),
+            Err(GzipError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+use bytes::{Buf, Bytes};
+
+use super::{types::VarInt, ProtocolError};
+
+/// Bounds-checked reader over the received bytes.
+///
+/// Every read checks that enough bytes remain and reports the name of the field being read,
+/// so truncated or malformed input produces a decode error instead of a panic.
+#[derive(Debug, Clone)]
+pub struct ByteReader {
+    bytes: Bytes,
+}
+
+impl ByteReader {
+    pub fn new(bytes: Bytes) -> Self {
+        Self { bytes }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.remaining()
+    }
+
+    fn ensure_remaining(&self, field: &'static str, needed: usize) -> Result<(), ProtocolError> {
+        if self.bytes.remaining() < needed {
+            return Err(ProtocolError::UnexpectedEof {
+                field,
+                needed,
+                remaining: self.bytes.remaining(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn get_u8(&mut self, field: &'static str) -> Result<u8, ProtocolError> {
+        self.ensure_remaining(field, 1)?;
+        Ok(self.bytes.get_u8())
+    }
+
+    pub fn get_i8(&mut self, field: &'static str) -> Result<i8, ProtocolError> {
+        self.ensure_remaining(field, 1)?;
+        Ok(self.bytes.get_i8())
+    }
+
+    pub fn get_u16(&mut self, field: &'static str) -> Result<u16, ProtocolError> {
+        self.ensure_remaining(field, 2)?;
+        Ok(self.bytes.get_u16())
+    }
+
+    pub fn get_i16(&mut self, field: &'static str) -> Result<i16, ProtocolError> {
+        self.ensure_remaining(field, 2)?;
+        Ok(self.bytes.get_i16())
+    }
+
+    pub fn get_u32(&mut self, field: &'static str) -> Result<u32, ProtocolError> {
+        self.ensure_remaining(field, 4)?;
+        Ok(self.bytes.get_u32())
+    }
+
+    pub fn get_i32(&mut self, field: &'static str) -> Result<i32, ProtocolError> {
+        self.ensure_remaining(field, 4)?;
+        Ok(self.bytes.get_i32())
+    }
+
+    pub fn get_u64(&mut self, field: &'static str) -> Result<u64, ProtocolError> {
+        self.ensure_remaining(field, 8)?;
+        Ok(self.bytes.get_u64())
+    }
+
+    pub fn get_i64(&mut self, field: &'static str) -> Result<i64, ProtocolError> {
+        self.ensure_remaining(field, 8)?;
+        Ok(self.bytes.get_i64())
+    }
+
+    pub fn get_varint(&mut self, field: &'static str) -> Result<i64, ProtocolError> {
+        VarInt::deserialize(&mut self.bytes)
+            .map_err(|source| ProtocolError::VarInt { field, source })
+    }
+
+    /// Signed variable size integer in zigzag encoding, as the fields of records
+    pub fn get_varlong(&mut self, field: &'static str) -> Result<i64, ProtocolError> {
+        let zigzag = self.get_varint(field)?;
+        Ok(((zigzag as u64) >> 1) as i64 ^ -(zigzag & 1))
+    }
+
+    /// Returns the bytes that were not read yet
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+
+    /// Splits off next `len` bytes without copying
+    pub fn get_bytes(&mut self, field: &'static str, len: usize) -> Result<Bytes, ProtocolError> {
+        self.ensure_remaining(field, len)?;
+        Ok(self.bytes.split_to(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::ByteReader;
+    use crate::protocol::ProtocolError;
+
+    #[test]
+    fn reads_fields_in_order() {
+        let mut r = ByteReader::new(Bytes::from_static(&[0, 7, 0, 0, 0, 42, 0x96, 0x01, 1, 2]));
+        assert_eq!(r.get_i16("a").unwrap(), 7);
+        assert_eq!(r.get_u32("b").unwrap(), 42);
+        assert_eq!(r.get_varint("c").unwrap(), 150);
+        assert_eq!(r.get_bytes("d", 2).unwrap().as_ref(), &[1, 2]);
+        assert_eq!(r.remaining(), 0);
+
+        let mut r = ByteReader::new(Bytes::from_static(&[0x01, 0x96, 0x01]));
+        assert_eq!(r.get_varlong("e").unwrap(), -1);
+        assert_eq!(r.get_varlong("f").unwrap(), 75);
+        assert_eq!(r.remaining(), 0);
+    }
+
+    #[test]
+    fn reports_truncated_field() {
+        let mut r = ByteReader::new(Bytes::from_static(&[0, 0, 1]));
+        let err = r.get_i32("correlation_id").unwrap_err();
+        assert!(matches!(
+            err,
+            ProtocolError::UnexpectedEof {
+                field: "correlation_id",
+                needed: 4,
+                remaining: 3
+            }
+        ));
+        // nothing was consumed by the failed read
+        assert_eq!(r.remaining(), 3);
+    }
+}
@@ -1,28 +1,162 @@
-use std::path::Path;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
 
-use anyhow::{Context, Result};
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 
-use super::types::{self, CompactNullableBytes, NullableBytes};
-use crate::protocol::types::{CompactArray, CompactString, Uuid, VarInt};
+use super::{
+    crc32c::crc32c,
+    gzip,
+    reader::ByteReader,
+    types::{self, CompactNullableString, NullableBytes, VarInt, VarLong},
+    ProtocolError,
+};
+use crate::{
+    protocol::types::{CompactArray, CompactString, Uuid},
+    storage::{Storage, StorageError},
+};
 
 pub struct RecordBatches {
     batches: Vec<RecordBatch>,
+    /// Topic names by topic id and ids by name, from the first topic record of each
+    topic_names: HashMap<String, String>,
+    topic_ids: HashMap<String, String>,
+    /// Partition records by topic id, ordered by partition id, the first record of each partition
+    partitions: HashMap<String, Vec<PartitionValue>>,
+    /// Config overrides by topic name, the value of the latest config record of each config
+    topic_configs: HashMap<String, BTreeMap<String, String>>,
+    /// Offset of the next record appended to the log
+    end_offset: i64,
 }
 
 impl RecordBatches {
-    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
-        let file_bytes = std::fs::read(path).context("read file")?;
-        let mut data = BytesMut::with_capacity(file_bytes.len());
-        data.extend_from_slice(&file_bytes);
-        let mut data = data.freeze();
+    pub fn from_file(storage: &dyn Storage, path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        let mut data = ByteReader::new(storage.read(path)?);
 
         let mut batches = Vec::new();
         while data.remaining() > 0 {
-            let record_batch = RecordBatch::from_bytes(&mut data);
+            let record_batch =
+                RecordBatch::from_bytes(&mut data).map_err(|source| StorageError::Corrupt {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
             batches.push(record_batch);
         }
-        Ok(Self { batches })
+        let end_offset = batches.last().map_or(0, |b| b.last_offset() + 1);
+        Ok(Self::new(batches, end_offset))
+    }
+
+    /// Metadata records of the directory of the metadata log partition, loaded as KRaft does: the latest
+    /// snapshot first, then the batches of the log segments from the end offset of the snapshot.
+    ///
+    /// Snapshots are the `<end offset>-<epoch>.checkpoint` files, with the records of the log before their
+    /// end offset, so the segments before it may have been deleted. Records missing between the snapshot,
+    /// or offset 0 without one, and the log are an error instead of leaving out the topics they created.
+    /// Control batches, e.g. the header and footer of a snapshot, are skipped.
+    pub fn load_metadata(
+        storage: &dyn Storage,
+        dir: impl AsRef<Path>,
+    ) -> Result<Self, StorageError> {
+        let dir = dir.as_ref();
+        let entries = storage.list(dir)?;
+        let file_names = |extension| {
+            entries.iter().filter_map(move |entry| {
+                let offset = entry.name.strip_suffix(extension)?.split('-').next()?;
+                (!entry.is_dir).then_some((offset.parse::<i64>().ok()?, entry.name.as_str()))
+            })
+        };
+
+        let mut batches = Vec::new();
+        let mut end_offset = 0;
+        if let Some((snapshot_end_offset, name)) = file_names(".checkpoint").max() {
+            read_batches(storage, &dir.join(name), &mut batches, |_| Ok(true))?;
+            end_offset = snapshot_end_offset;
+        }
+        // the names of the segments are their base offsets
+        let mut segments: Vec<_> = file_names(".log").collect();
+        segments.sort();
+        for (_, name) in segments {
+            let path = dir.join(name);
+            read_batches(storage, &path, &mut batches, |batch| {
+                if batch.last_offset() < end_offset {
+                    return Ok(false);
+                }
+                if batch.base_offset > end_offset {
+                    return Err(StorageError::MissingRecords {
+                        path: path.clone(),
+                        expected_offset: end_offset,
+                        base_offset: batch.base_offset,
+                    });
+                }
+                end_offset = batch.last_offset() + 1;
+                Ok(true)
+            })?;
+        }
+        Ok(Self::new(batches, end_offset))
+    }
+
+    fn new(batches: Vec<RecordBatch>, end_offset: i64) -> Self {
+        let mut topic_names = HashMap::new();
+        let mut topic_ids = HashMap::new();
+        let mut partitions: HashMap<String, BTreeMap<u32, PartitionValue>> = HashMap::new();
+        let mut topic_configs: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+        for record in batches.iter().flat_map(|b| &b.records) {
+            match &record.value {
+                RecordValue::Topic(topic) => {
+                    topic_names
+                        .entry(topic.topic_id.clone())
+                        .or_insert_with(|| topic.topic_name.clone());
+                    topic_ids
+                        .entry(topic.topic_name.clone())
+                        .or_insert_with(|| topic.topic_id.clone());
+                }
+                // the first record of a partition is kept, the changes after it apply to it
+                RecordValue::Partition(p) => {
+                    partitions
+                        .entry(p.topic_id.clone())
+                        .or_default()
+                        .entry(p.partition_id)
+                        .or_insert_with(|| p.clone());
+                }
+                RecordValue::PartitionChange(change) => {
+                    if let Some(p) = partitions
+                        .get_mut(&change.topic_id)
+                        .and_then(|topic_partitions| topic_partitions.get_mut(&change.partition_id))
+                    {
+                        change.apply(p);
+                    }
+                }
+                RecordValue::Config(config)
+                    if config.resource_type == ConfigValue::TOPIC_RESOURCE =>
+                {
+                    let configs = topic_configs
+                        .entry(config.resource_name.clone())
+                        .or_default();
+                    match &config.value {
+                        Some(value) => configs.insert(config.name.clone(), value.clone()),
+                        // a null value deletes the override
+                        None => configs.remove(&config.name),
+                    };
+                }
+                _ => {}
+            }
+        }
+        let partitions = partitions
+            .into_iter()
+            .map(|(topic_id, topic_partitions)| {
+                (topic_id, topic_partitions.into_values().collect())
+            })
+            .collect();
+        Self {
+            batches,
+            topic_names,
+            topic_ids,
+            partitions,
+            topic_configs,
+            end_offset,
+        }
     }
 
     #[allow(dead_code)]
@@ -30,29 +164,360 @@ impl RecordBatches {
         &self.batches
     }
 
-    pub fn raw_batch_for_topic(&self, topic_id: &str, partition_id: u32) -> Result<Option<Bytes>> {
-        let mut topic_name = String::new();
-        self.batches.iter().find(|&b| {
-            b.records.iter().any(|r| match &r.value {
-                RecordValue::Topic(topic) if topic.topic_id == topic_id => {
-                    topic_name = topic.topic_name.clone();
-                    true
+    /// Offset of the next record appended to the log, 0 if it is empty
+    pub fn end_offset(&self) -> i64 {
+        self.end_offset
+    }
+
+    /// Name of the topic with the id from its topic record
+    pub fn topic_name(&self, topic_id: &str) -> Option<&str> {
+        self.topic_names.get(topic_id).map(String::as_str)
+    }
+
+    /// Id of the topic with the name from its topic record
+    pub fn topic_id(&self, topic_name: &str) -> Option<&str> {
+        self.topic_ids.get(topic_name).map(String::as_str)
+    }
+
+    /// Value of the config of the topic from its latest config record, none if it is not set or was deleted
+    pub fn topic_config(&self, topic_name: &str, name: &str) -> Option<&str> {
+        self.topic_configs
+            .get(topic_name)?
+            .get(name)
+            .map(String::as_str)
+    }
+
+    /// Configs set for the topic by its config records, ordered by name
+    pub fn topic_configs(&self, topic_name: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.topic_configs
+            .get(topic_name)
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Latest registration of every broker that is not unregistered, ordered by broker id.
+    ///
+    /// Fence, unfence and unregister records apply only to the registration with their epoch.
+    pub fn registered_brokers(&self) -> Vec<RegisterBrokerValue> {
+        let mut brokers: BTreeMap<i32, RegisterBrokerValue> = BTreeMap::new();
+        for record in self.batches.iter().flat_map(|b| &b.records) {
+            let (change, fenced) = match &record.value {
+                RecordValue::RegisterBroker(broker) => {
+                    brokers.insert(broker.broker_id, broker.clone());
+                    continue;
+                }
+                RecordValue::UnregisterBroker(change) => {
+                    if brokers
+                        .get(&change.broker_id)
+                        .is_some_and(|b| b.broker_epoch == change.broker_epoch)
+                    {
+                        brokers.remove(&change.broker_id);
+                    }
+                    continue;
                 }
-                _ => false,
+                RecordValue::FenceBroker(change) => (change, true),
+                RecordValue::UnfenceBroker(change) => (change, false),
+                _ => continue,
+            };
+            if let Some(broker) = brokers
+                .get_mut(&change.broker_id)
+                .filter(|b| b.broker_epoch == change.broker_epoch)
+            {
+                broker.fenced = fenced;
+            }
+        }
+        brokers.into_values().collect()
+    }
+
+    /// Partition records of the topic, ordered by partition id, empty if the topic id is unknown.
+    /// Their number is the partition count of the topic.
+    pub fn partitions(&self, topic_id: &str) -> &[PartitionValue] {
+        self.partitions.get(topic_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Topic records, in the order the topics were created
+    pub fn topics(&self) -> impl Iterator<Item = &TopicValue> {
+        self.batches
+            .iter()
+            .flat_map(|b| &b.records)
+            .filter_map(|r| match &r.value {
+                RecordValue::Topic(topic) => Some(topic),
+                _ => None,
             })
-        });
+    }
+
+    /// Partition record of the topic partition, `None` if the topic has no such partition
+    pub fn partition(&self, topic_id: &str, partition_id: u32) -> Option<&PartitionValue> {
+        let partitions = self.partitions(topic_id);
+        partitions
+            .binary_search_by_key(&partition_id, |p| p.partition_id)
+            .ok()
+            .map(|i| &partitions[i])
+    }
+}
 
-        if topic_name.is_empty() {
-            return Ok(None);
+/// Decodes the batches of the file that are not control batches and that `keep` accepts
+fn read_batches(
+    storage: &dyn Storage,
+    path: &Path,
+    batches: &mut Vec<RecordBatch>,
+    mut keep: impl FnMut(&RecordBatch) -> Result<bool, StorageError>,
+) -> Result<(), StorageError> {
+    let corrupt = |source| StorageError::Corrupt {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut data = ByteReader::new(storage.read(path)?);
+    while data.remaining() > 0 {
+        let mut batch = RecordBatch::from_bytes_lazy(&mut data).map_err(corrupt)?;
+        if batch.is_control() || !keep(&batch)? {
+            continue;
         }
+        batch.decode_records().map_err(corrupt)?;
+        batches.push(batch);
+    }
+    Ok(())
+}
 
-        let file = format!(
-            "/tmp/kraft-combined-logs/{}-{}/00000000000000000000.log",
-            topic_name, partition_id
-        );
-        let file_bytes = std::fs::read(file).context("read file with messages")?;
+/// Record batches of a partition log served to a consumer, as raw bytes the way they are in the log
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogSlice {
+    /// Consecutive batches of the log, the first one contains the fetch offset
+    pub records: Bytes,
+    /// Base offset of the first batch and last offset of the last batch in `records`, `None` if there are none
+    pub offsets: Option<(i64, i64)>,
+    /// Base offset of the first batch of the log, 0 if it is empty
+    pub log_start_offset: i64,
+    /// Offset after the last record of the log. The partitions have no followers, so it is the high watermark.
+    pub log_end_offset: i64,
+}
+
+impl LogSlice {
+    /// Slices the batches of the log from the one that contains the fetch offset.
+    ///
+    /// Only the batch headers are read, the records are not decoded. Batches are added while they fit into
+    /// `max_bytes`, but the first one always is, as in Kafka, so that a consumer gets past a batch larger than
+    /// its limit.
+    pub fn read(
+        storage: &dyn Storage,
+        path: impl AsRef<Path>,
+        fetch_offset: i64,
+        max_bytes: usize,
+    ) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        let log = storage.read(path)?;
+        Self::slice(log, fetch_offset, max_bytes).map_err(|source| StorageError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn slice(log: Bytes, fetch_offset: i64, max_bytes: usize) -> Result<Self, ProtocolError> {
+        let mut slice = Self::default();
+        let (mut start, mut end) = (0, 0);
+        let mut src = ByteReader::new(log.clone());
+        while src.remaining() > 0 {
+            let batch_start = log.len() - src.remaining();
+            let batch = RecordBatch::from_bytes_lazy(&mut src)?;
+            let batch_end = log.len() - src.remaining();
+
+            if batch_start == 0 {
+                slice.log_start_offset = batch.base_offset;
+            }
+            slice.log_end_offset = batch.last_offset().saturating_add(1);
+            if batch.last_offset() < fetch_offset {
+                continue;
+            }
+            match &mut slice.offsets {
+                None => {
+                    (start, end) = (batch_start, batch_end);
+                    slice.offsets = Some((batch.base_offset, batch.last_offset()));
+                }
+                // the batches that follow a batch that did not fit are not added either
+                Some((_, last_offset)) if end == batch_start && batch_end - start <= max_bytes => {
+                    end = batch_end;
+                    *last_offset = batch.last_offset();
+                }
+                Some(_) => {}
+            }
+        }
+        slice.records = log.slice(start..end);
+        Ok(slice)
+    }
+}
+
+/// Batches with the records of the uncompressed ones compressed with gzip, for the Fetch responses of
+/// `--fetch-compression-type=gzip`.
+///
+/// Only the batch length, the compression type in the attributes and the CRC of a batch change, its offsets,
+/// timestamps and record count stay the same. Control batches, batches that do not get smaller and a batch
+/// cut off at the end, as the batches are not checked, are left as they are.
+pub fn gzip_batches(batches: &[u8]) -> Bytes {
+    const CRC_OFFSET: usize = 17;
+    const ATTRIBUTES_OFFSET: usize = 21;
+    /// The records follow the batch header and the record count
+    const RECORDS_OFFSET: usize = 61;
+    const COMPRESSION_MASK: i16 = 0x07;
+    const GZIP: i16 = 1;
+
+    let mut out = BytesMut::with_capacity(batches.len());
+    let mut rest = batches;
+    while let Some(&[a, b, c, d]) = rest.get(8..12) {
+        let end = 12 + i32::from_be_bytes([a, b, c, d]).max(0) as usize;
+        let Some(batch) = rest
+            .get(..end)
+            .filter(|batch| batch.len() >= RECORDS_OFFSET)
+        else {
+            break;
+        };
+        rest = &rest[end..];
+
+        let attributes =
+            i16::from_be_bytes([batch[ATTRIBUTES_OFFSET], batch[ATTRIBUTES_OFFSET + 1]]);
+        let records = &batch[RECORDS_OFFSET..];
+        let compressed = (attributes & (COMPRESSION_MASK | RecordBatch::CONTROL_BATCH) == 0)
+            .then(|| gzip::compress(records))
+            .filter(|compressed| compressed.len() < records.len());
+        let Some(compressed) = compressed else {
+            out.extend_from_slice(batch);
+            continue;
+        };
+
+        let start = out.len();
+        out.extend_from_slice(&batch[..RECORDS_OFFSET]);
+        out.extend_from_slice(&compressed);
+        let batch = &mut out[start..];
+        let batch_length = (batch.len() - 12) as i32;
+        batch[8..12].copy_from_slice(&batch_length.to_be_bytes());
+        batch[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 2]
+            .copy_from_slice(&(attributes | GZIP).to_be_bytes());
+        let crc = crc32c(&batch[ATTRIBUTES_OFFSET..]);
+        batch[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&crc.to_be_bytes());
+    }
+    out.extend_from_slice(rest);
+    out.freeze()
+}
+
+/// Offset and timestamp of a record in a partition log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordPosition {
+    pub offset: i64,
+    pub timestamp: i64,
+}
+
+/// Offsets and timestamps of the records in a partition log.
+///
+/// Only the batch headers and the fixed part of the records are read, the keys and values are skipped.
+/// Records of compressed batches cannot be read without decompressing them, such a batch is taken
+/// as a single record with the last offset and the max timestamp of the batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogOffsets {
+    pub log_start_offset: i64,
+    pub log_end_offset: i64,
+    positions: Vec<RecordPosition>,
+}
+
+impl LogOffsets {
+    /// Offsets of the log in the file, a missing file is an empty log
+    pub fn from_file(storage: &dyn Storage, path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        let file_bytes = match storage.read(path) {
+            Ok(file_bytes) => file_bytes,
+            Err(StorageError::NotFound(_)) => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+        Self::scan(file_bytes).map_err(|source| StorageError::Corrupt {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn scan(log: Bytes) -> Result<Self, ProtocolError> {
+        const COMPRESSION_MASK: i16 = 0x07;
+        const LOG_APPEND_TIME: i16 = 0x08;
+        const CONTROL_BATCH: i16 = 0x20;
+
+        let mut src = ByteReader::new(log);
+        let mut offsets = Self::default();
+        let mut first_batch = true;
+        while src.remaining() > 0 {
+            let base_offset = src.get_i64("base_offset")?;
+            let batch_length = src.get_i32("batch_length")?;
+            if batch_length < 0 {
+                return Err(ProtocolError::UnexpectedValue {
+                    field: "batch_length",
+                    value: batch_length.into(),
+                });
+            }
+            let mut batch = ByteReader::new(src.get_bytes("record batch", batch_length as usize)?);
+            _ = batch.get_i32("partition_leader_epoch")?;
+            _ = batch.get_i8("magic")?;
+            _ = batch.get_u32("crc")?;
+            let attributes = batch.get_i16("attributes")?;
+            let last_offset_delta = batch.get_i32("last_offset_delta")?;
+            let base_timestamp = batch.get_i64("base_timestamp")?;
+            let max_timestamp = batch.get_i64("max_timestamp")?;
+            _ = batch.get_i64("producer_id")?;
+            _ = batch.get_i16("producer_epoch")?;
+            _ = batch.get_i32("base_sequence")?;
+            let records_count = batch.get_i32("records_count")?;
 
-        Ok(Some(Bytes::from(file_bytes)))
+            if first_batch {
+                offsets.log_start_offset = base_offset;
+                first_batch = false;
+            }
+            // corrupt offsets and timestamps must not overflow
+            offsets.log_end_offset = base_offset
+                .saturating_add(last_offset_delta.into())
+                .saturating_add(1);
+
+            // control batches mark the ends of transactions, they have no records for the clients
+            if attributes & CONTROL_BATCH != 0 {
+                continue;
+            }
+            if attributes & COMPRESSION_MASK != 0 {
+                offsets.positions.push(RecordPosition {
+                    offset: base_offset.saturating_add(last_offset_delta.into()),
+                    timestamp: max_timestamp,
+                });
+                continue;
+            }
+            for _ in 0..records_count {
+                let length = batch.get_varlong("length")?;
+                let mut record =
+                    ByteReader::new(batch.get_bytes("record", length.max(0) as usize)?);
+                _ = record.get_i8("attributes")?;
+                let timestamp_delta = record.get_varlong("timestamp_delta")?;
+                let offset_delta = record.get_varlong("offset_delta")?;
+                offsets.positions.push(RecordPosition {
+                    offset: base_offset.saturating_add(offset_delta),
+                    // the broker sets the same time for all the records of the batch
+                    timestamp: if attributes & LOG_APPEND_TIME != 0 {
+                        max_timestamp
+                    } else {
+                        base_timestamp.saturating_add(timestamp_delta)
+                    },
+                });
+            }
+        }
+        Ok(offsets)
+    }
+
+    /// First record with the largest timestamp, none if the log has no records
+    pub fn max_timestamp(&self) -> Option<RecordPosition> {
+        self.positions
+            .iter()
+            .copied()
+            .reduce(|max, p| if p.timestamp > max.timestamp { p } else { max })
+    }
+
+    /// First record with a timestamp not earlier than the timestamp, none if all the records are earlier
+    pub fn offset_for_timestamp(&self, timestamp: i64) -> Option<RecordPosition> {
+        self.positions
+            .iter()
+            .find(|p| p.timestamp >= timestamp)
+            .copied()
     }
 }
 
@@ -102,26 +567,88 @@ pub struct RecordBatch {
     /// It is used to ensure the correct ordering and deduplication of messages produced by a Kafka producer.
     base_sequence: i32,
 
+    /// Decoded records, empty until `decode_records` for a batch read with `from_bytes_lazy`
     pub records: Vec<Record>, // NULLABLE_BYTES
+    /// Records array of a batch read with `from_bytes_lazy` that is not decoded yet
+    undecoded: Option<Bytes>,
 }
 
 impl RecordBatch {
-    pub fn from_bytes(src: &mut Bytes) -> Self {
-        let base_offset = src.get_i64();
-        let batch_length = src.get_i32();
-        let partition_leader_epoch = src.get_i32();
-        let magic = src.get_i8();
-        let crc = src.get_u32();
-        let attributes = src.get_i16();
-        let last_offset_delta = src.get_i32();
-        let base_timestamp = src.get_i64();
-        let max_timestamp = src.get_i64();
-        let producer_id = src.get_i64();
-        let producer_epoch = src.get_i16();
-        let base_sequence = src.get_i32();
-        let records = NullableBytes::deserialize::<Record, RecordBatch>(src);
+    /// Size of the batch header after the batch length up to the records array
+    const HEADER_AFTER_LENGTH: i32 = 45;
+    /// Attributes bit of the control batches
+    const CONTROL_BATCH: i16 = 0x20;
 
-        Self {
+    pub fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let mut batch = Self::header_from_bytes(src)?;
+        batch.records = NullableBytes::deserialize::<Record, RecordBatch>(src)?;
+        Ok(batch)
+    }
+
+    /// Reads the batch header and keeps the records as they are, they are decoded only by `decode_records`.
+    ///
+    /// For the batches whose raw bytes are served back, e.g. to consumers, when only the offsets and
+    /// timestamps of the header are needed. The records are delimited by the batch length.
+    pub fn from_bytes_lazy(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let mut batch = Self::header_from_bytes(src)?;
+        let records_len = batch.batch_length - Self::HEADER_AFTER_LENGTH;
+        if records_len < 4 {
+            return Err(ProtocolError::UnexpectedValue {
+                field: "batch_length",
+                value: batch.batch_length.into(),
+            });
+        }
+        batch.undecoded = Some(src.get_bytes("records", records_len as usize)?);
+        Ok(batch)
+    }
+
+    /// Records of the batch, decoded now if the batch was read with `from_bytes_lazy`
+    pub fn decode_records(&mut self) -> Result<&[Record], ProtocolError> {
+        if let Some(undecoded) = self.undecoded.take() {
+            let mut src = ByteReader::new(undecoded);
+            self.records = NullableBytes::deserialize::<Record, RecordBatch>(&mut src)?;
+            if src.remaining() > 0 {
+                return Err(ProtocolError::TrailingBytes {
+                    message: "record batch",
+                    remaining: src.remaining(),
+                });
+            }
+        }
+        Ok(&self.records)
+    }
+
+    /// Whether the records are decoded, i.e. the batch was not read with `from_bytes_lazy` or they were decoded since
+    pub fn is_decoded(&self) -> bool {
+        self.undecoded.is_none()
+    }
+
+    /// Whether the batch has control records, e.g. the markers of transactions, instead of data
+    pub fn is_control(&self) -> bool {
+        self.attributes & Self::CONTROL_BATCH != 0
+    }
+
+    /// Offset of the last record in the batch
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset
+            .saturating_add(self.last_offset_delta.into())
+    }
+
+    /// Fixed size part of the batch header up to the records array
+    fn header_from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let base_offset = src.get_i64("base_offset")?;
+        let batch_length = src.get_i32("batch_length")?;
+        let partition_leader_epoch = src.get_i32("partition_leader_epoch")?;
+        let magic = src.get_i8("magic")?;
+        let crc = src.get_u32("crc")?;
+        let attributes = src.get_i16("attributes")?;
+        let last_offset_delta = src.get_i32("last_offset_delta")?;
+        let base_timestamp = src.get_i64("base_timestamp")?;
+        let max_timestamp = src.get_i64("max_timestamp")?;
+        let producer_id = src.get_i64("producer_id")?;
+        let producer_epoch = src.get_i16("producer_epoch")?;
+        let base_sequence = src.get_i32("base_sequence")?;
+
+        Ok(Self {
             base_offset,
             batch_length,
             partition_leader_epoch,
@@ -134,25 +661,32 @@ impl RecordBatch {
             producer_id,
             producer_epoch,
             base_sequence,
-            records,
-        }
+            records: Vec::new(),
+            undecoded: None,
+        })
     }
 }
 
 impl types::Deserialize<Record> for RecordBatch {
-    fn deserialize(src: &mut Bytes) -> Record {
+    fn deserialize(src: &mut ByteReader) -> Result<Record, ProtocolError> {
         Record::from_bytes(src)
     }
 }
 
+/// The batch length and the CRC are set to the ones of the serialized records.
+/// Records not decoded yet are written as they were read.
 impl types::Serialize for RecordBatch {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
+    fn serialize_into(&mut self, b: &mut BytesMut) {
+        // the CRC covers the batch from the attributes to the end
+        const CRC_OFFSET: usize = 17;
+        const ATTRIBUTES_OFFSET: usize = 21;
+
+        let start = b.len();
         b.put_i64(self.base_offset);
-        b.put_i32(self.batch_length);
+        b.put_i32(0); // batch length
         b.put_i32(self.partition_leader_epoch);
         b.put_i8(self.magic);
-        b.put_u32(self.crc);
+        b.put_u32(0); // crc
         b.put_i16(self.attributes);
         b.put_i32(self.last_offset_delta);
         b.put_i64(self.base_timestamp);
@@ -160,7 +694,21 @@ impl types::Serialize for RecordBatch {
         b.put_i64(self.producer_id);
         b.put_i16(self.producer_epoch);
         b.put_i32(self.base_sequence);
-        b.freeze()
+        match &self.undecoded {
+            Some(undecoded) => b.put_slice(undecoded),
+            None => {
+                b.put_i32(self.records.len() as i32);
+                for record in &mut self.records {
+                    record.serialize_into(b);
+                }
+            }
+        }
+
+        let batch = &mut b[start..];
+        self.batch_length = batch.len() as i32 - 12;
+        batch[8..12].copy_from_slice(&self.batch_length.to_be_bytes());
+        self.crc = crc32c(&batch[ATTRIBUTES_OFFSET..]);
+        batch[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&self.crc.to_be_bytes());
     }
 }
 
@@ -173,30 +721,46 @@ pub struct Record {
     /// Attributes is a 1-byte big-endian integer indicating the attributes of the record. Currently, this field is unused in the protocol.
     attributes: i8,
     /// Timestamp Delta is a signed variable size integer indicating the difference between the timestamp of the record and the base timestamp of the record batch.
-    timestamp_delta: i64,
+    pub timestamp_delta: i64,
     /// Offset Delta is a signed variable size integer indicating the difference between the offset of the record and the base offset of the record batch.
-    offset_delta: i64,
-    /// Key is a byte array indicating the key of the record.
-    key: Vec<u8>,
+    pub offset_delta: i64,
+    /// Key is a byte array indicating the key of the record, `None` if the key is null.
+    pub key: Option<Bytes>,
     /// Value Length is a signed variable size integer indicating the length of the value of the record.
-    value_length: i64,
+    pub value_length: i64,
     /// Value is a byte array indicating the value of the record.
     pub value: RecordValue,
     headers: Vec<Header>,
 }
 
 impl Record {
-    pub fn from_bytes(src: &mut Bytes) -> Self {
-        let length = VarInt::deserialize(src);
-        let attributes = src.get_i8();
-        let timestamp_delta = VarInt::deserialize(src);
-        let offset_delta = VarInt::deserialize(src);
-        let key = CompactNullableBytes::deserialize(src);
-        let value_length = VarInt::deserialize(src);
-        let value = RecordValue::from_bytes(src);
-        let headers = CompactArray::deserialize::<Header, Record>(src);
-
-        Record {
+    pub fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let length = src.get_varlong("length")?;
+        let attributes = src.get_i8("attributes")?;
+        let timestamp_delta = src.get_varlong("timestamp_delta")?;
+        let offset_delta = src.get_varlong("offset_delta")?;
+        let key_length = src.get_varlong("key_length")?;
+        let key = if key_length < 0 {
+            None
+        } else {
+            Some(src.get_bytes("key", key_length as usize)?)
+        };
+        let value_length = src.get_varlong("value_length")?;
+        let value = RecordValue::from_bytes(src)?;
+        let headers_count = src.get_varlong("headers_count")?;
+        if headers_count < 0 {
+            return Err(ProtocolError::UnexpectedValue {
+                field: "headers_count",
+                value: headers_count,
+            });
+        }
+        // every header takes at least two bytes, so a made up count ends with a decode error
+        let mut headers = Vec::new();
+        for _ in 0..headers_count {
+            headers.push(Header::from_bytes(src)?);
+        }
+
+        Ok(Record {
             length,
             attributes,
             timestamp_delta,
@@ -205,26 +769,77 @@ impl Record {
             value_length,
             value,
             headers,
-        }
+        })
     }
 }
 
-impl types::Deserialize<Header> for Record {
-    fn deserialize(_src: &mut Bytes) -> Header {
-        // we assume that headers array is empty, so this would not be called
-        Header
+/// The length and the value length are set to the ones of the serialized record.
+/// The record is serialized into a buffer of its own first, as its length precedes it as a varint.
+impl types::Serialize for Record {
+    fn serialize_into(&mut self, dst: &mut BytesMut) {
+        let mut b = BytesMut::new();
+        b.put_i8(self.attributes);
+        VarLong::serialize_into(self.timestamp_delta, &mut b);
+        VarLong::serialize_into(self.offset_delta, &mut b);
+        match &self.key {
+            Some(key) => {
+                VarLong::serialize_into(key.len() as i64, &mut b);
+                b.put(key.clone());
+            }
+            None => VarLong::serialize_into(-1, &mut b),
+        }
+        let value = self.value.serialize();
+        self.value_length = value.len() as i64;
+        VarLong::serialize_into(self.value_length, &mut b);
+        b.put(value);
+        VarLong::serialize_into(self.headers.len() as i64, &mut b);
+        for _ in &self.headers {
+            // the keys and values of the headers are not kept
+            VarLong::serialize_into(0, &mut b);
+            VarLong::serialize_into(-1, &mut b);
+        }
+
+        self.length = b.len() as i64;
+        VarLong::serialize_into(self.length, dst);
+        dst.put(b);
     }
 }
 
+/// Header of a record, its key and value are skipped
 #[derive(Debug, Clone, Copy)]
 struct Header;
 
+impl Header {
+    fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let key_length = src.get_varlong("header_key_length")?;
+        if key_length < 0 {
+            return Err(ProtocolError::UnexpectedValue {
+                field: "header_key_length",
+                value: key_length,
+            });
+        }
+        _ = src.get_bytes("header_key", key_length as usize)?;
+        // -1 is a null value
+        let value_length = src.get_varlong("header_value_length")?;
+        if value_length > 0 {
+            _ = src.get_bytes("header_value", value_length as usize)?;
+        }
+        Ok(Header)
+    }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum RecordValue {
     FeatureLevel(FeatureLevelValue),
     Topic(TopicValue),
     Partition(PartitionValue),
+    PartitionChange(PartitionChangeValue),
+    Config(ConfigValue),
+    RegisterBroker(RegisterBrokerValue),
+    UnregisterBroker(BrokerEpochValue),
+    FenceBroker(BrokerEpochValue),
+    UnfenceBroker(BrokerEpochValue),
 }
 
 #[derive(Debug, Clone)]
@@ -251,13 +866,221 @@ pub struct PartitionValue {
 }
 
 impl types::Deserialize<u32> for PartitionValue {
-    fn deserialize(src: &mut Bytes) -> u32 {
-        src.get_u32()
+    fn deserialize(src: &mut ByteReader) -> Result<u32, ProtocolError> {
+        src.get_u32("replica id")
     }
 }
 
 impl types::Deserialize<String> for PartitionValue {
-    fn deserialize(src: &mut Bytes) -> String {
+    fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
+        Uuid::deserialize(src)
+    }
+}
+
+/// Change of a partition by the controller, e.g. of its leader or its in-sync replicas.
+///
+/// The changed fields are tagged fields of the record, the ones that are `None` do not change. The
+/// leader recovery state and the eligible leader replicas are not kept.
+#[derive(Debug, Clone, Default)]
+pub struct PartitionChangeValue {
+    pub partition_id: u32,
+    pub topic_id: String,
+    pub in_sync_replicas: Option<Vec<u32>>,
+    /// New leader of the partition, -1 if it has none
+    pub leader_id: Option<u32>,
+    pub replicas: Option<Vec<u32>>,
+    pub removing_replicas: Option<Vec<u32>>,
+    pub adding_replicas: Option<Vec<u32>>,
+    pub directories: Option<Vec<String>>,
+}
+
+impl PartitionChangeValue {
+    /// Leader of the record that leaves the leader as it is, Kafka's `NO_LEADER_CHANGE`
+    const NO_LEADER_CHANGE: u32 = -2i32 as u32;
+
+    /// Applies the change to the partition. A new leader starts a new leader epoch, every change is a new
+    /// partition epoch.
+    pub fn apply(&self, p: &mut PartitionValue) {
+        let replace = |replicas: &mut Vec<u32>, changed: &Option<Vec<u32>>| {
+            if let Some(changed) = changed {
+                replicas.clone_from(changed);
+            }
+        };
+        replace(&mut p.in_sync_replicas, &self.in_sync_replicas);
+        replace(&mut p.replicas, &self.replicas);
+        replace(&mut p.removing_replicas, &self.removing_replicas);
+        replace(&mut p.adding_replicas, &self.adding_replicas);
+        if let Some(directories) = &self.directories {
+            p.directories.clone_from(directories);
+        }
+        if let Some(leader_id) = self.leader_id {
+            p.leader_id = leader_id;
+            p.leader_epoch += 1;
+        }
+        p.partition_epoch += 1;
+    }
+
+    /// Decodes the tagged fields of the record
+    fn tagged_fields_from_bytes(&mut self, src: &mut ByteReader) -> Result<(), ProtocolError> {
+        let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+        for _ in 0..tagged_fields_count {
+            let tag = src.get_varint("tag")?;
+            let size = src.get_varint("tag size")?;
+            let mut field = ByteReader::new(src.get_bytes("tagged field", size as usize)?);
+            let replicas = |field: &mut ByteReader| {
+                CompactArray::deserialize::<u32, PartitionValue>(field).map(Some)
+            };
+            match tag {
+                0 => self.in_sync_replicas = replicas(&mut field)?,
+                1 => {
+                    let leader_id = field.get_u32("leader_id")?;
+                    self.leader_id = (leader_id != Self::NO_LEADER_CHANGE).then_some(leader_id);
+                }
+                2 => self.replicas = replicas(&mut field)?,
+                3 => self.removing_replicas = replicas(&mut field)?,
+                4 => self.adding_replicas = replicas(&mut field)?,
+                8 => {
+                    self.directories = Some(CompactArray::deserialize::<String, PartitionValue>(
+                        &mut field,
+                    )?)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn serialize_tagged_fields(&self, dst: &mut BytesMut) {
+        let replicas = |replicas: &[u32]| {
+            let mut b = BytesMut::new();
+            VarInt::serialize_into(replicas.len() as u64 + 1, &mut b);
+            for &replica in replicas {
+                b.put_u32(replica);
+            }
+            b.freeze()
+        };
+        let directories = |directories: &[String]| {
+            let mut b = BytesMut::new();
+            VarInt::serialize_into(directories.len() as u64 + 1, &mut b);
+            for directory in directories {
+                Uuid::serialize_into(directory, &mut b);
+            }
+            b.freeze()
+        };
+        let leader_id = |leader_id: u32| Bytes::copy_from_slice(&leader_id.to_be_bytes());
+        // the tags are in ascending order
+        let fields: Vec<(u64, Bytes)> = [
+            (0, self.in_sync_replicas.as_deref().map(replicas)),
+            (1, self.leader_id.map(leader_id)),
+            (2, self.replicas.as_deref().map(replicas)),
+            (3, self.removing_replicas.as_deref().map(replicas)),
+            (4, self.adding_replicas.as_deref().map(replicas)),
+            (8, self.directories.as_deref().map(directories)),
+        ]
+        .into_iter()
+        .filter_map(|(tag, field)| Some((tag, field?)))
+        .collect();
+        VarInt::serialize_into(fields.len() as u64, dst);
+        for (tag, field) in fields {
+            VarInt::serialize_into(tag, dst);
+            VarInt::serialize_into(field.len() as u64, dst);
+            dst.put(field);
+        }
+    }
+}
+
+/// Config of a resource set by e.g. `kafka-configs.sh --alter`, a null value deletes the config
+#[derive(Debug, Clone)]
+pub struct ConfigValue {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl ConfigValue {
+    /// Resource type of topic configs, Kafka's `ConfigResource.Type.TOPIC`
+    pub const TOPIC_RESOURCE: i8 = 2;
+}
+
+/// Registration of a broker with the controller, a broker registers again with a new epoch when it restarts
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct RegisterBrokerValue {
+    pub broker_id: i32,
+    pub incarnation_id: String,
+    pub broker_epoch: i64,
+    /// Listeners of the broker, in the order of its `listeners` config
+    pub end_points: Vec<BrokerEndpoint>,
+    pub rack: Option<String>,
+    /// Fenced brokers may not lead partitions and are not shown to clients
+    pub fenced: bool,
+}
+
+/// Broker registration that is unregistered, fenced or unfenced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BrokerEpochValue {
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+}
+
+impl BrokerEpochValue {
+    fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let broker_id = src.get_i32("broker_id")?;
+        let broker_epoch = src.get_i64("broker_epoch")?;
+        let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+        expect_value("tagged fields count", tagged_fields_count, 0)?;
+        Ok(Self {
+            broker_id,
+            broker_epoch,
+        })
+    }
+}
+
+/// Listener of a registered broker
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct BrokerEndpoint {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub security_protocol: i16,
+}
+
+impl types::Deserialize<BrokerEndpoint> for RegisterBrokerValue {
+    fn deserialize(src: &mut ByteReader) -> Result<BrokerEndpoint, ProtocolError> {
+        let name = CompactString::deserialize(src)?;
+        let host = CompactString::deserialize(src)?;
+        let port = src.get_u16("port")?;
+        let security_protocol = src.get_i16("security_protocol")?;
+        let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+        expect_value("tagged fields count", tagged_fields_count, 0)?;
+        Ok(BrokerEndpoint {
+            name,
+            host,
+            port,
+            security_protocol,
+        })
+    }
+}
+
+/// Supported versions of a feature, they are not needed and only skipped
+#[derive(Debug, Clone, Copy)]
+struct BrokerFeature;
+
+impl types::Deserialize<BrokerFeature> for RegisterBrokerValue {
+    fn deserialize(src: &mut ByteReader) -> Result<BrokerFeature, ProtocolError> {
+        let _name = CompactString::deserialize(src)?;
+        let _min_supported_version = src.get_i16("min_supported_version")?;
+        let _max_supported_version = src.get_i16("max_supported_version")?;
+        let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+        expect_value("tagged fields count", tagged_fields_count, 0)?;
+        Ok(BrokerFeature)
+    }
+}
+
+impl types::Deserialize<String> for RegisterBrokerValue {
+    fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
         Uuid::deserialize(src)
     }
 }
@@ -270,49 +1093,48 @@ pub struct FeatureLevelValue {
 }
 
 impl RecordValue {
-    pub fn from_bytes(src: &mut Bytes) -> Self {
+    pub fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
         // Frame Version is indicating the version of the format of the record.
-        let frame_version = src.get_u8();
-        assert_eq!(frame_version, 1);
+        let frame_version = src.get_u8("frame_version")?;
+        expect_value("frame version", frame_version.into(), 1)?;
 
-        let record_type = src.get_u8();
+        let record_type = src.get_u8("record_type")?;
+        let version = src.get_u8("version")?;
         match record_type {
             2 => {
                 // Topic Record Value
-                let version = src.get_u8();
-                assert_eq!(version, 0);
-                let topic_name = CompactString::deserialize(src);
-                let topic_id = Uuid::deserialize(src);
-
-                let tagged_fields_count = VarInt::deserialize(src);
-                assert_eq!(tagged_fields_count, 0);
-                RecordValue::Topic(TopicValue {
+                expect_value("topic record version", version.into(), 0)?;
+                let topic_name = CompactString::deserialize(src)?;
+                let topic_id = Uuid::deserialize(src)?;
+
+                let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+                expect_value("tagged fields count", tagged_fields_count, 0)?;
+                Ok(RecordValue::Topic(TopicValue {
                     topic_name,
                     topic_id,
-                })
+                }))
             }
             3 => {
                 // Partition Record Value
-                let version = src.get_u8();
-                assert_eq!(version, 1);
-                let partition_id = src.get_u32();
-                let topic_id = Uuid::deserialize(src);
+                expect_value("partition record version", version.into(), 1)?;
+                let partition_id = src.get_u32("partition_id")?;
+                let topic_id = Uuid::deserialize(src)?;
 
-                let replicas = CompactArray::deserialize::<_, PartitionValue>(src);
-                let in_sync_replicas = CompactArray::deserialize::<_, PartitionValue>(src);
-                let removing_replicas = CompactArray::deserialize::<_, PartitionValue>(src);
-                let adding_replicas = CompactArray::deserialize::<_, PartitionValue>(src);
+                let replicas = CompactArray::deserialize::<_, PartitionValue>(src)?;
+                let in_sync_replicas = CompactArray::deserialize::<_, PartitionValue>(src)?;
+                let removing_replicas = CompactArray::deserialize::<_, PartitionValue>(src)?;
+                let adding_replicas = CompactArray::deserialize::<_, PartitionValue>(src)?;
 
-                let leader_id = src.get_u32();
-                let leader_epoch = src.get_u32();
-                let partition_epoch = src.get_u32();
+                let leader_id = src.get_u32("leader_id")?;
+                let leader_epoch = src.get_u32("leader_epoch")?;
+                let partition_epoch = src.get_u32("partition_epoch")?;
 
-                let directories = CompactArray::deserialize::<String, PartitionValue>(src);
+                let directories = CompactArray::deserialize::<String, PartitionValue>(src)?;
 
-                let tagged_fields_count = VarInt::deserialize(src);
-                assert_eq!(tagged_fields_count, 0);
+                let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+                expect_value("tagged fields count", tagged_fields_count, 0)?;
 
-                RecordValue::Partition(PartitionValue {
+                Ok(RecordValue::Partition(PartitionValue {
                     partition_id,
                     topic_id,
                     replicas,
@@ -323,21 +1145,779 @@ impl RecordValue {
                     leader_epoch,
                     partition_epoch,
                     directories,
-                })
+                }))
+            }
+
+            4 => {
+                // Config Record Value
+                expect_value("config record version", version.into(), 0)?;
+                let resource_type = src.get_i8("resource_type")?;
+                let resource_name = CompactString::deserialize(src)?;
+                let name = CompactString::deserialize(src)?;
+                let value = CompactNullableString::deserialize(src)?;
+                let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+                expect_value("tagged fields count", tagged_fields_count, 0)?;
+                Ok(RecordValue::Config(ConfigValue {
+                    resource_type,
+                    resource_name,
+                    name,
+                    value,
+                }))
+            }
+
+            5 => {
+                // Partition Change Record Value, the versions add tagged fields
+                if version > 2 {
+                    return Err(ProtocolError::UnexpectedValue {
+                        field: "partition change record version",
+                        value: version.into(),
+                    });
+                }
+                let mut change = PartitionChangeValue {
+                    partition_id: src.get_u32("partition_id")?,
+                    topic_id: Uuid::deserialize(src)?,
+                    ..Default::default()
+                };
+                change.tagged_fields_from_bytes(src)?;
+                Ok(RecordValue::PartitionChange(change))
+            }
+
+            0 => {
+                // Register Broker Record Value, versions 1-3 add fields at the end or after the broker id
+                if version > 3 {
+                    return Err(ProtocolError::UnexpectedValue {
+                        field: "register broker record version",
+                        value: version.into(),
+                    });
+                }
+                let broker_id = src.get_i32("broker_id")?;
+                if version >= 2 {
+                    let _is_migrating_zk_broker = src.get_u8("is_migrating_zk_broker")?;
+                }
+                let incarnation_id = Uuid::deserialize(src)?;
+                let broker_epoch = src.get_i64("broker_epoch")?;
+                let end_points = CompactArray::deserialize::<_, RegisterBrokerValue>(src)?;
+                let _features =
+                    CompactArray::deserialize::<BrokerFeature, RegisterBrokerValue>(src)?;
+                let rack = CompactNullableString::deserialize(src)?;
+                // brokers of version 0 registrations are fenced until they send a heartbeat
+                let fenced = version < 1 || src.get_u8("fenced")? != 0;
+                if version >= 2 {
+                    let _in_controlled_shutdown = src.get_u8("in_controlled_shutdown")?;
+                }
+                if version >= 3 {
+                    let _log_dirs = CompactArray::deserialize::<String, RegisterBrokerValue>(src)?;
+                }
+                let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+                expect_value("tagged fields count", tagged_fields_count, 0)?;
+                Ok(RecordValue::RegisterBroker(RegisterBrokerValue {
+                    broker_id,
+                    incarnation_id,
+                    broker_epoch,
+                    end_points,
+                    rack,
+                    fenced,
+                }))
+            }
+            1 => {
+                // Unregister Broker Record Value
+                expect_value("unregister broker record version", version.into(), 0)?;
+                Ok(RecordValue::UnregisterBroker(BrokerEpochValue::from_bytes(
+                    src,
+                )?))
+            }
+
+            8 => {
+                // Fence Broker Record Value
+                expect_value("fence broker record version", version.into(), 0)?;
+                Ok(RecordValue::FenceBroker(BrokerEpochValue::from_bytes(src)?))
+            }
+            9 => {
+                // Unfence Broker Record Value
+                expect_value("unfence broker record version", version.into(), 0)?;
+                Ok(RecordValue::UnfenceBroker(BrokerEpochValue::from_bytes(
+                    src,
+                )?))
             }
 
             12 => {
                 // Feature Level Record Value
-                let version = src.get_u8();
-                assert_eq!(version, 0);
-                let name = CompactString::deserialize(src);
-                let level = src.get_u16();
-                let tagged_fields_count = VarInt::deserialize(src);
-                assert_eq!(tagged_fields_count, 0);
-                RecordValue::FeatureLevel(FeatureLevelValue { name, level })
+                expect_value("feature level record version", version.into(), 0)?;
+                let name = CompactString::deserialize(src)?;
+                let level = src.get_u16("level")?;
+                let tagged_fields_count = src.get_varint("tagged_fields_count")?;
+                expect_value("tagged fields count", tagged_fields_count, 0)?;
+                Ok(RecordValue::FeatureLevel(FeatureLevelValue { name, level }))
+            }
+
+            _ => Err(ProtocolError::UnexpectedValue {
+                field: "record type",
+                value: record_type.into(),
+            }),
+        }
+    }
+}
+
+impl RecordValue {
+    /// Serializes the record in the versions `from_bytes` reads, register broker records in version 1
+    /// as the fields of the later versions are not kept
+    pub fn serialize(&self) -> Bytes {
+        let mut b = BytesMut::new();
+        b.put_u8(1); // frame version
+        match self {
+            RecordValue::Topic(topic) => {
+                b.put_slice(&[2, 0]); // record type and version
+                CompactString::serialize_into(&topic.topic_name, &mut b);
+                Uuid::serialize_into(&topic.topic_id, &mut b);
+            }
+            RecordValue::Partition(p) => {
+                b.put_slice(&[3, 1]);
+                b.put_u32(p.partition_id);
+                Uuid::serialize_into(&p.topic_id, &mut b);
+                for replicas in [
+                    &p.replicas,
+                    &p.in_sync_replicas,
+                    &p.removing_replicas,
+                    &p.adding_replicas,
+                ] {
+                    CompactArray::serialize_into(&mut replicas.clone(), &mut b);
+                }
+                b.put_u32(p.leader_id);
+                b.put_u32(p.leader_epoch);
+                b.put_u32(p.partition_epoch);
+                VarInt::serialize_into(p.directories.len() as u64 + 1, &mut b);
+                for directory in &p.directories {
+                    Uuid::serialize_into(directory, &mut b);
+                }
+            }
+            RecordValue::PartitionChange(change) => {
+                b.put_slice(&[5, 0]);
+                b.put_u32(change.partition_id);
+                Uuid::serialize_into(&change.topic_id, &mut b);
+                change.serialize_tagged_fields(&mut b);
+                return b.freeze();
+            }
+            RecordValue::Config(config) => {
+                b.put_slice(&[4, 0]);
+                b.put_i8(config.resource_type);
+                CompactString::serialize_into(&config.resource_name, &mut b);
+                CompactString::serialize_into(&config.name, &mut b);
+                CompactNullableString::serialize_into(config.value.as_deref(), &mut b);
+            }
+            RecordValue::RegisterBroker(broker) => {
+                b.put_slice(&[0, 1]);
+                b.put_i32(broker.broker_id);
+                Uuid::serialize_into(&broker.incarnation_id, &mut b);
+                b.put_i64(broker.broker_epoch);
+                VarInt::serialize_into(broker.end_points.len() as u64 + 1, &mut b);
+                for end_point in &broker.end_points {
+                    CompactString::serialize_into(&end_point.name, &mut b);
+                    CompactString::serialize_into(&end_point.host, &mut b);
+                    b.put_u16(end_point.port);
+                    b.put_i16(end_point.security_protocol);
+                    b.put_u8(0); // tagged fields
+                }
+                VarInt::serialize_into(1, &mut b); // no features
+                CompactNullableString::serialize_into(broker.rack.as_deref(), &mut b);
+                b.put_u8(broker.fenced.into());
+            }
+            RecordValue::UnregisterBroker(broker) => broker.serialize(&mut b, 1),
+            RecordValue::FenceBroker(broker) => broker.serialize(&mut b, 8),
+            RecordValue::UnfenceBroker(broker) => broker.serialize(&mut b, 9),
+            RecordValue::FeatureLevel(feature) => {
+                b.put_slice(&[12, 0]);
+                CompactString::serialize_into(&feature.name, &mut b);
+                b.put_u16(feature.level);
+            }
+        }
+        b.put_u8(0); // tagged fields
+        b.freeze()
+    }
+}
+
+impl BrokerEpochValue {
+    fn serialize(&self, dst: &mut BytesMut, record_type: u8) {
+        dst.put_slice(&[record_type, 0]);
+        dst.put_i32(self.broker_id);
+        dst.put_i64(self.broker_epoch);
+    }
+}
+
+#[cfg(test)]
+impl RecordBatch {
+    /// Uncompressed batch of metadata records with the values, e.g. for a metadata log in tests
+    pub(crate) fn of_values(base_offset: i64, values: Vec<RecordValue>) -> Self {
+        let records: Vec<_> = values
+            .into_iter()
+            .enumerate()
+            .map(|(offset_delta, value)| Record {
+                length: 0,
+                attributes: 0,
+                timestamp_delta: 0,
+                offset_delta: offset_delta as i64,
+                key: None,
+                value_length: 0,
+                value,
+                headers: Vec::new(),
+            })
+            .collect();
+        Self {
+            base_offset,
+            batch_length: 0,
+            partition_leader_epoch: 0,
+            magic: 2,
+            crc: 0,
+            attributes: 0,
+            last_offset_delta: records.len() as i32 - 1,
+            base_timestamp: 0,
+            max_timestamp: 0,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            records,
+            undecoded: None,
+        }
+    }
+}
+
+fn expect_value(field: &'static str, value: i64, expected: i64) -> Result<(), ProtocolError> {
+    if value != expected {
+        return Err(ProtocolError::UnexpectedValue { field, value });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use super::{
+        gzip_batches, BrokerEndpoint, BrokerEpochValue, ConfigValue, FeatureLevelValue, Header,
+        LogOffsets, LogSlice, PartitionChangeValue, PartitionValue, Record, RecordBatch,
+        RecordBatches, RecordPosition, RecordValue, RegisterBrokerValue, TopicValue,
+    };
+    use crate::{
+        protocol::{
+            crc32c::crc32c,
+            gzip,
+            reader::ByteReader,
+            testing::{self, Gen},
+            types::{Serialize, VarLong},
+        },
+        storage::{MemoryStorage, StorageError},
+    };
+
+    /// Batch with records of the given timestamp deltas, each one with an empty key and value
+    fn batch(base_offset: i64, attributes: i16, base_timestamp: i64, deltas: &[i64]) -> Bytes {
+        let mut records = BytesMut::new();
+        for (offset_delta, timestamp_delta) in deltas.iter().enumerate() {
+            let mut record = BytesMut::new();
+            record.put_i8(0);
+            VarLong::serialize_into(*timestamp_delta, &mut record);
+            VarLong::serialize_into(offset_delta as i64, &mut record);
+            VarLong::serialize_into(-1, &mut record); // key
+            VarLong::serialize_into(-1, &mut record); // value
+            VarLong::serialize_into(0, &mut record); // headers
+            VarLong::serialize_into(record.len() as i64, &mut records);
+            records.put(record);
+        }
+
+        let mut b = BytesMut::new();
+        b.put_i32(0); // partition leader epoch
+        b.put_i8(2);
+        b.put_u32(0);
+        b.put_i16(attributes);
+        b.put_i32(deltas.len() as i32 - 1);
+        b.put_i64(base_timestamp);
+        b.put_i64(base_timestamp + deltas.iter().max().unwrap());
+        b.put_i64(-1);
+        b.put_i16(-1);
+        b.put_i32(-1);
+        b.put_i32(deltas.len() as i32);
+        b.put(records);
+
+        let mut batch = BytesMut::new();
+        batch.put_i64(base_offset);
+        batch.put_i32(b.len() as i32);
+        batch.put(b);
+        batch.freeze()
+    }
+
+    #[test]
+    fn scans_offsets_and_timestamps() {
+        let mut log = BytesMut::new();
+        log.put(batch(5, 0, 1000, &[0, 30, 10]));
+        log.put(batch(8, 0, 1000, &[30, 5]));
+        let offsets = LogOffsets::scan(log.clone().freeze()).unwrap();
+
+        assert_eq!((offsets.log_start_offset, offsets.log_end_offset), (5, 10));
+        let at = |offset, timestamp| Some(RecordPosition { offset, timestamp });
+        // the earliest of the records with the max timestamp
+        assert_eq!(offsets.max_timestamp(), at(6, 1030));
+        assert_eq!(offsets.offset_for_timestamp(1005), at(6, 1030));
+        assert_eq!(offsets.offset_for_timestamp(0), at(5, 1000));
+        assert_eq!(offsets.offset_for_timestamp(1031), None);
+
+        // log append time (bit 3) overrides the timestamps of the records
+        log.put(batch(10, 0x08, 2000, &[0, 1]));
+        let offsets = LogOffsets::scan(log.freeze()).unwrap();
+        assert_eq!(offsets.max_timestamp(), at(10, 2001));
+
+        let empty = LogOffsets::scan(Bytes::new()).unwrap();
+        assert_eq!((empty.log_start_offset, empty.log_end_offset), (0, 0));
+        assert_eq!(empty.max_timestamp(), None);
+    }
+
+    #[test]
+    fn gzips_the_records_of_uncompressed_batches() {
+        let uncompressed = batch(5, 0x08, 1000, &[0; 50]);
+        // a gzip batch, whose records are not compressed for the test, and a control batch
+        let gzipped = batch(55, 0x01, 1000, &[0; 50]);
+        let control = batch(105, 0x20, 1000, &[0; 50]);
+        let log = [uncompressed.clone(), gzipped.clone(), control.clone()].concat();
+
+        let compressed = gzip_batches(&log);
+        let mut src = ByteReader::new(compressed.clone());
+        let batch = RecordBatch::from_bytes_lazy(&mut src).unwrap();
+        assert_eq!((batch.base_offset, batch.last_offset()), (5, 54));
+        assert_eq!(batch.attributes, 0x09);
+        let records = &uncompressed[61..];
+        let gzip = &compressed[61..12 + batch.batch_length as usize];
+        assert!(gzip.len() < records.len());
+        assert_eq!(gzip, gzip::compress(records));
+        assert_eq!(
+            batch.crc,
+            crc32c(&compressed[21..12 + batch.batch_length as usize])
+        );
+        // the record count is not compressed
+        assert_eq!(compressed[57..61], uncompressed[57..61]);
+
+        let rest = compressed.slice(12 + batch.batch_length as usize..);
+        assert_eq!(rest, [gzipped, control].concat());
+        let offsets = LogOffsets::scan(compressed).unwrap();
+        assert_eq!(offsets.log_end_offset, 155);
+
+        // a batch cut off at the end is not compressed
+        assert_eq!(gzip_batches(&uncompressed[..100]), uncompressed[..100]);
+    }
+
+    #[test]
+    fn log_slices_from_the_fetch_offset() {
+        // offsets 0-2, 3-4 and 5
+        let batches = [
+            batch(0, 0, 1000, &[0, 1, 2]),
+            batch(3, 0, 1000, &[0, 1]),
+            batch(5, 0, 1000, &[0]),
+        ];
+        let log: Bytes = batches.concat().into();
+        let (a, b, c) = (batches[0].len(), batches[1].len(), batches[2].len());
+
+        let slice = LogSlice::slice(log.clone(), 0, usize::MAX).unwrap();
+        assert_eq!(slice.records, log);
+        assert_eq!(slice.offsets, Some((0, 5)));
+        assert_eq!((slice.log_start_offset, slice.log_end_offset), (0, 6));
+
+        // the batch with offset 4 and the next one that fits
+        let slice = LogSlice::slice(log.clone(), 4, b + c).unwrap();
+        assert_eq!(slice.records, log.slice(a..));
+        assert_eq!(slice.offsets, Some((3, 5)));
+
+        // the first batch even if it does not fit, then none
+        let slice = LogSlice::slice(log.clone(), 1, 1).unwrap();
+        assert_eq!(slice.records, log.slice(..a));
+        assert_eq!(slice.offsets, Some((0, 2)));
+
+        let slice = LogSlice::slice(log.clone(), 6, usize::MAX).unwrap();
+        assert!(slice.records.is_empty());
+        assert_eq!(slice.offsets, None);
+        assert_eq!(slice.log_end_offset, 6);
+
+        assert!(LogSlice::slice(log.slice(..a + 1), 0, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn corrupt_batches_are_errors() {
+        let valid = batch(5, 0, 1000, &[0, 30, 10]);
+        // every truncation and every byte replaced with 0xFF or 0x7F
+        let mut corrupt: Vec<Bytes> = (0..valid.len()).map(|len| valid.slice(..len)).collect();
+        for i in 0..valid.len() {
+            for byte in [0xFF, 0x7F] {
+                let mut bytes = BytesMut::from(&valid[..]);
+                bytes[i] = byte;
+                corrupt.push(bytes.freeze());
+            }
+        }
+        for bytes in corrupt {
+            _ = RecordBatch::from_bytes(&mut ByteReader::new(bytes.clone()));
+            if let Ok(mut batch) = RecordBatch::from_bytes_lazy(&mut ByteReader::new(bytes.clone()))
+            {
+                _ = batch.decode_records();
             }
+            _ = LogOffsets::scan(bytes);
+        }
 
-            _ => unimplemented!(),
+        let offsets = LogOffsets::scan(batch(i64::MAX, 0, 1000, &[0, 1])).unwrap();
+        assert_eq!(offsets.log_end_offset, i64::MAX);
+
+        // attributes, deltas, null key and value of a topic record, then a made up headers count
+        let mut record = BytesMut::new();
+        VarLong::serialize_into(0, &mut record);
+        record.put_slice(&[0, 0, 0, 0]);
+        VarLong::serialize_into(-1, &mut record);
+        record.put_slice(&[
+            1, 2, 0, 2, b'a', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]);
+        VarLong::serialize_into(i64::MAX, &mut record);
+        assert!(Record::from_bytes(&mut ByteReader::new(record.freeze())).is_err());
+    }
+
+    #[test]
+    fn log_files_are_read_from_storage() {
+        let valid = batch(0, 0, 1000, &[0, 1]);
+        let storage = MemoryStorage::with_files([
+            ("/logs/foo-0/0.log", valid.clone()),
+            ("/logs/foo-1/0.log", valid.slice(..valid.len() - 1)),
+        ]);
+
+        let offsets = LogOffsets::from_file(&storage, "/logs/foo-0/0.log").unwrap();
+        assert_eq!(offsets.log_end_offset, 2);
+        // nothing was produced to the partition
+        let offsets = LogOffsets::from_file(&storage, "/logs/foo-2/0.log").unwrap();
+        assert_eq!(offsets, LogOffsets::default());
+        assert!(matches!(
+            LogOffsets::from_file(&storage, "/logs/foo-1/0.log"),
+            Err(StorageError::Corrupt { .. })
+        ));
+        assert!(matches!(
+            RecordBatches::from_file(&storage, "/logs/__cluster_metadata-0/0.log"),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn topics_are_looked_up_by_name_and_id() {
+        const ID_1: &str = "00000000-0000-0000-0000-000000000001";
+        const ID_2: &str = "00000000-0000-0000-0000-000000000002";
+        const ID_3: &str = "00000000-0000-0000-0000-000000000003";
+        const ID_4: &str = "00000000-0000-0000-0000-000000000004";
+        let topic = |name: &str, id: &str| {
+            RecordValue::Topic(TopicValue {
+                topic_name: name.to_string(),
+                topic_id: id.to_string(),
+            })
+        };
+        let mut log = RecordBatch::of_values(0, vec![topic("foo", ID_1)])
+            .serialize()
+            .to_vec();
+        // a later record of the name or the id does not replace the topic
+        log.extend(
+            RecordBatch::of_values(1, vec![topic("bar", ID_2), topic("foo", ID_3)])
+                .serialize()
+                .to_vec(),
+        );
+        let storage = MemoryStorage::with_files([("/logs/__cluster_metadata-0/0.log", log)]);
+        let batches =
+            RecordBatches::from_file(&storage, "/logs/__cluster_metadata-0/0.log").unwrap();
+
+        assert_eq!(batches.topic_id("foo"), Some(ID_1));
+        assert_eq!(batches.topic_id("bar"), Some(ID_2));
+        assert_eq!(batches.topic_name(ID_2), Some("bar"));
+        assert_eq!(batches.topic_name(ID_3), Some("foo"));
+        assert_eq!(batches.topic_id("baz"), None);
+        assert_eq!(batches.topic_name(ID_4), None);
+    }
+
+    #[test]
+    fn metadata_is_loaded_from_the_latest_snapshot_and_the_log_after_it() {
+        const DIR: &str = "/logs/__cluster_metadata-0";
+        let topic = |name: &str, id: u8| {
+            RecordValue::Topic(TopicValue {
+                topic_name: name.to_string(),
+                topic_id: format!("00000000-0000-0000-0000-0000000000{id:02x}"),
+            })
+        };
+        let values = |base_offset, values| RecordBatch::of_values(base_offset, values).serialize();
+        // the header and footer of a snapshot are control batches, they are not decoded
+        let control = batch(0, 0x20, 0, &[0]);
+        let snapshot = [control.clone(), values(0, vec![topic("foo", 1)]), control].concat();
+        // the records before offset 5 are in the latest snapshot, the older one is not loaded
+        let log = [
+            values(2, vec![topic("old", 2)]),
+            values(4, vec![topic("bar", 3)]),
+        ]
+        .concat();
+        let segment = values(5, vec![topic("baz", 4), topic("qux", 5)]);
+        let storage = MemoryStorage::with_files([
+            (
+                format!("{DIR}/00000000000000000002-0000000001.checkpoint"),
+                &snapshot[..],
+            ),
+            (
+                format!("{DIR}/00000000000000000005-0000000001.checkpoint"),
+                &snapshot[..],
+            ),
+            (format!("{DIR}/00000000000000000002.log"), &log[..]),
+            (format!("{DIR}/00000000000000000005.log"), &segment[..]),
+            (format!("{DIR}/partition.metadata"), b"version: 0\n"),
+        ]);
+
+        let metadata = RecordBatches::load_metadata(&storage, DIR).unwrap();
+        let topics: Vec<_> = metadata.topics().map(|t| t.topic_name.as_str()).collect();
+        assert_eq!(topics, ["foo", "baz", "qux"]);
+        assert_eq!(metadata.end_offset(), 7);
+
+        // the first segment was deleted without a snapshot of it
+        let storage =
+            MemoryStorage::with_files([(format!("{DIR}/00000000000000000005.log"), segment)]);
+        assert!(matches!(
+            RecordBatches::load_metadata(&storage, DIR),
+            Err(StorageError::MissingRecords {
+                expected_offset: 0,
+                base_offset: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn partitions_are_looked_up_by_topic_id() {
+        const TOPIC_ID: &str = "00000000-0000-0000-0000-000000000001";
+        let partition = |partition_id, leader_id| {
+            RecordValue::Partition(PartitionValue {
+                partition_id,
+                topic_id: TOPIC_ID.to_string(),
+                replicas: vec![leader_id],
+                in_sync_replicas: vec![leader_id],
+                removing_replicas: Vec::new(),
+                adding_replicas: Vec::new(),
+                leader_id,
+                leader_epoch: 0,
+                partition_epoch: 0,
+                directories: Vec::new(),
+            })
+        };
+        let mut log = RecordBatch::of_values(0, vec![partition(2, 1), partition(0, 1)])
+            .serialize()
+            .to_vec();
+        // a later record of the partition does not replace it
+        log.extend(
+            RecordBatch::of_values(2, vec![partition(1, 1), partition(0, 2)])
+                .serialize()
+                .to_vec(),
+        );
+        let storage = MemoryStorage::with_files([("/logs/__cluster_metadata-0/0.log", log)]);
+        let batches =
+            RecordBatches::from_file(&storage, "/logs/__cluster_metadata-0/0.log").unwrap();
+
+        let partitions = batches.partitions(TOPIC_ID);
+        let ids: Vec<_> = partitions.iter().map(|p| p.partition_id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+        assert_eq!(batches.partition(TOPIC_ID, 0).unwrap().leader_id, 1);
+        assert_eq!(batches.partition(TOPIC_ID, 2).unwrap().partition_id, 2);
+        assert!(batches.partition(TOPIC_ID, 3).is_none());
+        assert!(batches
+            .partitions("00000000-0000-0000-0000-000000000002")
+            .is_empty());
+    }
+
+    #[test]
+    fn partition_changes_apply_to_the_partition_record() {
+        const TOPIC_ID: &str = "00000000-0000-0000-0000-000000000001";
+        let partition = PartitionValue {
+            partition_id: 0,
+            topic_id: TOPIC_ID.to_string(),
+            replicas: vec![1, 2],
+            in_sync_replicas: vec![1, 2],
+            removing_replicas: Vec::new(),
+            adding_replicas: Vec::new(),
+            leader_id: 1,
+            leader_epoch: 3,
+            partition_epoch: 5,
+            directories: Vec::new(),
+        };
+        let change = |in_sync_replicas: Vec<u32>, leader_id| {
+            RecordValue::PartitionChange(PartitionChangeValue {
+                partition_id: 0,
+                topic_id: TOPIC_ID.to_string(),
+                in_sync_replicas: Some(in_sync_replicas),
+                leader_id,
+                ..Default::default()
+            })
+        };
+        let log = RecordBatch::of_values(
+            0,
+            vec![
+                RecordValue::Partition(partition),
+                // the replica 1 falls out of sync and the leader moves to 2
+                change(vec![2], None),
+                change(vec![2], Some(2)),
+                // of a partition that does not exist
+                RecordValue::PartitionChange(PartitionChangeValue {
+                    partition_id: 1,
+                    topic_id: TOPIC_ID.to_string(),
+                    leader_id: Some(2),
+                    ..Default::default()
+                }),
+            ],
+        )
+        .serialize();
+        let storage = MemoryStorage::with_files([("/logs/__cluster_metadata-0/0.log", log)]);
+        let batches =
+            RecordBatches::from_file(&storage, "/logs/__cluster_metadata-0/0.log").unwrap();
+
+        let p = batches.partition(TOPIC_ID, 0).unwrap();
+        assert_eq!((p.leader_id, p.leader_epoch, p.partition_epoch), (2, 4, 7));
+        assert_eq!(p.in_sync_replicas, [2]);
+        assert_eq!(p.replicas, [1, 2]);
+        assert_eq!(batches.partitions(TOPIC_ID).len(), 1);
+    }
+
+    #[test]
+    fn topic_configs_are_the_latest_config_records() {
+        let config = |resource_type, name: &str, value: Option<&str>| {
+            RecordValue::Config(ConfigValue {
+                resource_type,
+                resource_name: "foo".to_string(),
+                name: name.to_string(),
+                value: value.map(str::to_string),
+            })
+        };
+        let log = [
+            RecordBatch::of_values(
+                0,
+                vec![
+                    config(ConfigValue::TOPIC_RESOURCE, "retention.ms", Some("1000")),
+                    config(
+                        ConfigValue::TOPIC_RESOURCE,
+                        "cleanup.policy",
+                        Some("compact"),
+                    ),
+                    // a broker config of the same name
+                    config(4, "segment.bytes", Some("1024")),
+                ],
+            )
+            .serialize(),
+            RecordBatch::of_values(
+                3,
+                vec![
+                    config(ConfigValue::TOPIC_RESOURCE, "retention.ms", Some("2000")),
+                    config(ConfigValue::TOPIC_RESOURCE, "cleanup.policy", None),
+                ],
+            )
+            .serialize(),
+        ]
+        .concat();
+        let storage = MemoryStorage::with_files([("/logs/__cluster_metadata-0/0.log", log)]);
+        let batches =
+            RecordBatches::from_file(&storage, "/logs/__cluster_metadata-0/0.log").unwrap();
+
+        assert_eq!(batches.topic_config("foo", "retention.ms"), Some("2000"));
+        assert_eq!(batches.topic_config("foo", "cleanup.policy"), None);
+        assert_eq!(batches.topic_config("foo", "segment.bytes"), None);
+        assert_eq!(
+            batches.topic_configs("foo").collect::<Vec<_>>(),
+            [("retention.ms", "2000")]
+        );
+        assert_eq!(batches.topic_configs("bar").count(), 0);
+    }
+
+    fn record_value(g: &mut Gen) -> RecordValue {
+        let broker_epoch = |g: &mut Gen| BrokerEpochValue {
+            broker_id: g.i32(),
+            broker_epoch: g.i64(),
+        };
+        match g.below(8) {
+            0 => RecordValue::Topic(TopicValue {
+                topic_name: g.string(300),
+                topic_id: g.uuid(),
+            }),
+            1 => RecordValue::Partition(PartitionValue {
+                partition_id: g.u32(),
+                topic_id: g.uuid(),
+                replicas: g.vec(5, Gen::u32),
+                in_sync_replicas: g.vec(5, Gen::u32),
+                removing_replicas: g.vec(5, Gen::u32),
+                adding_replicas: g.vec(5, Gen::u32),
+                leader_id: g.u32(),
+                leader_epoch: g.u32(),
+                partition_epoch: g.u32(),
+                directories: g.vec(3, Gen::uuid),
+            }),
+            2 => RecordValue::Config(ConfigValue {
+                resource_type: g.i8(),
+                resource_name: g.string(20),
+                name: g.string(20),
+                value: g.option(|g| g.string(200)),
+            }),
+            3 => RecordValue::RegisterBroker(RegisterBrokerValue {
+                broker_id: g.i32(),
+                incarnation_id: g.uuid(),
+                broker_epoch: g.i64(),
+                end_points: g.vec(3, |g| BrokerEndpoint {
+                    name: g.string(10),
+                    host: g.string(20),
+                    port: g.u16(),
+                    security_protocol: g.i16(),
+                }),
+                rack: g.option(|g| g.string(10)),
+                fenced: g.bool(),
+            }),
+            4 => RecordValue::UnregisterBroker(broker_epoch(g)),
+            5 => RecordValue::FenceBroker(broker_epoch(g)),
+            6 => RecordValue::UnfenceBroker(broker_epoch(g)),
+            _ => RecordValue::FeatureLevel(FeatureLevelValue {
+                name: g.string(20),
+                level: g.u16(),
+            }),
         }
     }
+
+    #[test]
+    fn metadata_record_batches_round_trip() {
+        testing::check(|g| {
+            let records = g.vec(20, |g| Record {
+                length: 0,
+                attributes: 0,
+                timestamp_delta: g.i64(),
+                offset_delta: g.i64(),
+                key: g.option(|g| g.bytes(200)),
+                value_length: 0,
+                value: record_value(g),
+                headers: vec![Header; g.below(3) as usize],
+            });
+            let mut batch = RecordBatch {
+                base_offset: g.i64(),
+                batch_length: 0,
+                partition_leader_epoch: g.i32(),
+                magic: 2,
+                crc: 0,
+                attributes: g.i16(),
+                last_offset_delta: g.i32(),
+                base_timestamp: g.i64(),
+                max_timestamp: g.i64(),
+                producer_id: g.i64(),
+                producer_epoch: g.i16(),
+                base_sequence: g.i32(),
+                records,
+                undecoded: None,
+            };
+            let bytes = batch.serialize();
+            assert_eq!(bytes.len(), 12 + batch.batch_length as usize);
+
+            let mut src = ByteReader::new(bytes.clone());
+            let mut decoded = RecordBatch::from_bytes(&mut src).unwrap();
+            assert_eq!(src.remaining(), 0);
+            assert_eq!(decoded.crc, batch.crc);
+            assert_eq!(decoded.records.len(), batch.records.len());
+            assert_eq!(decoded.serialize(), bytes);
+
+            let mut lazy =
+                RecordBatch::from_bytes_lazy(&mut ByteReader::new(bytes.clone())).unwrap();
+            assert!(!lazy.is_decoded());
+            assert_eq!(lazy.last_offset(), decoded.last_offset());
+            assert_eq!(lazy.clone().serialize(), bytes);
+            assert_eq!(lazy.decode_records().unwrap().len(), batch.records.len());
+            assert_eq!(lazy.serialize(), bytes);
+        });
+    }
 }
@@ -2,41 +2,129 @@ pub mod api_versions;
 pub mod describe_topic_partitions;
 pub mod fetch;
 
-use bytes::{Buf, Bytes};
+use bytes::{BufMut, BytesMut};
 
-use super::types::NullableString;
+use super::{
+    reader::ByteReader,
+    types::{NullableString, TaggedFields},
+    ApiKey, ProtocolError,
+};
 
-/// Request Header v2
+/// Request header, its version depends on the API and its version:
+/// v1 for versions that are not flexible, v2 with tagged fields for flexible versions
+/// and v0 without client id for ControlledShutdown v0.
 // https://kafka.apache.org/protocol.html#protocol_messages
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
-pub struct HeaderV2 {
+pub struct RequestHeader {
     pub request_api_key: i16,
     pub request_api_version: i16,
     pub correlation_id: i32,
-    pub client_id: String,
+    pub client_id: Option<String>,
 }
 
-impl HeaderV2 {
-    pub fn from_bytes(src: &mut Bytes) -> Self {
-        let request_api_key = src.get_i16(); // https://kafka.apache.org/protocol.html#protocol_api_keys
-        let request_api_version = src.get_i16();
-        let correlation_id = src.get_i32();
-        let client_id = NullableString::deserialize(src);
+impl RequestHeader {
+    pub fn from_bytes(src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let request_api_key = src.get_i16("request_api_key")?; // https://kafka.apache.org/protocol.html#protocol_api_keys
+        let request_api_version = src.get_i16("request_api_version")?;
+        let correlation_id = src.get_i32("correlation_id")?;
+
+        // unknown APIs are rejected, the header version does not matter for them
+        let api_key = ApiKey::try_from(request_api_key).ok();
+
+        let client_id = if api_key == Some(ApiKey::ControlledShutdown) && request_api_version == 0 {
+            None
+        } else {
+            NullableString::deserialize(src)?
+        };
 
         /*
         + tagged_fields: Optional tagged fields
-            This can be ignored for now, they're optional tagged fields used to introduce additional features over time
+            They're optional tagged fields used to introduce additional features over time
                 (https://cwiki.apache.org/confluence/display/KAFKA/KIP-482%3A+The+Kafka+Protocol+should+Support+Optional+Tagged+Fields).
-            The value for this will always be a null byte in this challenge (i.e. no tagged fields are present)
+            Only header v2 of flexible versions has them, the broker skips them
         */
-        _ = src.get_u8(); // tag buffer - An empty tagged field array, represented by a single byte of value 0x00.
+        if api_key.is_some_and(|api_key| api_key.is_flexible(request_api_version)) {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
 
-        Self {
+        Ok(Self {
             request_api_key,
             request_api_version,
             correlation_id,
             client_id,
+        })
+    }
+
+    /// Writes the header in the version `from_bytes` reads it, for sending requests to a broker
+    pub fn serialize(&self, dst: &mut BytesMut) {
+        dst.put_i16(self.request_api_key);
+        dst.put_i16(self.request_api_version);
+        dst.put_i32(self.correlation_id);
+
+        let api_key = ApiKey::try_from(self.request_api_key).ok();
+        if api_key != Some(ApiKey::ControlledShutdown) || self.request_api_version != 0 {
+            NullableString::serialize_into(self.client_id.as_deref(), dst);
+        }
+        if api_key.is_some_and(|api_key| api_key.is_flexible(self.request_api_version)) {
+            dst.put_u8(0); // empty tag buffer
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::RequestHeader;
+    use crate::protocol::{reader::ByteReader, ApiKey};
+
+    #[test]
+    fn header_version_depends_on_api_version() {
+        // ApiVersions v2 uses header v1 without tagged fields
+        let mut src = ByteReader::new(Bytes::from_static(&[0, 18, 0, 2, 0, 0, 0, 7, 0, 1, b'c']));
+        let header = RequestHeader::from_bytes(&mut src).unwrap();
+        assert_eq!(header.correlation_id, 7);
+        assert_eq!(header.client_id.as_deref(), Some("c"));
+        assert_eq!(src.remaining(), 0);
+
+        // ApiVersions v3 uses header v2 with tagged fields
+        let mut src = ByteReader::new(Bytes::from_static(&[
+            0, 18, 0, 3, 0, 0, 0, 7, 0xff, 0xff, 0,
+        ]));
+        let header = RequestHeader::from_bytes(&mut src).unwrap();
+        assert_eq!(header.client_id, None);
+        assert_eq!(src.remaining(), 0);
+
+        // ControlledShutdown v0 uses header v0 without client id
+        let mut src = ByteReader::new(Bytes::from_static(&[0, 7, 0, 0, 0, 0, 0, 7, 0, 0, 0, 1]));
+        let header = RequestHeader::from_bytes(&mut src).unwrap();
+        assert_eq!(header.client_id, None);
+        assert_eq!(src.remaining(), 4);
+    }
+
+    #[test]
+    fn serialized_header_round_trips() {
+        for (api_key, version) in [
+            (ApiKey::ApiVersions, 2),
+            (ApiKey::ApiVersions, 3),
+            (ApiKey::ControlledShutdown, 0),
+        ] {
+            let header = RequestHeader {
+                request_api_key: api_key.into(),
+                request_api_version: version,
+                correlation_id: 7,
+                client_id: (api_key != ApiKey::ControlledShutdown).then(|| "c".to_string()),
+            };
+            let mut dst = BytesMut::new();
+            header.serialize(&mut dst);
+
+            let mut src = ByteReader::new(dst.freeze());
+            let decoded = RequestHeader::from_bytes(&mut src).unwrap();
+            assert_eq!(decoded.request_api_version, version);
+            assert_eq!(decoded.correlation_id, 7);
+            assert_eq!(decoded.client_id, header.client_id);
+            assert_eq!(src.remaining(), 0);
         }
     }
 }
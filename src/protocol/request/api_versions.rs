@@ -1,23 +1,48 @@
-use anyhow::Result;
 use bytes::Bytes;
 
-use crate::protocol::response::api_versions::ApiVersionsResponseV3;
+use crate::protocol::{
+    generated::api_versions_request::ApiVersionsRequestData,
+    reader::ByteReader,
+    response::api_versions::{ApiVersionsApiKeys, ApiVersionsResponse},
+    ProtocolError,
+};
 
-use super::HeaderV2;
+use super::RequestHeader;
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct ApiVersionsRequest {
-    header: HeaderV2,
+    header: RequestHeader,
+    body: ApiVersionsRequestData,
 }
 
 impl ApiVersionsRequest {
     // https://kafka.apache.org/protocol.html#The_Messages_ApiVersions
-    pub fn from_bytes(src: &mut Bytes) -> Result<Self> {
-        let header = HeaderV2::from_bytes(src);
-        Ok(Self { header })
+    pub fn from_bytes(header: RequestHeader, src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let body = ApiVersionsRequestData::deserialize(src, header.request_api_version)?;
+
+        Ok(Self { header, body })
+    }
+
+    pub fn body(&self) -> &ApiVersionsRequestData {
+        &self.body
+    }
+
+    /// Serializes the request body in the version of the header
+    pub fn serialize(&self) -> Bytes {
+        self.body.serialize(self.header.request_api_version)
     }
 
-    pub fn process(self) -> ApiVersionsResponseV3 {
-        ApiVersionsResponseV3::new(self.header.correlation_id, self.header.request_api_version)
+    pub fn process(
+        self,
+        api_keys: Vec<ApiVersionsApiKeys>,
+        throttle_time_ms: i32,
+    ) -> ApiVersionsResponse {
+        ApiVersionsResponse::new(
+            self.header.correlation_id,
+            self.header.request_api_version,
+            api_keys,
+            throttle_time_ms,
+        )
     }
 }
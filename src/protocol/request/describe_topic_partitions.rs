@@ -1,41 +1,87 @@
-use bytes::{Buf, Bytes};
+use bytes::{BufMut, Bytes, BytesMut};
 
-use super::HeaderV2;
-use crate::protocol::types::{self, CompactArray, CompactString, TaggedFields};
+use super::RequestHeader;
+use crate::protocol::{
+    reader::ByteReader,
+    types::{self, CompactArray, CompactString, TaggedFields, VarInt},
+    ProtocolError,
+};
 
-#[allow(dead_code)]
+#[derive(Debug)]
 pub struct DescribeTopicPartitionsRequestV0 {
-    pub header: HeaderV2,
+    pub header: RequestHeader,
     pub topics: Vec<String>,
-    response_partition_limit: i32,
-    cursor: u8,
+    pub response_partition_limit: i32,
+    /// Only the null cursor 0xff is supported, the first page is always described
+    pub cursor: u8,
 }
 
 impl DescribeTopicPartitionsRequestV0 {
     // https://kafka.apache.org/protocol.html#The_Messages_DescribeTopicPartitions
-    pub fn from_bytes(src: &mut Bytes) -> Self {
-        let header = HeaderV2::from_bytes(src);
+    pub fn from_bytes(header: RequestHeader, src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let topics = CompactArray::deserialize::<_, Topic>(src)?;
+        let response_partition_limit = src.get_i32("response_partition_limit")?;
+        let cursor = src.get_u8("cursor")?; // A nullable field that can be used for pagination. Here, it is 0xff, indicating a null value
+        _ = TaggedFields::deserialize(src)?; // tag buffer
 
-        let topics = CompactArray::deserialize::<_, Topic>(src);
-        let response_partition_limit = src.get_i32();
-        let cursor = src.get_u8(); // A nullable field that can be used for pagination. Here, it is 0xff, indicating a null value
-        _ = TaggedFields::deserialize(src); // tag buffer
-
-        Self {
+        Ok(Self {
             header,
             topics,
             response_partition_limit,
             cursor,
+        })
+    }
+
+    /// Serializes the request body, the header is serialized separately
+    pub fn serialize(&self) -> Bytes {
+        let mut b = BytesMut::new();
+        VarInt::serialize_into(self.topics.len() as u64 + 1, &mut b);
+        for topic in &self.topics {
+            CompactString::serialize_into(topic, &mut b);
+            TaggedFields::serialize_into(&mut b); // tag buffer
         }
+        b.put_i32(self.response_partition_limit);
+        b.put_u8(self.cursor);
+        TaggedFields::serialize_into(&mut b); // tag buffer
+        b.freeze()
     }
 }
 
 struct Topic;
 
 impl types::Deserialize<String> for Topic {
-    fn deserialize(src: &mut Bytes) -> String {
-        let s = CompactString::deserialize(src);
-        _ = TaggedFields::deserialize(src); // tag buffer
-        s
+    fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
+        let s = CompactString::deserialize(src)?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DescribeTopicPartitionsRequestV0;
+    use crate::protocol::{reader::ByteReader, request::RequestHeader};
+
+    #[test]
+    fn serialized_request_round_trips() {
+        let header = || RequestHeader {
+            request_api_key: 75,
+            request_api_version: 0,
+            correlation_id: 7,
+            client_id: None,
+        };
+        let req = DescribeTopicPartitionsRequestV0 {
+            header: header(),
+            topics: vec!["foo".to_string(), "bar".to_string()],
+            response_partition_limit: 100,
+            cursor: 0xff,
+        };
+        let bytes = req.serialize();
+
+        let mut src = ByteReader::new(bytes.clone());
+        let decoded = DescribeTopicPartitionsRequestV0::from_bytes(header(), &mut src).unwrap();
+        assert_eq!(src.remaining(), 0);
+        assert_eq!(decoded.topics, ["foo", "bar"]);
+        assert_eq!(decoded.serialize(), bytes);
     }
 }
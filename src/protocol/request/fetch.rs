@@ -1,49 +1,54 @@
-use bytes::{Buf, Bytes};
+use bytes::{BufMut, Bytes, BytesMut};
 
-use crate::protocol::types::{self, CompactArray, CompactString, TaggedFields, Uuid};
+use crate::protocol::{
+    reader::ByteReader,
+    types::{
+        kafka_deserialize, kafka_serialize, CompactArray, CompactString, Int32, Int64,
+        TaggedFields, Uuid,
+    },
+    ProtocolError,
+};
 
-use super::HeaderV2;
+use super::RequestHeader;
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct FetchRequestV16 {
-    pub header: HeaderV2,
+    pub header: RequestHeader,
     /// The maximum time in milliseconds to wait for the response.
-    max_wait_ms: u32,
+    pub max_wait_ms: u32,
     /// The minimum bytes to accumulate in the response.
-    min_bytes: u32,
+    pub min_bytes: u32,
     /// The maximum bytes to fetch.
-    max_bytes: u32,
-    isolation_level: u8,
+    pub max_bytes: u32,
+    pub isolation_level: u8,
     /// The fetch session ID.
     pub session_id: u32,
     /// The fetch session epoch, which is used for ordering requests in a session.
-    session_epoch: u32,
+    pub session_epoch: i32,
     /// The topics to fetch.
     pub topics: Vec<TopicRequest>,
     /// In an incremental fetch request, the partitions to remove.
-    forgotten_topics_data: Vec<ForgottenTopicData>,
+    pub forgotten_topics_data: Vec<ForgottenTopicData>,
     /// Rack ID of the consumer making this request.
-    rack_id: String,
+    pub rack_id: String,
 }
 
 impl FetchRequestV16 {
     // https://kafka.apache.org/protocol.html#The_Messages_Fetch
-    pub fn from_bytes(src: &mut Bytes) -> Self {
-        let header = HeaderV2::from_bytes(src);
-
-        let max_wait_ms = src.get_u32();
-        let min_bytes = src.get_u32();
-        let max_bytes = src.get_u32();
-        let isolation_level = src.get_u8();
-        let session_id = src.get_u32();
-        let session_epoch = src.get_u32();
-        let topics = CompactArray::deserialize::<TopicRequest, Self>(src);
-        let forgotten_topics_data = CompactArray::deserialize::<ForgottenTopicData, Self>(src);
-        let rack_id = CompactString::deserialize(src);
-        _ = TaggedFields::deserialize(src); // tag buffer
-
-        Self {
+    pub fn from_bytes(header: RequestHeader, src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let max_wait_ms = src.get_u32("max_wait_ms")?;
+        let min_bytes = src.get_u32("min_bytes")?;
+        let max_bytes = src.get_u32("max_bytes")?;
+        let isolation_level = src.get_u8("isolation_level")?;
+        let session_id = src.get_u32("session_id")?;
+        let session_epoch = src.get_i32("session_epoch")?;
+        let topics = CompactArray::deserialize::<TopicRequest, TopicRequest>(src)?;
+        let forgotten_topics_data =
+            CompactArray::deserialize::<ForgottenTopicData, ForgottenTopicData>(src)?;
+        let rack_id = CompactString::deserialize(src)?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+
+        Ok(Self {
             header,
             max_wait_ms,
             min_bytes,
@@ -54,75 +59,149 @@ impl FetchRequestV16 {
             topics,
             forgotten_topics_data,
             rack_id,
-        }
+        })
+    }
+
+    /// Serializes the request body, the header is serialized separately
+    pub fn serialize(&mut self) -> Bytes {
+        let mut b = BytesMut::new();
+        b.put_u32(self.max_wait_ms);
+        b.put_u32(self.min_bytes);
+        b.put_u32(self.max_bytes);
+        b.put_u8(self.isolation_level);
+        b.put_u32(self.session_id);
+        b.put_i32(self.session_epoch);
+        CompactArray::serialize_into(&mut self.topics, &mut b);
+        CompactArray::serialize_into(&mut self.forgotten_topics_data, &mut b);
+        CompactString::serialize_into(&self.rack_id, &mut b);
+        TaggedFields::serialize_into(&mut b); // tag buffer
+        b.freeze()
     }
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct TopicRequest {
     pub topic_id: String,
     pub partitions: Vec<Partition>,
 }
 
-impl types::Deserialize<TopicRequest> for FetchRequestV16 {
-    fn deserialize(src: &mut Bytes) -> TopicRequest {
-        let topic_id = Uuid::deserialize(src);
-        let partitions = CompactArray::deserialize::<Partition, TopicRequest>(src);
-        _ = TaggedFields::deserialize(src); // tag buffer
-        TopicRequest {
-            topic_id,
-            partitions,
-        }
+kafka_deserialize! {
+    TopicRequest {
+        topic_id: Uuid,
+        partitions: CompactArray,
     }
+    tagged_fields
+}
+
+kafka_serialize! {
+    TopicRequest {
+        topic_id: Uuid,
+        partitions: CompactArray,
+    }
+    tagged_fields
 }
 
 #[derive(Debug)]
-#[allow(dead_code)]
-struct ForgottenTopicData {
-    topic_id: String,     // UUID
-    partitions: Vec<u32>, // The partitions indexes to forget.
+pub struct ForgottenTopicData {
+    pub topic_id: String,     // UUID
+    pub partitions: Vec<u32>, // The partitions indexes to forget.
 }
 
-impl types::Deserialize<ForgottenTopicData> for FetchRequestV16 {
-    fn deserialize(src: &mut Bytes) -> ForgottenTopicData {
-        let ftd = ForgottenTopicData {
-            topic_id: Uuid::deserialize(src),
-            partitions: CompactArray::deserialize::<u32, ForgottenTopicData>(src),
-        };
-        _ = TaggedFields::deserialize(src); // tag buffer
-        ftd
+kafka_deserialize! {
+    ForgottenTopicData {
+        topic_id: Uuid,
+        partitions: CompactArray,
     }
+    tagged_fields
 }
 
-impl types::Deserialize<u32> for ForgottenTopicData {
-    fn deserialize(src: &mut Bytes) -> u32 {
-        src.get_u32()
+kafka_serialize! {
+    ForgottenTopicData {
+        topic_id: Uuid,
+        partitions: CompactArray,
     }
+    tagged_fields
 }
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct Partition {
     pub partition: u32,
-    current_leader_epoch: u32,
-    fetch_offset: u64,
-    last_fetched_epoch: u32,
-    log_start_offset: u64,
-    partition_max_bytes: u32,
+    pub current_leader_epoch: u32,
+    pub fetch_offset: u64,
+    pub last_fetched_epoch: u32,
+    pub log_start_offset: u64,
+    pub partition_max_bytes: u32,
 }
 
-impl types::Deserialize<Partition> for TopicRequest {
-    fn deserialize(src: &mut Bytes) -> Partition {
-        let p = Partition {
-            partition: src.get_u32(),
-            current_leader_epoch: src.get_u32(),
-            fetch_offset: src.get_u64(),
-            last_fetched_epoch: src.get_u32(),
-            log_start_offset: src.get_u64(),
-            partition_max_bytes: src.get_u32(),
+kafka_deserialize! {
+    Partition {
+        partition: Int32,
+        current_leader_epoch: Int32,
+        fetch_offset: Int64,
+        last_fetched_epoch: Int32,
+        log_start_offset: Int64,
+        partition_max_bytes: Int32,
+    }
+    tagged_fields
+}
+
+kafka_serialize! {
+    Partition {
+        partition: Int32,
+        current_leader_epoch: Int32,
+        fetch_offset: Int64,
+        last_fetched_epoch: Int32,
+        log_start_offset: Int64,
+        partition_max_bytes: Int32,
+    }
+    tagged_fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FetchRequestV16, ForgottenTopicData, Partition, TopicRequest};
+    use crate::protocol::{reader::ByteReader, request::RequestHeader};
+
+    #[test]
+    fn serialized_request_round_trips() {
+        let header = || RequestHeader {
+            request_api_key: 1,
+            request_api_version: 16,
+            correlation_id: 7,
+            client_id: None,
         };
-        _ = TaggedFields::deserialize(src); // tag buffer
-        p
+        let mut req = FetchRequestV16 {
+            header: header(),
+            max_wait_ms: 500,
+            min_bytes: 1,
+            max_bytes: 1024,
+            isolation_level: 1,
+            session_id: 3,
+            session_epoch: 2,
+            topics: vec![TopicRequest {
+                topic_id: "00000000-0000-4000-8000-000000000001".to_string(),
+                partitions: vec![Partition {
+                    partition: 1,
+                    current_leader_epoch: 4,
+                    fetch_offset: 10,
+                    last_fetched_epoch: 4,
+                    log_start_offset: 0,
+                    partition_max_bytes: 512,
+                }],
+            }],
+            forgotten_topics_data: vec![ForgottenTopicData {
+                topic_id: "00000000-0000-4000-8000-000000000002".to_string(),
+                partitions: vec![0, 2],
+            }],
+            rack_id: "rack-a".to_string(),
+        };
+        let bytes = req.serialize();
+
+        let mut src = ByteReader::new(bytes.clone());
+        let mut decoded = FetchRequestV16::from_bytes(header(), &mut src).unwrap();
+        assert_eq!(src.remaining(), 0);
+        assert_eq!(decoded.rack_id, "rack-a");
+        assert_eq!(decoded.forgotten_topics_data[0].partitions, [0, 2]);
+        assert_eq!(decoded.serialize(), bytes);
     }
 }
@@ -1,51 +1,82 @@
 use bytes::{BufMut, Bytes, BytesMut};
 
+use super::{reader::ByteReader, types::TaggedFields, ApiKey, ProtocolError, ResponseMessage};
+
 pub mod api_versions;
 pub mod describe_topic_partitions;
 pub mod fetch;
 
-// The APIVersions response uses the "v0" header format, while all other responses use the "v1" header format.
-// The response header format (v0) is 4 bytes long, and contains exactly one field: correlation_id
-// The response header format (v1) contains an additional tag_buffer field.
+/// Response header, its version follows from the API and its version of the request.
+///
+/// Flexible versions use header v1 with a tag buffer, other versions header v0 with just the correlation id.
+/// The ApiVersions response always uses header v0, so that the client can read it before it knows
+/// which versions the broker supports.
 // https://kafka.apache.org/protocol.html#protocol_messages
-
-struct HeaderV0 {
+#[derive(Debug)]
+pub struct ResponseHeader {
     correlation_id: i32,
-    bytes: Bytes,
+    tagged_fields: bool,
 }
 
-impl HeaderV0 {
-    fn new(correlation_id: i32) -> Self {
+impl ResponseHeader {
+    pub fn new(api_key: ApiKey, api_version: i16, correlation_id: i32) -> Self {
         Self {
             correlation_id,
-            bytes: Bytes::new(),
+            tagged_fields: api_key != ApiKey::ApiVersions && api_key.is_flexible(api_version),
         }
     }
 
-    fn serialize(&mut self) -> &[u8] {
-        self.bytes = Bytes::copy_from_slice(self.correlation_id.to_be_bytes().as_slice());
-        &self.bytes
+    /// Reads the header of a response to a request of the API and version, for clients of a broker
+    pub fn from_bytes(
+        src: &mut ByteReader,
+        api_key: ApiKey,
+        api_version: i16,
+    ) -> Result<Self, ProtocolError> {
+        let mut header = Self::new(api_key, api_version, 0);
+        header.correlation_id = src.get_i32("correlation_id")?;
+        if header.tagged_fields {
+            _ = TaggedFields::deserialize(src)?;
+        }
+        Ok(header)
     }
-}
 
-struct HeaderV1 {
-    correlation_id: i32,
-    tag_buffer: u8,
-    bytes: BytesMut,
-}
+    pub fn correlation_id(&self) -> i32 {
+        self.correlation_id
+    }
 
-impl HeaderV1 {
-    fn new(correlation_id: i32) -> Self {
-        Self {
-            correlation_id,
-            tag_buffer: 0, // tag buffer - An empty tagged field array, represented by a single byte of value 0x00.
-            bytes: BytesMut::new(),
+    fn serialize(&self, dst: &mut BytesMut) {
+        dst.put_i32(self.correlation_id);
+        if self.tagged_fields {
+            dst.put_u8(0); // tag buffer - An empty tagged field array, represented by a single byte of value 0x00.
         }
     }
+}
+
+/// Response message with the header and the serialized response body, e.g. of a generated response
+pub fn message(header: ResponseHeader, body: Bytes) -> Bytes {
+    let mut bytes = ResponseMessage::buffer();
+    header.serialize(&mut bytes);
+    bytes.put(body);
+    ResponseMessage::finish(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::ResponseHeader;
+    use crate::protocol::ApiKey;
 
-    fn serialize(&mut self) -> &[u8] {
-        self.bytes.put_i32(self.correlation_id);
-        self.bytes.put_u8(self.tag_buffer);
-        &self.bytes
+    #[test]
+    fn header_version_depends_on_api_version() {
+        let header_len = |api_key, version| {
+            let mut dst = BytesMut::new();
+            ResponseHeader::new(api_key, version, 1).serialize(&mut dst);
+            dst.len()
+        };
+        assert_eq!(header_len(ApiKey::Fetch, 11), 4);
+        assert_eq!(header_len(ApiKey::Fetch, 12), 5);
+        assert_eq!(header_len(ApiKey::DescribeTopicPartitions, 0), 5);
+        assert_eq!(header_len(ApiKey::ApiVersions, 4), 4);
     }
 }
@@ -1,82 +1,157 @@
 use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::protocol::{
-    types::{self, CompactArray, Serialize, TaggedFields},
-    ApiKey, ErrorCode, Response,
+    reader::ByteReader,
+    types::{
+        kafka_serialize, Decode, FlexibleArray, Int16, Serialize, TaggedFields, Version,
+        VersionedDecode, VersionedDeserialize, VersionedEncode,
+    },
+    ApiKey, ErrorCode, ProtocolError, Response, ResponseMessage,
 };
 
-use super::HeaderV0;
+use super::ResponseHeader;
 
-// The APIVersions response uses the "v0" header format, while all other responses use the "v1" header format.
-// The response header format (v0) is 4 bytes long, and contains exactly one field: correlation_id
-// The response header format (v1) contains an additional tag_buffer field.
-// https://kafka.apache.org/protocol.html#protocol_messages
 // https://kafka.apache.org/protocol.html#The_Messages_ApiVersions
-pub struct ApiVersionsResponseV3 {
-    header: HeaderV0,
-    error_code: ErrorCode,
-    api_keys_vec: Vec<ApiVersionsApiKeys>,
-    throttle_time_ms: i32,
+pub struct ApiVersionsResponse {
+    header: ResponseHeader,
+    version: Version,
+    pub error_code: ErrorCode,
+    pub api_keys_vec: Vec<ApiVersionsApiKeys>,
+    pub throttle_time_ms: i32,
     bytes: BytesMut,
 }
 
-impl ApiVersionsResponseV3 {
-    pub fn new(correlation_id: i32, request_api_version: i16) -> Self {
-        let header = HeaderV0::new(correlation_id);
-
-        let api_keys_vec = vec![
-            ApiVersionsApiKeys {
-                api_key: ApiKey::ApiVersions,
-                min_version: 0,
-                max_version: 4,
-            },
-            ApiVersionsApiKeys {
-                api_key: ApiKey::DescribeTopicPartitions,
-                min_version: 0,
-                max_version: 0,
-            },
-            ApiVersionsApiKeys {
-                api_key: ApiKey::Fetch,
-                min_version: 0,
-                max_version: 16,
-            },
-        ];
-
-        let mut error_code = ErrorCode::None;
-        match request_api_version {
-            0..=4 => {}
-            _ => error_code = ErrorCode::UnsupportedVersion,
-        }
+impl ApiVersionsResponse {
+    pub const LOWEST_SUPPORTED_VERSION: i16 = 0;
+    pub const HIGHEST_SUPPORTED_VERSION: i16 = 4;
+    /// Version 3 is the first flexible version
+    pub const FLEXIBLE_SINCE: i16 = 3;
+
+    pub fn new(
+        correlation_id: i32,
+        request_api_version: i16,
+        api_keys: Vec<ApiVersionsApiKeys>,
+        throttle_time_ms: i32,
+    ) -> Self {
+        let error_code = if Self::is_supported(request_api_version) {
+            ErrorCode::None
+        } else {
+            ErrorCode::UnsupportedVersion
+        };
+
+        Self::with_error_code(
+            correlation_id,
+            request_api_version,
+            error_code,
+            api_keys,
+            throttle_time_ms,
+        )
+    }
+
+    /// Creates the response with the given list of supported APIs and error code.
+    ///
+    /// Unsupported request versions are answered in version 0, so that the client can decode the error
+    /// whatever version it speaks.
+    pub fn with_error_code(
+        correlation_id: i32,
+        request_api_version: i16,
+        error_code: ErrorCode,
+        api_keys_vec: Vec<ApiVersionsApiKeys>,
+        throttle_time_ms: i32,
+    ) -> Self {
+        let version = if Self::is_supported(request_api_version) {
+            request_api_version
+        } else {
+            Self::LOWEST_SUPPORTED_VERSION
+        };
 
         let mut resp = Self {
-            header,
+            header: ResponseHeader::new(ApiKey::ApiVersions, version, correlation_id),
+            version: Version::new(version, Self::FLEXIBLE_SINCE),
             error_code,
             api_keys_vec,
-            throttle_time_ms: 0,
-            bytes: BytesMut::new(),
+            throttle_time_ms,
+            bytes: ResponseMessage::buffer(),
         };
 
         resp.serialize();
         resp
     }
 
+    /// Reads the response body in the version of the request with the correlation id
+    pub fn from_bytes(
+        correlation_id: i32,
+        version: i16,
+        src: &mut ByteReader,
+    ) -> Result<Self, ProtocolError> {
+        let flexible_version = Version::new(version, Self::FLEXIBLE_SINCE);
+        let error_code = Int16::decode(src, "error_code")?;
+        let api_keys_vec = FlexibleArray::decode_versioned(src, "api_keys", flexible_version)?;
+        let throttle_time_ms = if version >= 1 {
+            src.get_i32("throttle_time_ms")?
+        } else {
+            0
+        };
+        if flexible_version.flexible {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+
+        let mut resp = Self {
+            header: ResponseHeader::new(ApiKey::ApiVersions, version, correlation_id),
+            version: flexible_version,
+            error_code,
+            api_keys_vec,
+            throttle_time_ms,
+            bytes: ResponseMessage::buffer(),
+        };
+
+        resp.serialize();
+        Ok(resp)
+    }
+
+    /// Copy of the serialized response of a supported version with the correlation id and throttle time
+    /// replaced, so that the same response need not be serialized for every request
+    pub fn patch(
+        serialized: &[u8],
+        version: i16,
+        correlation_id: i32,
+        throttle_time_ms: i32,
+    ) -> Bytes {
+        let mut bytes = BytesMut::from(serialized);
+        // message size, then the correlation id of the v0 header ApiVersions responses always have
+        bytes[4..8].copy_from_slice(&correlation_id.to_be_bytes());
+        if version >= 1 {
+            // the throttle time is the last field, before the tag buffer in flexible versions
+            let end = bytes.len() - usize::from(version >= Self::FLEXIBLE_SINCE);
+            bytes[end - 4..end].copy_from_slice(&throttle_time_ms.to_be_bytes());
+        }
+        bytes.freeze()
+    }
+
+    pub fn is_supported(version: i16) -> bool {
+        (Self::LOWEST_SUPPORTED_VERSION..=Self::HIGHEST_SUPPORTED_VERSION).contains(&version)
+    }
+
     /// Fills the internal `bytes` field with byte representation of the response
     // https://kafka.apache.org/protocol.html#The_Messages_ApiVersions
     fn serialize(&mut self) {
-        // HEADER v0
-        self.bytes.put(self.header.serialize());
-        // BODY - ApiVersions Response (Version: 3)
-        self.bytes.put(self.error_code.serialize());
-        self.bytes
-            .put(CompactArray::serialize(&mut self.api_keys_vec));
-        self.bytes.put_i32(self.throttle_time_ms);
-        self.bytes.put(TaggedFields::serialize()); // tag buffer
+        // HEADER
+        self.header.serialize(&mut self.bytes);
+        // BODY - ApiVersions Response
+        self.error_code.serialize_into(&mut self.bytes);
+        FlexibleArray::encode_versioned(&mut self.api_keys_vec, &mut self.bytes, self.version);
+        if self.version.version >= 1 {
+            self.bytes.put_i32(self.throttle_time_ms);
+        }
+        if self.version.flexible {
+            TaggedFields::serialize_into(&mut self.bytes); // tag buffer
+        }
     }
 }
 
-impl Response for ApiVersionsResponseV3 {
-    fn as_bytes(&self) -> &[u8] {
-        &self.bytes
+impl Response for ApiVersionsResponse {
+    fn into_bytes(self) -> Bytes {
+        ResponseMessage::finish(self.bytes)
     }
 }
 
@@ -86,13 +161,102 @@ pub struct ApiVersionsApiKeys {
     pub max_version: i16,
 }
 
-impl types::Serialize for ApiVersionsApiKeys {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
-        b.put_i16(self.api_key.into());
-        b.put_i16(self.min_version);
-        b.put_i16(self.max_version);
-        b.put(TaggedFields::serialize()); // tag buffer
-        b.freeze()
+kafka_serialize! {
+    versioned ApiVersionsApiKeys {
+        api_key: Int16,
+        min_version: Int16,
+        max_version: Int16,
+    }
+}
+
+impl VersionedDeserialize<ApiVersionsApiKeys> for ApiVersionsApiKeys {
+    fn deserialize_versioned(
+        src: &mut ByteReader,
+        version: Version,
+    ) -> Result<Self, ProtocolError> {
+        let api_key = Int16::decode(src, "api_key")?;
+        let min_version = src.get_i16("min_version")?;
+        let max_version = src.get_i16("max_version")?;
+        if version.flexible {
+            _ = TaggedFields::deserialize(src)?; // tag buffer
+        }
+        Ok(Self {
+            api_key,
+            min_version,
+            max_version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiVersionsApiKeys, ApiVersionsResponse};
+    use crate::protocol::{reader::ByteReader, response::ResponseHeader, ApiKey, Response};
+
+    fn api_keys() -> Vec<ApiVersionsApiKeys> {
+        [
+            ApiKey::ApiVersions,
+            ApiKey::DescribeTopicPartitions,
+            ApiKey::Fetch,
+        ]
+        .into_iter()
+        .map(|api_key| ApiVersionsApiKeys {
+            api_key,
+            min_version: 0,
+            max_version: 0,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn classic_encoding_for_old_versions() {
+        let v0 = ApiVersionsResponse::new(7, 0, api_keys(), 0).into_bytes();
+        // message size, correlation_id, error_code, INT32 array length, 3 * (api_key, min, max), no throttle time
+        assert_eq!(v0.len(), 4 + 4 + 2 + 4 + 3 * 6);
+        assert_eq!(&v0[10..14], &[0, 0, 0, 3]);
+
+        let v2 = ApiVersionsResponse::new(7, 2, api_keys(), 0).into_bytes();
+        assert_eq!(v2.len(), 4 + 4 + 2 + 4 + 3 * 6 + 4);
+    }
+
+    #[test]
+    fn compact_encoding_for_flexible_versions() {
+        let v4 = ApiVersionsResponse::new(7, 4, api_keys(), 0).into_bytes();
+        // message size, correlation_id, error_code, varint array length, 3 * (api_key, min, max, tag buffer), throttle time, tag buffer
+        assert_eq!(v4.len(), 4 + 4 + 2 + 1 + 3 * 7 + 4 + 1);
+        assert_eq!(v4[10], 4);
+    }
+
+    #[test]
+    fn unsupported_version_answered_in_v0() {
+        let resp = ApiVersionsResponse::new(7, 99, api_keys(), 0).into_bytes();
+        assert_eq!(&resp[8..10], &[0, 35]);
+        assert_eq!(resp.len(), 4 + 4 + 2 + 4 + 3 * 6);
+    }
+
+    #[test]
+    fn patched_response_is_the_serialized_one() {
+        for version in 0..=4 {
+            let template = ApiVersionsResponse::new(0, version, api_keys(), 0).into_bytes();
+            let patched = ApiVersionsResponse::patch(&template, version, 7, 10);
+            let serialized = ApiVersionsResponse::new(7, version, api_keys(), 10).into_bytes();
+            assert_eq!(patched, serialized);
+        }
+    }
+
+    #[test]
+    fn serialized_response_round_trips() {
+        for version in 0..=4 {
+            let bytes = ApiVersionsResponse::new(7, version, api_keys(), 10).into_bytes();
+
+            let mut src = ByteReader::new(bytes.slice(4..));
+            let header =
+                ResponseHeader::from_bytes(&mut src, ApiKey::ApiVersions, version).unwrap();
+            let resp = ApiVersionsResponse::from_bytes(header.correlation_id(), version, &mut src)
+                .unwrap();
+            assert_eq!(src.remaining(), 0);
+            assert_eq!(resp.api_keys_vec.len(), 3);
+            assert_eq!(resp.into_bytes(), bytes);
+        }
     }
 }
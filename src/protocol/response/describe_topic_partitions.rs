@@ -1,55 +1,89 @@
+use std::fmt;
+
 use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::protocol::{
-    types::{self, *},
-    ErrorCode, Response,
+    reader::ByteReader,
+    types::{
+        kafka_deserialize, kafka_serialize, Boolean, CompactArray, CompactString, Int16, Int32,
+        TaggedFields, Uuid,
+    },
+    ApiKey, ErrorCode, ProtocolError, Response, ResponseMessage,
 };
 
-use super::HeaderV1;
+use super::ResponseHeader;
 
 pub struct DescribeTopicPartitionsResponseV0 {
-    header: HeaderV1,
-    throttle_time_ms: i32,
-    topics: Vec<Topic>,
-    next_cursor: u8,
+    header: ResponseHeader,
+    pub throttle_time_ms: i32,
+    pub topics: Vec<Topic>,
+    pub next_cursor: u8,
     bytes: BytesMut,
 }
 
 impl DescribeTopicPartitionsResponseV0 {
-    pub fn new(correlation_id: i32, topics: Vec<Topic>) -> Self {
-        let header = HeaderV1::new(correlation_id);
-
+    pub fn new(correlation_id: i32, throttle_time_ms: i32, topics: Vec<Topic>) -> Self {
         let mut resp = Self {
-            header,
-            throttle_time_ms: 0,
+            header: ResponseHeader::new(ApiKey::DescribeTopicPartitions, 0, correlation_id),
+            throttle_time_ms,
             topics,
             next_cursor: 0xFF,
-            bytes: BytesMut::new(),
+            bytes: ResponseMessage::buffer(),
         };
 
         resp.serialize();
         resp
     }
 
+    /// Reads the response body received from a broker, for the request with the correlation id
+    pub fn from_bytes(correlation_id: i32, src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let topics = CompactArray::deserialize::<Topic, Topic>(src)?;
+        let next_cursor = src.get_u8("next_cursor")?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+
+        let mut resp = Self {
+            header: ResponseHeader::new(ApiKey::DescribeTopicPartitions, 0, correlation_id),
+            throttle_time_ms,
+            topics,
+            next_cursor,
+            bytes: ResponseMessage::buffer(),
+        };
+
+        resp.serialize();
+        Ok(resp)
+    }
+
     /// Fills the internal `bytes` field with byte representation of the response
     // https://kafka.apache.org/protocol.html#The_Messages_DescribeTopicPartitions
     fn serialize(&mut self) {
         // HEADER
-        self.bytes.put(self.header.serialize());
+        self.header.serialize(&mut self.bytes);
         // BODY
         self.bytes.put_i32(self.throttle_time_ms);
-        self.bytes.put(CompactArray::serialize(&mut self.topics));
+        CompactArray::serialize_into(&mut self.topics, &mut self.bytes);
         self.bytes.put_u8(self.next_cursor);
         self.bytes.put_u8(0); // tag buffer
     }
 }
 
+impl fmt::Debug for DescribeTopicPartitionsResponseV0 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DescribeTopicPartitionsResponseV0")
+            .field("throttle_time_ms", &self.throttle_time_ms)
+            .field("topics", &self.topics)
+            .field("next_cursor", &self.next_cursor)
+            .finish()
+    }
+}
+
 impl Response for DescribeTopicPartitionsResponseV0 {
-    fn as_bytes(&self) -> &[u8] {
-        &self.bytes
+    fn into_bytes(self) -> Bytes {
+        ResponseMessage::finish(self.bytes)
     }
 }
 
+#[derive(Debug)]
 pub struct Topic {
     pub error_code: ErrorCode,
     pub name: String,     // COMPACT_NULLABLE_STRING
@@ -59,30 +93,41 @@ pub struct Topic {
     pub topic_authorized_operations: i32, // A 4-byte integer (bitfield) representing the authorized operations for this topic.
 }
 
-impl types::Serialize for Topic {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
-        b.put(self.error_code.serialize());
-        b.put(CompactString::serialize(&self.name));
-        b.put(Uuid::serialize(&self.topic_id));
-        b.put_u8(self.is_internal.into());
-        b.put(CompactArray::serialize(&mut self.partitions));
-        b.put_i32(self.topic_authorized_operations);
-        b.put(TaggedFields::serialize()); //
-        b.freeze()
+kafka_serialize! {
+    Topic {
+        error_code: Int16,
+        name: CompactString,
+        topic_id: Uuid,
+        is_internal: Boolean,
+        partitions: CompactArray,
+        topic_authorized_operations: Int32,
+    }
+    tagged_fields
+}
+
+kafka_deserialize! {
+    Topic {
+        error_code: Int16,
+        name: CompactString,
+        topic_id: Uuid,
+        is_internal: Boolean,
+        partitions: CompactArray,
+        topic_authorized_operations: Int32,
     }
+    tagged_fields
 }
 
+#[derive(Debug)]
 pub struct Partition {
-    error_code: ErrorCode,
-    partition_index: u32,
-    leader_id: u32,
-    leader_epoch: u32,
-    replicas: Vec<u32>,
-    in_sync_replicas: Vec<u32>,
-    eligible_leader_replicas: Vec<u32>,
-    last_known_eligible_leader_replicas: Vec<u32>,
-    off_line_replicas: Vec<u32>,
+    pub error_code: ErrorCode,
+    pub partition_index: u32,
+    pub leader_id: u32,
+    pub leader_epoch: u32,
+    pub replicas: Vec<u32>,
+    pub in_sync_replicas: Vec<u32>,
+    pub eligible_leader_replicas: Vec<u32>,
+    pub last_known_eligible_leader_replicas: Vec<u32>,
+    pub off_line_replicas: Vec<u32>,
 }
 
 impl Partition {
@@ -112,29 +157,72 @@ impl Partition {
     }
 }
 
-impl types::Serialize for Partition {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
-        b.put(self.error_code.serialize());
-        b.put_u32(self.partition_index);
-        b.put_u32(self.leader_id);
-        b.put_u32(self.leader_epoch);
-        b.put(CompactArray::serialize(&mut self.replicas));
-        b.put(CompactArray::serialize(&mut self.in_sync_replicas));
-        b.put(CompactArray::serialize(&mut self.eligible_leader_replicas));
-        b.put(CompactArray::serialize(
-            &mut self.last_known_eligible_leader_replicas,
-        ));
-        b.put(CompactArray::serialize(&mut self.off_line_replicas));
-        b.put(TaggedFields::serialize()); // tag buffer
-        b.freeze()
+kafka_serialize! {
+    Partition {
+        error_code: Int16,
+        partition_index: Int32,
+        leader_id: Int32,
+        leader_epoch: Int32,
+        replicas: CompactArray,
+        in_sync_replicas: CompactArray,
+        eligible_leader_replicas: CompactArray,
+        last_known_eligible_leader_replicas: CompactArray,
+        off_line_replicas: CompactArray,
     }
+    tagged_fields
 }
 
-impl types::Serialize for u32 {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::with_capacity(4);
-        b.extend_from_slice(&self.to_be_bytes());
-        b.freeze()
+kafka_deserialize! {
+    Partition {
+        error_code: Int16,
+        partition_index: Int32,
+        leader_id: Int32,
+        leader_epoch: Int32,
+        replicas: CompactArray,
+        in_sync_replicas: CompactArray,
+        eligible_leader_replicas: CompactArray,
+        last_known_eligible_leader_replicas: CompactArray,
+        off_line_replicas: CompactArray,
+    }
+    tagged_fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DescribeTopicPartitionsResponseV0, Partition, Topic};
+    use crate::protocol::{
+        reader::ByteReader, response::ResponseHeader, ApiKey, ErrorCode, Response,
+    };
+
+    #[test]
+    fn serialized_response_round_trips() {
+        let topic = Topic {
+            error_code: ErrorCode::None,
+            name: "foo".to_string(),
+            topic_id: "00000000-0000-4000-8000-000000000001".to_string(),
+            is_internal: false,
+            partitions: vec![Partition::new(
+                ErrorCode::None,
+                0,
+                1,
+                0,
+                vec![1],
+                vec![1],
+                vec![],
+                vec![],
+                vec![],
+            )],
+            topic_authorized_operations: 0x0df8,
+        };
+        let bytes = DescribeTopicPartitionsResponseV0::new(7, 0, vec![topic]).into_bytes();
+
+        let mut src = ByteReader::new(bytes.slice(4..));
+        let header =
+            ResponseHeader::from_bytes(&mut src, ApiKey::DescribeTopicPartitions, 0).unwrap();
+        let resp = DescribeTopicPartitionsResponseV0::from_bytes(header.correlation_id(), &mut src)
+            .unwrap();
+        assert_eq!(src.remaining(), 0);
+        assert_eq!(resp.topics[0].name, "foo");
+        assert_eq!(resp.into_bytes(), bytes);
     }
 }
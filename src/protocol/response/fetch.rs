@@ -1,62 +1,127 @@
+use std::fmt;
+
 use bytes::{BufMut, Bytes, BytesMut};
 
 use crate::protocol::{
     self,
-    types::{self, CompactArray, Serialize, TaggedFields, Uuid},
-    ErrorCode,
+    reader::ByteReader,
+    types::{
+        kafka_deserialize, kafka_serialize, CompactArray, CompactRecords, Decode, Int16, Int32,
+        Int64, Records, Serialize, TaggedFields, Uuid,
+    },
+    ApiKey, ErrorCode, ProtocolError, ResponseMessage,
 };
 
-use super::HeaderV1;
+use super::ResponseHeader;
 
 pub struct FetchResponseV16 {
-    header: HeaderV1,
-    throttle_time_ms: i32,
-    error_code: ErrorCode,
-    session_id: u32,
-    responses: Vec<TopicResponse>,
+    header: ResponseHeader,
+    pub throttle_time_ms: i32,
+    pub error_code: ErrorCode,
+    pub session_id: u32,
+    pub responses: Vec<TopicResponse>,
     bytes: BytesMut,
 }
 
 impl FetchResponseV16 {
-    pub fn new(correlation_id: i32, session_id: u32, responses: Vec<TopicResponse>) -> Self {
-        let header = HeaderV1::new(correlation_id);
+    const VERSION: i16 = 16;
 
+    pub fn new(
+        correlation_id: i32,
+        throttle_time_ms: i32,
+        session_id: u32,
+        responses: Vec<TopicResponse>,
+    ) -> Self {
         let mut resp = Self {
-            header,
-            throttle_time_ms: 0,
+            header: ResponseHeader::new(ApiKey::Fetch, Self::VERSION, correlation_id),
+            throttle_time_ms,
             error_code: ErrorCode::None,
             session_id,
             responses,
-            bytes: BytesMut::new(),
+            bytes: ResponseMessage::buffer(),
+        };
+
+        resp.serialize();
+        resp
+    }
+
+    /// Creates the response with top-level error code and no topic responses
+    pub fn error(
+        correlation_id: i32,
+        throttle_time_ms: i32,
+        session_id: u32,
+        error_code: ErrorCode,
+    ) -> Self {
+        let mut resp = Self {
+            header: ResponseHeader::new(ApiKey::Fetch, Self::VERSION, correlation_id),
+            throttle_time_ms,
+            error_code,
+            session_id,
+            responses: Vec::new(),
+            bytes: ResponseMessage::buffer(),
         };
 
         resp.serialize();
         resp
     }
 
+    /// Reads the response body received from a broker, for the request with the correlation id
+    // https://kafka.apache.org/protocol.html#The_Messages_Fetch
+    pub fn from_bytes(correlation_id: i32, src: &mut ByteReader) -> Result<Self, ProtocolError> {
+        let throttle_time_ms = src.get_i32("throttle_time_ms")?;
+        let error_code = Int16::decode(src, "error_code")?;
+        let session_id = src.get_u32("session_id")?;
+        let responses = CompactArray::deserialize::<TopicResponse, TopicResponse>(src)?;
+        _ = TaggedFields::deserialize(src)?; // tag buffer
+
+        let mut resp = Self {
+            header: ResponseHeader::new(ApiKey::Fetch, Self::VERSION, correlation_id),
+            throttle_time_ms,
+            error_code,
+            session_id,
+            responses,
+            bytes: ResponseMessage::buffer(),
+        };
+
+        resp.serialize();
+        Ok(resp)
+    }
+
     /// Fills the internal `bytes` field with byte representation of the response
     // https://kafka.apache.org/protocol.html#The_Messages_Fetch
     fn serialize(&mut self) {
         // HEADER
-        self.bytes.put(self.header.serialize());
+        self.header.serialize(&mut self.bytes);
         // BODY
         self.bytes.put_i32(self.throttle_time_ms);
-        self.bytes.put(self.error_code.serialize());
+        self.error_code.serialize_into(&mut self.bytes);
         self.bytes.put_u32(self.session_id);
-        self.bytes.put(CompactArray::serialize(&mut self.responses));
-        self.bytes.put(TaggedFields::serialize()); // tag buffer
+        CompactArray::serialize_into(&mut self.responses, &mut self.bytes);
+        TaggedFields::serialize_into(&mut self.bytes); // tag buffer
+    }
+}
+
+impl fmt::Debug for FetchResponseV16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FetchResponseV16")
+            .field("throttle_time_ms", &self.throttle_time_ms)
+            .field("error_code", &self.error_code)
+            .field("session_id", &self.session_id)
+            .field("responses", &self.responses)
+            .finish()
     }
 }
 
 impl protocol::Response for FetchResponseV16 {
-    fn as_bytes(&self) -> &[u8] {
-        &self.bytes
+    fn into_bytes(self) -> Bytes {
+        ResponseMessage::finish(self.bytes)
     }
 }
 
+#[derive(Debug)]
 pub struct TopicResponse {
-    topic_id: String, // UUID
-    partitions: Vec<TopicPartition>,
+    pub topic_id: String, // UUID
+    pub partitions: Vec<TopicPartition>,
 }
 
 impl TopicResponse {
@@ -68,26 +133,23 @@ impl TopicResponse {
     }
 }
 
-impl types::Serialize for TopicResponse {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
-        b.put(Uuid::serialize(&self.topic_id));
-        b.put(CompactArray::serialize(&mut self.partitions));
-        b.put(TaggedFields::serialize()); // tag buffer
-        b.freeze()
+kafka_serialize! {
+    TopicResponse {
+        topic_id: Uuid,
+        partitions: CompactArray,
     }
+    tagged_fields
 }
 
-pub struct BatchBytes {
-    pub bytes: Bytes,
-}
-
-impl types::Serialize for BatchBytes {
-    fn serialize(&mut self) -> Bytes {
-        self.bytes.clone()
+kafka_deserialize! {
+    TopicResponse {
+        topic_id: Uuid,
+        partitions: CompactArray,
     }
+    tagged_fields
 }
 
+#[derive(Debug)]
 pub struct TopicPartition {
     pub partition_index: u32,
     pub error_code: ErrorCode,
@@ -96,33 +158,100 @@ pub struct TopicPartition {
     pub log_start_offset: i64,
     pub aborted_transactions: Vec<AbortedTransaction>,
     pub preferred_read_replica: i32,
-    pub record_batches: Vec<BatchBytes>,
+    pub records: Records,
 }
 
-impl types::Serialize for TopicPartition {
-    fn serialize(&mut self) -> Bytes {
-        let mut b = BytesMut::new();
-        b.put_u32(self.partition_index);
-        b.put(self.error_code.serialize());
-        b.put_i64(self.high_watermark);
-        b.put_i64(self.last_stable_offset);
-        b.put_i64(self.log_start_offset);
-        b.put(CompactArray::serialize(&mut self.aborted_transactions));
-        b.put_i32(self.preferred_read_replica);
-        b.put(CompactArray::serialize(&mut self.record_batches));
-        b.put(TaggedFields::serialize()); // tag buffer
-        b.freeze()
+kafka_serialize! {
+    TopicPartition {
+        partition_index: Int32,
+        error_code: Int16,
+        high_watermark: Int64,
+        last_stable_offset: Int64,
+        log_start_offset: Int64,
+        aborted_transactions: CompactArray,
+        preferred_read_replica: Int32,
+        records: CompactRecords,
     }
+    tagged_fields
 }
 
-#[allow(dead_code)]
+kafka_deserialize! {
+    TopicPartition {
+        partition_index: Int32,
+        error_code: Int16,
+        high_watermark: Int64,
+        last_stable_offset: Int64,
+        log_start_offset: Int64,
+        aborted_transactions: CompactArray,
+        preferred_read_replica: Int32,
+        records: CompactRecords,
+    }
+    tagged_fields
+}
+
+#[derive(Debug)]
 pub struct AbortedTransaction {
-    producer_id: u64,
-    first_offset: u64,
+    pub producer_id: u64,
+    pub first_offset: u64,
 }
 
-impl types::Serialize for AbortedTransaction {
-    fn serialize(&mut self) -> Bytes {
-        todo!()
+kafka_serialize! {
+    AbortedTransaction {
+        producer_id: Int64,
+        first_offset: Int64,
+    }
+    tagged_fields
+}
+
+kafka_deserialize! {
+    AbortedTransaction {
+        producer_id: Int64,
+        first_offset: Int64,
+    }
+    tagged_fields
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{AbortedTransaction, FetchResponseV16, TopicPartition, TopicResponse};
+    use crate::protocol::{
+        reader::ByteReader, response::ResponseHeader, types::Records, ApiKey, ErrorCode, Response,
+    };
+
+    #[test]
+    fn serialized_response_round_trips() {
+        let mut records = Records::default();
+        // base offset, batch length and the 3 bytes of the rest of the batch
+        records.push(Bytes::from_static(&[
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 1, 2, 3,
+        ]));
+        let partition = TopicPartition {
+            partition_index: 0,
+            error_code: ErrorCode::None,
+            high_watermark: 1,
+            last_stable_offset: 1,
+            log_start_offset: 0,
+            aborted_transactions: vec![AbortedTransaction {
+                producer_id: 5,
+                first_offset: 0,
+            }],
+            preferred_read_replica: -1,
+            records,
+        };
+        let topic = TopicResponse::new(
+            "00000000-0000-4000-8000-000000000001".to_string(),
+            vec![partition],
+        );
+        let bytes = FetchResponseV16::new(7, 0, 3, vec![topic]).into_bytes();
+
+        let mut src = ByteReader::new(bytes.slice(4..));
+        let header = ResponseHeader::from_bytes(&mut src, ApiKey::Fetch, 16).unwrap();
+        let resp = FetchResponseV16::from_bytes(header.correlation_id(), &mut src).unwrap();
+        assert_eq!(src.remaining(), 0);
+        assert_eq!(resp.session_id, 3);
+        assert_eq!(resp.responses[0].partitions[0].records.len(), 15);
+        assert_eq!(resp.into_bytes(), bytes);
     }
 }
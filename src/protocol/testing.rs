@@ -0,0 +1,129 @@
+//! Random values for property tests of the encodings, e.g. that deserializing a serialized value gives it back.
+//!
+//! The values come from seeded generators, so a failing case is reproduced by its seed, which is printed.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use bytes::Bytes;
+
+/// Cases every property is checked with
+pub const CASES: u64 = 256;
+
+/// Checks the property with `CASES` generators of different seeds
+pub fn check(mut property: impl FnMut(&mut Gen)) {
+    for seed in 1..=CASES {
+        let mut g = Gen::new(seed);
+        if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| property(&mut g))) {
+            eprintln!("property failed for seed {seed}");
+            panic::resume_unwind(panic);
+        }
+    }
+}
+
+/// Pseudo-random generator (xorshift64*), not for anything but tests
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Self {
+        // the state must not be zero, mix the seed so that close seeds do not start alike
+        Self {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Value in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.u64() % n
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.u64() & 1 == 1
+    }
+
+    /// Number of a random bit width, so small numbers and the lengths where the varints grow by a byte
+    /// are as likely as large ones
+    pub fn number(&mut self) -> u64 {
+        match self.below(8) {
+            0 => [0, 1, 127, 128, 16383, 16384, i64::MAX as u64, u64::MAX][self.below(8) as usize],
+            _ => self.u64() >> self.below(64),
+        }
+    }
+
+    pub fn i64(&mut self) -> i64 {
+        self.number() as i64
+    }
+
+    pub fn i32(&mut self) -> i32 {
+        self.number() as i32
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        self.number() as u32
+    }
+
+    pub fn i16(&mut self) -> i16 {
+        self.number() as i16
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        self.number() as u16
+    }
+
+    pub fn i8(&mut self) -> i8 {
+        self.number() as i8
+    }
+
+    /// Length up to `max`, lengths of one or two varint bytes alike
+    pub fn len(&mut self, max: usize) -> usize {
+        match self.below(4) {
+            0 => 0,
+            1 => self.below(8) as usize,
+            _ => self.below(max as u64 + 1) as usize,
+        }
+    }
+
+    /// String of up to `max_len` characters, some of them multibyte
+    pub fn string(&mut self, max_len: usize) -> String {
+        const CHARS: [char; 8] = ['a', 'z', '0', '-', '.', '_', 'é', '€'];
+        let len = self.len(max_len);
+        (0..len)
+            .map(|_| CHARS[self.below(CHARS.len() as u64) as usize])
+            .collect()
+    }
+
+    pub fn bytes(&mut self, max_len: usize) -> Bytes {
+        let len = self.len(max_len);
+        (0..len).map(|_| self.u64() as u8).collect()
+    }
+
+    /// UUID in the format the protocol types decode it to
+    pub fn uuid(&mut self) -> String {
+        let hex = format!("{:016x}{:016x}", self.u64(), self.u64());
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        )
+    }
+
+    pub fn option<T>(&mut self, value: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        self.bool().then(|| value(self))
+    }
+
+    pub fn vec<T>(&mut self, max_len: usize, mut item: impl FnMut(&mut Self) -> T) -> Vec<T> {
+        let len = self.len(max_len);
+        (0..len).map(|_| item(self)).collect()
+    }
+}
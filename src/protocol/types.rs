@@ -1,13 +1,166 @@
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use thiserror::Error;
+
+use super::{reader::ByteReader, ProtocolError};
 
 // https://kafka.apache.org/protocol.html#protocol_types
 
+/// Serialization of a message structure into the buffer of the whole message, so that nested structures
+/// are not serialized into buffers of their own first
 pub trait Serialize {
-    fn serialize(&mut self) -> Bytes;
+    /// Appends the serialized value to `dst`
+    fn serialize_into(&mut self, dst: &mut BytesMut);
+
+    /// Serialized value in a buffer of its own
+    fn serialize(&mut self) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_into(&mut b);
+        b.freeze()
+    }
 }
 
 pub trait Deserialize<T> {
-    fn deserialize(src: &mut Bytes) -> T;
+    fn deserialize(src: &mut ByteReader) -> Result<T, ProtocolError>;
+}
+
+/// Encoding of Rust values of type `T` as a protocol type, implemented by the protocol type markers.
+/// Used by `kafka_serialize!` macro to map struct fields to wire types.
+pub trait Encode<T> {
+    fn encode(value: &mut T, dst: &mut BytesMut);
+}
+
+/// Decoding of Rust values of type `T` from a protocol type, implemented by the protocol type markers.
+/// Used by `kafka_deserialize!` macro to map struct fields to wire types.
+pub trait Decode<T> {
+    fn decode(src: &mut ByteReader, field: &'static str) -> Result<T, ProtocolError>;
+}
+
+/// Version of a message being encoded or decoded.
+///
+/// Flexible versions (KIP-482) use compact encodings for strings, arrays and bytes and carry a tag buffer
+/// at the end of every structure, older versions use INT16/INT32 length prefixes and no tag buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub version: i16,
+    pub flexible: bool,
+}
+
+impl Version {
+    /// `flexible_since` is the first flexible version of the message, as in `flexibleVersions` of its schema
+    pub fn new(version: i16, flexible_since: i16) -> Self {
+        Self {
+            version,
+            flexible: version >= flexible_since,
+        }
+    }
+}
+
+/// Serialization of a message structure whose encoding depends on the message version
+pub trait VersionedSerialize {
+    /// Appends the value serialized in the version to `dst`
+    fn serialize_versioned_into(&mut self, dst: &mut BytesMut, version: Version);
+
+    /// Value serialized in the version in a buffer of its own
+    fn serialize_versioned(&mut self, version: Version) -> Bytes {
+        let mut b = BytesMut::new();
+        self.serialize_versioned_into(&mut b, version);
+        b.freeze()
+    }
+}
+
+/// Deserialization of a message structure whose encoding depends on the message version
+#[allow(dead_code)]
+pub trait VersionedDeserialize<T> {
+    fn deserialize_versioned(src: &mut ByteReader, version: Version) -> Result<T, ProtocolError>;
+}
+
+/// Version dependent encoding of Rust values of type `T`.
+/// Every `Encode` protocol type encodes the same way in all versions.
+pub trait VersionedEncode<T> {
+    fn encode_versioned(value: &mut T, dst: &mut BytesMut, version: Version);
+}
+
+/// Version dependent decoding of Rust values of type `T`.
+/// Every `Decode` protocol type decodes the same way in all versions.
+#[allow(dead_code)]
+pub trait VersionedDecode<T> {
+    fn decode_versioned(
+        src: &mut ByteReader,
+        field: &'static str,
+        version: Version,
+    ) -> Result<T, ProtocolError>;
+}
+
+impl<T, W: Encode<T>> VersionedEncode<T> for W {
+    fn encode_versioned(value: &mut T, dst: &mut BytesMut, _version: Version) {
+        W::encode(value, dst)
+    }
+}
+
+impl<T, W: Decode<T>> VersionedDecode<T> for W {
+    fn decode_versioned(
+        src: &mut ByteReader,
+        field: &'static str,
+        _version: Version,
+    ) -> Result<T, ProtocolError> {
+        W::decode(src, field)
+    }
+}
+
+/// Implements `Encode` and `Decode` for fixed size integer protocol types
+macro_rules! fixed_size_types {
+    ($($(#[$doc:meta])* $marker:ident => [$($rust:ty: $put:ident, $get:ident),+];)+) => {
+        $(
+            $(#[$doc])*
+            pub struct $marker;
+
+            $(
+                impl Encode<$rust> for $marker {
+                    fn encode(value: &mut $rust, dst: &mut BytesMut) {
+                        dst.$put(*value);
+                    }
+                }
+
+                impl Decode<$rust> for $marker {
+                    fn decode(src: &mut ByteReader, field: &'static str) -> Result<$rust, ProtocolError> {
+                        src.$get(field)
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+fixed_size_types! {
+    /// Represents an integer between -2^7 and 2^7-1 inclusive.
+    #[allow(dead_code)]
+    Int8 => [i8: put_i8, get_i8];
+    /// Represents an integer between -2^15 and 2^15-1 inclusive. The values are encoded using two bytes in network byte order (big-endian).
+    Int16 => [i16: put_i16, get_i16];
+    /// Represents an integer between -2^31 and 2^31-1 inclusive. The values are encoded using four bytes in network byte order (big-endian).
+    Int32 => [i32: put_i32, get_i32, u32: put_u32, get_u32];
+    /// Represents an integer between -2^63 and 2^63-1 inclusive. The values are encoded using eight bytes in network byte order (big-endian).
+    Int64 => [i64: put_i64, get_i64, u64: put_u64, get_u64];
+    /// Represents an integer between 0 and 65535 inclusive. The values are encoded using two bytes in network byte order (big-endian).
+    #[allow(dead_code)]
+    UInt16 => [u16: put_u16, get_u16];
+}
+
+/// Represents a boolean value in a byte. Values 0 and 1 are used to represent false and true respectively.
+/// When reading a boolean value, any non-zero value is considered true.
+pub struct Boolean;
+
+impl Encode<bool> for Boolean {
+    fn encode(value: &mut bool, dst: &mut BytesMut) {
+        dst.put_u8((*value).into());
+    }
+}
+
+impl Decode<bool> for Boolean {
+    fn decode(src: &mut ByteReader, field: &'static str) -> Result<bool, ProtocolError> {
+        Ok(src.get_u8(field)? != 0)
+    }
 }
 
 /// Represents a sequence of characters. First the length N + 1 is given as an UNSIGNED_VARINT.
@@ -17,23 +170,37 @@ pub struct CompactString;
 impl CompactString {
     pub fn serialize(s: &str) -> Bytes {
         let mut b = BytesMut::new();
-        let len = s.len() as u8 + 1;
-        b.put_u8(len);
-        b.put(s.as_bytes());
+        Self::serialize_into(s, &mut b);
         b.freeze()
     }
 
-    pub fn deserialize(src: &mut Bytes) -> String {
-        let len = VarInt::deserialize(src); // string length + 1
+    pub fn serialize_into(s: &str, dst: &mut BytesMut) {
+        VarInt::serialize_into(s.len() as u64 + 1, dst);
+        dst.put(s.as_bytes());
+    }
+
+    pub fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
+        let len = src.get_varint("COMPACT_STRING length")?; // string length + 1
         let string_len = if len > 1 { len as usize - 1 } else { 0 };
-        let bytes = src.slice(..string_len);
-        src.advance(string_len);
-        String::from_utf8_lossy(&bytes).into_owned()
+        let bytes = src.get_bytes("COMPACT_STRING data", string_len)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
     }
 }
 
 impl Deserialize<String> for CompactString {
-    fn deserialize(src: &mut Bytes) -> String {
+    fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
+        Self::deserialize(src)
+    }
+}
+
+impl Encode<String> for CompactString {
+    fn encode(value: &mut String, dst: &mut BytesMut) {
+        Self::serialize_into(value, dst);
+    }
+}
+
+impl Decode<String> for CompactString {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<String, ProtocolError> {
         Self::deserialize(src)
     }
 }
@@ -44,12 +211,85 @@ impl Deserialize<String> for CompactString {
 pub struct NullableString;
 
 impl NullableString {
-    pub fn deserialize(src: &mut Bytes) -> String {
-        let len = src.get_i16();
-        let string_len = if len == -1 { 0 } else { len as usize };
-        let bytes = src.slice(..string_len);
-        src.advance(string_len);
-        String::from_utf8_lossy(&bytes).into_owned()
+    pub fn serialize(s: Option<&str>) -> Bytes {
+        let mut b = BytesMut::new();
+        Self::serialize_into(s, &mut b);
+        b.freeze()
+    }
+
+    pub fn serialize_into(s: Option<&str>, dst: &mut BytesMut) {
+        match s {
+            Some(s) => {
+                dst.put_i16(s.len() as i16);
+                dst.put(s.as_bytes());
+            }
+            None => dst.put_i16(-1),
+        }
+    }
+
+    pub fn deserialize(src: &mut ByteReader) -> Result<Option<String>, ProtocolError> {
+        let len = src.get_i16("NULLABLE_STRING length")?;
+        if len < 0 {
+            return Ok(None);
+        }
+        let bytes = src.get_bytes("NULLABLE_STRING data", len as usize)?;
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+}
+
+impl Encode<Option<String>> for NullableString {
+    fn encode(value: &mut Option<String>, dst: &mut BytesMut) {
+        Self::serialize_into(value.as_deref(), dst);
+    }
+}
+
+impl Decode<Option<String>> for NullableString {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<Option<String>, ProtocolError> {
+        Self::deserialize(src)
+    }
+}
+
+/// Represents a sequence of characters or null. For non-null strings, first the length N + 1 is given as an UNSIGNED_VARINT.
+/// Then N bytes follow which are the UTF-8 encoding of the character sequence. A null string is represented with a length of 0.
+pub struct CompactNullableString;
+
+#[allow(dead_code)]
+impl CompactNullableString {
+    pub fn serialize(s: Option<&str>) -> Bytes {
+        let mut b = BytesMut::new();
+        Self::serialize_into(s, &mut b);
+        b.freeze()
+    }
+
+    pub fn serialize_into(s: Option<&str>, dst: &mut BytesMut) {
+        match s {
+            Some(s) => {
+                VarInt::serialize_into(s.len() as u64 + 1, dst);
+                dst.put(s.as_bytes());
+            }
+            None => dst.put_u8(0),
+        }
+    }
+
+    pub fn deserialize(src: &mut ByteReader) -> Result<Option<String>, ProtocolError> {
+        let len = src.get_varint("COMPACT_NULLABLE_STRING length")?; // string length + 1
+        if len == 0 {
+            return Ok(None);
+        }
+        let bytes = src.get_bytes("COMPACT_NULLABLE_STRING data", len as usize - 1)?;
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+}
+
+impl Encode<Option<String>> for CompactNullableString {
+    fn encode(value: &mut Option<String>, dst: &mut BytesMut) {
+        Self::serialize_into(value.as_deref(), dst);
+    }
+}
+
+impl Decode<Option<String>> for CompactNullableString {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<Option<String>, ProtocolError> {
+        Self::deserialize(src)
     }
 }
 
@@ -61,28 +301,45 @@ pub struct CompactArray;
 impl CompactArray {
     pub fn serialize<T: Serialize>(items: &mut [T]) -> Bytes {
         let mut b = BytesMut::new();
+        Self::serialize_into(items, &mut b);
+        b.freeze()
+    }
+
+    pub fn serialize_into<T: Serialize>(items: &mut [T], dst: &mut BytesMut) {
         // COMPACT ARRAY: N+1, because null array is represented as 0, empty array (actual length of 0) is represented as 1
-        let len = items.len() as u8 + 1;
-        b.put_u8(len);
+        VarInt::serialize_into(items.len() as u64 + 1, dst);
 
         for item in items.iter_mut() {
-            b.put(item.serialize());
+            item.serialize_into(dst);
         }
-
-        b.freeze()
     }
 
-    pub fn deserialize<T, U: Deserialize<T>>(src: &mut Bytes) -> Vec<T> {
-        let len = VarInt::deserialize(src); // array length + 1
+    pub fn deserialize<T, U: Deserialize<T>>(
+        src: &mut ByteReader,
+    ) -> Result<Vec<T>, ProtocolError> {
+        let len = src.get_varint("COMPACT_ARRAY length")?; // array length + 1
         let items_len = if len > 1 { len as usize - 1 } else { 0 };
 
-        let mut items = Vec::with_capacity(items_len);
+        // do not trust the declared length when allocating, every item occupies at least one byte
+        let mut items = Vec::with_capacity(items_len.min(src.remaining()));
         for _ in 0..items_len {
-            let item = U::deserialize(src);
+            let item = U::deserialize(src)?;
             items.push(item);
         }
 
-        items
+        Ok(items)
+    }
+}
+
+impl<T: Serialize> Encode<Vec<T>> for CompactArray {
+    fn encode(value: &mut Vec<T>, dst: &mut BytesMut) {
+        Self::serialize_into(value, dst);
+    }
+}
+
+impl<T: Deserialize<T>> Decode<Vec<T>> for CompactArray {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<Vec<T>, ProtocolError> {
+        Self::deserialize::<T, T>(src)
     }
 }
 
@@ -93,17 +350,19 @@ pub struct Array;
 
 #[allow(dead_code)]
 impl Array {
-    pub fn deserialize<T, U: Deserialize<T>>(src: &mut Bytes) -> Vec<T> {
-        let len = src.get_i32();
-        let items_len = if len == -1 { 0 } else { len as usize };
+    pub fn deserialize<T, U: Deserialize<T>>(
+        src: &mut ByteReader,
+    ) -> Result<Vec<T>, ProtocolError> {
+        let len = src.get_i32("ARRAY length")?;
+        let items_len = if len < 0 { 0 } else { len as usize };
 
-        let mut items = Vec::with_capacity(items_len);
+        let mut items = Vec::with_capacity(items_len.min(src.remaining()));
         for _ in 0..items_len {
-            let item = U::deserialize(src);
+            let item = U::deserialize(src)?;
             items.push(item);
         }
 
-        items
+        Ok(items)
     }
 }
 
@@ -112,26 +371,19 @@ impl Array {
 /// A null value is encoded with length of -1 and there are no following bytes.
 pub struct NullableBytes;
 
-#[allow(dead_code)]
 impl NullableBytes {
-    pub fn serialize(bytes: &[u8]) -> Bytes {
-        let mut b = BytesMut::new();
-        let len = bytes.len() as i32 + 1;
-        b.put_i32(len);
-        b.put(bytes);
-        b.freeze()
-    }
+    pub fn deserialize<T, U: Deserialize<T>>(
+        src: &mut ByteReader,
+    ) -> Result<Vec<T>, ProtocolError> {
+        let len = src.get_i32("NULLABLE_BYTES length")?;
+        let items_len = if len < 0 { 0 } else { len as usize };
 
-    pub fn deserialize<T, U: Deserialize<T>>(src: &mut Bytes) -> Vec<T> {
-        let len = src.get_i32();
-        let items_len = if len == -1 { 0 } else { len as usize };
-
-        let mut items = Vec::with_capacity(items_len);
+        let mut items = Vec::with_capacity(items_len.min(src.remaining()));
         for _ in 0..items_len {
-            let item = U::deserialize(src);
+            let item = U::deserialize(src)?;
             items.push(item);
         }
-        items
+        Ok(items)
     }
 }
 
@@ -143,18 +395,98 @@ pub struct CompactNullableBytes;
 impl CompactNullableBytes {
     pub fn serialize(bytes: &[u8]) -> Bytes {
         let mut b = BytesMut::new();
-        let len = bytes.len() as u8 + 1; // should be varint
-        b.put_u8(len);
-        b.put(bytes);
+        Self::serialize_into(bytes, &mut b);
         b.freeze()
     }
 
-    pub fn deserialize(src: &mut Bytes) -> Vec<u8> {
-        let len = VarInt::deserialize(src);
+    pub fn serialize_into(bytes: &[u8], dst: &mut BytesMut) {
+        VarInt::serialize_into(bytes.len() as u64 + 1, dst);
+        dst.put(bytes);
+    }
+
+    pub fn deserialize(src: &mut ByteReader) -> Result<Vec<u8>, ProtocolError> {
+        let len = src.get_varint("COMPACT_NULLABLE_BYTES length")?;
         let bytes_len = if len > 1 { len as usize - 1 } else { 0 };
-        let bytes = src.slice(..bytes_len);
-        src.advance(bytes_len);
-        Vec::from(bytes)
+        let bytes = src.get_bytes("COMPACT_NULLABLE_BYTES data", bytes_len)?;
+        Ok(Vec::from(bytes))
+    }
+}
+
+impl Encode<Vec<u8>> for CompactNullableBytes {
+    fn encode(value: &mut Vec<u8>, dst: &mut BytesMut) {
+        Self::serialize_into(value, dst);
+    }
+}
+
+impl Decode<Vec<u8>> for CompactNullableBytes {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<Vec<u8>, ProtocolError> {
+        Self::deserialize(src)
+    }
+}
+
+/// Record batches transferred as a single sequence of bytes, see [`CompactRecords`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Records {
+    batches: Vec<Bytes>,
+}
+
+impl Records {
+    /// Appends raw bytes of one or more consecutive record batches
+    pub fn push(&mut self, batches: Bytes) {
+        self.batches.push(batches);
+    }
+
+    /// Raw bytes of the record batches in the order they were added or received
+    #[allow(dead_code)]
+    pub fn batches(&self) -> &[Bytes] {
+        &self.batches
+    }
+
+    /// Size of all batches in bytes
+    pub fn len(&self) -> usize {
+        self.batches.iter().map(Bytes::len).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Represents a sequence of Kafka records as COMPACT_NULLABLE_BYTES.
+/// First the total length N + 1 of all record batches is given as an UNSIGNED_VARINT, then the batches follow.
+pub struct CompactRecords;
+
+impl Encode<Records> for CompactRecords {
+    fn encode(value: &mut Records, dst: &mut BytesMut) {
+        VarInt::serialize_into(value.len() as u64 + 1, dst);
+        for batch in &value.batches {
+            dst.put(batch.clone());
+        }
+    }
+}
+
+impl Decode<Records> for CompactRecords {
+    fn decode(src: &mut ByteReader, field: &'static str) -> Result<Records, ProtocolError> {
+        let len = src.get_varint(field)?; // length + 1, 0 is null
+        let len = if len > 1 { len as usize - 1 } else { 0 };
+        let mut bytes = ByteReader::new(src.get_bytes(field, len)?);
+
+        // split into batches: Base Offset (8 bytes) and Batch Length (4 bytes) precede the rest of every batch
+        let mut records = Records::default();
+        while bytes.remaining() > 0 {
+            let mut header = bytes.clone();
+            _ = header.get_i64("base_offset")?;
+            let batch_length = header.get_i32("batch_length")?;
+            if batch_length < 0 {
+                return Err(ProtocolError::UnexpectedValue {
+                    field: "batch_length",
+                    value: batch_length.into(),
+                });
+            }
+            records.push(bytes.get_bytes("record batch", 12 + batch_length as usize)?);
+        }
+        Ok(records)
     }
 }
 
@@ -162,35 +494,198 @@ pub struct Uuid;
 
 impl Uuid {
     pub fn serialize(s: &str) -> Bytes {
-        let mut b = BytesMut::with_capacity(32);
-        b.extend_from_slice(&hex::decode(s.replace('-', "")).expect("valid UUID string"));
+        let mut b = BytesMut::with_capacity(16);
+        Self::serialize_into(s, &mut b);
         b.freeze()
     }
 
-    pub fn deserialize(src: &mut Bytes) -> String {
+    pub fn serialize_into(s: &str, dst: &mut BytesMut) {
+        dst.extend_from_slice(&hex::decode(s.replace('-', "")).expect("valid UUID string"));
+    }
+
+    pub fn deserialize(src: &mut ByteReader) -> Result<String, ProtocolError> {
         // 00000000-0000-0000-0000-000000000000
-        let mut s = hex::encode(src.slice(..16));
-        src.advance(16);
+        let mut s = hex::encode(src.get_bytes("UUID", 16)?);
         s.insert(8, '-');
         s.insert(13, '-');
         s.insert(18, '-');
         s.insert(23, '-');
-        s
+        Ok(s)
+    }
+}
+
+impl Encode<String> for Uuid {
+    fn encode(value: &mut String, dst: &mut BytesMut) {
+        Self::serialize_into(value, dst);
+    }
+}
+
+impl Decode<String> for Uuid {
+    fn decode(src: &mut ByteReader, _field: &'static str) -> Result<String, ProtocolError> {
+        Self::deserialize(src)
+    }
+}
+
+/// STRING in non-flexible versions, COMPACT_STRING in flexible versions.
+/// STRING length N is given as an INT16, COMPACT_STRING length N + 1 as an UNSIGNED_VARINT.
+#[allow(dead_code)]
+pub struct FlexibleString;
+
+impl VersionedEncode<String> for FlexibleString {
+    fn encode_versioned(value: &mut String, dst: &mut BytesMut, version: Version) {
+        if version.flexible {
+            VarInt::serialize_into(value.len() as u64 + 1, dst);
+        } else {
+            dst.put_i16(value.len() as i16);
+        }
+        dst.put(value.as_bytes());
+    }
+}
+
+impl VersionedDecode<String> for FlexibleString {
+    fn decode_versioned(
+        src: &mut ByteReader,
+        field: &'static str,
+        version: Version,
+    ) -> Result<String, ProtocolError> {
+        if version.flexible {
+            return CompactString::deserialize(src);
+        }
+        let len = src.get_i16(field)?;
+        if len < 0 {
+            return Err(ProtocolError::UnexpectedValue {
+                field,
+                value: len.into(),
+            });
+        }
+        let bytes = src.get_bytes(field, len as usize)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// ARRAY in non-flexible versions, COMPACT_ARRAY in flexible versions.
+/// Items are encoded in the same message version.
+pub struct FlexibleArray;
+
+impl FlexibleArray {
+    fn decode_len(
+        src: &mut ByteReader,
+        field: &'static str,
+        version: Version,
+    ) -> Result<usize, ProtocolError> {
+        if version.flexible {
+            let len = src.get_varint(field)?; // array length + 1, 0 is null array
+            Ok(if len > 1 { len as usize - 1 } else { 0 })
+        } else {
+            let len = src.get_i32(field)?; // -1 is null array
+            Ok(if len < 0 { 0 } else { len as usize })
+        }
+    }
+
+    fn encode_len(len: usize, dst: &mut BytesMut, version: Version) {
+        if version.flexible {
+            VarInt::serialize_into(len as u64 + 1, dst);
+        } else {
+            dst.put_i32(len as i32);
+        }
     }
 }
 
+impl<T: VersionedSerialize> VersionedEncode<Vec<T>> for FlexibleArray {
+    fn encode_versioned(value: &mut Vec<T>, dst: &mut BytesMut, version: Version) {
+        Self::encode_len(value.len(), dst, version);
+        for item in value.iter_mut() {
+            item.serialize_versioned_into(dst, version);
+        }
+    }
+}
+
+impl<T: VersionedDeserialize<T>> VersionedDecode<Vec<T>> for FlexibleArray {
+    fn decode_versioned(
+        src: &mut ByteReader,
+        field: &'static str,
+        version: Version,
+    ) -> Result<Vec<T>, ProtocolError> {
+        let items_len = Self::decode_len(src, field, version)?;
+        // do not trust the declared length when allocating, every item occupies at least one byte
+        let mut items = Vec::with_capacity(items_len.min(src.remaining()));
+        for _ in 0..items_len {
+            items.push(T::deserialize_versioned(src, version)?);
+        }
+        Ok(items)
+    }
+}
+
+/// BYTES in non-flexible versions, COMPACT_BYTES in flexible versions.
+/// BYTES length N is given as an INT32, COMPACT_BYTES length N + 1 as an UNSIGNED_VARINT.
+#[allow(dead_code)]
+pub struct FlexibleBytes;
+
+impl VersionedEncode<Vec<u8>> for FlexibleBytes {
+    fn encode_versioned(value: &mut Vec<u8>, dst: &mut BytesMut, version: Version) {
+        FlexibleArray::encode_len(value.len(), dst, version);
+        dst.put(value.as_slice());
+    }
+}
+
+impl VersionedDecode<Vec<u8>> for FlexibleBytes {
+    fn decode_versioned(
+        src: &mut ByteReader,
+        field: &'static str,
+        version: Version,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let len = FlexibleArray::decode_len(src, field, version)?;
+        Ok(Vec::from(src.get_bytes(field, len)?))
+    }
+}
+
+/// Tag buffer of flexible versions (KIP-482): an UNSIGNED_VARINT count of tagged fields, each an UNSIGNED_VARINT
+/// tag, an UNSIGNED_VARINT size and the field of that size. The fields are in ascending tag order.
 pub struct TaggedFields;
 
 impl TaggedFields {
-    // Tag buffer - In this challenge an empty tagged field array, represented by a single byte of value 0x00.
+    /// Empty tag buffer, the broker sends no tagged fields
     pub fn serialize() -> Bytes {
         let mut b = BytesMut::with_capacity(1);
-        b.put_u8(0); // tag buffer
+        Self::serialize_into(&mut b);
         b.freeze()
     }
 
-    pub fn deserialize(src: &mut Bytes) -> u8 {
-        src.get_u8() // tag buffer
+    pub fn serialize_into(dst: &mut BytesMut) {
+        dst.put_u8(0); // tag buffer
+    }
+
+    /// Skips the tagged fields of the tag buffer and returns their number. The broker reads none of them,
+    /// receivers skip the tags they do not know.
+    pub fn deserialize(src: &mut ByteReader) -> Result<usize, ProtocolError> {
+        let count = src.get_varint("TAG_BUFFER count")?;
+        if count < 0 {
+            return Err(ProtocolError::UnexpectedValue {
+                field: "TAG_BUFFER count",
+                value: count,
+            });
+        }
+        let mut previous_tag = None;
+        // every field takes at least two bytes, so a made up count ends with a decode error
+        for _ in 0..count {
+            let tag = src.get_varint("tag")?;
+            if tag < 0 || previous_tag.is_some_and(|previous| tag <= previous) {
+                return Err(ProtocolError::UnexpectedValue {
+                    field: "tag",
+                    value: tag,
+                });
+            }
+            previous_tag = Some(tag);
+            let size = src.get_varint("tagged field size")?;
+            if size < 0 {
+                return Err(ProtocolError::UnexpectedValue {
+                    field: "tagged field size",
+                    value: size,
+                });
+            }
+            _ = src.get_bytes("tagged field", size as usize)?;
+        }
+        Ok(count as usize)
     }
 }
 
@@ -198,63 +693,441 @@ impl TaggedFields {
 pub struct VarInt;
 
 impl VarInt {
-    pub(crate) fn deserialize<T>(buf: &mut T) -> i64
+    /// Decodes UNSIGNED_VARINT, values above `i64::MAX` wrap around
+    pub fn deserialize<T>(buf: &mut T) -> Result<i64, VarIntError>
     where
         T: bytes::Buf,
     {
+        // 64-bit value takes at most 10 groups of 7 bits
         const MAX_BYTES: usize = 10;
-        if buf.remaining() == 0 {
-            panic!("buffer is empty")
+
+        let mut res: u64 = 0;
+        for n_byte in 0..MAX_BYTES {
+            if buf.remaining() == 0 {
+                return Err(VarIntError::Truncated);
+            }
+
+            let b = buf.get_u8();
+            if n_byte == MAX_BYTES - 1 && b > 1 {
+                // last byte can hold only the single remaining bit of u64
+                return Err(VarIntError::Overflow);
+            }
+
+            // drop the continuation bit, groups of 7 bits are stored least significant first
+            res |= u64::from(b & 0b0111_1111) << (7 * n_byte);
+
+            if b & 0b1000_0000 == 0 {
+                // highest bit (continuation bit) is zero, this is the last byte
+                return Ok(res as i64);
+            }
         }
 
-        let buf_len = buf.remaining();
+        Err(VarIntError::Overflow)
+    }
+
+    /// Encodes the value as UNSIGNED_VARINT
+    pub fn serialize(value: u64) -> Bytes {
+        let mut b = BytesMut::with_capacity(10);
+        Self::serialize_into(value, &mut b);
+        b.freeze()
+    }
+
+    /// Appends the value encoded as UNSIGNED_VARINT to `dst`
+    pub fn serialize_into(mut value: u64, dst: &mut BytesMut) {
+        while value >= 0b1000_0000 {
+            // lowest 7 bits with the continuation bit set
+            dst.put_u8((value as u8 & 0b0111_1111) | 0b1000_0000);
+            value >>= 7;
+        }
+        dst.put_u8(value as u8);
+    }
+}
 
-        let mut b0 = buf.get_i8() as i64;
-        let mut res = b0 & 0b0111_1111; // drop the MSB (continuation bit)
-        let mut n_bytes = 1;
+/// Signed variable size integer in zigzag encoding, as the fields of records,
+/// read with [`ByteReader::get_varlong`]
+pub struct VarLong;
 
-        while b0 & 0b1000_0000 != 0 && n_bytes <= MAX_BYTES {
-            // highest bit (continuation bit) in the first byte is one, get another byte
+impl VarLong {
+    pub fn serialize(value: i64) -> Bytes {
+        VarInt::serialize(((value << 1) ^ (value >> 63)) as u64)
+    }
 
-            if buf.remaining() == 0 {
-                if buf_len >= MAX_BYTES {
-                    panic!("invalid varint")
+    pub fn serialize_into(value: i64, dst: &mut BytesMut) {
+        VarInt::serialize_into(((value << 1) ^ (value >> 63)) as u64, dst);
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VarIntError {
+    #[error("buffer ended before the last byte of varint")]
+    Truncated,
+    #[error("varint does not fit into 64 bits")]
+    Overflow,
+}
+
+impl Serialize for u32 {
+    fn serialize_into(&mut self, dst: &mut BytesMut) {
+        dst.put_u32(*self);
+    }
+}
+
+impl Deserialize<u32> for u32 {
+    fn deserialize(src: &mut ByteReader) -> Result<u32, ProtocolError> {
+        src.get_u32("INT32")
+    }
+}
+
+/// Implements `Serialize` for a struct by encoding its fields in the listed order as the given protocol types.
+/// Optional `tagged_fields` at the end appends an empty tag buffer.
+///
+/// ```ignore
+/// kafka_serialize! {
+///     TopicResponse {
+///         topic_id: Uuid,
+///         partitions: CompactArray,
+///     }
+///     tagged_fields
+/// }
+/// ```
+///
+/// Structures whose encoding depends on the message version are prefixed with `versioned` and implement
+/// `VersionedSerialize` instead. Fields added in a later version are marked with `since`,
+/// flexible versions get the tag buffer appended.
+///
+/// ```ignore
+/// kafka_serialize! {
+///     versioned ApiVersionsResponseBody {
+///         error_code: Int16,
+///         api_keys: FlexibleArray,
+///         throttle_time_ms: Int32 since 1,
+///     }
+/// }
+/// ```
+macro_rules! kafka_serialize {
+    (versioned $ty:ident { $($field:ident: $wire:ident $(since $min:literal)?),* $(,)? }) => {
+        impl $crate::protocol::types::VersionedSerialize for $ty {
+            fn serialize_versioned_into(
+                &mut self,
+                dst: &mut ::bytes::BytesMut,
+                version: $crate::protocol::types::Version,
+            ) {
+                $(
+                    if kafka_serialize!(@present version $($min)?) {
+                        <$wire as $crate::protocol::types::VersionedEncode<_>>::encode_versioned(
+                            &mut self.$field,
+                            dst,
+                            version,
+                        );
+                    }
+                )*
+                if version.flexible {
+                    $crate::protocol::types::TaggedFields::serialize_into(dst); // tag buffer
                 }
-                panic!("buffer is too short ({} bytes) or invalid varint", buf_len)
             }
+        }
+    };
+    (@present $version:ident) => { true };
+    (@present $version:ident $min:literal) => { $version.version >= $min };
+    ($ty:ident { $($field:ident: $wire:ident),* $(,)? } tagged_fields) => {
+        impl $crate::protocol::types::Serialize for $ty {
+            fn serialize_into(&mut self, dst: &mut ::bytes::BytesMut) {
+                $(<$wire as $crate::protocol::types::Encode<_>>::encode(&mut self.$field, dst);)*
+                $crate::protocol::types::TaggedFields::serialize_into(dst); // tag buffer
+            }
+        }
+    };
+    ($ty:ident { $($field:ident: $wire:ident),* $(,)? }) => {
+        impl $crate::protocol::types::Serialize for $ty {
+            fn serialize_into(&mut self, dst: &mut ::bytes::BytesMut) {
+                $(<$wire as $crate::protocol::types::Encode<_>>::encode(&mut self.$field, dst);)*
+            }
+        }
+    };
+}
 
-            let b1 = buf.get_i8() as i64;
-            if buf.remaining() == 0 && b1 & 0b1000_0000 != 0 {
-                // last byte still starts with 1
-
-                if buf_len >= 8 {
-                    panic!("invalid varint")
+/// Implements `Deserialize` for a struct by decoding its fields in the listed order from the given protocol types.
+/// All fields of the struct have to be listed. Optional `tagged_fields` at the end reads the tag buffer.
+///
+/// ```ignore
+/// kafka_deserialize! {
+///     TopicRequest {
+///         topic_id: Uuid,
+///         partitions: CompactArray,
+///     }
+///     tagged_fields
+/// }
+/// ```
+///
+/// With the `versioned` prefix the struct implements `VersionedDeserialize`, fields marked with `since`
+/// that are not present in the decoded version get their default value.
+macro_rules! kafka_deserialize {
+    (versioned $ty:ident { $($field:ident: $wire:ident $(since $min:literal)?),* $(,)? }) => {
+        impl $crate::protocol::types::VersionedDeserialize<$ty> for $ty {
+            fn deserialize_versioned(
+                src: &mut $crate::protocol::reader::ByteReader,
+                version: $crate::protocol::types::Version,
+            ) -> Result<$ty, $crate::protocol::ProtocolError> {
+                $(
+                    let $field = if kafka_deserialize!(@present version $($min)?) {
+                        <$wire as $crate::protocol::types::VersionedDecode<_>>::decode_versioned(
+                            src,
+                            stringify!($field),
+                            version,
+                        )?
+                    } else {
+                        Default::default()
+                    };
+                )*
+                if version.flexible {
+                    _ = $crate::protocol::types::TaggedFields::deserialize(src)?; // tag buffer
                 }
-                panic!("buffer is too short ({} bytes) or invalid varint", buf_len)
+                Ok($ty { $($field),* })
+            }
+        }
+    };
+    (@present $version:ident) => { true };
+    (@present $version:ident $min:literal) => { $version.version >= $min };
+    ($ty:ident { $($field:ident: $wire:ident),* $(,)? } tagged_fields) => {
+        impl $crate::protocol::types::Deserialize<$ty> for $ty {
+            fn deserialize(
+                src: &mut $crate::protocol::reader::ByteReader,
+            ) -> Result<$ty, $crate::protocol::ProtocolError> {
+                $(let $field = <$wire as $crate::protocol::types::Decode<_>>::decode(src, stringify!($field))?;)*
+                _ = $crate::protocol::types::TaggedFields::deserialize(src)?; // tag buffer
+                Ok($ty { $($field),* })
             }
+        }
+    };
+    ($ty:ident { $($field:ident: $wire:ident),* $(,)? }) => {
+        impl $crate::protocol::types::Deserialize<$ty> for $ty {
+            fn deserialize(
+                src: &mut $crate::protocol::reader::ByteReader,
+            ) -> Result<$ty, $crate::protocol::ProtocolError> {
+                $(let $field = <$wire as $crate::protocol::types::Decode<_>>::decode(src, stringify!($field))?;)*
+                Ok($ty { $($field),* })
+            }
+        }
+    };
+}
+
+pub(crate) use kafka_deserialize;
+pub(crate) use kafka_serialize;
 
-            // drop the continuation bit and convert to big-endian
-            res += (b1 & 0b0111_1111) << 7;
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{
+        Boolean, CompactArray, CompactNullableString, CompactRecords, CompactString, Decode,
+        Deserialize, Encode, FlexibleArray, FlexibleString, Int32, NullableString, Records,
+        Serialize, TaggedFields, Uuid, VarInt, VarIntError, VarLong, Version, VersionedDeserialize,
+        VersionedSerialize,
+    };
+    use crate::protocol::{reader::ByteReader, testing, ProtocolError};
+
+    #[derive(Debug, PartialEq)]
+    struct Item {
+        id: u32,
+        name: String,
+        flag: bool,
+        ids: Vec<u32>,
+    }
 
-            n_bytes += 1;
+    kafka_serialize! {
+        Item {
+            id: Int32,
+            name: CompactString,
+            flag: Boolean,
+            ids: CompactArray,
+        }
+        tagged_fields
+    }
 
-            b0 = b1;
+    kafka_deserialize! {
+        Item {
+            id: Int32,
+            name: CompactString,
+            flag: Boolean,
+            ids: CompactArray,
         }
+        tagged_fields
+    }
+
+    #[test]
+    fn macro_generated_round_trip() {
+        let mut item = Item {
+            id: 7,
+            name: "foo".to_string(),
+            flag: true,
+            ids: vec![1, 2],
+        };
+        let bytes = item.serialize();
+        assert_eq!(
+            bytes.as_ref(),
+            &[0, 0, 0, 7, 4, b'f', b'o', b'o', 1, 3, 0, 0, 0, 1, 0, 0, 0, 2, 0]
+        );
 
-        res
+        let mut src = ByteReader::new(bytes);
+        assert_eq!(Item::deserialize(&mut src).unwrap(), item);
+        assert_eq!(src.remaining(), 0);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::VarInt;
+    #[test]
+    fn nullable_strings_round_trip() {
+        for value in [None, Some(""), Some("client")] {
+            let mut src = ByteReader::new(NullableString::serialize(value));
+            assert_eq!(
+                NullableString::deserialize(&mut src).unwrap().as_deref(),
+                value
+            );
+            assert_eq!(src.remaining(), 0);
+
+            let mut src = ByteReader::new(CompactNullableString::serialize(value));
+            assert_eq!(
+                CompactNullableString::deserialize(&mut src)
+                    .unwrap()
+                    .as_deref(),
+                value
+            );
+            assert_eq!(src.remaining(), 0);
+        }
+        assert_eq!(NullableString::serialize(None).as_ref(), &[0xFF, 0xFF]);
+        assert_eq!(CompactNullableString::serialize(None).as_ref(), &[0]);
+    }
+
+    #[test]
+    fn compact_records() {
+        // two batches with Batch Length 1 and 2
+        let batches = [
+            Bytes::from_static(&[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 9]),
+            Bytes::from_static(&[0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 8, 7]),
+        ];
+        let mut records = Records::default();
+        records.push(batches[0].clone());
+        records.push(batches[1].clone());
+
+        let mut b = BytesMut::new();
+        CompactRecords::encode(&mut records, &mut b);
+        assert_eq!(b[0], 13 + 14 + 1);
+        assert_eq!(b.len(), 1 + 13 + 14);
+
+        let mut src = ByteReader::new(b.freeze());
+        let decoded = CompactRecords::decode(&mut src, "records").unwrap();
+        assert_eq!(decoded.batches(), &batches);
+        assert_eq!(src.remaining(), 0);
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct VersionedItem {
+        id: u32,
+        name: String,
+        items: Vec<u32>,
+    }
+
+    impl VersionedSerialize for u32 {
+        fn serialize_versioned_into(&mut self, dst: &mut BytesMut, _version: Version) {
+            self.serialize_into(dst)
+        }
+    }
+
+    impl VersionedDeserialize<u32> for u32 {
+        fn deserialize_versioned(
+            src: &mut ByteReader,
+            _version: Version,
+        ) -> Result<u32, ProtocolError> {
+            u32::deserialize(src)
+        }
+    }
+
+    kafka_serialize! {
+        versioned VersionedItem {
+            id: Int32,
+            name: FlexibleString since 1,
+            items: FlexibleArray,
+        }
+    }
+
+    kafka_deserialize! {
+        versioned VersionedItem {
+            id: Int32,
+            name: FlexibleString since 1,
+            items: FlexibleArray,
+        }
+    }
+
+    #[test]
+    fn versioned_encodings() {
+        let mut item = VersionedItem {
+            id: 7,
+            name: "foo".to_string(),
+            items: vec![1],
+        };
+
+        let v0 = Version::new(0, 2);
+        let bytes = item.serialize_versioned(v0);
+        assert_eq!(bytes.as_ref(), &[0, 0, 0, 7, 0, 0, 0, 1, 0, 0, 0, 1]);
+        let decoded = VersionedItem::deserialize_versioned(&mut ByteReader::new(bytes), v0);
+        assert_eq!(decoded.unwrap().name, ""); // not present in version 0
+
+        let v1 = Version::new(1, 2);
+        let bytes = item.serialize_versioned(v1);
+        assert_eq!(
+            bytes.as_ref(),
+            &[0, 0, 0, 7, 0, 3, b'f', b'o', b'o', 0, 0, 0, 1, 0, 0, 0, 1]
+        );
+        let mut src = ByteReader::new(bytes);
+        assert_eq!(
+            VersionedItem::deserialize_versioned(&mut src, v1).unwrap(),
+            item
+        );
+        assert_eq!(src.remaining(), 0);
+
+        let v2 = Version::new(2, 2);
+        let bytes = item.serialize_versioned(v2);
+        assert_eq!(
+            bytes.as_ref(),
+            &[0, 0, 0, 7, 4, b'f', b'o', b'o', 2, 0, 0, 0, 1, 0]
+        );
+        let mut src = ByteReader::new(bytes);
+        assert_eq!(
+            VersionedItem::deserialize_versioned(&mut src, v2).unwrap(),
+            item
+        );
+        assert_eq!(src.remaining(), 0);
+    }
+
+    #[test]
+    fn tagged_fields_are_skipped() {
+        let read = |bytes: &'static [u8]| {
+            let mut src = ByteReader::new(Bytes::from_static(bytes));
+            TaggedFields::deserialize(&mut src).map(|count| (count, src.remaining()))
+        };
+        assert_eq!(read(&[0, 7]).unwrap(), (0, 1));
+        // tag 0 of 3 bytes and tag 5 of 1 byte, before the next field
+        assert_eq!(
+            read(&[2, 0, 3, b'a', b'b', b'c', 5, 1, 0xff, 7]).unwrap(),
+            (2, 1)
+        );
+
+        // a field longer than the buffer, tags out of order
+        assert!(matches!(
+            read(&[1, 0, 4, b'a']),
+            Err(ProtocolError::UnexpectedEof { .. })
+        ));
+        assert!(matches!(
+            read(&[2, 1, 0, 1, 0]),
+            Err(ProtocolError::UnexpectedValue {
+                field: "tag",
+                value: 1
+            })
+        ));
+    }
 
     #[test]
-    #[should_panic]
     fn varint_empty_buf() {
         let mut buf = &[][..];
         assert_eq!(buf.len(), 0);
-        _ = VarInt::deserialize(&mut buf);
+        assert_eq!(VarInt::deserialize(&mut buf), Err(VarIntError::Truncated));
     }
 
     #[test]
@@ -262,12 +1135,12 @@ mod tests {
         let mut buf = &[0b01101000][..];
         assert_eq!(buf.len(), 1);
         let r = VarInt::deserialize(&mut buf);
-        assert_eq!(r, 104);
+        assert_eq!(r, Ok(104));
 
         let mut buf = &[0b01101000, 0b01101000][..];
         assert_eq!(buf.len(), 2);
         let r = VarInt::deserialize(&mut buf);
-        assert_eq!(r, 104);
+        assert_eq!(r, Ok(104));
     }
 
     #[test]
@@ -275,6 +1148,93 @@ mod tests {
         let mut buf: &[u8] = &[0b10010110, 0b00000001][..];
         assert_eq!(buf.len(), 2);
         let r = VarInt::deserialize(&mut buf);
-        assert_eq!(r, 150);
+        assert_eq!(r, Ok(150));
+    }
+
+    #[test]
+    fn varint_3_bytes() {
+        let mut buf: &[u8] = &[0b10000000, 0b10000000, 0b00000001][..];
+        let r = VarInt::deserialize(&mut buf);
+        assert_eq!(r, Ok(1 << 14));
+    }
+
+    #[test]
+    fn varint_serialize() {
+        assert_eq!(VarInt::serialize(1).as_ref(), &[0b00000001]);
+        assert_eq!(VarInt::serialize(150).as_ref(), &[0b10010110, 0b00000001]);
+
+        let mut buf = VarInt::serialize(u64::MAX);
+        assert_eq!(buf.len(), 10);
+        assert_eq!(VarInt::deserialize(&mut buf), Ok(-1));
+    }
+
+    #[test]
+    fn varint_truncated() {
+        let mut buf: &[u8] = &[0b10010110][..];
+        assert_eq!(VarInt::deserialize(&mut buf), Err(VarIntError::Truncated));
+    }
+
+    #[test]
+    fn varint_overflow() {
+        let mut buf: &[u8] = &[0xFF; 11][..];
+        assert_eq!(VarInt::deserialize(&mut buf), Err(VarIntError::Overflow));
+
+        let mut buf: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02][..];
+        assert_eq!(VarInt::deserialize(&mut buf), Err(VarIntError::Overflow));
+
+        let mut buf: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01][..];
+        assert_eq!(VarInt::deserialize(&mut buf), Ok(-1));
+    }
+
+    #[test]
+    fn compact_strings_and_arrays_round_trip() {
+        testing::check(|g| {
+            // longer than 127 bytes the lengths take two varint bytes
+            let s = g.string(300);
+            let mut src = ByteReader::new(CompactString::serialize(&s));
+            assert_eq!(CompactString::deserialize(&mut src).unwrap(), s);
+            assert_eq!(src.remaining(), 0);
+
+            let s = g.option(|g| g.string(300));
+            let mut src = ByteReader::new(CompactNullableString::serialize(s.as_deref()));
+            assert_eq!(CompactNullableString::deserialize(&mut src).unwrap(), s);
+            assert_eq!(src.remaining(), 0);
+
+            let mut items = g.vec(300, |g| g.u32());
+            let mut src = ByteReader::new(CompactArray::serialize(&mut items));
+            assert_eq!(
+                CompactArray::deserialize::<u32, u32>(&mut src).unwrap(),
+                items
+            );
+            assert_eq!(src.remaining(), 0);
+        });
+    }
+
+    #[test]
+    fn varints_and_uuids_round_trip() {
+        testing::check(|g| {
+            let value = g.number();
+            let mut src = ByteReader::new(VarInt::serialize(value));
+            assert_eq!(src.get_varint("varint").unwrap(), value as i64);
+            assert_eq!(src.remaining(), 0);
+
+            let value = g.i64();
+            let value = if g.bool() {
+                value
+            } else {
+                value.wrapping_neg()
+            };
+            let bytes = VarLong::serialize(value);
+            // zigzag keeps small negative numbers short
+            assert!(bytes.len() <= 1 + (64 - value.unsigned_abs().leading_zeros() as usize) / 7);
+            let mut src = ByteReader::new(bytes);
+            assert_eq!(src.get_varlong("varlong").unwrap(), value);
+            assert_eq!(src.remaining(), 0);
+
+            let uuid = g.uuid();
+            let mut src = ByteReader::new(Uuid::serialize(&uuid));
+            assert_eq!(Uuid::deserialize(&mut src).unwrap(), uuid);
+            assert_eq!(src.remaining(), 0);
+        });
     }
 }
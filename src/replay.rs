@@ -0,0 +1,246 @@
+//! Replay of captured request frames without a socket, for debugging and benchmarking offline.
+//!
+//! The requests of a capture are processed one after the other by [`logic::process`] with the configuration
+//! and the logs of the broker, and their response frames are written to a file in the same order.
+//! A capture is either the frames with their size prefixes, as read from a connection, or a log of
+//! `--trace-wire=hex`, whose request hexdumps are the frames. The requests of all connections of a trace
+//! are replayed in the order they were logged, by the anonymous principal and without throttling.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{bail, Context, Result};
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    codec::KafkaFrameCodec,
+    config::Config,
+    logic::{self, authorizer::KafkaPrincipal, sasl::Authentication, BrokerContext},
+    protocol::{reader::ByteReader, request::RequestHeader},
+    storage::FileStorage,
+};
+
+/// Processes the requests of `config.replay` and writes the responses to `config.replay_output`
+pub fn replay(config: Config) -> Result<()> {
+    let input = config.replay.clone().context("no capture to replay")?;
+    let output = config
+        .replay_output
+        .clone()
+        .unwrap_or_else(|| responses_file(&input));
+
+    let capture = std::fs::read(&input).with_context(|| format!("read {}", input.display()))?;
+    let requests = requests(&capture).with_context(|| format!("parse {}", input.display()))?;
+
+    let broker = Arc::new(BrokerContext::new(Arc::new(config), Arc::new(FileStorage)));
+    let start = Instant::now();
+    let replayed = replay_requests(&broker, requests);
+    let elapsed = start.elapsed();
+    std::fs::write(&output, &replayed.responses)
+        .with_context(|| format!("write {}", output.display()))?;
+
+    eprintln!(
+        "replayed {} requests of {} in {} ms: {} responses written to {}, {} requests failed",
+        replayed.requests,
+        input.display(),
+        elapsed.as_millis(),
+        replayed.answered,
+        output.display(),
+        replayed.failed
+    );
+    Ok(())
+}
+
+/// `<capture>.responses` next to the capture
+fn responses_file(input: &Path) -> PathBuf {
+    let mut file = input.as_os_str().to_owned();
+    file.push(".responses");
+    PathBuf::from(file)
+}
+
+/// Response frames of the replayed requests
+#[derive(Debug, Default)]
+struct Replayed {
+    /// The response frames with their size prefixes, in request order
+    responses: Vec<u8>,
+    requests: usize,
+    answered: usize,
+    /// Requests the connection would have been closed after, they are logged and skipped
+    failed: usize,
+}
+
+/// Processes the request frames without their size prefixes in turn
+fn replay_requests(broker: &Arc<BrokerContext>, requests: Vec<Bytes>) -> Replayed {
+    let mut replayed = Replayed::default();
+    for (i, msg) in requests.into_iter().enumerate() {
+        replayed.requests += 1;
+        let mut reader = ByteReader::new(msg);
+        let processed = RequestHeader::from_bytes(&mut reader)
+            .context("parse request header")
+            .and_then(|header| {
+                let body = reader.into_bytes();
+                let principal = KafkaPrincipal::anonymous();
+                logic::process(
+                    broker.clone(),
+                    header,
+                    principal,
+                    Authentication::None,
+                    body,
+                )
+            });
+        match processed {
+            // requests without a response, e.g. Produce with acks 0
+            Ok(processed) if processed.response.is_empty() => {}
+            Ok(processed) => {
+                replayed.responses.extend_from_slice(&processed.response);
+                replayed.answered += 1;
+            }
+            Err(err) => {
+                eprintln!("Error: request {i} of the capture: {err:?}");
+                replayed.failed += 1;
+            }
+        }
+    }
+    replayed
+}
+
+/// Request frames of the capture without their size prefixes.
+///
+/// A size prefix starts with a zero byte for any frame below the maximum size, a text capture is a trace.
+fn requests(capture: &[u8]) -> Result<Vec<Bytes>> {
+    if capture.first().is_some_and(|b| b.is_ascii_graphic()) {
+        let trace = std::str::from_utf8(capture).context("trace is not UTF-8")?;
+        return traced_requests(trace);
+    }
+
+    let mut codec = KafkaFrameCodec::default();
+    let mut src = BytesMut::from(capture);
+    let mut requests = Vec::new();
+    while let Some(msg) = codec.decode_eof(&mut src)? {
+        requests.push(msg);
+    }
+    Ok(requests)
+}
+
+/// Request frames of the hexdumps of a `--trace-wire=hex` log, other lines of the log are skipped
+fn traced_requests(trace: &str) -> Result<Vec<Bytes>> {
+    let mut requests = Vec::new();
+    // request whose hexdump lines follow
+    let mut request: Option<Vec<u8>> = None;
+    for (i, line) in trace.lines().enumerate() {
+        // hexdump lines are `  <offset>  <up to 16 bytes in hex>  |<ascii>|`
+        if let (Some(msg), Some(hex)) = (&mut request, line.strip_prefix("  ")) {
+            let hex = hex
+                .get(10..57)
+                .or_else(|| hex.get(10..))
+                .unwrap_or_default();
+            for byte in hex.split_whitespace() {
+                let byte = u8::from_str_radix(byte, 16)
+                    .with_context(|| format!("line {}: invalid hexdump byte '{byte}'", i + 1))?;
+                msg.push(byte);
+            }
+            continue;
+        }
+        requests.extend(request.take().map(Bytes::from));
+        if line.starts_with("wire ") && line.contains(" <- ") {
+            request = Some(Vec::new());
+        }
+    }
+    requests.extend(request.map(Bytes::from));
+
+    if requests.iter().any(|msg| msg.is_empty()) {
+        bail!("requests without hexdump, the trace has to be logged with --trace-wire=hex");
+    }
+    Ok(requests)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bytes::Bytes;
+
+    use super::{replay_requests, requests, responses_file};
+    use crate::{
+        logic::BrokerContext,
+        protocol::{
+            decode_response, encode_request,
+            generated::{
+                api_versions_request::ApiVersionsRequestData,
+                api_versions_response::ApiVersionsResponseData,
+            },
+            request::RequestHeader,
+            ApiKey, ErrorCode,
+        },
+        storage::MemoryStorage,
+    };
+
+    fn api_versions(correlation_id: i32) -> Bytes {
+        let header = RequestHeader {
+            request_api_key: ApiKey::ApiVersions.into(),
+            request_api_version: 4,
+            correlation_id,
+            client_id: Some("replay".to_string()),
+        };
+        encode_request(&header, ApiVersionsRequestData::default().serialize(4))
+    }
+
+    #[test]
+    fn replays_captured_frames_in_order() {
+        let capture = [api_versions(1), api_versions(2)].concat();
+        let broker = BrokerContext::with_storage(MemoryStorage::default());
+
+        let replayed = replay_requests(&broker, requests(&capture).unwrap());
+        assert_eq!(
+            (replayed.requests, replayed.answered, replayed.failed),
+            (2, 2, 0)
+        );
+
+        let mut responses = Bytes::from(replayed.responses);
+        for correlation_id in [1, 2] {
+            let size = i32::from_be_bytes(responses[..4].try_into().unwrap()) as usize;
+            let msg = responses.split_to(4 + size).slice(4..);
+            let (header, response) = decode_response(
+                msg,
+                ApiKey::ApiVersions,
+                4,
+                ApiVersionsResponseData::deserialize,
+            )
+            .unwrap();
+            assert_eq!(header.correlation_id(), correlation_id);
+            assert_eq!(response.error_code, i16::from(ErrorCode::None));
+        }
+        assert!(responses.is_empty());
+    }
+
+    #[test]
+    fn reads_the_requests_of_a_hex_trace() {
+        let request = api_versions(7);
+        let mut trace = String::from("accepted new connection\n");
+        trace.push_str("wire 127.0.0.1:50000 <- ApiVersions v4 correlation_id=7 size=29\n");
+        for (i, chunk) in request[4..].chunks(16).enumerate() {
+            let hex: Vec<_> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            trace.push_str(&format!("  {:08x}  {:<47}  |...|\n", i * 16, hex.join(" ")));
+        }
+        trace.push_str("wire 127.0.0.1:50000 -> ApiVersions v4 correlation_id=7 size=8\n");
+        trace.push_str("  00000000  00 00 00 07                                       |....|\n");
+
+        assert_eq!(
+            requests(trace.as_bytes()).unwrap(),
+            vec![request.slice(4..)]
+        );
+
+        let headers_only = "wire 127.0.0.1:50000 <- ApiVersions v4 correlation_id=7 size=29\n";
+        assert!(requests(headers_only.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn responses_are_written_next_to_the_capture() {
+        assert_eq!(
+            responses_file(Path::new("/tmp/requests.bin")),
+            Path::new("/tmp/requests.bin.responses")
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    sync::watch,
+    task::JoinHandle,
+    time::{self, MissedTickBehavior},
+};
+
+/// Periodic background tasks of the broker, e.g. expiry of idle state, as Kafka's `KafkaScheduler`.
+///
+/// Tasks run on the blocking thread pool, so that they may read or write the logs. A task is never run
+/// twice at the same time, a run taking longer than the period delays the next one.
+pub struct Scheduler {
+    shutdown: watch::Sender<bool>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            shutdown: watch::channel(false).0,
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Runs the task every period, the first time one period from now. A zero period is treated as 1 ms.
+    pub fn schedule(
+        &self,
+        name: &'static str,
+        period: Duration,
+        task: impl Fn() + Send + Sync + 'static,
+    ) {
+        let period = period.max(Duration::from_millis(1));
+        let task = Arc::new(task);
+        let mut shutdown = self.shutdown.subscribe();
+
+        let handle = tokio::spawn(async move {
+            let mut interval = time::interval_at(time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let task = Arc::clone(&task);
+                        if let Err(e) = tokio::task::spawn_blocking(move || task()).await {
+                            eprintln!("Error: scheduled task {name} failed: {e}");
+                        }
+                    }
+                    _ = shutdown.changed() => break,
+                }
+            }
+        });
+        self.tasks
+            .lock()
+            .expect("scheduler lock is not poisoned")
+            .push(handle);
+    }
+
+    /// Stops scheduling the tasks and waits for the running ones to finish
+    pub async fn shutdown(&self) {
+        _ = self.shutdown.send(true);
+        let tasks =
+            std::mem::take(&mut *self.tasks.lock().expect("scheduler lock is not poisoned"));
+        for task in tasks {
+            _ = task.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::Scheduler;
+
+    #[tokio::test]
+    async fn runs_tasks_until_shutdown() {
+        let scheduler = Scheduler::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        scheduler.schedule("count", Duration::from_millis(10), {
+            let runs = Arc::clone(&runs);
+            move || {
+                runs.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        // panicking tasks do not stop the schedule
+        scheduler.schedule("panic", Duration::from_millis(10), || panic!("task failed"));
+
+        tokio::time::sleep(Duration::from_millis(55)).await;
+        scheduler.shutdown().await;
+        let after_shutdown = runs.load(Ordering::Relaxed);
+        assert!(after_shutdown >= 2, "ran {after_shutdown} times");
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), after_shutdown);
+    }
+}
@@ -0,0 +1,510 @@
+use crate::{
+    admin,
+    codec::{FrameReader, FrameWriter, Framed, KafkaFrameCodec, WireTrace},
+    config,
+    logic::{
+        self,
+        authorizer::KafkaPrincipal,
+        sasl::{Authentication, Authenticator, SaslResponse},
+        BrokerContext, ProcessedRequest, UnsupportedApiKeyError,
+    },
+    metrics,
+    protocol::{reader::ByteReader, request, ErrorCode},
+    scheduler,
+    storage::FileStorage,
+};
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpSocket, TcpStream},
+    sync::{mpsc, OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+};
+
+/// Runs the broker with the configuration until it is interrupted with Ctrl-C
+pub async fn run(config: config::Config) -> Result<()> {
+    let listeners = listen(&config)?;
+    let broker = BrokerContext::new(Arc::new(config), Arc::new(FileStorage));
+    serve(listeners, broker, async {
+        _ = tokio::signal::ctrl_c().await;
+    })
+    .await
+}
+
+/// Accepts the connections of the clients on all listeners until `shutdown` completes, the connections
+/// that are open by then are served until the clients close them
+pub async fn serve(
+    listeners: Vec<TcpListener>,
+    broker: BrokerContext,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    let broker = Arc::new(broker);
+    let connections = Arc::new(Connections::default());
+    if let Some(addr) = broker.config.metrics_addr() {
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr).await {
+                eprintln!("Error: {:?}", e);
+            }
+        });
+    }
+    if let Some(addr) = broker.config.admin_addr() {
+        let (broker, connections) = (broker.clone(), connections.clone());
+        tokio::spawn(async move {
+            if let Err(e) = admin::serve(addr, broker, connections).await {
+                eprintln!("Error: {:?}", e);
+            }
+        });
+    }
+
+    let scheduler = scheduler::Scheduler::new();
+    logic::schedule_tasks(&scheduler, &broker);
+    tokio::pin!(shutdown);
+
+    let mut next_listener = 0;
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = accept(&listeners, &mut next_listener) => accepted?,
+            _ = &mut shutdown => break,
+        };
+        stream
+            .set_nodelay(broker.config.tcp_nodelay)
+            .context("set TCP_NODELAY")?;
+
+        let broker = broker.clone();
+        let connection = connections.open(peer);
+        tokio::spawn(async move {
+            eprintln!("accepted new connection");
+            metrics::metrics().connection_opened();
+            // there is no TLS listener, clients are anonymous until they authenticate with SASL
+            let principal = KafkaPrincipal::anonymous();
+            // e.g. a frame with an invalid size, after which the stream cannot be read any further
+            handle_connection(stream, peer, broker, principal)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: connection from {peer} closed: {:?}", e);
+                });
+            metrics::metrics().connection_closed();
+            drop(connection);
+        });
+    }
+
+    eprintln!("shutting down");
+    scheduler.shutdown().await;
+    Ok(())
+}
+
+/// Accepts the next connection of any of the listeners.
+///
+/// The listeners are polled from the one after the listener of the previous connection, so that a busy
+/// listener does not starve the others.
+async fn accept(
+    listeners: &[TcpListener],
+    next: &mut usize,
+) -> io::Result<(TcpStream, SocketAddr)> {
+    std::future::poll_fn(|cx| {
+        for i in (0..listeners.len()).map(|i| (*next + i) % listeners.len()) {
+            if let Poll::Ready(accepted) = listeners[i].poll_accept(cx) {
+                *next = i + 1;
+                return Poll::Ready(accepted);
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Connections open to the broker, for the admin endpoint
+#[derive(Default)]
+pub struct Connections {
+    next_id: AtomicU64,
+    open: Mutex<BTreeMap<u64, OpenConnection>>,
+}
+
+/// Connection open to the broker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenConnection {
+    pub peer: SocketAddr,
+    /// Time the connection was accepted, in milliseconds since the Unix epoch
+    pub opened_ms: i64,
+}
+
+impl Connections {
+    /// Adds the accepted connection, it is removed when the guard is dropped
+    fn open(self: &Arc<Self>, peer: SocketAddr) -> ConnectionGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let opened_ms = logic::now_ms();
+        self.open
+            .lock()
+            .expect("connections lock is not poisoned")
+            .insert(id, OpenConnection { peer, opened_ms });
+        ConnectionGuard {
+            connections: self.clone(),
+            id,
+        }
+    }
+
+    /// The open connections, in the order they were accepted
+    pub fn list(&self) -> Vec<OpenConnection> {
+        let open = self.open.lock().expect("connections lock is not poisoned");
+        open.values().cloned().collect()
+    }
+}
+
+/// Removes the connection from the open ones when it is closed
+struct ConnectionGuard {
+    connections: Arc<Connections>,
+    id: u64,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.connections
+            .open
+            .lock()
+            .expect("connections lock is not poisoned")
+            .remove(&self.id);
+    }
+}
+
+/// Binds the listener sockets of the bind addresses, the first one is the one of `listen_addr`.
+///
+/// With port 0 the first listener gets a free port and the others listen on the same one.
+pub fn listen(config: &config::Config) -> Result<Vec<TcpListener>> {
+    let mut listeners: Vec<TcpListener> = Vec::new();
+    for mut addr in config.listen_addrs() {
+        if let Some(first) = listeners.first() {
+            addr.set_port(first.local_addr().context("get listener address")?.port());
+        }
+        listeners.push(listen_on(addr, config)?);
+    }
+    Ok(listeners)
+}
+
+/// Binds the listener socket.
+///
+/// Keepalive and buffer sizes are set on the listener so that the accepted sockets inherit them,
+/// the receive buffer has to be set before `listen` to take effect on the TCP window scaling.
+fn listen_on(addr: SocketAddr, config: &config::Config) -> Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()
+    } else {
+        TcpSocket::new_v6()
+    }
+    .context("create socket")?;
+
+    socket.set_reuseaddr(true).context("set SO_REUSEADDR")?;
+    socket
+        .set_keepalive(config.tcp_keepalive)
+        .context("set SO_KEEPALIVE")?;
+    if let Some(size) = config.socket_send_buffer_bytes {
+        socket.set_send_buffer_size(size).context("set SO_SNDBUF")?;
+    }
+    if let Some(size) = config.socket_receive_buffer_bytes {
+        socket.set_recv_buffer_size(size).context("set SO_RCVBUF")?;
+    }
+
+    socket.bind(addr).with_context(|| format!("bind {addr}"))?;
+    socket
+        .listen(1024)
+        .with_context(|| format!("listen on {addr}"))
+}
+
+/// Reads requests and writes responses of one connection.
+///
+/// Clients may send next requests before they receive the responses to the previous ones (pipelining).
+/// The requests are read by the connection task and processed concurrently, a writer task writes their
+/// responses in the order the requests came in. The responses are buffered and flushed when the response
+/// to the next request is not ready yet, so the responses to a pipelined batch are written together.
+/// When the writer stops, e.g. because writing failed, no more requests are read and the connection is closed.
+///
+/// At most `max_in_flight_requests` are processed at a time. Until one of them is answered no more requests
+/// are read, so the socket buffers fill up and TCP flow control slows down the client.
+///
+/// The connection is closed when no request comes for `connections.max.idle.ms` and no response is pending.
+/// The stream is generic, so that it can be a plain TCP stream or wrap one with e.g. TLS.
+/// The principal is the identity the client authenticated with when the connection was established,
+/// on SASL listeners it is replaced by the principal of each authentication. The SASL requests are processed
+/// in turn as they are read, and the connection is closed after a failed authentication.
+/// With `--trace-wire` the frames are logged with the address of the peer.
+/// The connection is closed too when the rest of a started request or a response is not transferred within
+/// the socket timeouts.
+async fn handle_connection<S>(
+    stream: S,
+    peer: SocketAddr,
+    broker: Arc<BrokerContext>,
+    principal: KafkaPrincipal,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let mut framed = Framed::new(stream, KafkaFrameCodec::default());
+    framed.timeouts(
+        broker.config.socket_read_timeout,
+        broker.config.socket_write_timeout,
+    );
+    if let Some(mode) = broker.config.trace_wire {
+        framed.trace(WireTrace::new(peer, mode));
+    }
+    let (reader, writer) = framed.into_split();
+
+    let max_in_flight = broker.config.max_in_flight_requests;
+    let (responses, responses_rx) = mpsc::channel(max_in_flight);
+    let writer = tokio::spawn(write_responses(writer, responses_rx));
+
+    let read = read_requests(reader, &broker, principal, responses).await;
+    // the sender is dropped, so the writer ends once it has written the responses of the requests read
+    let written = writer.await.context("join writer task")?;
+    read.and(written)
+}
+
+/// Request being processed, it holds a permit of the connection until its response is written
+struct InFlight {
+    response: JoinHandle<Result<ProcessedRequest>>,
+    permit: OwnedSemaphorePermit,
+}
+
+/// Reads the requests of the connection and sends them, being processed, to the writer in request order
+async fn read_requests<R: AsyncRead + Unpin>(
+    mut reader: FrameReader<R>,
+    broker: &Arc<BrokerContext>,
+    principal: KafkaPrincipal,
+    responses: mpsc::Sender<InFlight>,
+) -> Result<()> {
+    let max_idle = broker.config.connections_max_idle;
+    let max_in_flight = broker.config.max_in_flight_requests;
+    let request_timeout = broker.config.request_timeout;
+    let permits = Arc::new(Semaphore::new(max_in_flight));
+    let mut authenticator = Authenticator::new(&broker.config);
+
+    loop {
+        let permit = tokio::select! {
+            permit = permits.clone().acquire_owned() => permit.expect("semaphore is not closed"),
+            _ = responses.closed() => break,
+        };
+        let msg = tokio::select! {
+            msg = reader.next_frame() => msg.context("read message")?,
+            _ = idle(&permits, max_in_flight - 1, max_idle) => {
+                eprintln!("closing connection idle for {} ms", max_idle.as_millis());
+                break;
+            }
+            _ = responses.closed() => break,
+        };
+        // the peer closed the connection, the writer writes the remaining responses
+        let Some(msg) = msg else { break };
+        metrics::metrics().bytes_received(msg.len() + 4); // with message size
+
+        let mut principal = principal.clone();
+        let mut authentication = Authentication::None;
+        if let Some(authenticator) = authenticator.as_mut() {
+            if let Some(sasl) = authenticate(authenticator, broker, &msg)? {
+                let processed = ProcessedRequest {
+                    response: sasl.response,
+                    throttle: Duration::ZERO,
+                };
+                let in_flight = InFlight {
+                    response: tokio::spawn(std::future::ready(Ok(processed))),
+                    permit,
+                };
+                if responses.send(in_flight).await.is_err() || sasl.failed {
+                    break;
+                }
+                continue;
+            }
+            principal = authenticator.principal();
+            authentication = authenticator.authentication();
+        }
+        let in_flight = InFlight {
+            response: tokio::spawn(process_message(
+                broker.clone(),
+                msg,
+                principal,
+                authentication,
+                request_timeout,
+            )),
+            permit,
+        };
+        if responses.send(in_flight).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Completes when no request is in flight for `max_idle`, i.e. all `permits` but the one of the next request
+/// are available for that long
+async fn idle(permits: &Semaphore, permits_in_flight: usize, max_idle: Duration) {
+    let _all = permits
+        .acquire_many(permits_in_flight as u32)
+        .await
+        .expect("semaphore is not closed");
+    tokio::time::sleep(max_idle).await;
+}
+
+/// Writes the responses of the requests in request order until the reader stops sending them
+async fn write_responses<W: AsyncWrite + Unpin>(
+    mut writer: FrameWriter<W>,
+    mut responses: mpsc::Receiver<InFlight>,
+) -> Result<()> {
+    let written: Result<()> = async {
+        let mut next = responses.recv().await;
+        while let Some(in_flight) = next {
+            let processed = in_flight
+                .response
+                .await
+                .context("join request task")?
+                .context("process request")?;
+            // like Kafka, mute the channel of a client that exceeded its quota,
+            // the responses before are not held back
+            if !processed.throttle.is_zero() {
+                writer.flush().await.context("write response")?;
+                tokio::time::sleep(processed.throttle).await;
+            }
+            // requests without a response, e.g. Produce with acks 0, have an empty one
+            if !processed.response.is_empty() {
+                writer
+                    .send(&processed.response)
+                    .await
+                    .context("write response")?;
+                metrics::metrics().bytes_sent(processed.response.len());
+            }
+            drop(in_flight.permit);
+
+            // write the responses that are ready together, flush when the next one is not
+            next = responses.try_recv().ok();
+            if !next
+                .as_ref()
+                .is_some_and(|next| next.response.is_finished())
+            {
+                writer.flush().await.context("write response")?;
+                if next.is_none() {
+                    next = responses.recv().await;
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+    // the responses buffered before an error are written too, the client may wait for them
+    let flushed = writer.flush().await.context("write response");
+    written.and(flushed)
+}
+
+/// Processes the request if it is a part of the SASL exchange, `None` if it is another request,
+/// which may be processed for the authenticated principal. Requests that may not be processed
+/// in the SASL state of the connection are an error, the connection is closed.
+fn authenticate(
+    authenticator: &mut Authenticator,
+    broker: &Arc<BrokerContext>,
+    msg: &Bytes,
+) -> Result<Option<SaslResponse>> {
+    let start = std::time::Instant::now();
+    let mut reader = ByteReader::new(msg.clone());
+    let header = request::RequestHeader::from_bytes(&mut reader).context("parse request header")?;
+    let api_key = header.request_api_key;
+
+    authenticator
+        .check(api_key, start)
+        .context("close SASL connection")?;
+    if !Authenticator::is_sasl_request(api_key) {
+        return Ok(None);
+    }
+    let result = authenticator.process(broker.clone(), header, reader.into_bytes(), start);
+    metrics::metrics().request_processed(api_key, start.elapsed(), result.is_err());
+    result.map(Some)
+}
+
+/// Processes the request on the blocking thread pool, as it reads the logs from disk.
+///
+/// Requests not processed in `timeout` are answered with REQUEST_TIMED_OUT error if the API has an error code
+/// in its response, otherwise the connection is closed. The blocking task cannot be cancelled and finishes
+/// in the background.
+async fn process_message(
+    broker: Arc<BrokerContext>,
+    msg: Bytes,
+    principal: KafkaPrincipal,
+    authentication: Authentication,
+    timeout: Duration,
+) -> Result<ProcessedRequest> {
+    let mut reader = ByteReader::new(msg);
+    let header = request::RequestHeader::from_bytes(&mut reader).context("parse request header")?;
+    let body = reader.into_bytes();
+
+    let task = tokio::task::spawn_blocking({
+        let broker = broker.clone();
+        let header = header.clone();
+        let principal = principal.clone();
+        move || logic::process(broker, header, principal, authentication, body)
+    });
+
+    match tokio::time::timeout(timeout, task).await {
+        Ok(processed) => processed
+            .context("join request processing task")?
+            .inspect_err(|err| {
+                if let Some(e) = err.downcast_ref::<UnsupportedApiKeyError>() {
+                    // I could create a specific error response here but I just print the error
+                    // and terminate the connection because I don't know what respose Kafka is supposed to return
+                    eprintln!("Error: {e}");
+                }
+            }),
+        Err(_) => {
+            let correlation_id = header.correlation_id;
+            eprintln!(
+                "request {correlation_id} of client '{}' timed out after {} ms",
+                header.client_id.as_deref().unwrap_or_default(),
+                timeout.as_millis()
+            );
+            logic::error_response(
+                broker,
+                header,
+                principal,
+                authentication,
+                ErrorCode::RequestTimedOut,
+            )
+            .with_context(|| format!("request {correlation_id} timed out"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::accept;
+
+    #[tokio::test]
+    async fn listeners_are_accepted_in_turn() {
+        let listeners = [
+            TcpListener::bind("127.0.0.1:0").await.unwrap(),
+            TcpListener::bind("127.0.0.1:0").await.unwrap(),
+        ];
+        let addrs = listeners.each_ref().map(|l| l.local_addr().unwrap());
+        // the first listener has connections waiting all the time
+        let mut clients = Vec::new();
+        for addr in [addrs[0], addrs[0], addrs[1]] {
+            clients.push(TcpStream::connect(addr).await.unwrap());
+        }
+
+        let mut next = 0;
+        let mut accepted = Vec::new();
+        for _ in 0..3 {
+            let (stream, _) = accept(&listeners, &mut next).await.unwrap();
+            accepted.push(stream.local_addr().unwrap());
+        }
+        assert_eq!(accepted, [addrs[0], addrs[1], addrs[0]]);
+    }
+}
@@ -0,0 +1,398 @@
+//! Files of the log directories: the partition logs, the metadata log and `meta.properties`.
+//!
+//! The broker reads and appends them through the [`Storage`] of its context, the [`FileStorage`] of the
+//! file system. Unit tests use a [`MemoryStorage`] instead, so they do not depend on the log directories
+//! existing. Records the broker writes itself are appended with a [`BatchWriter`].
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use thiserror::Error;
+
+use crate::protocol::{crc32c::crc32c, record_batch::LogOffsets, types::VarLong, ProtocolError};
+
+/// Backend the log files are kept in, the paths are those of the files in the log directories
+pub trait Storage: Send + Sync {
+    /// Contents of the file, `NotFound` error if there is no such file
+    fn read(&self, path: &Path) -> Result<Bytes, StorageError>;
+
+    /// Appends the data to the file, the file and its directories are created if they do not exist
+    fn append(&self, path: &Path, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Files and directories directly in the directory, sorted by name.
+    /// `NotFound` error if there is no such directory.
+    fn list(&self, dir: &Path) -> Result<Vec<Entry>, StorageError>;
+}
+
+/// Errors of reading and writing the log files
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// The file or directory does not exist, e.g. the log of a partition nothing was produced to yet
+    #[error("{} does not exist", .0.display())]
+    NotFound(PathBuf),
+    #[error("{} is not a directory", .0.display())]
+    NotADirectory(PathBuf),
+    #[error("{}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The file was read, but its record batches could not be decoded
+    #[error("corrupt log {}: {source}", path.display())]
+    Corrupt {
+        path: PathBuf,
+        #[source]
+        source: ProtocolError,
+    },
+    /// A log does not have the records from the offset, e.g. its first segments were deleted without
+    /// a snapshot of them
+    #[error("{}: records from offset {expected_offset} are missing, the batch after them starts at {base_offset}", path.display())]
+    MissingRecords {
+        path: PathBuf,
+        expected_offset: i64,
+        base_offset: i64,
+    },
+}
+
+impl StorageError {
+    fn io(path: &Path, source: io::Error) -> Self {
+        if source.kind() == io::ErrorKind::NotFound {
+            Self::NotFound(path.to_path_buf())
+        } else {
+            Self::Io {
+                path: path.to_path_buf(),
+                source,
+            }
+        }
+    }
+}
+
+/// File or directory in a directory of the storage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    /// Size of the file in bytes, 0 for a directory
+    pub len: u64,
+}
+
+/// Files on the local file system
+#[derive(Debug, Default)]
+pub struct FileStorage;
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> Result<Bytes, StorageError> {
+        std::fs::read(path)
+            .map(Bytes::from)
+            .map_err(|err| StorageError::io(path, err))
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> Result<(), StorageError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| StorageError::io(dir, err))?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(data))
+            .map_err(|err| StorageError::io(path, err))
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<Entry>, StorageError> {
+        let io_error = |err| StorageError::io(dir, err);
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let metadata = entry.metadata().map_err(io_error)?;
+            entries.push(Entry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: metadata.is_dir(),
+                len: if metadata.is_dir() { 0 } else { metadata.len() },
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+}
+
+/// Files kept in memory, e.g. for tests.
+///
+/// There are no empty directories: a directory exists as long as there is a file in it.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    /// Storage with the files of the paths and contents
+    pub fn with_files<P: AsRef<Path>, D: AsRef<[u8]>>(
+        files: impl IntoIterator<Item = (P, D)>,
+    ) -> Self {
+        let storage = Self::default();
+        for (path, data) in files {
+            storage
+                .append(path.as_ref(), data.as_ref())
+                .expect("appending to memory does not fail");
+        }
+        storage
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files.lock().expect("files are not poisoned")
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, path: &Path) -> Result<Bytes, StorageError> {
+        self.files()
+            .get(path)
+            .map(|data| Bytes::copy_from_slice(data))
+            .ok_or_else(|| StorageError::NotFound(path.to_path_buf()))
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> Result<(), StorageError> {
+        let mut files = self.files();
+        if let Some(file) = path
+            .ancestors()
+            .skip(1)
+            .find(|dir| files.contains_key(*dir))
+        {
+            return Err(StorageError::NotADirectory(file.to_path_buf()));
+        }
+        files.entry(path.to_path_buf()).or_default().extend(data);
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> Result<Vec<Entry>, StorageError> {
+        let files = self.files();
+        let mut entries: Vec<Entry> = Vec::new();
+        for (path, data) in files.range(dir.to_path_buf()..) {
+            let Ok(rest) = path.strip_prefix(dir) else {
+                break;
+            };
+            let mut components = rest.components();
+            let Some(Component::Normal(name)) = components.next() else {
+                // the directory itself is a file
+                return Err(StorageError::NotADirectory(dir.to_path_buf()));
+            };
+            let name = name.to_string_lossy().into_owned();
+            let is_dir = components.next().is_some();
+            // the files of a directory are next to each other in the map
+            if entries.last().is_some_and(|last| last.name == name) {
+                continue;
+            }
+            entries.push(Entry {
+                name,
+                is_dir,
+                len: if is_dir { 0 } else { data.len() as u64 },
+            });
+        }
+        if entries.is_empty() {
+            return Err(StorageError::NotFound(dir.to_path_buf()));
+        }
+        Ok(entries)
+    }
+}
+
+/// Key and value of a record written with a [`BatchWriter`], either of them can be null
+pub type KeyValue<'a> = (Option<&'a [u8]>, Option<&'a [u8]>);
+
+/// Appends the records the broker writes itself to a log, e.g. to an internal topic, as uncompressed record
+/// batches of magic 2 like the ones the clients produce.
+///
+/// The writer assigns the offsets, from the end offset of the log, and the timestamps of the records.
+/// Its appends are serialized, so the log must not be appended to other than through the writer.
+pub struct BatchWriter {
+    path: PathBuf,
+    leader_epoch: i32,
+    lock: Mutex<()>,
+}
+
+impl BatchWriter {
+    pub fn new(path: impl Into<PathBuf>, leader_epoch: i32) -> Self {
+        Self {
+            path: path.into(),
+            leader_epoch,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends the key and value pairs as one batch, all records with the timestamp as their CreateTime.
+    /// Returns the offset of the first record, nothing is appended if there are no records.
+    pub fn append(
+        &self,
+        storage: &dyn Storage,
+        records: &[KeyValue],
+        timestamp_ms: i64,
+    ) -> Result<i64, StorageError> {
+        let _lock = self.lock.lock().expect("batch writer lock is not poisoned");
+        let base_offset = LogOffsets::from_file(storage, &self.path)?.log_end_offset;
+        if !records.is_empty() {
+            let batch = Self::batch(base_offset, self.leader_epoch, records, timestamp_ms);
+            storage.append(&self.path, &batch)?;
+        }
+        Ok(base_offset)
+    }
+
+    /// Record batch of the records, with its length and CRC
+    pub fn batch(
+        base_offset: i64,
+        leader_epoch: i32,
+        records: &[KeyValue],
+        timestamp_ms: i64,
+    ) -> BytesMut {
+        // the CRC covers the batch from the attributes to the end
+        const CRC_OFFSET: usize = 17;
+        const ATTRIBUTES_OFFSET: usize = 21;
+
+        let mut b = BytesMut::new();
+        b.put_i64(base_offset);
+        b.put_i32(0); // batch length
+        b.put_i32(leader_epoch);
+        b.put_i8(2); // magic
+        b.put_u32(0); // crc
+        b.put_i16(0); // attributes: no compression, CreateTime
+        b.put_i32(records.len() as i32 - 1); // last offset delta
+        b.put_i64(timestamp_ms);
+        b.put_i64(timestamp_ms);
+        b.put_i64(-1); // producer id
+        b.put_i16(-1); // producer epoch
+        b.put_i32(-1); // base sequence
+        b.put_i32(records.len() as i32);
+
+        let mut record = BytesMut::new();
+        for (offset_delta, (key, value)) in records.iter().enumerate() {
+            record.clear();
+            record.put_i8(0); // attributes
+            VarLong::serialize_into(0, &mut record); // timestamp delta
+            VarLong::serialize_into(offset_delta as i64, &mut record);
+            for data in [key, value] {
+                match data {
+                    Some(data) => {
+                        VarLong::serialize_into(data.len() as i64, &mut record);
+                        record.put_slice(data);
+                    }
+                    None => VarLong::serialize_into(-1, &mut record),
+                }
+            }
+            VarLong::serialize_into(0, &mut record); // headers
+            VarLong::serialize_into(record.len() as i64, &mut b);
+            b.put_slice(&record);
+        }
+
+        let batch_length = b.len() as i32 - 12;
+        b[8..12].copy_from_slice(&batch_length.to_be_bytes());
+        let crc = crc32c(&b[ATTRIBUTES_OFFSET..]);
+        b[CRC_OFFSET..ATTRIBUTES_OFFSET].copy_from_slice(&crc.to_be_bytes());
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{BatchWriter, Entry, MemoryStorage, Storage, StorageError};
+    use crate::protocol::{
+        reader::ByteReader,
+        record_batch::{RecordBatch, RecordBatches, RecordValue, TopicValue},
+        types::Serialize,
+    };
+
+    #[test]
+    fn memory_storage_lists_files_and_directories() {
+        let storage = MemoryStorage::with_files([
+            ("/logs/foo-0/0.log", "abc"),
+            ("/logs/foo-0/1.log", "de"),
+            ("/logs/meta.properties", "cluster.id=x"),
+            ("/logs/foo-1/0.log", ""),
+            ("/logs-2/bar-0/0.log", "f"),
+        ]);
+        storage
+            .append(Path::new("/logs/foo-0/1.log"), b"fg")
+            .unwrap();
+
+        let entry = |name: &str, is_dir, len| Entry {
+            name: name.to_string(),
+            is_dir,
+            len,
+        };
+        assert_eq!(
+            storage.list(Path::new("/logs")).unwrap(),
+            [
+                entry("foo-0", true, 0),
+                entry("foo-1", true, 0),
+                entry("meta.properties", false, 12)
+            ]
+        );
+        assert_eq!(
+            storage.list(Path::new("/logs/foo-0")).unwrap(),
+            [entry("0.log", false, 3), entry("1.log", false, 4)]
+        );
+        assert_eq!(
+            &storage.read(Path::new("/logs/foo-0/1.log")).unwrap()[..],
+            b"defg"
+        );
+
+        fn not_found<T: std::fmt::Debug>(result: Result<T, StorageError>) -> bool {
+            matches!(result.unwrap_err(), StorageError::NotFound(_))
+        }
+        assert!(not_found(storage.read(Path::new("/logs/foo-2/0.log"))));
+        assert!(not_found(storage.list(Path::new("/logs/foo-2"))));
+        assert!(not_found(storage.list(Path::new("/log"))));
+        assert!(matches!(
+            storage.list(Path::new("/logs/meta.properties")),
+            Err(StorageError::NotADirectory(_))
+        ));
+        assert!(matches!(
+            storage.append(Path::new("/logs/meta.properties/0.log"), b""),
+            Err(StorageError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn batch_writer_appends_batches_at_the_end_offset() {
+        const PATH: &str = "/logs/__cluster_metadata-0/00000000000000000000.log";
+        let topic = |name: &str, id: u8| {
+            RecordValue::Topic(TopicValue {
+                topic_name: name.to_string(),
+                topic_id: format!("00000000-0000-0000-0000-0000000000{id:02x}"),
+            })
+            .serialize()
+        };
+        let storage = MemoryStorage::default();
+        let writer = BatchWriter::new(PATH, 3);
+
+        let (foo, bar, baz) = (topic("foo", 1), topic("bar", 2), topic("baz", 3));
+        assert_eq!(
+            writer
+                .append(&storage, &[(None, Some(&foo))], 1000)
+                .unwrap(),
+            0
+        );
+        assert_eq!(writer.append(&storage, &[], 1000).unwrap(), 1);
+        let records = [(Some(&b"key"[..]), Some(&bar[..])), (None, Some(&baz[..]))];
+        assert_eq!(writer.append(&storage, &records, 2000).unwrap(), 1);
+
+        let metadata = RecordBatches::from_file(&storage, PATH).unwrap();
+        let topics: Vec<_> = metadata.topics().map(|t| t.topic_name.as_str()).collect();
+        assert_eq!(topics, ["foo", "bar", "baz"]);
+        assert_eq!(metadata.end_offset(), 3);
+        let batch = &metadata.batches()[1];
+        assert_eq!((batch.base_offset, batch.last_offset()), (1, 2));
+        assert_eq!(batch.records[0].key.as_deref(), Some(&b"key"[..]));
+
+        // the CRC matches, the batch serializes to the same bytes
+        let written = BatchWriter::batch(1, 3, &records, 2000);
+        let mut batch =
+            RecordBatch::from_bytes(&mut ByteReader::new(written.clone().freeze())).unwrap();
+        assert_eq!(batch.serialize(), written);
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+
+use kafka_starter_rust::{
+    client::Client,
+    config::Config,
+    protocol::{
+        generated::produce_request::{PartitionProduceData, ProduceRequestData, TopicProduceData},
+        request::fetch::{Partition, TopicRequest},
+        ApiKey, ErrorCode,
+    },
+    Broker,
+};
+
+mod common;
+
+#[tokio::test]
+async fn embedded_broker_answers_requests() -> Result<()> {
+    let config = Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+    let log_dir = broker.log_dir().to_path_buf();
+    assert!(log_dir.join("__cluster_metadata-0").is_dir());
+
+    let mut client = Client::connect(broker.addr(), "test").await?;
+
+    let versions = client.api_versions().await?;
+    assert_eq!(versions.error_code, i16::from(ErrorCode::None));
+    assert!(versions
+        .api_keys
+        .iter()
+        .any(|api| api.api_key == i16::from(ApiKey::Fetch)));
+
+    let metadata = client.metadata(None).await?;
+    assert!(metadata.topics.is_empty());
+    assert_eq!(metadata.brokers.len(), 1);
+    assert_eq!(metadata.brokers[0].port, i32::from(broker.addr().port()));
+
+    // the metadata log is empty, there are no topics to produce to or fetch from
+    let produced = client
+        .produce(ProduceRequestData {
+            acks: 1,
+            timeout_ms: 1000,
+            topic_data: vec![TopicProduceData {
+                name: "foo".to_string(),
+                partition_data: vec![PartitionProduceData {
+                    index: 0,
+                    records: Vec::new(),
+                }],
+            }],
+            ..ProduceRequestData::default()
+        })
+        .await?
+        .expect("response with acks 1");
+    assert_eq!(
+        produced.responses[0].partition_responses[0].error_code,
+        i16::from(ErrorCode::UnknownTopicOrPartition)
+    );
+
+    let fetched = client
+        .fetch(vec![TopicRequest {
+            topic_id: "00000000-0000-4000-8000-000000000001".to_string(),
+            partitions: vec![Partition {
+                partition: 0,
+                current_leader_epoch: 0,
+                fetch_offset: 0,
+                last_fetched_epoch: 0,
+                log_start_offset: 0,
+                partition_max_bytes: 1024,
+            }],
+        }])
+        .await?;
+    assert_eq!(fetched.error_code, ErrorCode::None);
+    assert_eq!(
+        fetched.responses[0].partitions[0].error_code,
+        ErrorCode::UnknownTopicId
+    );
+
+    drop(client);
+    broker.shutdown().await?;
+    assert!(!log_dir.exists());
+    Ok(())
+}
+
+#[tokio::test]
+async fn brokers_of_a_process_keep_their_own_config_and_logs() -> Result<()> {
+    let config = || Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let first = Broker::start(config()).await?;
+    let second = Broker::start(config()).await?;
+    assert_ne!(first.addr().port(), second.addr().port());
+    assert_ne!(first.log_dir(), second.log_dir());
+
+    common::create_topic(&first, "foo", "00000000-0000-4000-8000-000000000001", 1)?;
+    for (broker, topics) in [(&first, 1), (&second, 0)] {
+        let mut client = Client::connect(broker.addr(), "test").await?;
+        let metadata = client.metadata(None).await?;
+        assert_eq!(metadata.brokers[0].port, i32::from(broker.addr().port()));
+        assert_eq!(metadata.topics.len(), topics);
+    }
+
+    first.shutdown().await?;
+    second.shutdown().await?;
+    Ok(())
+}
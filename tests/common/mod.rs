@@ -0,0 +1,52 @@
+//! Setup shared by the integration tests
+
+use anyhow::Result;
+
+use kafka_starter_rust::{
+    protocol::record_batch::{PartitionValue, RecordValue, TopicValue},
+    storage::{BatchWriter, FileStorage},
+    BrokerHandle,
+};
+
+/// Writes the records of the topic to the metadata log, its partitions are led by the broker
+pub fn create_topic(
+    broker: &BrokerHandle,
+    topic_name: &str,
+    topic_id: &str,
+    partitions: u32,
+) -> Result<()> {
+    let topic = RecordValue::Topic(TopicValue {
+        topic_name: topic_name.to_string(),
+        topic_id: topic_id.to_string(),
+    })
+    .serialize();
+    let partitions: Vec<_> = (0..partitions)
+        .map(|partition_id| {
+            RecordValue::Partition(PartitionValue {
+                partition_id,
+                topic_id: topic_id.to_string(),
+                replicas: vec![1],
+                in_sync_replicas: vec![1],
+                removing_replicas: Vec::new(),
+                adding_replicas: Vec::new(),
+                leader_id: 1,
+                leader_epoch: 0,
+                partition_epoch: 0,
+                directories: Vec::new(),
+            })
+            .serialize()
+        })
+        .collect();
+
+    let records: Vec<_> = [&topic]
+        .into_iter()
+        .chain(&partitions)
+        .map(|value| (None, Some(&value[..])))
+        .collect();
+    let metadata_log = broker
+        .log_dir()
+        .join("__cluster_metadata-0")
+        .join("00000000000000000000.log");
+    BatchWriter::new(&metadata_log, 0).append(&FileStorage, &records, 0)?;
+    Ok(())
+}
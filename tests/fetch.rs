@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use kafka_starter_rust::{
+    client::Client,
+    config::Config,
+    protocol::{
+        generated::produce_request::{PartitionProduceData, ProduceRequestData, TopicProduceData},
+        request::fetch::{Partition, TopicRequest},
+        ErrorCode,
+    },
+    storage::BatchWriter,
+    Broker,
+};
+
+mod common;
+
+const TOPIC_ID: &str = "00000000-0000-4000-8000-000000000001";
+
+#[tokio::test]
+async fn waiting_fetch_gets_the_records_when_they_are_produced() -> Result<()> {
+    let config = Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+    common::create_topic(&broker, "foo", TOPIC_ID, 1)?;
+
+    // the client waits up to 500 ms for records, nothing is produced yet
+    let mut consumer = Client::connect(broker.addr(), "consumer").await?;
+    let fetch = tokio::spawn(async move {
+        let started = Instant::now();
+        let fetched = consumer
+            .fetch(vec![TopicRequest {
+                topic_id: TOPIC_ID.to_string(),
+                partitions: vec![Partition {
+                    partition: 0,
+                    current_leader_epoch: 0,
+                    fetch_offset: 0,
+                    last_fetched_epoch: 0,
+                    log_start_offset: 0,
+                    partition_max_bytes: 1024,
+                }],
+            }])
+            .await?;
+        anyhow::Ok((started.elapsed(), fetched))
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut producer = Client::connect(broker.addr(), "producer").await?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let produced = producer
+        .produce(ProduceRequestData {
+            acks: 1,
+            timeout_ms: 1000,
+            topic_data: vec![TopicProduceData {
+                name: "foo".to_string(),
+                partition_data: vec![PartitionProduceData {
+                    index: 0,
+                    records: BatchWriter::batch(0, 0, &[(None, Some(b"a"))], now_ms).to_vec(),
+                }],
+            }],
+            ..ProduceRequestData::default()
+        })
+        .await?
+        .expect("response with acks 1");
+    assert_eq!(
+        produced.responses[0].partition_responses[0].error_code,
+        i16::from(ErrorCode::None)
+    );
+
+    // the fetch is answered once the batch is appended, not after waiting out its max wait
+    let (waited, fetched) = fetch.await??;
+    let partition = &fetched.responses[0].partitions[0];
+    assert_eq!(partition.error_code, ErrorCode::None);
+    assert_eq!(partition.high_watermark, 1);
+    assert!(!partition.records.is_empty());
+    assert!(waited < Duration::from_millis(500), "waited {waited:?}");
+
+    drop(producer);
+    broker.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Loads the fixtures: the request frames a client sent on one connection and the response frames the broker
+//! answered, in the order of the connection.
+//!
+//! A fixture has `request` and `response` sections with the messages, without the size they are prefixed
+//! with on the wire, as hex bytes. Whitespace is ignored and `#` starts a comment. The hexdump lines
+//! of the `--trace-wire=hex` log can be pasted as they are, the offsets and the ASCII column are skipped.
+//! `??` matches any byte of a response, e.g. of a timestamp. A request without a response section,
+//! e.g. Produce with acks 0, is not answered.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+
+pub struct Fixture {
+    pub name: String,
+    pub exchanges: Vec<Exchange>,
+}
+
+pub struct Exchange {
+    pub request: Vec<u8>,
+    /// Bytes of the response, `None` for the bytes that may differ
+    pub response: Option<Vec<Option<u8>>>,
+}
+
+impl Exchange {
+    /// Describes the first difference of the response from the expected one
+    pub fn mismatch(&self, actual: &[u8]) -> Option<String> {
+        let expected = self.response.as_ref()?;
+        let differs = |(e, a): (&Option<u8>, &u8)| e.is_some_and(|e| e != *a);
+        let first = expected
+            .iter()
+            .zip(actual)
+            .position(differs)
+            .or((expected.len() != actual.len()).then_some(expected.len().min(actual.len())))?;
+        let expected: String = expected
+            .iter()
+            .map(|b| b.map_or("??".to_string(), |b| format!("{b:02x}")))
+            .collect();
+        Some(format!(
+            "first difference at byte {first}\n  expected {expected}\n  actual   {}",
+            hex::encode(actual)
+        ))
+    }
+}
+
+/// Fixtures of the `.txt` files in the directory, sorted by name
+pub fn load_all(dir: &Path) -> Result<Vec<Fixture>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "txt"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let text =
+                fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+            parse(&name, &text).with_context(|| format!("parse {}", path.display()))
+        })
+        .collect()
+}
+
+pub fn parse(name: &str, text: &str) -> Result<Fixture> {
+    let mut exchanges: Vec<Exchange> = Vec::new();
+    let mut in_response = false;
+    for (n, line) in text.lines().enumerate() {
+        let line = hexdump_bytes(line.trim())
+            .unwrap_or_else(|| line.split('#').next().unwrap_or_default().trim());
+        match line {
+            "" => continue,
+            "request" => {
+                exchanges.push(Exchange {
+                    request: Vec::new(),
+                    response: None,
+                });
+                in_response = false;
+                continue;
+            }
+            "response" => {
+                let Some(exchange) = exchanges.last_mut() else {
+                    bail!("line {}: response before a request", n + 1);
+                };
+                exchange.response = Some(Vec::new());
+                in_response = true;
+                continue;
+            }
+            _ => {}
+        }
+        let Some(exchange) = exchanges.last_mut() else {
+            bail!("line {}: bytes before a request", n + 1);
+        };
+        let bytes = parse_bytes(line).with_context(|| format!("line {}", n + 1))?;
+        match &mut exchange.response {
+            Some(response) if in_response => response.extend(bytes),
+            _ => {
+                for byte in bytes {
+                    exchange
+                        .request
+                        .push(byte.with_context(|| format!("line {}: `??` in a request", n + 1))?);
+                }
+            }
+        }
+    }
+    if exchanges.is_empty() {
+        bail!("no requests");
+    }
+    Ok(Fixture {
+        name: name.to_string(),
+        exchanges,
+    })
+}
+
+/// Bytes of a hexdump line without its offset and ASCII column, which may contain `#`
+fn hexdump_bytes(line: &str) -> Option<&str> {
+    let (offset, rest) = line.split_once("  ")?;
+    let is_offset = offset.len() == 8 && offset.bytes().all(|b| b.is_ascii_hexdigit());
+    let (bytes, _ascii) = rest.split_once('|')?;
+    is_offset.then_some(bytes)
+}
+
+fn parse_bytes(line: &str) -> Result<Vec<Option<u8>>> {
+    let digits: String = line.split_whitespace().collect();
+    if digits.len() & 1 == 1 {
+        bail!("odd number of hex digits");
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| match &digits[i..i + 2] {
+            "??" => Ok(None),
+            byte => u8::from_str_radix(byte, 16)
+                .map(Some)
+                .with_context(|| format!("invalid byte `{byte}`")),
+        })
+        .collect()
+}
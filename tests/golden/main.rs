@@ -0,0 +1,56 @@
+//! Replays the requests of the fixtures and compares the responses with the ones of Kafka 3.7, byte by byte.
+//!
+//! Every fixture is replayed on its own connection to a broker without topics.
+
+mod fixture;
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use kafka_starter_rust::{config::Config, Broker};
+
+// all fixtures are replayed against one broker
+#[tokio::test]
+async fn responses_match_kafka() -> Result<()> {
+    let fixtures =
+        fixture::load_all(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/fixtures"))?;
+    assert!(!fixtures.is_empty(), "no fixtures");
+
+    let config = Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+
+    let mut mismatches = Vec::new();
+    for fixture in &fixtures {
+        let mut stream = TcpStream::connect(broker.addr()).await?;
+        for (n, exchange) in fixture.exchanges.iter().enumerate() {
+            stream.write_i32(exchange.request.len() as i32).await?;
+            stream.write_all(&exchange.request).await?;
+            if exchange.response.is_none() {
+                continue;
+            }
+
+            let size = stream
+                .read_i32()
+                .await
+                .with_context(|| format!("{}: read response {}", fixture.name, n + 1))?;
+            let mut response = vec![0; size as usize];
+            stream.read_exact(&mut response).await?;
+            if let Some(mismatch) = exchange.mismatch(&response) {
+                mismatches.push(format!("{} response {}: {mismatch}", fixture.name, n + 1));
+            }
+        }
+    }
+
+    broker.shutdown().await?;
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+    Ok(())
+}
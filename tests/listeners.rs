@@ -0,0 +1,63 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use anyhow::Result;
+
+use kafka_starter_rust::{client::Client, config::Config, protocol::ErrorCode, Broker};
+
+#[tokio::test]
+async fn broker_listens_on_every_bind_address() -> Result<()> {
+    let config = Config {
+        bind: vec![
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ],
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+    assert!(broker.addr().is_ipv4());
+
+    // both listeners got the same free port
+    let ipv6 = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), broker.addr().port());
+    for addr in [broker.addr(), ipv6] {
+        let mut client = Client::connect(addr, "test").await?;
+        let versions = client.api_versions().await?;
+        assert_eq!(versions.error_code, i16::from(ErrorCode::None));
+
+        // the first bind address is advertised
+        let metadata = client.metadata(None).await?;
+        assert_eq!(metadata.brokers[0].host, "127.0.0.1");
+    }
+
+    broker.shutdown().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn dual_stack_wildcard_serves_ipv4_too() -> Result<()> {
+    // on Linux `::` accepts IPv4 connections, binding `0.0.0.0` next to it would fail
+    let config = Config {
+        bind: vec![
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        ],
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+
+    let port = broker.addr().port();
+    for ip in [
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ] {
+        let mut client = Client::connect(SocketAddr::new(ip, port), "test").await?;
+        let versions = client.api_versions().await?;
+        assert_eq!(versions.error_code, i16::from(ErrorCode::None));
+    }
+
+    broker.shutdown().await?;
+    Ok(())
+}
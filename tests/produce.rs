@@ -0,0 +1,92 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use kafka_starter_rust::{
+    client::Client,
+    config::Config,
+    protocol::{
+        generated::produce_request::{PartitionProduceData, ProduceRequestData, TopicProduceData},
+        reader::ByteReader,
+        record_batch::RecordBatch,
+        ErrorCode,
+    },
+    storage::BatchWriter,
+    Broker,
+};
+
+mod common;
+
+const CONNECTIONS: usize = 8;
+const BATCHES_PER_CONNECTION: usize = 25;
+
+#[tokio::test]
+async fn concurrent_produce_requests_get_the_next_offsets() -> Result<()> {
+    let config = Config {
+        port: 0,
+        log_dirs: Vec::new(),
+        ..Config::default()
+    };
+    let broker = Broker::start(config).await?;
+
+    common::create_topic(&broker, "foo", "00000000-0000-4000-8000-000000000001", 1)?;
+
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+    let records = BatchWriter::batch(0, 0, &[(None, Some(b"a")), (None, Some(b"b"))], now_ms);
+    let producers: Vec<_> = (0..CONNECTIONS)
+        .map(|i| {
+            let (addr, records) = (broker.addr(), records.to_vec());
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr, &format!("producer-{i}")).await?;
+                let mut offsets = Vec::new();
+                for _ in 0..BATCHES_PER_CONNECTION {
+                    let produced = client
+                        .produce(ProduceRequestData {
+                            acks: 1,
+                            timeout_ms: 1000,
+                            topic_data: vec![TopicProduceData {
+                                name: "foo".to_string(),
+                                partition_data: vec![PartitionProduceData {
+                                    index: 0,
+                                    records: records.clone(),
+                                }],
+                            }],
+                            ..ProduceRequestData::default()
+                        })
+                        .await?
+                        .expect("response with acks 1");
+                    let partition = &produced.responses[0].partition_responses[0];
+                    assert_eq!(partition.error_code, i16::from(ErrorCode::None));
+                    offsets.push(partition.base_offset);
+                }
+                anyhow::Ok(offsets)
+            })
+        })
+        .collect();
+    let mut offsets = Vec::new();
+    for producer in producers {
+        offsets.extend(producer.await??);
+    }
+
+    // every batch got offsets of its own, and the log has them in order
+    let batches = (CONNECTIONS * BATCHES_PER_CONNECTION) as i64;
+    offsets.sort();
+    assert_eq!(offsets, (0..batches).map(|i| i * 2).collect::<Vec<_>>());
+    let log = std::fs::read(
+        broker
+            .log_dir()
+            .join("foo-0")
+            .join("00000000000000000000.log"),
+    )?;
+    let mut log = ByteReader::new(log.into());
+    let mut next_offset = 0;
+    while log.remaining() > 0 {
+        let batch = RecordBatch::from_bytes_lazy(&mut log)?;
+        assert_eq!(batch.base_offset, next_offset);
+        next_offset = batch.last_offset() + 1;
+    }
+    assert_eq!(next_offset, batches * 2);
+
+    broker.shutdown().await?;
+    Ok(())
+}